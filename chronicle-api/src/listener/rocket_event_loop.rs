@@ -21,7 +21,10 @@ use chronicle_broker::{
     SyncData,
 };
 use chronicle_common::{
-    config::PartitionConfig,
+    config::{
+        MqttType,
+        PartitionConfig,
+    },
     metrics::{
         prometheus::{
             self,
@@ -29,9 +32,12 @@ use chronicle_common::{
             TextEncoder,
         },
         INCOMING_REQUESTS,
+        LATEST_MILESTONE_INDEX,
+        LATEST_MILESTONE_TIMESTAMP,
         REGISTRY,
         RESPONSE_CODE_COLLECTOR,
         RESPONSE_TIME_COLLECTOR,
+        SYNC_LAG,
     },
     SyncRange,
 };
@@ -59,6 +65,7 @@ use rocket::{
     get,
     http::ContentType,
     response::{
+        status,
         Content,
         Responder,
     },
@@ -132,6 +139,8 @@ fn construct_rocket(rocket: Rocket) -> Rocket {
                 info,
                 metrics,
                 service,
+                health_live,
+                health_ready,
                 sync,
                 get_message,
                 get_message_metadata,
@@ -294,8 +303,182 @@ async fn metrics() -> Result<String, ListenerError> {
 }
 
 #[get("/service")]
-async fn service() -> Json<Service> {
-    Json(SERVICE.read().await.clone())
+async fn service() -> Json<ServiceResponse> {
+    // The gauges stay at zero until the broker hears from the network
+    let latest_milestone_index = LATEST_MILESTONE_INDEX.get();
+    let latest_milestone_timestamp = LATEST_MILESTONE_TIMESTAMP.get();
+    Json(ServiceResponse {
+        service: SERVICE.read().await.clone(),
+        latest_milestone_index: (latest_milestone_index > 0).then(|| latest_milestone_index as u32),
+        latest_milestone_timestamp: (latest_milestone_timestamp > 0).then(|| latest_milestone_timestamp as u64),
+        sync_lag: (latest_milestone_index > 0).then(|| SYNC_LAG.get() as u32),
+    })
+}
+
+#[get("/health/live")]
+async fn health_live() -> status::Custom<Json<HealthResponse>> {
+    let service = SERVICE.read().await;
+    // Chronicle is alive as long as none of its applications stopped for good
+    let checks = service
+        .microservices
+        .values()
+        .map(|app| HealthCheck {
+            name: app.get_name(),
+            healthy: !app.is_stopped(),
+            details: describe_status(app).to_string(),
+        })
+        .collect();
+    health_response(checks)
+}
+
+#[get("/health/ready")]
+async fn health_ready(keyspaces: State<'_, HashSet<String>>) -> status::Custom<Json<HealthResponse>> {
+    let config = get_config_async().await;
+    let mut checks = vec![scylla_check(&keyspaces).await];
+    let service = SERVICE.read().await;
+    let broker = service.microservices.get("ChronicleBroker");
+    for mqtt_type in [
+        MqttType::Messages,
+        MqttType::MessagesReferenced,
+        MqttType::LatestMilestone,
+    ] {
+        if config.broker_config.mqtt_brokers.contains_key(&mqtt_type) {
+            checks.push(mqtt_check(broker, mqtt_type));
+        }
+    }
+    checks.push(archiver_check(broker, config.broker_config.logs_dir.is_some()));
+    checks.push(sync_lag_check(config.api_config.max_sync_lag));
+    health_response(checks)
+}
+
+fn health_response(checks: Vec<HealthCheck>) -> status::Custom<Json<HealthResponse>> {
+    let healthy = checks.iter().all(|check| check.healthy);
+    let status = if healthy {
+        Status::Ok
+    } else {
+        Status::ServiceUnavailable
+    };
+    status::Custom(status, Json(HealthResponse { healthy, checks }))
+}
+
+fn describe_status(service: &Service) -> &'static str {
+    if service.is_running() {
+        "Running"
+    } else if service.is_initializing() {
+        "Initializing"
+    } else if service.is_degraded() {
+        "Degraded"
+    } else if service.is_maintenance() {
+        "Maintenance"
+    } else if service.is_stopping() {
+        "Stopping"
+    } else if service.is_stopped() {
+        "Stopped"
+    } else {
+        "Starting"
+    }
+}
+
+async fn scylla_check(keyspaces: &HashSet<String>) -> HealthCheck {
+    let name = "Scylla".to_string();
+    let keyspace = match keyspaces.iter().next() {
+        Some(keyspace) => keyspace.clone(),
+        None => {
+            return HealthCheck {
+                name,
+                healthy: false,
+                details: "No keyspace configured".to_string(),
+            }
+        }
+    };
+    // Any response, even an empty one, proves that scylla is reachable
+    match query::<Milestone, _, _>(
+        ChronicleKeyspace::new(keyspace.clone()),
+        MilestoneIndex::from(0),
+        None,
+        None,
+    )
+    .await
+    {
+        Ok(_) | Err(ListenerError::NoResults) => HealthCheck {
+            name,
+            healthy: true,
+            details: format!("Reachable through keyspace {}", keyspace),
+        },
+        Err(e) => HealthCheck {
+            name,
+            healthy: false,
+            details: format!("Unreachable: {}", e),
+        },
+    }
+}
+
+fn mqtt_check(broker: Option<&Service>, mqtt_type: MqttType) -> HealthCheck {
+    // The mqtt feeds are named after their topic and url, ie messages@tcp://localhost:1883
+    let prefix = format!("{}@", mqtt_type.topic());
+    let (mut running, mut total) = (0, 0);
+    for feed in broker
+        .iter()
+        .flat_map(|broker| broker.microservices.values())
+        .filter(|microservice| microservice.get_name().starts_with(&prefix))
+    {
+        total += 1;
+        if feed.is_running() {
+            running += 1;
+        }
+    }
+    HealthCheck {
+        name: format!("Mqtt {:?}", mqtt_type),
+        healthy: running > 0,
+        details: format!("{} of {} feeds running", running, total),
+    }
+}
+
+fn archiver_check(broker: Option<&Service>, enabled: bool) -> HealthCheck {
+    let name = "Archiver".to_string();
+    if !enabled {
+        return HealthCheck {
+            name,
+            healthy: true,
+            details: "Disabled".to_string(),
+        };
+    }
+    match broker.and_then(|broker| broker.microservices.get(&name)) {
+        // The archiver degrades once it leaves a maybe corrupted log file behind
+        Some(archiver) if archiver.is_degraded() => HealthCheck {
+            name,
+            healthy: false,
+            details: "Maybe corrupted log file".to_string(),
+        },
+        Some(archiver) => HealthCheck {
+            name,
+            healthy: archiver.is_running(),
+            details: describe_status(archiver).to_string(),
+        },
+        None => HealthCheck {
+            name,
+            healthy: false,
+            details: "Not running".to_string(),
+        },
+    }
+}
+
+fn sync_lag_check(max_sync_lag: u32) -> HealthCheck {
+    let name = "Sync lag".to_string();
+    // The gauges stay at zero until the broker hears from the network
+    if LATEST_MILESTONE_INDEX.get() == 0 {
+        return HealthCheck {
+            name,
+            healthy: false,
+            details: "Unknown network tip".to_string(),
+        };
+    }
+    let sync_lag = SYNC_LAG.get() as u32;
+    HealthCheck {
+        name,
+        healthy: sync_lag <= max_sync_lag,
+        details: format!("{} milestones behind the network tip (max {})", sync_lag, max_sync_lag),
+    }
 }
 
 #[get("/<keyspace>/sync")]
@@ -1182,6 +1365,38 @@ mod tests {
             .expect("Failed to deserialize Service Response!");
     }
 
+    #[rocket::async_test]
+    async fn health_live() {
+        let client = construct_client().await;
+
+        let res = client.get("/api/health/live").dispatch().await;
+        assert_eq!(res.status(), Status::Ok);
+        assert_eq!(res.content_type(), Some(ContentType::JSON));
+        check_cors_headers(&res);
+        let body: HealthResponse = serde_json::from_str(&res.into_string().await.expect("No body returned!"))
+            .expect("Failed to deserialize Health Response!");
+        assert!(body.healthy);
+    }
+
+    #[rocket::async_test]
+    async fn health_ready() {
+        let client = construct_client().await;
+
+        let res = client.get("/api/health/ready").dispatch().await;
+        assert_eq!(res.status(), Status::ServiceUnavailable);
+        assert_eq!(res.content_type(), Some(ContentType::JSON));
+        check_cors_headers(&res);
+        let body: HealthResponse = serde_json::from_str(&res.into_string().await.expect("No body returned!"))
+            .expect("Failed to deserialize Health Response!");
+        assert!(!body.healthy);
+        let scylla = body
+            .checks
+            .iter()
+            .find(|check| check.name == "Scylla")
+            .expect("No scylla check returned!");
+        assert!(!scylla.healthy);
+    }
+
     #[rocket::async_test]
     async fn get_message() {
         let client = construct_client().await;
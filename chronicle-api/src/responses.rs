@@ -28,6 +28,7 @@ use chronicle_storage::access::{
     TransactionRes,
     UnlockRes,
 };
+use scylla_rs::prelude::Service;
 use serde::{
     Deserialize,
     Serialize,
@@ -180,6 +181,34 @@ pub(crate) enum ListenerResponse {
     Analytics { ranges: Vec<AnalyticData> },
 }
 
+/// Response of GET /api/service
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct ServiceResponse {
+    #[serde(flatten)]
+    pub service: Service,
+    #[serde(rename = "latestMilestoneIndex")]
+    pub latest_milestone_index: Option<u32>,
+    #[serde(rename = "latestMilestoneTimestamp")]
+    pub latest_milestone_timestamp: Option<u64>,
+    #[serde(rename = "syncLag")]
+    pub sync_lag: Option<u32>,
+}
+
+/// Response of GET /api/health/live and GET /api/health/ready
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct HealthResponse {
+    pub healthy: bool,
+    pub checks: Vec<HealthCheck>,
+}
+
+/// The outcome of a single health check
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct HealthCheck {
+    pub name: String,
+    pub healthy: bool,
+    pub details: String,
+}
+
 impl TryFrom<Message> for ListenerResponse {
     type Error = Cow<'static, str>;
 
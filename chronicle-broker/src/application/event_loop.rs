@@ -2,8 +2,17 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use super::*;
-use chronicle_common::get_history_mut;
-use futures::SinkExt;
+use chronicle_common::{
+    get_history_mut,
+    metrics::{
+        LATEST_MILESTONE_INDEX,
+        LATEST_MILESTONE_TIMESTAMP,
+    },
+};
+use futures::{
+    future::AbortHandle,
+    SinkExt,
+};
 
 #[async_trait]
 impl<H: ChronicleBrokerScope> EventLoop<H> for ChronicleBroker<H> {
@@ -17,6 +26,10 @@ impl<H: ChronicleBrokerScope> EventLoop<H> for ChronicleBroker<H> {
                         let socket_msg = BrokerSocketMsg::ChronicleBroker(importer_session);
                         self.response_to_sockets(&socket_msg).await;
                     }
+                    BrokerEvent::Syncer(syncer_session) => {
+                        let socket_msg = BrokerSocketMsg::ChronicleBroker(syncer_session);
+                        self.response_to_sockets(&socket_msg).await;
+                    }
                     BrokerEvent::Passthrough(passthrough_events) => {
                         match passthrough_events.try_get_my_event() {
                             Ok(my_event) => match my_event {
@@ -53,46 +66,125 @@ impl<H: ChronicleBrokerScope> EventLoop<H> for ChronicleBroker<H> {
                                         BrokerTopology::RemoveMqttMessages(url) => {
                                             self.remove_mqtt::<Messages>(MqttType::Messages, url)
                                         }
-                                        BrokerTopology::Import { .. } => {
-                                            self.handle_import(topology).await;
+                                        BrokerTopology::AddMqttLatestMilestone(url) => {
+                                            if let Some(mqtt) =
+                                                self.add_mqtt(LatestMilestone, MqttType::LatestMilestone, url)
+                                            {
+                                                tokio::spawn(mqtt.start(self.handle.clone()));
+                                            }
+                                        }
+                                        BrokerTopology::RemoveMqttLatestMilestone(url) => {
+                                            self.remove_mqtt::<LatestMilestone>(MqttType::LatestMilestone, url)
+                                        }
+                                        BrokerTopology::Import { ref path, .. } => {
+                                            if let Some(job_id) = self.import_jobs.create(&topology) {
+                                                info!("Import job {} imports {}", job_id, path.display());
+                                                let event = ImporterSession::Job {
+                                                    id: job_id,
+                                                    path: path.clone(),
+                                                };
+                                                let socket_msg = BrokerSocketMsg::ChronicleBroker(event);
+                                                self.response_to_sockets(&socket_msg).await;
+                                                self.handle_import(job_id, topology).await;
+                                                self.import_jobs.save(true).await;
+                                            }
                                             self.try_close_importer_session().await;
                                         }
-                                        BrokerTopology::Requesters(ref mut requester_topology) => {
+                                        BrokerTopology::Requesters(ref requester_topology) => {
                                             match requester_topology {
                                                 RequesterTopology::AddEndpoint(ref url) => {
-                                                    let reqwest_client = reqwest::Client::new();
-                                                    if let Some(url) = BrokerConfig::adjust_api_endpoint(url.clone()) {
-                                                        if let Err(e) =
-                                                            BrokerConfig::verify_endpoint(&reqwest_client, &url).await
-                                                        {
-                                                            error!("{}", e);
-                                                            let socket_msg =
-                                                                BrokerSocketMsg::ChronicleBroker(Err(topology.clone()));
-                                                            self.response_to_sockets::<Result<BrokerTopology, BrokerTopology>>(&socket_msg).await;
-                                                        } else {
-                                                            *requester_topology =
-                                                                RequesterTopology::AddEndpoint(url.clone());
-                                                            self.collector_handles.values().for_each(|h| {
-                                                                h.send_requester_topology(requester_topology.clone());
-                                                            });
-                                                            let socket_msg =
-                                                                BrokerSocketMsg::ChronicleBroker(Ok(topology.clone()));
-                                                            self.response_to_sockets::<Result<BrokerTopology, BrokerTopology>>(&socket_msg).await;
-                                                        }
-                                                    } else {
-                                                        let socket_msg =
-                                                            BrokerSocketMsg::ChronicleBroker(Err(topology.clone()));
-                                                        self.response_to_sockets::<Result<BrokerTopology, BrokerTopology>>(&socket_msg).await;
-                                                    };
+                                                    self.handle_add_endpoint(url.clone(), None).await;
                                                 }
-                                                RequesterTopology::RemoveEndpoint(_) => {
+                                                RequesterTopology::AddAuthenticatedEndpoint { ref url, ref auth } => {
+                                                    self.handle_add_endpoint(url.clone(), Some(auth.clone())).await;
+                                                }
+                                                RequesterTopology::RemoveEndpoint(ref url) => {
+                                                    self.endpoints_health.remove(url);
                                                     self.collector_handles.values().for_each(|h| {
                                                         h.send_requester_topology(requester_topology.clone());
                                                     });
                                                 }
                                             }
                                         }
+                                        BrokerTopology::Replay { ref path, speed } => {
+                                            let result = match self.add_replayer(path.clone(), speed) {
+                                                Ok(()) => Ok(topology),
+                                                Err(e) => {
+                                                    error!("{}", e);
+                                                    Err(topology)
+                                                }
+                                            };
+                                            let socket_msg = BrokerSocketMsg::ChronicleBroker(result);
+                                            self.response_to_sockets::<Result<BrokerTopology, BrokerTopology>>(
+                                                &socket_msg,
+                                            )
+                                            .await;
+                                        }
+                                        BrokerTopology::Backfill(ref backfill) => {
+                                            let result = match self.change_backfill(backfill.clone()).await {
+                                                Ok(()) => Ok(topology),
+                                                Err(e) => {
+                                                    error!("{}", e);
+                                                    Err(topology)
+                                                }
+                                            };
+                                            let socket_msg = BrokerSocketMsg::ChronicleBroker(result);
+                                            self.response_to_sockets::<Result<BrokerTopology, BrokerTopology>>(
+                                                &socket_msg,
+                                            )
+                                            .await;
+                                        }
+                                        BrokerTopology::ImportThrottle(ref import_throttle) => {
+                                            let result = match self.change_import_throttle(import_throttle.clone()) {
+                                                Ok(()) => Ok(topology),
+                                                Err(e) => {
+                                                    error!("{}", e);
+                                                    Err(topology)
+                                                }
+                                            };
+                                            let socket_msg = BrokerSocketMsg::ChronicleBroker(result);
+                                            self.response_to_sockets::<Result<BrokerTopology, BrokerTopology>>(
+                                                &socket_msg,
+                                            )
+                                            .await;
+                                        }
+                                        BrokerTopology::ReloadFilter(ref path) => {
+                                            let result = match self.reload_filter(path.clone()) {
+                                                Ok(()) => Ok(topology),
+                                                Err(e) => {
+                                                    error!("{}", e);
+                                                    Err(topology)
+                                                }
+                                            };
+                                            let socket_msg = BrokerSocketMsg::ChronicleBroker(result);
+                                            self.response_to_sockets::<Result<BrokerTopology, BrokerTopology>>(
+                                                &socket_msg,
+                                            )
+                                            .await;
+                                        }
+                                    }
+                                }
+                                ChronicleBrokerThrough::EndpointsHealth => {
+                                    let socket_msg = BrokerSocketMsg::ChronicleBroker(
+                                        self.endpoints_health.report_all(Instant::now()),
+                                    );
+                                    self.response_to_sockets(&socket_msg).await;
+                                }
+                                ChronicleBrokerThrough::ImportJobs(command) => {
+                                    if let ImportJobCommand::List = command {
+                                        let socket_msg = BrokerSocketMsg::ChronicleBroker(self.import_jobs.reports());
+                                        self.response_to_sockets(&socket_msg).await;
+                                        continue;
                                     }
+                                    let result = self.handle_import_job_command(command).await.map_err(|e| {
+                                        error!("{}", e);
+                                        e.to_string()
+                                    });
+                                    self.import_jobs.save(true).await;
+                                    let socket_msg = BrokerSocketMsg::ChronicleBroker(result);
+                                    self.response_to_sockets::<Result<ImportJobReport, String>>(&socket_msg)
+                                        .await;
+                                    self.try_close_importer_session().await;
                                 }
                                 ChronicleBrokerThrough::ExitProgram => {
                                     supervisor.exit_program(false);
@@ -103,6 +195,54 @@ impl<H: ChronicleBrokerScope> EventLoop<H> for ChronicleBroker<H> {
                             }
                         }
                     }
+                    BrokerEvent::NetworkTip(network_tip) => {
+                        // several feed sources announce the same milestone, so only move the tip forward
+                        if self
+                            .network_tip
+                            .map_or(true, |tip| tip.milestone_index < network_tip.milestone_index)
+                        {
+                            self.network_tip.replace(network_tip);
+                            LATEST_MILESTONE_INDEX.set(network_tip.milestone_index as i64);
+                            LATEST_MILESTONE_TIMESTAMP.set(network_tip.timestamp as i64);
+                            if let Some(syncer_handle) = self.syncer_handle.as_ref() {
+                                let _ = syncer_handle.send(SyncerEvent::NetworkTip(network_tip));
+                            }
+                        }
+                    }
+                    BrokerEvent::Fetched(job_id, fetched) => {
+                        self.in_progress_fetches -= 1;
+                        match fetched {
+                            Ok(import_topology) => {
+                                // the job continues from the local copy, even once paused
+                                if let BrokerTopology::Import { ref path, .. } = import_topology {
+                                    self.import_jobs.set_path(job_id, path.clone());
+                                }
+                                if self.import_jobs.is_running(job_id) {
+                                    self.handle_import(job_id, import_topology).await;
+                                }
+                                self.remove_fetched(job_id).await;
+                            }
+                            Err(event) => {
+                                self.import_jobs.fail(job_id);
+                                let socket_msg = BrokerSocketMsg::ChronicleBroker(event);
+                                self.response_to_sockets(&socket_msg).await;
+                            }
+                        }
+                        self.import_jobs.save(true).await;
+                        self.try_close_importer_session().await;
+                    }
+                    BrokerEvent::StreamedLog(id, reader) => {
+                        self.streamed_logs.insert(id, reader);
+                    }
+                    BrokerEvent::ImportCheckpoint {
+                        job_id,
+                        path,
+                        milestone_index,
+                        bytes,
+                    } => {
+                        self.import_jobs.imported(job_id, &path, milestone_index, bytes);
+                        self.import_jobs.save(false).await;
+                    }
                     BrokerEvent::Scylla(service) => {
                         if let Err(Need::Restart) = status.as_ref() {
                             if service.is_running() {
@@ -121,15 +261,33 @@ impl<H: ChronicleBrokerScope> EventLoop<H> for ChronicleBroker<H> {
                             BrokerChild::Collector(service) => {
                                 self.service.update_microservice(service.get_name(), service.clone());
                             }
-                            BrokerChild::Importer(service, _status, parallelism) => {
+                            BrokerChild::Importer(service, importer_status, parallelism) => {
                                 if service.is_stopped() {
                                     self.in_progress_importers -= 1;
                                     self.service.delete_microservice(&service.get_name());
                                     // return parallelism
                                     self.parallelism_points += parallelism;
+                                    // update the job of the log file
+                                    let path = PathBuf::from(service.get_name());
+                                    if let Some(job_id) = self.import_jobs.find_running_log(&path) {
+                                        let log_state = if importer_status.is_ok() {
+                                            JobLogState::Done
+                                        } else if self.service.is_stopping() || !self.import_jobs.is_running(job_id) {
+                                            // interrupted, so it continues from its checkpoint once resumed
+                                            JobLogState::Pending
+                                        } else {
+                                            JobLogState::Failed
+                                        };
+                                        self.import_jobs.set_log_state(job_id, &path, log_state);
+                                        self.import_jobs.save(true).await;
+                                        self.remove_fetched(job_id).await;
+                                    }
+                                    if let Some(uploader_handle) = self.uploader_handle.as_ref() {
+                                        uploader_handle.send(UploaderEvent::Release(path)).ok();
+                                    }
                                     // check if there are any pending
-                                    if let Some(import_topology) = self.pending_imports.pop() {
-                                        self.handle_import(import_topology).await;
+                                    if let Some((job_id, import_topology)) = self.pending_imports.pop() {
+                                        self.handle_import(job_id, import_topology).await;
                                     }
                                     // remove its handle from importer_handles
                                     self.importer_handles.remove(&service.get_name());
@@ -167,6 +325,12 @@ impl<H: ChronicleBrokerScope> EventLoop<H> for ChronicleBroker<H> {
                                 }
                                 self.service.update_microservice(service.get_name(), service.clone());
                             }
+                            BrokerChild::Uploader(service, _uploader_status) => {
+                                if service.is_stopped() {
+                                    self.uploader_handle.take();
+                                }
+                                self.service.update_microservice(service.get_name(), service.clone());
+                            }
                             BrokerChild::Archiver(service, archiver_status) => {
                                 // Handle abort
                                 if let Err(Need::Abort) = archiver_status {
@@ -233,6 +397,14 @@ impl<H: ChronicleBrokerScope> EventLoop<H> for ChronicleBroker<H> {
                                                         new_mqtt.start_after(restart_after, self.handle.clone()),
                                                     );
                                                 }
+                                                Topics::LatestMilestone => {
+                                                    let new_mqtt = self
+                                                        .add_mqtt(LatestMilestone, MqttType::LatestMilestone, url)
+                                                        .unwrap();
+                                                    tokio::spawn(
+                                                        new_mqtt.start_after(restart_after, self.handle.clone()),
+                                                    );
+                                                }
                                             }
                                         } else if asked_to_shutdown && service.is_stopped() {
                                             self.service.delete_microservice(&microservice_name);
@@ -241,6 +413,26 @@ impl<H: ChronicleBrokerScope> EventLoop<H> for ChronicleBroker<H> {
                                         }
                                     }
                                 }
+                                if service.is_running() || service.is_stopped() {
+                                    self.check_mqtt_fallback();
+                                }
+                            }
+                            BrokerChild::Replayer(service, _replayer_status) => {
+                                if service.is_stopped() {
+                                    self.service.delete_microservice(&service.get_name());
+                                    self.replayer_handles.remove(&service.get_name());
+                                } else {
+                                    self.service.update_microservice(service.get_name(), service.clone());
+                                }
+                            }
+                            BrokerChild::Poller(service, _poller_status) => {
+                                if service.is_stopped() {
+                                    self.service.delete_microservice(&service.get_name());
+                                    self.poller_handles.remove(&service.get_name());
+                                    self.fallback_pollers.remove(&service.get_name());
+                                } else {
+                                    self.service.update_microservice(service.get_name(), service.clone());
+                                }
                             }
                             BrokerChild::Websocket(microservice, opt_ws_tx) => {
                                 is_not_websocket_child = false;
@@ -309,12 +501,17 @@ impl<H: ChronicleBrokerScope> ChronicleBroker<H> {
     }
     pub(crate) fn add_mqtt<T: Topic>(&mut self, topic: T, mqtt_type: MqttType, url: Url) -> Option<Mqtt<T>> {
         let config = get_config();
-        let mqtt = MqttBuilder::new()
+        let mut mqtt_builder = MqttBuilder::new()
             .collectors_handles(self.collector_handles.clone())
             .topic(topic)
             .url(url.clone())
             .stream_capacity(config.broker_config.mqtt_stream_capacity)
-            .build();
+            .qos(config.broker_config.mqtt_qos(mqtt_type))
+            .connection(config.broker_config.mqtt_connection(&url));
+        if let Some(feed_recorder) = self.feed_recorder.clone() {
+            mqtt_builder = mqtt_builder.recorder(feed_recorder);
+        }
+        let mqtt = mqtt_builder.build();
         let microservice = mqtt.clone_service();
         let microservice_name = microservice.get_name();
         if let None = self.service.microservices.get(&microservice_name) {
@@ -337,38 +534,290 @@ impl<H: ChronicleBrokerScope> ChronicleBroker<H> {
             None
         }
     }
-    async fn handle_import(&mut self, import_topology: BrokerTopology) {
+    /// Compile and swap in the wasm filter module, either from the given path or the configured one
+    #[cfg(feature = "filter")]
+    pub(crate) fn reload_filter(&mut self, path: Option<PathBuf>) -> anyhow::Result<()> {
+        let config = get_config();
+        let mut wasm_filter = config.broker_config.wasm_filter.clone().unwrap_or_default();
+        if let Some(path) = path {
+            wasm_filter.module_path = path
+                .to_str()
+                .ok_or_else(|| anyhow!("Unable to convert path to string"))?
+                .to_owned();
+        } else if config.broker_config.wasm_filter.is_none() {
+            bail!("No wasm filter module configured");
+        }
+        let limits = chronicle_filter::wasm::WasmLimits {
+            fuel_per_call: wasm_filter.fuel_per_call,
+            max_memory_bytes: wasm_filter.max_memory_bytes,
+        };
+        chronicle_filter::wasm::load_wasm_filter(&wasm_filter.module_path, limits)?;
+        info!("Loaded wasm filter: {}", wasm_filter.module_path);
+        let mut new_config = config.clone();
+        new_config.broker_config.wasm_filter = Some(wasm_filter);
+        if new_config != config {
+            get_history_mut().update(new_config.into());
+        }
+        Ok(())
+    }
+    #[cfg(not(feature = "filter"))]
+    pub(crate) fn reload_filter(&mut self, _path: Option<PathBuf>) -> anyhow::Result<()> {
+        bail!("Chronicle broker was built without the filter feature")
+    }
+    /// Verify that the imported messages can be re-routed through the loaded filter into the target keyspace
+    #[cfg(feature = "filter")]
+    fn verify_import_filter(filter: &ImportFilter) -> anyhow::Result<()> {
+        ensure!(
+            chronicle_filter::wasm::current_filter().is_some(),
+            "No wasm filter loaded to re-route the imported messages"
+        );
+        ensure!(
+            get_config()
+                .storage_config
+                .keyspaces
+                .iter()
+                .any(|keyspace| keyspace.name == filter.keyspace),
+            "The target keyspace {} isn't configured",
+            filter.keyspace
+        );
+        Ok(())
+    }
+    #[cfg(not(feature = "filter"))]
+    fn verify_import_filter(_filter: &ImportFilter) -> anyhow::Result<()> {
+        bail!("Chronicle broker was built without the filter feature")
+    }
+    /// Add the api endpoint to the requesters once it's verified, and report the outcome to the sockets.
+    /// The credentials only go to the requesters, so they never reach the sockets
+    async fn handle_add_endpoint(&mut self, url: Url, auth: Option<ApiConnectionConfig>) {
+        let reply = |url| BrokerTopology::Requesters(RequesterTopology::AddEndpoint(url));
+        let result = match self.add_endpoint(url.clone(), auth.as_ref()).await {
+            Ok(url) => {
+                let requester_topology = match auth {
+                    Some(auth) => RequesterTopology::AddAuthenticatedEndpoint { url: url.clone(), auth },
+                    None => RequesterTopology::AddEndpoint(url.clone()),
+                };
+                self.collector_handles.values().for_each(|h| {
+                    h.send_requester_topology(requester_topology.clone());
+                });
+                Ok(reply(url))
+            }
+            Err(e) => {
+                error!("{}", e);
+                Err(reply(url))
+            }
+        };
+        let socket_msg = BrokerSocketMsg::ChronicleBroker(result);
+        self.response_to_sockets::<Result<BrokerTopology, BrokerTopology>>(&socket_msg)
+            .await;
+    }
+    /// Verify the api endpoint with the given connection settings, or its configured ones
+    pub(crate) async fn add_endpoint(&mut self, url: Url, auth: Option<&ApiConnectionConfig>) -> anyhow::Result<Url> {
+        let url = BrokerConfig::adjust_api_endpoint(url.clone()).ok_or_else(|| anyhow!("Invalid endpoint: {}", url))?;
+        let config = get_config();
+        let reqwest_client = reqwest::Client::new();
+        let api_client = match auth {
+            Some(auth) => auth.api_client(
+                &reqwest_client,
+                Duration::from_secs(config.broker_config.request_timeout_secs),
+            )?,
+            None => config.broker_config.api_client(&url, &reqwest_client)?,
+        };
+        BrokerConfig::verify_endpoint(&api_client, &url).await?;
+        Ok(url)
+    }
+    /// Change the backfill strategy and priority ranges of the syncer, and keep them for the next runs
+    pub(crate) async fn change_backfill(&mut self, backfill: BackfillConfig) -> anyhow::Result<()> {
+        backfill.verify()?;
+        let syncer_handle = self
+            .syncer_handle
+            .as_ref()
+            .ok_or_else(|| anyhow!("The syncer is not running"))?;
+        syncer_handle
+            .send_async(SyncerEvent::Backfill(backfill.clone()))
+            .await
+            .map_err(|_| anyhow!("The syncer is shutting down"))?;
+        let config = get_config();
+        let mut new_config = config.clone();
+        new_config.broker_config.backfill = backfill;
+        if new_config != config {
+            get_history_mut().update(new_config.into());
+        }
+        Ok(())
+    }
+    /// Change the write rate limits shared by the running and future importers
+    pub(crate) fn change_import_throttle(&mut self, import_throttle: ImportThrottleConfig) -> anyhow::Result<()> {
+        import_throttle.verify()?;
+        self.import_throttle.configure(import_throttle.clone());
+        let config = get_config();
+        let mut new_config = config.clone();
+        new_config.broker_config.import_throttle = import_throttle;
+        if new_config != config {
+            get_history_mut().update(new_config.into());
+        }
+        Ok(())
+    }
+    /// Start polling the api endpoints while no MQTT feed is running, and stop once one is back
+    pub(crate) fn check_mqtt_fallback(&mut self) {
+        let config = get_config();
+        if !config.broker_config.polling_feed.fallback || self.service.is_stopping() {
+            return ();
+        }
+        let messages_prefix = format!("{}@", Messages::name());
+        let messages_referenced_prefix = format!("{}@", MessagesReferenced::name());
+        let mqtt_running = self.service.microservices.iter().any(|(name, service)| {
+            (name.starts_with(&messages_prefix) || name.starts_with(&messages_referenced_prefix))
+                && service.is_running()
+        });
+        if mqtt_running {
+            for poller_name in self.fallback_pollers.drain() {
+                if let Some(poller_handle) = self.poller_handles.remove(&poller_name) {
+                    info!("Mqtt feed is back, shutting down fallback Poller: {}", poller_name);
+                    poller_handle.shutdown();
+                }
+            }
+        } else if self.fallback_pollers.is_empty() {
+            warn!("All Mqtt feeds are down, falling back to polling the api endpoints");
+            for url in config.broker_config.api_endpoints.iter() {
+                if let Some(poller_name) = self.add_poller(url.clone()) {
+                    self.fallback_pollers.insert(poller_name);
+                }
+            }
+        }
+    }
+    /// Build and spawn a poller for the given api endpoint, returning its name if it didn't exist yet
+    pub(crate) fn add_poller(&mut self, url: Url) -> Option<String> {
+        let config = get_config();
+        let reqwest_client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.broker_config.request_timeout_secs))
+            .build()
+            .expect("Expected reqwest client to build correctly");
+        let api_client = match config.broker_config.api_client(&url, &reqwest_client) {
+            Ok(api_client) => api_client,
+            Err(e) => {
+                error!("Unable to build the client of Poller: {}, error: {}", url, e);
+                return None;
+            }
+        };
+        let poller = PollerBuilder::new()
+            .url(url)
+            .api_client(api_client)
+            .poll_interval(Duration::from_millis(config.broker_config.polling_feed.interval_ms))
+            .collectors_handles(self.collector_handles.clone())
+            .build();
+        let microservice = poller.clone_service();
+        let microservice_name = microservice.get_name();
+        if let None = self.service.microservices.get(&microservice_name) {
+            self.service
+                .update_microservice(microservice_name.clone(), microservice);
+            let (abort_handle, abort_registration) = AbortHandle::new_pair();
+            self.poller_handles
+                .insert(microservice_name.clone(), PollerHandle::new(abort_handle));
+            tokio::spawn(poller.start_abortable(abort_registration, self.handle.clone()));
+            Some(microservice_name)
+        } else {
+            None
+        }
+    }
+    /// Build and spawn a replayer pushing the recording at the given path through the collectors
+    pub(crate) fn add_replayer(&mut self, path: PathBuf, speed: Option<f64>) -> anyhow::Result<()> {
+        ensure!(path.exists(), "No feed recording found at {:?}", path);
+        let mut replayer_builder = ReplayerBuilder::new()
+            .path(path)
+            .collectors_handles(self.collector_handles.clone());
+        if let Some(speed) = speed {
+            replayer_builder = replayer_builder.speed(speed);
+        }
+        let replayer = replayer_builder.build();
+        let microservice = replayer.clone_service();
+        let microservice_name = microservice.get_name();
+        ensure!(
+            self.service.microservices.get(&microservice_name).is_none(),
+            "The Replayer: {}, is already replaying",
+            microservice_name
+        );
+        self.service
+            .update_microservice(microservice_name.clone(), microservice);
+        let (abort_handle, abort_registration) = AbortHandle::new_pair();
+        self.replayer_handles
+            .insert(microservice_name, ReplayerHandle::new(abort_handle));
+        tokio::spawn(replayer.start_abortable(abort_registration, self.handle.clone()));
+        Ok(())
+    }
+    async fn handle_import(&mut self, job_id: u64, import_topology: BrokerTopology) {
         if let BrokerTopology::Import {
             ref path,
             resume,
             ref import_range,
             import_type,
+            ref filter,
         } = import_topology
         {
             // don't do anything if the service is shutting down
             if self.service.is_stopping() {
                 return ();
             }
+            if let Some(Err(e)) = filter.as_ref().map(Self::verify_import_filter) {
+                self.import_jobs.fail(job_id);
+                let event = ImporterSession::PathError {
+                    path: path.clone(),
+                    msg: e.to_string(),
+                };
+                let socket_msg = BrokerSocketMsg::ChronicleBroker(event);
+                self.response_to_sockets(&socket_msg).await;
+                return ();
+            }
+            // remote sources are downloaded first, then imported from the local copy
+            if let Some(url) = path.to_str().filter(|path| path.starts_with("s3://")) {
+                match Url::parse(url) {
+                    Ok(url) => self.spawn_fetch(job_id, url, resume, import_range.clone(), import_type, filter.clone()),
+                    Err(e) => {
+                        self.import_jobs.fail(job_id);
+                        let event = ImporterSession::PathError {
+                            path: path.clone(),
+                            msg: format!("Invalid url: {}", e),
+                        };
+                        let socket_msg = BrokerSocketMsg::ChronicleBroker(event);
+                        self.response_to_sockets(&socket_msg).await;
+                    }
+                }
+                return ();
+            }
             // check if we have enough parallelism points
             if self.parallelism_points == 0 {
                 // add it to pending list
-                self.pending_imports.push(import_topology);
+                self.pending_imports.push((job_id, import_topology));
                 return ();
             }
-            if path.is_file() {
+            // http(s) logs and the logs streamed over the websockets are imported without being downloaded first
+            if path.is_file() || is_http(path) || is_stream(path) {
+                // skip the log file if the job imported it already
+                if self.import_jobs.get(job_id).map_or(false, |job| job.is_imported(path)) {
+                    self.import_jobs.refresh(job_id);
+                    return ();
+                }
                 // build importer
                 self.spawn_importer(
+                    job_id,
                     path.clone(),
                     resume,
                     import_range.clone(),
                     import_type,
+                    filter.clone(),
                     self.parallelism_points,
                 )
                 .await;
             } else if path.is_dir() {
-                self.spawn_importers(path.clone(), resume, import_range.clone(), import_type)
-                    .await;
+                self.spawn_importers(
+                    job_id,
+                    path.clone(),
+                    resume,
+                    import_range.clone(),
+                    import_type,
+                    filter.clone(),
+                )
+                .await;
             } else {
+                self.import_jobs.fail(job_id);
                 let event = ImporterSession::PathError {
                     path: path.clone(),
                     msg: "Invalid path".into(),
@@ -378,8 +827,79 @@ impl<H: ChronicleBrokerScope> ChronicleBroker<H> {
             }
         }
     }
+    fn spawn_fetch(
+        &mut self,
+        job_id: u64,
+        url: Url,
+        resume: bool,
+        import_range: Option<Range<u32>>,
+        import_type: ImportType,
+        filter: Option<ImportFilter>,
+    ) {
+        let handle = self.handle.clone().expect("Expected broker handle");
+        let config = get_config().broker_config.archive_upload.unwrap_or_default();
+        let dir = self.fetched_dir();
+        self.in_progress_fetches += 1;
+        tokio::spawn(async move {
+            let fetched = match download(&url, &config, &dir).await {
+                Ok(path) => Ok(BrokerTopology::Import {
+                    path,
+                    resume,
+                    import_range,
+                    import_type,
+                    filter,
+                }),
+                Err(e) => Err(ImporterSession::PathError {
+                    path: PathBuf::from(url.as_str()),
+                    msg: e.to_string(),
+                }),
+            };
+            handle.send(BrokerEvent::Fetched(job_id, fetched)).ok();
+        });
+    }
+    /// The directory the remote sources of the import jobs are downloaded to
+    fn fetched_dir(&self) -> PathBuf {
+        self.logs_dir_path
+            .clone()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("fetched")
+    }
+    /// Remove the downloaded copy of the remote source of the job, once the job completed
+    async fn remove_fetched(&mut self, job_id: u64) {
+        let path = match self.import_jobs.get(job_id) {
+            Some(job) if job.state() == ImportJobState::Completed && job.path().starts_with(self.fetched_dir()) => {
+                job.path().to_path_buf()
+            }
+            _ => return,
+        };
+        let removed = if path.is_dir() {
+            tokio::fs::remove_dir_all(&path).await
+        } else if path.is_file() {
+            // along with the manifest downloaded next to it
+            let manifest = manifest_path(&path);
+            if manifest.exists() {
+                tokio::fs::remove_file(&manifest).await.ok();
+            }
+            tokio::fs::remove_file(&path).await
+        } else {
+            return;
+        };
+        match removed {
+            Ok(()) => info!(
+                "Removed {} fetched by the completed import job {}",
+                path.display(),
+                job_id
+            ),
+            Err(e) => warn!(
+                "Unable to remove {} fetched by import job {}: {}",
+                path.display(),
+                job_id,
+                e
+            ),
+        }
+    }
     async fn try_close_importer_session(&mut self) {
-        if self.in_progress_importers == 0 {
+        if self.in_progress_importers == 0 && self.in_progress_fetches == 0 {
             let event = ImporterSession::Close;
             let socket_msg = BrokerSocketMsg::ChronicleBroker(event);
             self.response_to_sockets(&socket_msg).await;
@@ -387,22 +907,44 @@ impl<H: ChronicleBrokerScope> ChronicleBroker<H> {
     }
     fn build_and_start_importer<T: ImportMode>(
         &mut self,
+        job_id: u64,
         file_path: PathBuf,
         resume: bool,
         import_range: Option<Range<u32>>,
+        filter: Option<ImportFilter>,
         parallelism: u8,
     ) {
         let mut importer_builder = ImporterBuilder::<T>::new();
         if let Some(import_range) = import_range {
             importer_builder = importer_builder.import_range(import_range);
         };
+        if let Some(filter) = filter {
+            importer_builder = importer_builder.filter(filter);
+        };
+        let (checkpoint, position) = self
+            .import_jobs
+            .get(job_id)
+            .map(|job| (job.checkpoint(&file_path), job.position(&file_path)))
+            .unwrap_or_default();
+        if let Some(reader) = stream_id(&file_path).and_then(|id| self.streamed_logs.remove(id)) {
+            importer_builder = importer_builder.stream(reader);
+        }
         let importer = importer_builder
+            .job_id(job_id)
+            .checkpoint(checkpoint)
+            .position(position)
             .file_path(file_path)
             .resume(resume)
             .parallelism(parallelism)
+            .throttle(self.import_throttle.clone())
             .retries_per_query(50) // TODO get it from config
             .chronicle_id(0) // TODO get it from config
             .build();
+        if let Some(uploader_handle) = self.uploader_handle.as_ref() {
+            uploader_handle
+                .send(UploaderEvent::Hold(importer.get_name().into()))
+                .ok();
+        }
         let handle = importer.clone_handle().expect("Expected existing importer handle");
         self.importer_handles.insert(importer.get_name(), handle);
         let service = Service::new();
@@ -411,10 +953,12 @@ impl<H: ChronicleBrokerScope> ChronicleBroker<H> {
     }
     async fn spawn_importer(
         &mut self,
+        job_id: u64,
         file_path: PathBuf,
         resume: bool,
         import_range: Option<Range<u32>>,
         import_type: ImportType,
+        filter: Option<ImportFilter>,
         parallelism: u8,
     ) {
         // don't do anything if the service is shutting down
@@ -427,12 +971,27 @@ impl<H: ChronicleBrokerScope> ChronicleBroker<H> {
             }
             match import_type {
                 ImportType::All => {
-                    self.build_and_start_importer::<All>(file_path, resume, import_range, parallelism);
+                    self.build_and_start_importer::<All>(
+                        job_id,
+                        file_path.clone(),
+                        resume,
+                        import_range,
+                        filter,
+                        parallelism,
+                    );
                 }
                 ImportType::Analytics => {
-                    self.build_and_start_importer::<Analytics>(file_path, resume, import_range, parallelism);
+                    self.build_and_start_importer::<Analytics>(
+                        job_id,
+                        file_path.clone(),
+                        resume,
+                        import_range,
+                        filter,
+                        parallelism,
+                    );
                 }
             }
+            self.import_jobs.set_log_state(job_id, &file_path, JobLogState::Running);
             self.in_progress_importers += 1;
             self.parallelism_points -= parallelism;
         } else {
@@ -447,22 +1006,44 @@ impl<H: ChronicleBrokerScope> ChronicleBroker<H> {
     }
     async fn spawn_importers(
         &mut self,
+        job_id: u64,
         path: PathBuf,
         resume: bool,
         import_range: Option<Range<u32>>,
         import_type: ImportType,
+        filter: Option<ImportFilter>,
     ) {
         let mut import_files = Vec::new();
         if let Ok(mut dir_entry) = tokio::fs::read_dir(&path).await {
             while let Ok(Some(p)) = dir_entry.next_entry().await {
                 let file_path = p.path();
-                if file_path.is_file() {
+                if file_path.is_file() && is_finished_log(&file_path) {
                     import_files.push(file_path);
                 }
             }
         };
-        let import_files_len = import_files.len();
-        if import_files_len == 0 {
+        // a log converted to the binary format is only imported from its binary file
+        let is_binary = |file_path: &PathBuf| {
+            file_path
+                .extension()
+                .map_or(false, |ext| ext == binary::BINARY_LOG_EXTENSION)
+        };
+        let stem = |file_path: &PathBuf| {
+            file_path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.split('.').next())
+                .map(ToOwned::to_owned)
+        };
+        let binary_logs = import_files
+            .iter()
+            .filter(|file_path| is_binary(file_path))
+            .filter_map(stem)
+            .collect::<std::collections::HashSet<_>>();
+        import_files
+            .retain(|file_path| is_binary(file_path) || !stem(file_path).map_or(false, |s| binary_logs.contains(&s)));
+        if import_files.is_empty() {
+            self.import_jobs.fail(job_id);
             let event = ImporterSession::PathError {
                 path,
                 msg: "No LogFiles in the provided path".into(),
@@ -471,20 +1052,42 @@ impl<H: ChronicleBrokerScope> ChronicleBroker<H> {
             self.response_to_sockets(&socket_msg).await;
             return ();
         }
+        // skip the log files the job imported already, and track the remaining ones
+        if let Some(job) = self.import_jobs.get(job_id) {
+            import_files.retain(|file_path| !job.is_imported(file_path));
+        }
+        for file_path in import_files.iter() {
+            self.import_jobs.set_log_state(job_id, file_path, JobLogState::Pending);
+        }
+        let import_files_len = import_files.len();
+        if import_files_len == 0 {
+            self.import_jobs.refresh(job_id);
+            return ();
+        }
         if self.parallelism_points as usize > import_files_len {
             let parallelism = (self.parallelism_points as usize / import_files_len) as u8;
             for file_path in import_files {
-                self.spawn_importer(file_path, resume, import_range.clone(), import_type, parallelism)
-                    .await
+                self.spawn_importer(
+                    job_id,
+                    file_path,
+                    resume,
+                    import_range.clone(),
+                    import_type,
+                    filter.clone(),
+                    parallelism,
+                )
+                .await
             }
         } else {
             // unwrap is safe
             let file_path = import_files.pop().expect("Expected import file");
             self.spawn_importer(
+                job_id,
                 file_path,
                 resume,
                 import_range.clone(),
                 import_type,
+                filter.clone(),
                 self.parallelism_points,
             )
             .await;
@@ -495,10 +1098,83 @@ impl<H: ChronicleBrokerScope> ChronicleBroker<H> {
                     resume,
                     import_range: import_range.clone(),
                     import_type,
+                    filter: filter.clone(),
                 };
-                self.pending_imports.push(topology);
+                self.pending_imports.push((job_id, topology));
+            }
+        }
+    }
+    /// Pause, resume or cancel the import job
+    async fn handle_import_job_command(&mut self, command: ImportJobCommand) -> anyhow::Result<ImportJobReport> {
+        let (job_id, state) = match command {
+            ImportJobCommand::List => bail!("Listing the import jobs doesn't change them"),
+            ImportJobCommand::Pause(job_id) => (job_id, ImportJobState::Paused),
+            ImportJobCommand::Resume(job_id) => (job_id, ImportJobState::Running),
+            ImportJobCommand::Cancel(job_id) => (job_id, ImportJobState::Cancelled),
+        };
+        let job = self
+            .import_jobs
+            .get(job_id)
+            .ok_or_else(|| anyhow!("No import job with id {}", job_id))?;
+        let current = job.state();
+        match state {
+            ImportJobState::Paused => ensure!(
+                current == ImportJobState::Running,
+                "Import job {} is {:?}, only running jobs can be paused",
+                job_id,
+                current
+            ),
+            ImportJobState::Running => {
+                ensure!(
+                    matches!(current, ImportJobState::Paused | ImportJobState::Failed),
+                    "Import job {} is {:?}, only paused or failed jobs can be resumed",
+                    job_id,
+                    current
+                );
+                ensure!(
+                    !job.is_stopping(),
+                    "The importers of job {} are still stopping, try again later",
+                    job_id
+                );
+                ensure!(!self.service.is_stopping(), "The broker is stopping");
             }
+            _ => ensure!(
+                !matches!(current, ImportJobState::Completed | ImportJobState::Cancelled),
+                "Import job {} is already {:?}",
+                job_id,
+                current
+            ),
         }
+        let topology = job.topology();
+        self.import_jobs.set_state(job_id, state);
+        if state == ImportJobState::Running {
+            info!("Resuming import job {}", job_id);
+            self.handle_import(job_id, topology).await;
+        } else {
+            info!("Import job {} is {:?}, stopping its importers", job_id, state);
+            self.pending_imports
+                .retain(|(pending_job_id, _)| *pending_job_id != job_id);
+            for path in self.import_jobs.running_logs(job_id) {
+                if let Some(importer_handle) = path.to_str().and_then(|path| self.importer_handles.get(path)) {
+                    importer_handle.send(ImporterEvent::Shutdown).ok();
+                }
+            }
+        }
+        Ok(self
+            .import_jobs
+            .get(job_id)
+            .map(ImportJob::report)
+            .expect("Expected import job"))
+    }
+    /// Continue the import jobs which were running when the broker stopped, from their checkpoints
+    pub(crate) async fn resume_import_jobs(&mut self) {
+        for job_id in self.import_jobs.running() {
+            if let Some(topology) = self.import_jobs.get(job_id).map(ImportJob::topology) {
+                info!("Resuming import job {}", job_id);
+                self.handle_import(job_id, topology).await;
+            }
+        }
+        self.import_jobs.save(true).await;
     }
     pub(crate) async fn response_to_sockets<T: Serialize>(&mut self, msg: &BrokerSocketMsg<T>) {
         for socket in self.websockets.values_mut() {
@@ -527,6 +1203,23 @@ impl<H: ChronicleBrokerScope> ChronicleBroker<H> {
                 info!("Shutting down Mqtt: {}", mqtt_name);
                 mqtt_handle.shutdown();
             }
+            // shutdown pollers
+            self.fallback_pollers.clear();
+            for (poller_name, poller_handle) in self.poller_handles.drain() {
+                info!("Shutting down Poller: {}", poller_name);
+                poller_handle.shutdown();
+            }
+            // shutdown replayers
+            for (replayer_name, replayer_handle) in self.replayer_handles.drain() {
+                info!("Shutting down Replayer: {}", replayer_name);
+                replayer_handle.shutdown();
+            }
+            // the recorder flushes and stops once the mqtts drop their handles
+            self.feed_recorder.take();
+            // stop serving the metrics
+            if let Some(monitor_handle) = self.monitor_handle.take() {
+                monitor_handle.stop();
+            }
             // shutdown collectors
             for (collector_name, collector_handle) in self.collector_handles.drain() {
                 info!("Shutting down Collector: {}", collector_name);
@@ -545,6 +1238,10 @@ impl<H: ChronicleBrokerScope> ChronicleBroker<H> {
             if let Some(syncer) = self.syncer_handle.take() {
                 syncer.shutdown();
             }
+            // the uploader stops once the archiver dropped its handle too, after uploading the last logs
+            if let Some(uploader) = self.uploader_handle.take() {
+                uploader.shutdown();
+            }
             // shutdown importers
             for (importer_name, importer_handle) in self.importer_handles.drain() {
                 info!("Shutting down importer: {}", importer_name);
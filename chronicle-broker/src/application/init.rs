@@ -10,13 +10,43 @@ impl<H: ChronicleBrokerScope> Init<H> for ChronicleBroker<H> {
         if let Some(ref mut supervisor) = supervisor {
             let config = get_config_async().await;
             supervisor.status_change(self.service.clone());
+            // Serve the metrics (if enabled) before anything else, so they are available while the broker starts
+            if let Some(metrics_address) = config.broker_config.metrics_address {
+                if self.monitor_handle.is_none() {
+                    match Monitor::spawn(metrics_address).await {
+                        Ok(monitor_handle) => {
+                            self.monitor_handle.replace(monitor_handle);
+                        }
+                        Err(e) => error!("Unable to serve the metrics on {}: {}", metrics_address, e),
+                    }
+                }
+            }
+            // Load the wasm filter module (if any) before any message gets collected
+            #[cfg(feature = "filter")]
+            if config.broker_config.wasm_filter.is_some() {
+                self.reload_filter(None).map_err(|e| {
+                    error!("{}", e);
+                    Need::Abort
+                })?;
+            }
             // Query sync table
             self.query_sync_table().await?;
+            // Recover the .part files left behind by a crash, the milestones lost with their tail get logged again
+            let mut recovered = Vec::new();
+            if let Some(dir_path) = self.logs_dir_path.as_ref() {
+                recovered = recover_part_files(dir_path, &self.sync_data).await.map_err(|e| {
+                    error!("Unable to recover the log files: {}", e);
+                    Need::Abort
+                })?;
+                for range in recovered.iter().flat_map(|RecoveredLog { lost, .. }| lost.iter()) {
+                    self.sync_data.mark_unlogged(range);
+                }
+            }
             info!("Current: {:#?}", self.sync_data);
             // Get the gap_start
             let gap_start = self.sync_data.gaps.first().unwrap().start;
             // create syncer_builder
-            let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+            let (tx, rx) = crate::inbox::channel("syncer", config.broker_config.inbox_capacity.syncer);
             let syncer_handle = SyncerHandle { tx };
             let syncer_inbox = SyncerInbox { rx };
             let (one, recv) = tokio::sync::oneshot::channel();
@@ -24,19 +54,53 @@ impl<H: ChronicleBrokerScope> Init<H> for ChronicleBroker<H> {
                 .sync_data(self.sync_data.clone())
                 .handle(syncer_handle.clone())
                 .first_ask(AskSyncer::FillGaps)
+                .backfill(config.broker_config.backfill.clone())
                 .oneshot(one)
                 .inbox(syncer_inbox);
             let archiver_handle;
             if let Some(dir_path) = self.logs_dir_path.as_ref() {
                 let max_log_size = config.broker_config.max_log_size.unwrap_or(MAX_LOG_SIZE);
                 // create archiver_builder
-                let mut archiver = ArchiverBuilder::new()
+                let mut archiver_builder = ArchiverBuilder::new()
                     .dir_path(dir_path.clone())
                     .keyspace(self.default_keyspace.clone())
                     .solidifiers_count(self.collector_count)
                     .max_log_size(max_log_size)
-                    .oneshot(recv)
-                    .build();
+                    .recovered(recovered)
+                    .oneshot(recv);
+                if let Some(compression) = config.broker_config.log_compression {
+                    archiver_builder = archiver_builder.compression(compression.into());
+                }
+                if let Some(signing_key_path) = config.broker_config.log_manifest.signing_key_path.as_ref() {
+                    match ManifestSigner::load(Path::new(signing_key_path)) {
+                        Ok(signer) => {
+                            info!("Signing the log manifests with the key {}", signer.public_key());
+                            archiver_builder = archiver_builder.signer(signer);
+                        }
+                        Err(e) => {
+                            error!("{}", e);
+                            return Err(Need::Abort);
+                        }
+                    }
+                }
+                if let Some(archive_upload) = config.broker_config.archive_upload.clone() {
+                    match bucket(&archive_upload, &archive_upload.bucket) {
+                        Ok(bucket) => {
+                            let mut uploader = UploaderBuilder::new()
+                                .config(archive_upload)
+                                .bucket(bucket)
+                                .logs_dir(dir_path.clone())
+                                .build();
+                            let uploader_handle = uploader.take_handle().expect("Expected uploader handle");
+                            archiver_builder = archiver_builder.uploader(uploader_handle.clone());
+                            self.uploader_handle.replace(uploader_handle);
+                            // start uploader
+                            tokio::spawn(uploader.start(self.handle.clone()));
+                        }
+                        Err(e) => error!("Unable to upload the finished logs: {}", e),
+                    }
+                }
+                let mut archiver = archiver_builder.build();
                 archiver_handle = archiver.take_handle();
                 syncer_builder = syncer_builder
                     .first_ask(AskSyncer::Complete)
@@ -63,7 +127,10 @@ impl<H: ChronicleBrokerScope> Init<H> for ChronicleBroker<H> {
                     requesters_channels.push((tx, rx));
                 }
                 // create collector_builder
-                let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+                let (tx, rx) = crate::inbox::channel(
+                    &format!("collector_{}", partition_id),
+                    config.broker_config.inbox_capacity.collector,
+                );
                 let collector_handle = CollectorHandle { tx, requesters_senders };
                 let collector_inbox = CollectorInbox { rx };
                 self.collector_handles.insert(partition_id, collector_handle.clone());
@@ -77,12 +144,16 @@ impl<H: ChronicleBrokerScope> Init<H> for ChronicleBroker<H> {
                     .reqwest_client(reqwest_client.clone())
                     .retries_per_query(config.broker_config.retries_per_query)
                     .retries_per_endpoint(config.broker_config.retries_per_endpoint)
+                    .endpoints_health(self.endpoints_health.clone())
                     .requesters_channels(requesters_channels)
                     .partition_id(partition_id);
 
                 collector_builders.push(collector_builder);
                 // create solidifier_builder
-                let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+                let (tx, rx) = crate::inbox::channel(
+                    &format!("solidifier_{}", partition_id),
+                    config.broker_config.inbox_capacity.solidifier,
+                );
                 let solidifier_handle = SolidifierHandle { tx };
                 let solidifier_inbox = SolidifierInbox { rx };
                 self.solidifier_handles.insert(partition_id, solidifier_handle.clone());
@@ -92,6 +163,9 @@ impl<H: ChronicleBrokerScope> Init<H> for ChronicleBroker<H> {
                 if let Some(archiver_handle) = archiver_handle.clone().take() {
                     solidifier_builder = solidifier_builder.archiver_handle(archiver_handle);
                 }
+                if let Some(milestone_keys) = config.storage_config.milestone_keys(self.default_keyspace.name()) {
+                    solidifier_builder = solidifier_builder.milestone_keys(milestone_keys);
+                }
                 solidifier_builder = solidifier_builder
                     .gap_start(gap_start)
                     .keyspace(self.default_keyspace.clone())
@@ -110,6 +184,16 @@ impl<H: ChronicleBrokerScope> Init<H> for ChronicleBroker<H> {
                 .update_sync_data_every(self.complete_gaps_interval)
                 .build();
             tokio::spawn(syncer.start(self.handle.clone()));
+            // Spawn the feed recorder (if any) before the mqtt brokers it records
+            if let Some(feed_recording) = config.broker_config.feed_recording.as_ref() {
+                if self.feed_recorder.is_none() {
+                    let feed_recorder = FeedRecorder::spawn(feed_recording).await.map_err(|e| {
+                        error!("Unable to start the feed recorder: {}", e);
+                        Need::Abort
+                    })?;
+                    self.feed_recorder.replace(feed_recorder);
+                }
+            }
             // Spawn mqtt brokers
             for broker_url in config
                 .broker_config
@@ -135,6 +219,18 @@ impl<H: ChronicleBrokerScope> Init<H> for ChronicleBroker<H> {
                     tokio::spawn(mqtt.start(self.handle.clone()));
                 }
             }
+            for broker_url in config
+                .broker_config
+                .mqtt_brokers
+                .get(&MqttType::LatestMilestone)
+                .iter()
+                .flat_map(|v| v.iter())
+                .cloned()
+            {
+                if let Some(mqtt) = self.add_mqtt(LatestMilestone, MqttType::LatestMilestone, broker_url) {
+                    tokio::spawn(mqtt.start(self.handle.clone()));
+                }
+            }
             // we finalize them
             for collector_builder in collector_builders {
                 let collector = collector_builder
@@ -142,12 +238,31 @@ impl<H: ChronicleBrokerScope> Init<H> for ChronicleBroker<H> {
                     .build();
                 tokio::spawn(collector.start(self.handle.clone()));
             }
+            // Spawn the pollers selected as feed source
+            for url in config.broker_config.polling_feed.endpoints.iter().cloned() {
+                self.add_poller(url);
+            }
+            // Fallback to polling right away if there are no Mqtt feeds at all
+            let no_mqtt_feeds = [MqttType::Messages, MqttType::MessagesReferenced]
+                .iter()
+                .all(|mqtt_type| {
+                    config
+                        .broker_config
+                        .mqtt_brokers
+                        .get(mqtt_type)
+                        .map_or(true, |v| v.is_empty())
+                });
+            if no_mqtt_feeds {
+                self.check_mqtt_fallback();
+            }
             for solidifier_builder in solidifier_builders {
                 let solidifier = solidifier_builder
                     .collector_handles(self.collector_handles.clone())
                     .build();
                 tokio::spawn(solidifier.start(self.handle.clone()));
             }
+            // Continue the import jobs interrupted by the last shutdown
+            self.resume_import_jobs().await;
             status
         } else {
             Err(Need::Abort)
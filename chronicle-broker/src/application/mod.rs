@@ -6,17 +6,43 @@ use crate::{
     collector::*,
     importer::*,
     listener::*,
+    manifest::{
+        manifest_path,
+        ManifestSigner,
+    },
+    monitor::*,
     mqtt::*,
+    poller::*,
+    recording::*,
+    remote::{
+        is_http,
+        is_stream,
+        stream_id,
+        RemoteLogReader,
+    },
+    replayer::*,
+    requester::EndpointsHealth,
     solidifier::*,
     syncer::*,
+    uploader::*,
     websocket::*,
 };
 use async_trait::async_trait;
-use chronicle_common::config::BrokerConfig;
+use chronicle_common::config::{
+    ApiConnectionConfig,
+    BackfillConfig,
+    BrokerConfig,
+    ImportThrottleConfig,
+};
 use std::{
+    collections::HashSet,
     ops::Range,
+    path::Path,
     str::FromStr,
-    time::Duration,
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
 mod event_loop;
@@ -59,16 +85,25 @@ pub struct ChronicleBroker<H: ChronicleBrokerScope> {
     websockets: HashMap<String, WsTx>,
     listener_handle: Option<ListenerHandle>,
     mqtt_handles: HashMap<String, MqttHandle>,
+    poller_handles: HashMap<String, PollerHandle>,
+    fallback_pollers: HashSet<String>,
+    replayer_handles: HashMap<String, ReplayerHandle>,
+    feed_recorder: Option<FeedRecorderHandle>,
+    monitor_handle: Option<MonitorHandle>,
     importer_handles: HashMap<String, ImporterHandle>,
     asked_to_shutdown: HashMap<String, ()>,
     parallelism: u8,
     complete_gaps_interval: Duration,
     parallelism_points: u8,
-    pending_imports: Vec<BrokerTopology>,
+    pending_imports: Vec<(u64, BrokerTopology)>,
+    import_jobs: ImportJobs,
+    import_throttle: ImportThrottle,
     in_progress_importers: usize,
+    in_progress_fetches: usize,
     collector_count: u8,
     collector_handles: HashMap<u8, CollectorHandle>,
     solidifier_handles: HashMap<u8, SolidifierHandle>,
+    endpoints_health: EndpointsHealth,
     logs_dir_path: Option<PathBuf>,
     handle: Option<BrokerHandle<H>>,
     inbox: BrokerInbox<H>,
@@ -76,6 +111,10 @@ pub struct ChronicleBroker<H: ChronicleBrokerScope> {
     sync_range: SyncRange,
     sync_data: SyncData,
     syncer_handle: Option<SyncerHandle>,
+    uploader_handle: Option<UploaderHandle>,
+    /// The logs streamed over the websockets, until their import starts
+    streamed_logs: HashMap<String, RemoteLogReader>,
+    network_tip: Option<NetworkTip>,
 }
 
 /// SubEvent type, indicates the children
@@ -84,6 +123,10 @@ pub enum BrokerChild {
     Listener(Service),
     /// Used by Mqtt to keep Broker up to date with its service
     Mqtt(Service, Option<MqttHandle>, Result<(), Need>),
+    /// Used by Poller to keep Broker up to date with its service
+    Poller(Service, Result<(), Need>),
+    /// Used by Replayer to keep Broker up to date with its service
+    Replayer(Service, Result<(), Need>),
     /// Used by Collector(s) to keep Broker up to date with its service
     Collector(Service),
     /// Used by Solidifier(s) to keep Broker up to date with its service
@@ -92,6 +135,8 @@ pub enum BrokerChild {
     Archiver(Service, Result<(), Need>),
     /// Used by Syncer to keep Broker up to date with its service
     Syncer(Service, Result<(), Need>),
+    /// Used by Uploader to keep Broker up to date with its service
+    Uploader(Service, Result<(), Need>),
     /// Used by Importer to keep Broker up to date with its service, u8 is parallelism
     Importer(Service, Result<(), Need>, u8),
     /// Used by Websocket to keep Broker up to date with its service
@@ -102,12 +147,31 @@ pub enum BrokerChild {
 pub enum BrokerEvent<T> {
     /// Importer Session
     Importer(ImporterSession),
+    /// Syncer progress session
+    Syncer(SyncerSession),
     /// It's the passthrough event, which the scylla application will receive from
     Passthrough(T),
     /// Used by broker children to push their service
     Children(BrokerChild),
     /// Used by Scylla to keep Broker up to date with scylla status
     Scylla(Service),
+    /// Used by Mqtt to keep Broker up to date with the latest milestone of the network
+    NetworkTip(NetworkTip),
+    /// Used by the fetch tasks to import the logs they downloaded for the import job, or to report their failure
+    Fetched(u64, Result<BrokerTopology, ImporterSession>),
+    /// Used by the websockets to hand over the log their client streams under the given id, ie from its stdin
+    StreamedLog(String, RemoteLogReader),
+    /// Used by the importers to checkpoint the milestones they imported for their job
+    ImportCheckpoint {
+        /// The import job
+        job_id: u64,
+        /// The imported log file
+        path: PathBuf,
+        /// The imported milestone
+        milestone_index: u32,
+        /// The log bytes of the milestone
+        bytes: u64,
+    },
 }
 
 /// implementation of the AppBuilder
@@ -150,28 +214,41 @@ impl<H: ChronicleBrokerScope> Builder for ChronicleBrokerBuilder<H> {
         } else {
             logs_dir_path = None;
         }
+        let import_jobs = ImportJobs::load(logs_dir_path.as_ref());
         let parallelism = self.parallelism.unwrap_or(25);
         ChronicleBroker::<H> {
             service: Service::new(),
             websockets: HashMap::new(),
             listener_handle: self.listener_handle,
             mqtt_handles: HashMap::new(),
+            poller_handles: HashMap::new(),
+            fallback_pollers: HashSet::new(),
+            replayer_handles: HashMap::new(),
+            feed_recorder: None,
+            monitor_handle: None,
             importer_handles: HashMap::new(),
             asked_to_shutdown: HashMap::new(),
             collector_count: self.collector_count.unwrap_or(10),
             collector_handles: HashMap::new(),
             solidifier_handles: HashMap::new(),
+            endpoints_health: EndpointsHealth::new(config.broker_config.endpoint_health.clone()),
             syncer_handle: None,
+            uploader_handle: None,
+            streamed_logs: HashMap::new(),
             parallelism,
             parallelism_points: parallelism,
             pending_imports: Vec::new(),
+            import_jobs,
+            import_throttle: ImportThrottle::new(config.broker_config.import_throttle.clone()),
             in_progress_importers: 0,
+            in_progress_fetches: 0,
             logs_dir_path,
             handle,
             inbox,
             default_keyspace,
             sync_range,
             sync_data,
+            network_tip: None,
             complete_gaps_interval: Duration::from_secs(self.complete_gaps_interval_secs.unwrap()),
         }
         .set_name()
@@ -0,0 +1,550 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Binary archive format, which unlike the json lines logs can be read starting from any milestone.
+//!
+//! A `<from>to<to>.bin` file is laid out as follows:
+//! - the `CHRBIN01` magic
+//! - one record per milestone, ie its u32 little endian length followed by the bincode encoded milestone data, whose
+//!   milestone payload and messages are packed
+//! - the bincode encoded index, which maps every milestone index to the offset of its record
+//! - the u64 little endian offset of the index, followed by the `CHRIDX01` magic
+
+use super::*;
+use crate::compression::LogReader;
+use bee_message::{
+    prelude::MilestonePayload,
+    MESSAGE_ID_LENGTH,
+};
+use bincode::Options;
+use std::{
+    io::{
+        Read,
+        Seek,
+        SeekFrom,
+    },
+    path::Path,
+};
+use tokio::io::{
+    AsyncReadExt,
+    AsyncSeekExt,
+    BufReader,
+    BufWriter,
+};
+
+/// The extension of the binary archive files
+pub const BINARY_LOG_EXTENSION: &str = "bin";
+const HEADER_MAGIC: &[u8; 8] = b"CHRBIN01";
+const FOOTER_MAGIC: &[u8; 8] = b"CHRIDX01";
+/// The index offset followed by the footer magic
+const FOOTER_SIZE: u64 = 16;
+/// The length prefix of a record
+const RECORD_LEN_SIZE: u64 = 4;
+/// The bincode encoded length of an index entry
+const INDEX_ENTRY_SIZE: u64 = 12;
+
+/// The options of `bincode::deserialize`, which refuse to decode more than the given bytes
+fn bincode_options(limit: u64) -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(limit)
+}
+
+#[derive(Serialize, Deserialize)]
+struct BinaryMilestone {
+    milestone_index: u32,
+    /// The packed milestone payload
+    milestone: Option<Vec<u8>>,
+    messages: Vec<BinaryMessage>,
+    pending: Vec<[u8; MESSAGE_ID_LENGTH]>,
+    created_by: CreatedBy,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BinaryMessage {
+    /// The packed message
+    message: Vec<u8>,
+    metadata: MessageMetadata,
+}
+
+impl From<&MilestoneData> for BinaryMilestone {
+    fn from(milestone_data: &MilestoneData) -> Self {
+        Self {
+            milestone_index: milestone_data.milestone_index,
+            milestone: milestone_data.milestone.as_ref().map(|payload| payload.pack_new()),
+            messages: milestone_data
+                .messages
+                .values()
+                .map(|FullMessage(message, metadata)| BinaryMessage {
+                    message: message.pack_new(),
+                    metadata: metadata.clone(),
+                })
+                .collect(),
+            pending: milestone_data
+                .pending
+                .keys()
+                .map(|message_id| {
+                    let mut bytes = [0; MESSAGE_ID_LENGTH];
+                    bytes.copy_from_slice(message_id.as_ref());
+                    bytes
+                })
+                .collect(),
+            created_by: milestone_data.created_by,
+        }
+    }
+}
+
+impl TryFrom<BinaryMilestone> for MilestoneData {
+    type Error = anyhow::Error;
+    fn try_from(binary: BinaryMilestone) -> Result<Self, Self::Error> {
+        let mut milestone_data = MilestoneData::new(binary.milestone_index, binary.created_by);
+        if let Some(payload) = binary.milestone {
+            let payload = MilestonePayload::unpack(&mut payload.as_slice())
+                .map_err(|e| anyhow!("Unable to unpack milestone payload: {:?}", e))?;
+            milestone_data.milestone.replace(Box::new(payload));
+        }
+        for BinaryMessage { message, metadata } in binary.messages {
+            let message =
+                Message::unpack(&mut message.as_slice()).map_err(|e| anyhow!("Unable to unpack message: {:?}", e))?;
+            milestone_data
+                .messages
+                .insert(metadata.message_id, FullMessage(message, metadata));
+        }
+        for message_id in binary.pending {
+            milestone_data.pending.insert(MessageId::new(message_id), ());
+        }
+        Ok(milestone_data)
+    }
+}
+
+/// Writes milestone data to a binary archive file, in ascending milestone index order
+pub struct BinaryLogWriter {
+    file: BufWriter<File>,
+    index: Vec<(u32, u64)>,
+    offset: u64,
+}
+
+impl BinaryLogWriter {
+    /// Create the binary archive file at the given path
+    pub async fn create(path: &Path) -> anyhow::Result<Self> {
+        let mut file = BufWriter::new(File::create(path).await?);
+        file.write_all(HEADER_MAGIC).await?;
+        Ok(Self {
+            file,
+            index: Vec::new(),
+            offset: HEADER_MAGIC.len() as u64,
+        })
+    }
+    /// Append the milestone data record
+    pub async fn append(&mut self, milestone_data: &MilestoneData) -> anyhow::Result<()> {
+        let milestone_index = milestone_data.milestone_index();
+        if let Some((last, _)) = self.index.last() {
+            ensure!(
+                *last < milestone_index,
+                "Milestone data {} appended after {}",
+                milestone_index,
+                last
+            );
+        }
+        let record = bincode::serialize(&BinaryMilestone::from(milestone_data))?;
+        let len = u32::try_from(record.len())
+            .map_err(|_| anyhow!("Milestone data {} is too big for a binary log", milestone_index))?;
+        self.file.write_u32_le(len).await?;
+        self.file.write_all(&record).await?;
+        self.index.push((milestone_index, self.offset));
+        self.offset += RECORD_LEN_SIZE + record.len() as u64;
+        Ok(())
+    }
+    /// Write the index and the footer, then sync the file
+    pub async fn finish(mut self) -> anyhow::Result<()> {
+        let index = bincode::serialize(&self.index)?;
+        self.file.write_all(&index).await?;
+        self.file.write_u64_le(self.offset).await?;
+        self.file.write_all(FOOTER_MAGIC).await?;
+        self.file.flush().await?;
+        self.file.get_mut().sync_all().await?;
+        Ok(())
+    }
+}
+
+/// Reads milestone data from a binary archive file, seeking through its index
+pub struct BinaryLogReader {
+    file: BufReader<File>,
+    index: Vec<(u32, u64)>,
+    /// The offset of the index, which is where the records end
+    records_end: u64,
+    /// The index position of the next record
+    cursor: usize,
+    /// The index position where reading stops
+    end: usize,
+}
+
+impl BinaryLogReader {
+    /// Open the binary archive file at the given path, whose records don't exceed the max log size
+    pub fn open(path: &Path, max_log_size: u64) -> anyhow::Result<Self> {
+        Self::from_std(std::fs::File::open(path)?, max_log_size)
+    }
+    /// Read the index of the given binary archive file, and position it at its first record.
+    /// The index is checked against the file size, and no record may exceed the max log size
+    pub fn from_std(mut file: std::fs::File, max_log_size: u64) -> anyhow::Result<Self> {
+        let len = file.metadata()?.len();
+        ensure!(
+            len >= HEADER_MAGIC.len() as u64 + FOOTER_SIZE,
+            "File is too small to be a binary log"
+        );
+        let mut magic = [0; 8];
+        file.read_exact(&mut magic)?;
+        ensure!(&magic == HEADER_MAGIC, "Missing binary log header");
+        file.seek(SeekFrom::End(-(FOOTER_SIZE as i64)))?;
+        let mut footer = [0; FOOTER_SIZE as usize];
+        file.read_exact(&mut footer)?;
+        ensure!(
+            &footer[8..] == FOOTER_MAGIC,
+            "Missing binary log index, the file may be unfinished"
+        );
+        let mut offset = [0; 8];
+        offset.copy_from_slice(&footer[..8]);
+        let records_end = u64::from_le_bytes(offset);
+        ensure!(
+            records_end >= HEADER_MAGIC.len() as u64 && records_end <= len - FOOTER_SIZE,
+            "Invalid binary log index offset"
+        );
+        // the index is a length prefix followed by its entries
+        let index_len = len - FOOTER_SIZE - records_end;
+        ensure!(
+            index_len >= 8 && (index_len - 8) % INDEX_ENTRY_SIZE == 0,
+            "Invalid binary log index length"
+        );
+        file.seek(SeekFrom::Start(records_end))?;
+        let mut index = vec![0; index_len as usize];
+        file.read_exact(&mut index)?;
+        let index: Vec<(u32, u64)> = bincode_options(index_len).deserialize(&index)?;
+        ensure!(
+            index.len() as u64 == (index_len - 8) / INDEX_ENTRY_SIZE,
+            "Invalid binary log index length"
+        );
+        // the records are in ascending milestone index order, and fill the file up to the index
+        let mut next_offset = HEADER_MAGIC.len() as u64;
+        for (position, (milestone_index, offset)) in index.iter().enumerate() {
+            ensure!(
+                *offset == next_offset,
+                "Invalid binary log index offset for milestone {}",
+                milestone_index
+            );
+            if let Some((next_milestone_index, next)) = index.get(position + 1) {
+                ensure!(
+                    milestone_index < next_milestone_index,
+                    "Binary log index out of order at milestone {}",
+                    milestone_index
+                );
+                next_offset = *next;
+            } else {
+                next_offset = records_end;
+            }
+            ensure!(
+                next_offset > *offset + RECORD_LEN_SIZE && next_offset - offset - RECORD_LEN_SIZE <= max_log_size,
+                "Invalid binary log record length for milestone {}",
+                milestone_index
+            );
+        }
+        ensure!(next_offset == records_end, "Invalid binary log index offset");
+        file.seek(SeekFrom::Start(HEADER_MAGIC.len() as u64))?;
+        let end = index.len();
+        Ok(Self {
+            file: BufReader::new(File::from_std(file)),
+            index,
+            records_end,
+            cursor: 0,
+            end,
+        })
+    }
+    /// The first milestone index of the file
+    pub fn from_ms_index(&self) -> Option<u32> {
+        self.index.first().map(|(milestone_index, _)| *milestone_index)
+    }
+    /// The milestone index following the last one of the file
+    pub fn to_ms_index(&self) -> Option<u32> {
+        self.index.last().map(|(milestone_index, _)| milestone_index + 1)
+    }
+    fn offset(&self, position: usize) -> u64 {
+        self.index
+            .get(position)
+            .map(|(_, offset)| *offset)
+            .unwrap_or(self.records_end)
+    }
+    /// The bytes of the records left to read
+    pub fn remaining(&self) -> u64 {
+        self.offset(self.end) - self.offset(self.cursor)
+    }
+    /// Only read the records of the milestones within the given range
+    pub async fn seek_range(&mut self, range: &std::ops::Range<u32>) -> anyhow::Result<()> {
+        self.cursor = self
+            .index
+            .partition_point(|(milestone_index, _)| *milestone_index < range.start);
+        self.end = self
+            .index
+            .partition_point(|(milestone_index, _)| *milestone_index < range.end)
+            .max(self.cursor);
+        self.file.seek(SeekFrom::Start(self.offset(self.cursor))).await?;
+        Ok(())
+    }
+    /// Read the next milestone data record
+    pub async fn next(&mut self) -> anyhow::Result<Option<MilestoneData>> {
+        if self.cursor >= self.end {
+            return Ok(None);
+        }
+        let (milestone_index, offset) = self.index[self.cursor];
+        // the record fills the bytes up to the next one, which the index got checked against
+        let expected = self.offset(self.cursor + 1) - offset - RECORD_LEN_SIZE;
+        let len = self.file.read_u32_le().await? as u64;
+        ensure!(
+            len == expected,
+            "Found a record of {} bytes for milestone {} where the index expects {}",
+            len,
+            milestone_index,
+            expected
+        );
+        let mut record = vec![0; len as usize];
+        self.file.read_exact(&mut record).await?;
+        let binary: BinaryMilestone = bincode_options(len).deserialize(&record)?;
+        ensure!(
+            binary.milestone_index == milestone_index,
+            "Found milestone data {} where the index expects {}",
+            binary.milestone_index,
+            milestone_index
+        );
+        self.cursor += 1;
+        MilestoneData::try_from(binary).map(Some)
+    }
+}
+
+/// Convert a json lines log, compressed or not, to a binary log and return the number of converted milestones
+pub async fn json_to_binary(src: &Path, dest: &Path) -> anyhow::Result<usize> {
+    let mut reader = LogReader::open(src).await?;
+    let mut writer = BinaryLogWriter::create(dest).await?;
+    let mut line = String::new();
+    let mut count = 0;
+    while reader.read_line(&mut line).await? != 0 {
+        let milestone_data: MilestoneData = serde_json::from_str(&line)?;
+        writer.append(&milestone_data).await?;
+        line.clear();
+        count += 1;
+    }
+    writer.finish().await?;
+    Ok(count)
+}
+
+/// Convert a binary log, whose records don't exceed the max log size, to a json lines log and return the number of
+/// converted milestones
+pub async fn binary_to_json(src: &Path, dest: &Path, max_log_size: u64) -> anyhow::Result<usize> {
+    let mut reader = BinaryLogReader::open(src, max_log_size)?;
+    let mut file = BufWriter::new(File::create(dest).await?);
+    let mut count = 0;
+    while let Some(milestone_data) = reader.next().await? {
+        let mut milestone_data_json = serde_json::to_string(&milestone_data)?;
+        milestone_data_json.push('\n');
+        file.write_all(milestone_data_json.as_bytes()).await?;
+        count += 1;
+    }
+    file.flush().await?;
+    file.get_mut().sync_all().await?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Runtime::new().unwrap().block_on(future)
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("chronicle-binary-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn milestone_data(milestone_index: u32) -> MilestoneData {
+        let mut milestone_data = MilestoneData::new(milestone_index, CreatedBy::Syncer);
+        milestone_data
+            .pending
+            .insert(MessageId::new([milestone_index as u8; MESSAGE_ID_LENGTH]), ());
+        milestone_data
+    }
+
+    fn write_log(path: &Path, milestones: &[u32]) {
+        block_on(async {
+            let mut writer = BinaryLogWriter::create(path).await.unwrap();
+            for milestone_index in milestones {
+                writer.append(&milestone_data(*milestone_index)).await.unwrap();
+            }
+            writer.finish().await.unwrap();
+        })
+    }
+
+    fn read_log(reader: &mut BinaryLogReader) -> Vec<u32> {
+        block_on(async {
+            let mut milestones = Vec::new();
+            while let Some(milestone_data) = reader.next().await.unwrap() {
+                assert!(milestone_data.pending.contains_key(&MessageId::new(
+                    [milestone_data.milestone_index as u8; MESSAGE_ID_LENGTH]
+                )));
+                milestones.push(milestone_data.milestone_index);
+            }
+            milestones
+        })
+    }
+
+    /// Rewrite the index of the binary log at the given path, along with its footer
+    fn rewrite_index(path: &Path, rewrite: impl FnOnce(&mut Vec<(u32, u64)>)) {
+        let mut bytes = std::fs::read(path).unwrap();
+        let footer = bytes.len() - FOOTER_SIZE as usize;
+        let mut offset = [0; 8];
+        offset.copy_from_slice(&bytes[footer..footer + 8]);
+        let records_end = u64::from_le_bytes(offset) as usize;
+        let mut index: Vec<(u32, u64)> = bincode::deserialize(&bytes[records_end..footer]).unwrap();
+        rewrite(&mut index);
+        bytes.truncate(records_end);
+        bytes.extend(bincode::serialize(&index).unwrap());
+        bytes.extend(&(records_end as u64).to_le_bytes());
+        bytes.extend(FOOTER_MAGIC);
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    #[test]
+    fn written_milestones_are_read_back() {
+        let dir = temp_dir("round-trip");
+        let path = dir.join("10to13.bin");
+        write_log(&path, &[10, 11, 12]);
+        let mut reader = BinaryLogReader::open(&path, MAX_LOG_SIZE).unwrap();
+        assert_eq!((reader.from_ms_index(), reader.to_ms_index()), (Some(10), Some(13)));
+        assert_eq!(read_log(&mut reader), vec![10, 11, 12]);
+        assert_eq!(reader.remaining(), 0);
+        // the milestones have to be appended in ascending order
+        block_on(async {
+            let mut writer = BinaryLogWriter::create(&dir.join("unordered.bin")).await.unwrap();
+            writer.append(&milestone_data(11)).await.unwrap();
+            assert!(writer.append(&milestone_data(11)).await.is_err());
+            assert!(writer.append(&milestone_data(10)).await.is_err());
+        });
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn seek_range_reads_the_milestones_within_the_range() {
+        let dir = temp_dir("seek-range");
+        let path = dir.join("10to15.bin");
+        write_log(&path, &[10, 11, 12, 13, 14]);
+        let mut reader = BinaryLogReader::open(&path, MAX_LOG_SIZE).unwrap();
+        let mut seek_range = |range: std::ops::Range<u32>| {
+            block_on(reader.seek_range(&range)).unwrap();
+            read_log(&mut reader)
+        };
+        assert_eq!(seek_range(11..13), vec![11, 12]);
+        // backwards, and past the bounds of the file
+        assert_eq!(seek_range(0..11), vec![10]);
+        assert_eq!(seek_range(13..100), vec![13, 14]);
+        assert_eq!(seek_range(0..100), vec![10, 11, 12, 13, 14]);
+        assert!(seek_range(15..100).is_empty());
+        assert!(seek_range(0..10).is_empty());
+        assert!(seek_range(12..12).is_empty());
+        assert!(seek_range(13..11).is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_indexes_are_rejected() {
+        let dir = temp_dir("invalid-index");
+        let path = dir.join("10to13.bin");
+        let open = |rewrite: fn(&mut Vec<(u32, u64)>)| {
+            write_log(&path, &[10, 11, 12]);
+            rewrite_index(&path, rewrite);
+            BinaryLogReader::open(&path, MAX_LOG_SIZE)
+        };
+        assert!(open(|_| ()).is_ok());
+        // reordered milestones, or records
+        assert!(open(|index| index.swap(0, 1)).is_err());
+        assert!(open(|index| {
+            let (first, second) = (index[0].1, index[1].1);
+            index[0].1 = second;
+            index[1].1 = first;
+        })
+        .is_err());
+        // the records have to start right after the header, and end at the index
+        assert!(open(|index| index[0].1 += 1).is_err());
+        assert!(open(|index| index[2].1 = index[2].1 + 1000).is_err());
+        // a record missing from the index would span the next one, which fails once it gets read
+        let mut reader = open(|index| {
+            index.remove(1);
+        })
+        .unwrap();
+        assert!(block_on(reader.next()).is_err());
+        let mut reader = open(|index| {
+            index.pop();
+        })
+        .unwrap();
+        assert_eq!(block_on(reader.next()).unwrap().unwrap().milestone_index, 10);
+        assert!(block_on(reader.next()).is_err());
+        // a truncated index
+        write_log(&path, &[10, 11, 12]);
+        let mut bytes = std::fs::read(&path).unwrap();
+        let footer = bytes.split_off(bytes.len() - FOOTER_SIZE as usize);
+        bytes.truncate(bytes.len() - INDEX_ENTRY_SIZE as usize);
+        let records_end = u64::from_le_bytes(footer[..8].try_into().unwrap());
+        bytes.extend(&records_end.to_le_bytes());
+        bytes.extend(FOOTER_MAGIC);
+        std::fs::write(&path, bytes).unwrap();
+        assert!(BinaryLogReader::open(&path, MAX_LOG_SIZE).is_err());
+        // an unfinished log
+        block_on(async {
+            let mut writer = BinaryLogWriter::create(&path).await.unwrap();
+            writer.append(&milestone_data(10)).await.unwrap();
+            writer.file.flush().await.unwrap();
+        });
+        assert!(BinaryLogReader::open(&path, MAX_LOG_SIZE).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn records_above_the_max_log_size_are_rejected() {
+        let dir = temp_dir("max-log-size");
+        let path = dir.join("10to12.bin");
+        write_log(&path, &[10, 11]);
+        let record_len = bincode::serialize(&BinaryMilestone::from(&milestone_data(10)))
+            .unwrap()
+            .len() as u64;
+        assert!(BinaryLogReader::open(&path, record_len).is_ok());
+        assert!(BinaryLogReader::open(&path, record_len - 1).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn json_logs_convert_to_binary_and_back() {
+        let dir = temp_dir("convert");
+        let json = (10..13)
+            .map(|milestone_index| serde_json::to_string(&milestone_data(milestone_index)).unwrap() + "\n")
+            .collect::<String>();
+        std::fs::write(dir.join("10to13.log"), &json).unwrap();
+        block_on(async {
+            assert_eq!(
+                json_to_binary(&dir.join("10to13.log"), &dir.join("10to13.bin"))
+                    .await
+                    .unwrap(),
+                3
+            );
+            assert_eq!(
+                binary_to_json(&dir.join("10to13.bin"), &dir.join("converted.log"), MAX_LOG_SIZE)
+                    .await
+                    .unwrap(),
+                3
+            );
+        });
+        assert_eq!(std::fs::read_to_string(dir.join("converted.log")).unwrap(), json);
+        // a malformed line fails the conversion
+        std::fs::write(dir.join("malformed.log"), "{\"milestone_index\":\n").unwrap();
+        assert!(block_on(json_to_binary(&dir.join("malformed.log"), &dir.join("malformed.bin"))).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -3,8 +3,15 @@
 
 use super::*;
 use bee_message::prelude::MilestoneIndex;
-use chronicle_common::Wrapper;
+use chronicle_common::{
+    metrics::{
+        ARCHIVER_BYTES_WRITTEN,
+        LATEST_LOGGED_MILESTONE,
+    },
+    Wrapper,
+};
 use chronicle_storage::access::SyncRecord;
+use futures::FutureExt;
 
 #[async_trait::async_trait]
 impl<H: ChronicleBrokerScope> EventLoop<BrokerHandle<H>> for Archiver {
@@ -35,10 +42,9 @@ impl<H: ChronicleBrokerScope> EventLoop<BrokerHandle<H>> for Archiver {
                 ArchiverEvent::Close(milestone_index) => {
                     // to prevent overlap, we ensure to only close syncer milestone_index when it's less than next
                     if milestone_index < next {
-                        self.close_log_file(milestone_index).await.map_err(|e| {
-                            error!("{}", e);
-                            Need::Abort
-                        })?;
+                        self.close_log_file(milestone_index)
+                            .await
+                            .map_err(|e| self.abort(e, _supervisor))?;
                     }
                 }
                 ArchiverEvent::MilestoneData(milestone_data, opt_upper_limit) => {
@@ -55,10 +61,7 @@ impl<H: ChronicleBrokerScope> EventLoop<BrokerHandle<H>> for Archiver {
                             if next.eq(&ms_index) {
                                 self.handle_milestone_data(ms_data.into_inner(), opt_upper_limit)
                                     .await
-                                    .map_err(|e| {
-                                        error!("{}", e);
-                                        Need::Abort
-                                    })?;
+                                    .map_err(|e| self.abort(e, _supervisor))?;
                                 next += 1;
                             } else if ms_index > next {
                                 // Safety check to prevent potential rare race condition
@@ -66,17 +69,13 @@ impl<H: ChronicleBrokerScope> EventLoop<BrokerHandle<H>> for Archiver {
                                 if self.milestones_data.len() > self.solidifiers_count as usize {
                                     error!("Identified gap in the new incoming data: {}..{}", next, ms_index);
                                     // Close the file which we're unable atm to append on top.
-                                    self.close_log_file(next).await.map_err(|e| {
-                                        error!("{}", e);
-                                        Need::Abort
-                                    })?;
+                                    self.close_log_file(next)
+                                        .await
+                                        .map_err(|e| self.abort(e, _supervisor))?;
                                     // this supposed to create new file
                                     self.handle_milestone_data(ms_data.into_inner(), opt_upper_limit)
                                         .await
-                                        .map_err(|e| {
-                                            error!("{}", e);
-                                            Need::Abort
-                                        })?;
+                                        .map_err(|e| self.abort(e, _supervisor))?;
                                     // reset next
                                     next = ms_index + 1;
                                 } else {
@@ -93,17 +92,13 @@ impl<H: ChronicleBrokerScope> EventLoop<BrokerHandle<H>> for Archiver {
                             // handle syncer milestone data;
                             self.handle_milestone_data(milestone_data, opt_upper_limit)
                                 .await
-                                .map_err(|e| {
-                                    error!("{}", e);
-                                    Need::Abort
-                                })?;
+                                .map_err(|e| self.abort(e, _supervisor))?;
                             // it overlaps with the incoming flow.
                         } else if milestone_data.milestone_index() == next {
                             // we handle the milestone_data from syncer as Incoming without upper_ms_limit
-                            self.handle_milestone_data(milestone_data, None).await.map_err(|e| {
-                                error!("{}", e);
-                                Need::Abort
-                            })?;
+                            self.handle_milestone_data(milestone_data, None)
+                                .await
+                                .map_err(|e| self.abort(e, _supervisor))?;
                             next += 1;
                         } else {
                             // we received a futuristic milestone_data from syncer.
@@ -118,6 +113,16 @@ impl<H: ChronicleBrokerScope> EventLoop<BrokerHandle<H>> for Archiver {
 }
 
 impl Archiver {
+    /// Log the error and abort, flagging the archiver as degraded if it left a maybe corrupted log file behind
+    fn abort<H: ChronicleBrokerScope>(&mut self, e: anyhow::Error, supervisor: &mut Option<BrokerHandle<H>>) -> Need {
+        error!("{}", e);
+        if self.logs.iter().any(|log| log.maybe_corrupted) {
+            self.service.update_status(ServiceStatus::Degraded);
+            let event = BrokerEvent::Children(BrokerChild::Archiver(self.service.clone(), Ok(())));
+            let _ = supervisor.as_mut().expect("Archiver Expected BrokerHandle").send(event);
+        }
+        Need::Abort
+    }
     async fn close_log_file(&mut self, milestone_index: u32) -> anyhow::Result<()> {
         if let Some((i, log_file)) = self
             .logs
@@ -125,7 +130,7 @@ impl Archiver {
             .enumerate()
             .find(|(_, log)| log.to_ms_index == milestone_index)
         {
-            Self::finish_log_file(log_file, &self.dir_path).await?;
+            Self::finish_log_file(log_file, &self.dir_path, &self.finish_options, &mut self.finishing).await?;
             // remove finished log file
             let log_file = self.logs.remove(i);
             self.push_to_processed(log_file);
@@ -160,13 +165,13 @@ impl Archiver {
                 // check if now the log_file reached an upper limit to finish the file
                 if log_file.upper_ms_limit == log_file.to_ms_index {
                     self.cleanup.push(log_file.from_ms_index);
-                    Self::finish_log_file(log_file, &self.dir_path).await?;
+                    Self::finish_log_file(log_file, &self.dir_path, &self.finish_options, &mut self.finishing).await?;
                 }
             } else {
                 // push it into cleanup
                 self.cleanup.push(log_file.from_ms_index);
                 // Finish it;
-                Self::finish_log_file(log_file, &self.dir_path).await?;
+                Self::finish_log_file(log_file, &self.dir_path, &self.finish_options, &mut self.finishing).await?;
                 info!(
                     "{} hits filesize limit: {} bytes, contains: {} milestones data",
                     log_file.filename,
@@ -227,7 +232,7 @@ impl Archiver {
         // check if we hit an upper_ms_limit, as this is possible when the log_file only needs 1 milestone data.
         if log_file.upper_ms_limit == log_file.to_ms_index {
             // finish it
-            Self::finish_log_file(&mut log_file, &self.dir_path).await?;
+            Self::finish_log_file(&mut log_file, &self.dir_path, &self.finish_options, &mut self.finishing).await?;
             // add it to processed
             self.push_to_processed(log_file);
         } else {
@@ -248,7 +253,7 @@ impl Archiver {
                         // push it into cleanup to get removed and pushed to processed
                         self.cleanup.push(l.from_ms_index);
                         // finish the file
-                        Self::finish_log_file(l, &self.dir_path).await?;
+                        Self::finish_log_file(l, &self.dir_path, &self.finish_options, &mut self.finishing).await?;
                     }
 
                     prev_log = l;
@@ -274,6 +279,10 @@ impl Archiver {
         retries_per_query: usize,
     ) -> anyhow::Result<()> {
         log_file.append_line(&milestone_data_line).await?;
+        ARCHIVER_BYTES_WRITTEN.inc_by(milestone_data_line.len() as u64);
+        if LATEST_LOGGED_MILESTONE.get() < ms_index as i64 {
+            LATEST_LOGGED_MILESTONE.set(ms_index as i64);
+        }
         // insert into the DB, without caring about the response
         let sync_key = chronicle_common::Synckey;
         let synced_record = SyncRecord::new(MilestoneIndex(ms_index), None, Some(0));
@@ -289,8 +298,19 @@ impl Archiver {
             ));
         Ok(())
     }
-    async fn finish_log_file(log_file: &mut LogFile, dir_path: &PathBuf) -> anyhow::Result<()> {
-        log_file.finish(dir_path).await?;
+    async fn finish_log_file(
+        log_file: &mut LogFile,
+        dir_path: &PathBuf,
+        finish_options: &FinishOptions,
+        finishing: &mut Vec<JoinHandle<()>>,
+    ) -> anyhow::Result<()> {
+        let task = log_file.finish(dir_path, finish_options.clone()).await?;
+        // forget the tasks which are done already
+        *finishing = std::mem::take(finishing)
+            .into_iter()
+            .filter_map(|mut task| (&mut task).now_or_never().is_none().then(|| task))
+            .collect();
+        finishing.push(task);
         log_file.set_finished();
         info!(
             "Finished {}.part, LogFile: {}to{}.log",
@@ -2,6 +2,17 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use super::*;
+use bee_message::prelude::MilestoneIndex;
+use chronicle_common::Synckey;
+use chronicle_storage::access::SyncRecord;
+use std::ops::Range;
+use tokio::sync::mpsc::{
+    unbounded_channel,
+    UnboundedSender,
+};
+
+/// The sync rows inserted at once while unlogging the lost milestones, the next ones wait for their responses
+const UNLOG_BATCH_SIZE: usize = 1000;
 
 #[async_trait::async_trait]
 impl<H: ChronicleBrokerScope> Init<BrokerHandle<H>> for Archiver {
@@ -16,6 +27,30 @@ impl<H: ChronicleBrokerScope> Init<BrokerHandle<H>> for Archiver {
                 return Err(Need::Abort);
             }
         };
+        // finish the log files recovered at startup, and unlog the milestones lost with their tail
+        for RecoveredLog { log_file, lost } in std::mem::take(&mut self.recovered) {
+            if let Some(mut log_file) = log_file {
+                match log_file.finish(&self.dir_path, self.finish_options.clone()).await {
+                    Ok(task) => self.finishing.push(task),
+                    Err(e) => {
+                        alert!("Unable to finish the recovered log file {}: {}", log_file.filename, e)
+                            .await
+                            .ok();
+                        return Err(Need::Abort);
+                    }
+                }
+                info!(
+                    "Finished the recovered {}, LogFile: {}to{}.log",
+                    log_file.filename, log_file.from_ms_index, log_file.to_ms_index
+                );
+                self.processed.push(log_file.from_ms_index..log_file.to_ms_index);
+            }
+            self.unlog(&lost).await.map_err(|e| {
+                error!("Unable to unlog the lost milestones {:?}: {}", lost, e);
+                Need::Abort
+            })?;
+        }
+        self.processed.sort_by(|a, b| b.start.cmp(&a.start));
         self.service.update_status(ServiceStatus::Initializing);
         let event = BrokerEvent::Children(BrokerChild::Archiver(self.service.clone(), Ok(())));
         let _ = _supervisor
@@ -26,3 +61,94 @@ impl<H: ChronicleBrokerScope> Init<BrokerHandle<H>> for Archiver {
         Ok(())
     }
 }
+
+impl Archiver {
+    /// Mark the lost milestones as synced but unlogged in the sync table, so they get archived again.
+    /// The rows are inserted in batches, each one answered before the next is sent
+    async fn unlog(&self, lost: &[Range<u32>]) -> anyhow::Result<()> {
+        let mut milestones = lost.iter().cloned().flatten().peekable();
+        while milestones.peek().is_some() {
+            let (tx, mut rx) = unbounded_channel();
+            let mut pending = 0;
+            for milestone_index in milestones.by_ref().take(UNLOG_BATCH_SIZE) {
+                let unlogged_record = SyncRecord::new(MilestoneIndex(milestone_index), None, None);
+                self.keyspace
+                    .insert(&Synckey, &unlogged_record)
+                    .consistency(Consistency::One)
+                    .build()?
+                    .send_local(UnlogWorker::boxed(
+                        tx.clone(),
+                        self.keyspace.clone(),
+                        unlogged_record,
+                        self.retries_per_query,
+                    ));
+                pending += 1;
+            }
+            drop(tx);
+            while let Some(result) = rx.recv().await {
+                result?;
+                pending -= 1;
+            }
+            ensure!(pending == 0, "{} sync rows got no response", pending);
+        }
+        Ok(())
+    }
+}
+
+/// Inserts an unlogged sync row, and reports the outcome back to the archiver
+#[derive(Clone)]
+struct UnlogWorker {
+    tx: UnboundedSender<anyhow::Result<()>>,
+    keyspace: ChronicleKeyspace,
+    unlogged_record: SyncRecord,
+    retries: usize,
+}
+
+impl UnlogWorker {
+    fn boxed(
+        tx: UnboundedSender<anyhow::Result<()>>,
+        keyspace: ChronicleKeyspace,
+        unlogged_record: SyncRecord,
+        retries: usize,
+    ) -> Box<Self> {
+        Box::new(Self {
+            tx,
+            keyspace,
+            unlogged_record,
+            retries,
+        })
+    }
+}
+
+impl Worker for UnlogWorker {
+    fn handle_response(self: Box<Self>, giveload: Vec<u8>) -> anyhow::Result<()> {
+        let _ = self
+            .tx
+            .send(Decoder::try_from(giveload).and_then(|decoder| decoder.get_void()));
+        Ok(())
+    }
+    fn handle_error(
+        mut self: Box<Self>,
+        mut error: WorkerError,
+        reporter: &Option<ReporterHandle>,
+    ) -> anyhow::Result<()> {
+        if let WorkerError::Cql(ref mut cql_error) = error {
+            if let (Some(id), Some(reporter)) = (cql_error.take_unprepared_id(), reporter) {
+                handle_insert_unprepared_error(&self, &self.keyspace, &Synckey, &self.unlogged_record, id, reporter)?;
+                return Ok(());
+            }
+        }
+        if self.retries > 0 {
+            self.retries -= 1;
+            let req = self
+                .keyspace
+                .insert_query(&Synckey, &self.unlogged_record)
+                .consistency(Consistency::One)
+                .build()?;
+            tokio::spawn(async { req.send_global(self) });
+        } else {
+            let _ = self.tx.send(Err(anyhow!("{:?}", error)));
+        }
+        Ok(())
+    }
+}
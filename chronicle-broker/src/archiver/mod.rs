@@ -9,14 +9,43 @@ use crate::{
         BrokerHandle,
         ChronicleBrokerScope,
     },
+    compression::{
+        compress_log,
+        Codec,
+        LogReader,
+    },
+    manifest::{
+        LogManifest,
+        ManifestSigner,
+        ManifestVerifier,
+    },
+    remote::{
+        download_log,
+        is_http,
+        is_stream,
+        RemoteLogReader,
+    },
     syncer::Ascending,
+    uploader::UploaderHandle,
+};
+use anyhow::{
+    bail,
+    ensure,
+};
+use binary::{
+    BinaryLogReader,
+    BINARY_LOG_EXTENSION,
 };
-use anyhow::bail;
 use chronicle_common::alert;
 use chronicle_storage::access::ChronicleKeyspace;
 use std::{
     collections::BinaryHeap,
     convert::TryFrom,
+    io::{
+        Read,
+        Seek,
+        SeekFrom,
+    },
     ops::{
         Deref,
         DerefMut,
@@ -28,17 +57,22 @@ use tokio::{
         File,
         OpenOptions,
     },
-    io::{
-        AsyncBufReadExt,
-        AsyncWriteExt,
-        BufReader,
-    },
+    io::AsyncWriteExt,
     sync::oneshot::Receiver,
+    task::JoinHandle,
 };
+/// Binary archive format with a milestone index
+pub mod binary;
 mod event_loop;
 mod init;
+mod recovery;
 mod terminating;
 
+pub use recovery::{
+    recover_part_files,
+    RecoveredLog,
+};
+
 /// The maximum bytes size for a given log file;
 pub const MAX_LOG_SIZE: u64 = u32::MAX as u64;
 
@@ -49,20 +83,24 @@ builder!(ArchiverBuilder {
     oneshot: Receiver<u32>,
     solidifiers_count: u8,
     retries_per_query: usize,
-    dir_path: PathBuf
+    dir_path: PathBuf,
+    compression: Codec,
+    uploader: UploaderHandle,
+    signer: ManifestSigner,
+    recovered: Vec<RecoveredLog>
 });
 
 /// ArchiverHandle to be passed to the supervisor and solidifers
 #[derive(Clone)]
 pub struct ArchiverHandle {
-    pub(crate) tx: tokio::sync::mpsc::UnboundedSender<ArchiverEvent>,
+    pub(crate) tx: InboxSender<ArchiverEvent>,
 }
 /// ArchiverInbox is used to recv events from solidifier(s)
 pub struct ArchiverInbox {
-    pub(crate) rx: tokio::sync::mpsc::UnboundedReceiver<ArchiverEvent>,
+    pub(crate) rx: InboxReceiver<ArchiverEvent>,
 }
 impl Deref for ArchiverHandle {
-    type Target = tokio::sync::mpsc::UnboundedSender<ArchiverEvent>;
+    type Target = InboxSender<ArchiverEvent>;
 
     fn deref(&self) -> &Self::Target {
         &self.tx
@@ -97,18 +135,54 @@ pub enum ArchiverEvent {
 /// Write ahead file which stores ordered milestones data by milestone index.
 pub struct LogFile {
     len: u64,
+    /// The file size when it got opened for reading
+    size: u64,
     filename: String,
     /// Included milestone data
     from_ms_index: u32,
     /// NotIncluded (yet) milestone data
     to_ms_index: u32,
     upper_ms_limit: u32,
-    file: BufReader<File>,
+    io: LogFileIo,
+    /// Verifies the read lines against the manifest of the file, if any
+    verifier: Option<ManifestVerifier>,
+    /// The milestone lines skipped ahead of the first read one, ie when resuming an import
+    skipped_lines: u64,
+    /// The lines left to skip before the next read, for the compressed logs which can't seek to the position
+    lines_to_skip: u64,
     /// Identifier if it had io error
     maybe_corrupted: bool,
     finished: bool,
 }
 
+/// What happens to a log file once it is finished, in the background
+#[derive(Clone, Default)]
+pub struct FinishOptions {
+    /// The codec the finished log gets compressed with
+    pub compression: Option<Codec>,
+    /// The uploader the finished log and its manifest get queued to
+    pub uploader: Option<UploaderHandle>,
+    /// The signer of the manifest
+    pub signer: Option<ManifestSigner>,
+}
+
+/// The underlying file of a LogFile, either appended by the archiver or read by the importer
+enum LogFileIo {
+    Writer(File),
+    Reader(LogReader),
+    Binary(BinaryLogReader),
+}
+
+impl std::fmt::Debug for LogFileIo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogFileIo::Writer(file) => f.debug_tuple("Writer").field(file).finish(),
+            LogFileIo::Reader(reader) => f.debug_tuple("Reader").field(&reader.codec()).finish(),
+            LogFileIo::Binary(reader) => f.debug_tuple("Binary").field(&reader.remaining()).finish(),
+        }
+    }
+}
+
 impl LogFile {
     /// Create a new Write-ahead-log file for a starting milestone index
     pub async fn create(
@@ -134,36 +208,96 @@ impl LogFile {
         };
         Ok(Self {
             len: 0,
+            size: 0,
             filename,
             from_ms_index: milestone_index,
             to_ms_index: milestone_index,
             upper_ms_limit: opt_upper_limit.unwrap_or(u32::MAX),
-            file: BufReader::new(file),
+            io: LogFileIo::Writer(file),
+            verifier: None,
+            skipped_lines: 0,
+            lines_to_skip: 0,
             maybe_corrupted: false,
             finished: false,
         })
     }
 
-    /// Complete a log file and save it to the given directory
-    pub async fn finish(&mut self, dir_path: &PathBuf) -> anyhow::Result<()> {
+    /// Complete a log file and save it to the given directory, once its manifest is written next to it, signed if a
+    /// signer is provided. The log then gets compressed and queued for upload along with its manifest in the
+    /// background, if a codec and an uploader are provided.
+    /// Returns the background task, which has to be awaited before shutting down
+    pub async fn finish(&mut self, dir_path: &PathBuf, options: FinishOptions) -> anyhow::Result<JoinHandle<()>> {
         let new_file_name = format!("{}to{}.log", self.from_ms_index, self.to_ms_index);
         let new_file_path = dir_path.join(&new_file_name);
         let old_file_path = dir_path.join(&self.filename);
-        if let Err(e) = tokio::fs::rename(old_file_path, new_file_path).await {
-            self.maybe_corrupted = true;
-            bail!(e)
+        if let LogFileIo::Writer(file) = &mut self.io {
+            if let Err(e) = file.sync_all().await {
+                self.maybe_corrupted = true;
+                bail!(e)
+            };
+        }
+        // the manifest is written ahead of the rename, so a finished log never lacks its manifest
+        let manifest_path = match Self::write_manifest(&old_file_path, &new_file_path, options.signer.as_ref()).await {
+            Ok(manifest_path) => Some(manifest_path),
+            Err(e) => {
+                alert!(
+                    "Unable to write the manifest of LogFile: {}, error: {}",
+                    new_file_path.display(),
+                    e
+                )
+                .await
+                .ok();
+                None
+            }
         };
-        if let Err(e) = self.file.get_mut().sync_all().await {
+        if let Err(e) = tokio::fs::rename(old_file_path, &new_file_path).await {
             self.maybe_corrupted = true;
             bail!(e)
         };
-        Ok(())
+        Ok(tokio::spawn(async move {
+            let mut finished_path = new_file_path;
+            if let Some(codec) = options.compression {
+                match compress_log(&finished_path, codec).await {
+                    Ok(compressed_path) => {
+                        info!("Compressed LogFile: {}", compressed_path.display());
+                        finished_path = compressed_path;
+                    }
+                    Err(e) => error!("Unable to compress LogFile: {}, error: {}", finished_path.display(), e),
+                }
+            }
+            if let Some(uploader) = options.uploader {
+                uploader.upload(finished_path);
+                if let Some(manifest_path) = manifest_path {
+                    uploader.upload(manifest_path);
+                }
+            }
+        }))
+    }
+
+    /// Compute and write the manifest of the log file finished from the given part file, signed if a signer is
+    /// provided
+    async fn write_manifest(
+        part_path: &std::path::Path,
+        path: &std::path::Path,
+        signer: Option<&ManifestSigner>,
+    ) -> anyhow::Result<PathBuf> {
+        let mut manifest = LogManifest::compute_from(part_path, path).await?;
+        if let Some(signer) = signer {
+            signer.sign(&mut manifest);
+        }
+        manifest.write(path).await
     }
 
     /// Append a new line to the log file
     pub async fn append_line(&mut self, line: &Vec<u8>) -> anyhow::Result<()> {
+        let file = match &mut self.io {
+            LogFileIo::Writer(file) => file,
+            LogFileIo::Reader(_) | LogFileIo::Binary(_) => {
+                bail!("Unable to append to the read only log file: {}", self.filename)
+            }
+        };
         // append to the file
-        if let Err(e) = self.file.write_all(line.as_ref()).await {
+        if let Err(e) = file.write_all(line.as_ref()).await {
             self.maybe_corrupted = true;
             // Check if the error was because of disk overflow
             if let std::io::ErrorKind::WriteZero = e.kind() {
@@ -193,23 +327,58 @@ impl LogFile {
                 "Cannot fetch next milestone data from maybe corrupted LogFile",
             ));
         }
-        if self.len == 0 {
+        let reader = match &mut self.io {
+            LogFileIo::Reader(reader) => reader,
+            LogFileIo::Binary(reader) => {
+                let next = reader.next().await.map_err(|e| {
+                    self.maybe_corrupted = true;
+                    std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string())
+                })?;
+                self.len = reader.remaining();
+                if next.is_none() {
+                    self.finished = true;
+                }
+                return Ok(next);
+            }
+            LogFileIo::Writer(_) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    "Cannot fetch next milestone data from a LogFile opened for writing",
+                ))
+            }
+        };
+        // the remaining len of a compressed file doesn't reach zero before its last lines are decoded
+        // and the len of a streamed file stays zero if its size is unknown
+        if self.len == 0 && self.size > 0 && reader.codec().is_none() {
             self.finished = true;
-            return Ok(None);
+            return self.verify_end().map(|_| None);
+        }
+        if self.lines_to_skip > 0 {
+            let lines_to_skip = std::mem::take(&mut self.lines_to_skip);
+            reader.skip_lines(lines_to_skip).await.map_err(|e| {
+                self.maybe_corrupted = true;
+                e
+            })?;
         }
         let mut milestone_data_line: String = String::new();
-        match self.file.read_line(&mut milestone_data_line).await {
+        match reader.read_line(&mut milestone_data_line).await {
             Ok(n) => {
                 if n == 0 {
                     self.finished = true;
-                    return Ok(None);
+                    return self.verify_end().map(|_| None);
+                }
+                if let Some(verifier) = self.verifier.as_mut() {
+                    verifier.verify_line(milestone_data_line.as_bytes()).map_err(|e| {
+                        self.maybe_corrupted = true;
+                        std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string())
+                    })?;
                 }
                 let milestone_data: MilestoneData = serde_json::from_str(&milestone_data_line).map_err(|e| {
                     self.maybe_corrupted = true;
                     let error_fmt = format!("Unable to deserialize milestone data bytes. Error: {}", e);
                     std::io::Error::new(std::io::ErrorKind::InvalidData, error_fmt)
                 })?;
-                self.len -= milestone_data_line.len() as u64;
+                self.len = self.size.saturating_sub(reader.consumed());
                 Ok(Some(milestone_data))
             }
             Err(err) => {
@@ -219,11 +388,42 @@ impl LogFile {
         }
     }
 
-    /// Get the file length
+    /// Verify the line count and the checksum of the manifest once all the lines are read
+    fn verify_end(&mut self) -> Result<(), std::io::Error> {
+        if let Some(verifier) = self.verifier.take() {
+            verifier.finish().map_err(|e| {
+                self.maybe_corrupted = true;
+                std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string())
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Verify the milestone lines against the given manifest as they are read.
+    /// Note: the manifest signature is expected to be verified already
+    pub fn verify_with(&mut self, manifest: LogManifest, path: &std::path::Path) {
+        if let LogFileIo::Reader(_) = self.io {
+            self.verifier.replace(manifest.verifier_from(path, self.skipped_lines));
+        }
+    }
+
+    /// Get the remaining file length, which is the compressed length for compressed files
     pub fn len(&self) -> u64 {
         self.len
     }
 
+    /// Only fetch the milestone data within the given range.
+    /// Note: only binary logs can seek through their index, returns false for the json logs
+    pub async fn seek_range(&mut self, range: &std::ops::Range<u32>) -> anyhow::Result<bool> {
+        if let LogFileIo::Binary(reader) = &mut self.io {
+            reader.seek_range(range).await?;
+            self.len = reader.remaining();
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
     fn set_finished(&mut self) {
         self.finished = true;
     }
@@ -242,12 +442,42 @@ impl LogFile {
         self.to_ms_index
     }
 }
+/// Whether the path points to a finished log file, ie 0to10.log, 0to10.log.zst or 0to10.bin
+pub fn is_finished_log(path: &std::path::Path) -> bool {
+    let name = match path.file_name().and_then(|name| name.to_str()) {
+        Some(name) => name,
+        None => return false,
+    };
+    let (stem, ext) = name.split_once('.').unwrap_or((name, ""));
+    let mut range = stem.split("to").map(str::parse::<u32>);
+    let valid_range = matches!(
+        (range.next(), range.next(), range.next()),
+        (Some(Ok(_)), Some(Ok(_)), None)
+    );
+    valid_range
+        && [
+            "log",
+            "log.zst",
+            "log.gz",
+            "log.fin",
+            "log.fin.zst",
+            "log.fin.gz",
+            BINARY_LOG_EXTENSION,
+        ]
+        .contains(&ext)
+}
+
 /// Archiver state
 pub struct Archiver {
     service: Service,
     dir_path: PathBuf,
     logs: Vec<LogFile>,
     max_log_size: u64,
+    finish_options: FinishOptions,
+    /// The background tasks of the finished logs, awaited before shutting down
+    finishing: Vec<JoinHandle<()>>,
+    /// The `.part` files recovered at startup, to be finished
+    recovered: Vec<RecoveredLog>,
     cleanup: Vec<u32>,
     processed: Vec<std::ops::Range<u32>>,
     milestones_data: BinaryHeap<Ascending<MilestoneData>>,
@@ -270,7 +500,8 @@ impl<H: ChronicleBrokerScope> ActorBuilder<BrokerHandle<H>> for ArchiverBuilder
 impl Builder for ArchiverBuilder {
     type State = Archiver;
     fn build(self) -> Self::State {
-        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let capacity = get_config().broker_config.inbox_capacity.archiver;
+        let (tx, rx) = crate::inbox::channel("archiver", capacity);
         let handle = Some(ArchiverHandle { tx });
         let inbox = ArchiverInbox { rx };
         let dir_path = self.dir_path.expect("Expected log dictionary path");
@@ -280,6 +511,13 @@ impl Builder for ArchiverBuilder {
             logs: Vec::new(),
             cleanup: Vec::with_capacity(2),
             max_log_size: self.max_log_size.unwrap_or(MAX_LOG_SIZE),
+            finish_options: FinishOptions {
+                compression: self.compression,
+                uploader: self.uploader,
+                signer: self.signer,
+            },
+            finishing: Vec::new(),
+            recovered: self.recovered.unwrap_or_default(),
             processed: Vec::new(),
             keyspace: self.keyspace.unwrap(),
             solidifiers_count: self.solidifiers_count.unwrap(),
@@ -312,27 +550,211 @@ impl<H: ChronicleBrokerScope> AknShutdown<Archiver> for BrokerHandle<H> {
         let _ = self.send(event);
     }
 }
+/// Parse the milestone range out of a finished log file name, ie 0to10.log, 0to10.log.zst or 0to10.bin
+fn parse_log_filename(filename: &str) -> anyhow::Result<(String, u32, u32)> {
+    let filename = filename
+        .split('.')
+        .next()
+        .ok_or(anyhow::anyhow!("Invalid filename!"))?
+        .to_owned();
+    let split = filename.split("to").collect::<Vec<_>>();
+    anyhow::ensure!(split.len() == 2, "Invalid filename!");
+    let (from_ms_index, to_ms_index) = (split[0].parse()?, split[1].parse()?);
+    Ok((filename, from_ms_index, to_ms_index))
+}
+
+/// Whether the offset of the file of the given length points to the start of a line
+fn is_line_start(file: &mut std::fs::File, offset: u64, len: u64) -> std::io::Result<bool> {
+    if offset == 0 {
+        return Ok(true);
+    } else if offset > len {
+        return Ok(false);
+    }
+    let mut previous = [0u8];
+    file.seek(SeekFrom::Start(offset - 1))?;
+    file.read_exact(&mut previous)?;
+    Ok(previous[0] == b'\n')
+}
+
+/// The position of a milestone line in a log file, ie the checkpoint an import resumes from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LogPosition {
+    /// The milestone lines ahead of the position
+    pub line: u64,
+    /// The byte offset of the position in the uncompressed log
+    pub offset: u64,
+}
+
+impl LogFile {
+    /// Open the finished log file at the given path for reading, or stream it if the path holds an http(s) url.
+    /// The log is read from the given position: the uncompressed logs seek to it, the compressed ones skip the lines
+    /// ahead of it, and the binary ones are read through their index instead
+    pub async fn open(file_path: &PathBuf, position: LogPosition) -> anyhow::Result<LogFile> {
+        ensure!(
+            !is_stream(file_path),
+            "The streamed LogFile {} is no longer available",
+            file_path.display()
+        );
+        match file_path.to_str().filter(|_| is_http(file_path)) {
+            Some(url) => Self::from_url(&Url::parse(url)?, position).await,
+            None => Self::from_path(file_path.clone(), position),
+        }
+    }
+    /// Stream the finished log file at the given http(s) url for reading, starting at the given position if the log
+    /// is uncompressed.
+    /// Note: binary logs are read through their index, so they are downloaded first
+    pub async fn from_url(url: &Url, position: LogPosition) -> anyhow::Result<LogFile> {
+        let name = url
+            .path_segments()
+            .and_then(|segments| segments.last())
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("Url {} does not point to a file!", url))?;
+        let (filename, from_ms_index, to_ms_index) = parse_log_filename(name)?;
+        if name.ends_with(&format!(".{}", BINARY_LOG_EXTENSION)) {
+            let dir = get_config()
+                .broker_config
+                .logs_dir
+                .map(PathBuf::from)
+                .unwrap_or_else(std::env::temp_dir)
+                .join("fetched");
+            tokio::fs::create_dir_all(&dir).await?;
+            let nanos = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos();
+            let path = dir.join(format!("{}.{}.download", name, nanos));
+            download_log(url, &path).await?;
+            let std_file = std::fs::File::open(&path);
+            // the opened file stays readable once removed, so nothing is left behind
+            tokio::fs::remove_file(&path).await.ok();
+            let max_log_size = get_config().broker_config.max_log_size.unwrap_or(MAX_LOG_SIZE);
+            let reader = BinaryLogReader::from_std(std_file?, max_log_size)?;
+            let len = reader.remaining();
+            return Ok(LogFile {
+                len,
+                size: len,
+                filename,
+                from_ms_index,
+                to_ms_index,
+                upper_ms_limit: to_ms_index,
+                io: LogFileIo::Binary(reader),
+                verifier: None,
+                skipped_lines: 0,
+                lines_to_skip: 0,
+                maybe_corrupted: false,
+                finished: false,
+            });
+        }
+        let codec = Codec::from_path(std::path::Path::new(name));
+        // compressed logs can't be read from the middle of their frames, so their lines are skipped instead
+        let (offset, lines_to_skip) = match codec {
+            None => (position.offset, 0),
+            Some(_) => (0, position.line),
+        };
+        let (reader, size) = RemoteLogReader::open(url, offset).await?;
+        // the size is unknown if the server doesn't provide the content length
+        let size = size.unwrap_or(0);
+        Ok(LogFile {
+            len: size,
+            size,
+            filename,
+            from_ms_index,
+            to_ms_index,
+            upper_ms_limit: to_ms_index,
+            io: LogFileIo::Reader(LogReader::from_reader(reader, codec)),
+            verifier: None,
+            skipped_lines: position.line,
+            lines_to_skip,
+            maybe_corrupted: false,
+            finished: false,
+        })
+    }
+    /// Read the log file with the given name streamed over the broker websocket
+    pub fn from_stream(path: &std::path::Path, reader: RemoteLogReader) -> anyhow::Result<LogFile> {
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| anyhow::anyhow!("Invalid streamed LogFile {}", path.display()))?;
+        let (filename, from_ms_index, to_ms_index) = parse_log_filename(name)?;
+        Ok(LogFile {
+            len: 0,
+            size: 0,
+            filename,
+            from_ms_index,
+            to_ms_index,
+            upper_ms_limit: to_ms_index,
+            io: LogFileIo::Reader(LogReader::from_reader(reader, Codec::from_path(path))),
+            verifier: None,
+            skipped_lines: 0,
+            lines_to_skip: 0,
+            maybe_corrupted: false,
+            finished: false,
+        })
+    }
+}
+
 impl TryFrom<PathBuf> for LogFile {
     type Error = anyhow::Error;
     fn try_from(file_path: PathBuf) -> Result<Self, Self::Error> {
-        if let Some(filename) = file_path.file_stem() {
-            let filename = filename
-                .to_str()
-                .ok_or(anyhow::anyhow!("Invalid filename!"))?
-                .to_owned();
-            let split = filename.split("to").collect::<Vec<_>>();
-            anyhow::ensure!(split.len() == 2, "Invalid filename!");
-            let (from_ms_index, to_ms_index) = (split[0].parse()?, split[1].parse()?);
-            let std_file = std::fs::OpenOptions::new().write(false).read(true).open(file_path)?;
-            let len = std_file.metadata()?.len();
+        Self::from_path(file_path, LogPosition::default())
+    }
+}
+
+impl LogFile {
+    /// Open the local log file at the given path for reading from the given position
+    fn from_path(file_path: PathBuf, position: LogPosition) -> anyhow::Result<Self> {
+        if let Some(filename) = file_path.file_name() {
+            let (filename, from_ms_index, to_ms_index) =
+                parse_log_filename(filename.to_str().ok_or(anyhow::anyhow!("Invalid filename!"))?)?;
+            let mut std_file = std::fs::OpenOptions::new().write(false).read(true).open(&file_path)?;
+            if file_path
+                .extension()
+                .map(|ext| ext == BINARY_LOG_EXTENSION)
+                .unwrap_or(false)
+            {
+                let max_log_size = get_config().broker_config.max_log_size.unwrap_or(MAX_LOG_SIZE);
+                let reader = BinaryLogReader::from_std(std_file, max_log_size)?;
+                let len = reader.remaining();
+                return Ok(LogFile {
+                    len,
+                    size: len,
+                    filename,
+                    from_ms_index,
+                    to_ms_index,
+                    upper_ms_limit: to_ms_index,
+                    io: LogFileIo::Binary(reader),
+                    verifier: None,
+                    skipped_lines: 0,
+                    lines_to_skip: 0,
+                    maybe_corrupted: false,
+                    finished: false,
+                });
+            }
+            let codec = Codec::from_path(&file_path);
+            let mut len = std_file.metadata()?.len();
+            let mut lines_to_skip = 0;
+            if position.line > 0 {
+                if codec.is_none() && position.offset > 0 && is_line_start(&mut std_file, position.offset, len)? {
+                    std_file.seek(SeekFrom::Start(position.offset))?;
+                    len -= position.offset;
+                } else {
+                    // the compressed logs can't seek, nor can the logs whose checkpoint doesn't point to a line start
+                    std_file.seek(SeekFrom::Start(0))?;
+                    lines_to_skip = position.line;
+                }
+            }
             let file = tokio::fs::File::from_std(std_file);
             Ok(LogFile {
                 len,
+                size: len,
                 filename,
                 from_ms_index,
                 to_ms_index,
                 upper_ms_limit: to_ms_index,
-                file: BufReader::new(file),
+                io: LogFileIo::Reader(LogReader::new(file, codec)),
+                verifier: None,
+                skipped_lines: position.line,
+                lines_to_skip,
                 maybe_corrupted: false,
                 finished: false,
             })
@@ -0,0 +1,292 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Startup recovery of the `.part` log files left behind by a crash or a disk overflow, along with the leftovers
+//! of the interrupted compressions.
+
+use super::*;
+use crate::{
+    manifest::{
+        is_manifest,
+        range_of,
+    },
+    uploader::{
+        UploadRecord,
+        UPLOAD_MANIFEST,
+    },
+};
+use std::{
+    ops::Range,
+    path::Path,
+};
+use tokio::io::{
+    AsyncBufRead,
+    AsyncBufReadExt,
+    BufReader,
+};
+
+/// A `.part` log file truncated at its last complete milestone line
+pub struct RecoveredLog {
+    /// The truncated log file, None if it had no complete milestone line and got removed
+    pub(crate) log_file: Option<LogFile>,
+    /// The logged milestones of the dropped tail, in ascending order, which have to be archived again
+    pub lost: Vec<Range<u32>>,
+}
+
+#[derive(Deserialize)]
+struct MilestoneIndexOnly {
+    milestone_index: u32,
+}
+
+/// Recover the `.part` log files of the given directory.
+/// Every file gets truncated at its last fully parseable milestone line, and the milestones of the dropped tail are
+/// returned as lost where the sync data marks them as logged, up to the next log file. The truncated
+/// files are then finished by the archiver, as if they were never interrupted. The `.tmp` files of the interrupted
+/// compressions and manifests are removed, as well as the plain logs which got compressed already
+pub async fn recover_part_files(dir_path: &PathBuf, sync_data: &SyncData) -> anyhow::Result<Vec<RecoveredLog>> {
+    let mut recovered = Vec::new();
+    let mut dir = match tokio::fs::read_dir(dir_path).await {
+        Ok(dir) => dir,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => bail!(e),
+    };
+    while let Some(entry) = dir.next_entry().await? {
+        let path = entry.path();
+        if remove_leftover(&path).await? {
+            continue;
+        }
+        let from_ms_index = match path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(".part"))
+            .and_then(|stem| stem.parse::<u32>().ok())
+        {
+            Some(from_ms_index) => from_ms_index,
+            None => continue,
+        };
+        match LogFile::recover(&path, from_ms_index).await {
+            Ok(log) => recovered.push(log),
+            Err(e) => {
+                alert!("Unable to recover the log file {}: {}", path.display(), e)
+                    .await
+                    .ok();
+            }
+        }
+    }
+    if recovered.is_empty() {
+        return Ok(Vec::new());
+    }
+    recovered.sort_by_key(|(_, dropped)| dropped.start);
+    let starts = log_starts(dir_path).await?;
+    Ok(recovered
+        .into_iter()
+        .map(|(log_file, dropped)| {
+            let next_start = starts.iter().filter(|start| **start >= dropped.start).min().copied();
+            let lost = logged_lost(dropped, next_start, &sync_data.completed);
+            if !lost.is_empty() {
+                warn!("Unlogging the milestones {:?} lost with a recovered log", lost);
+            }
+            RecoveredLog { log_file, lost }
+        })
+        .collect())
+}
+
+/// Remove the file at the given path if it's left over by an interrupted compression or manifest
+async fn remove_leftover(path: &PathBuf) -> anyhow::Result<bool> {
+    let leftover = match path.extension().and_then(|ext| ext.to_str()) {
+        // ie 0to10.log.zst.tmp or 0to10.manifest.tmp
+        Some("tmp") => {
+            let target = path.with_extension("");
+            is_finished_log(&target) || is_manifest(&target)
+        }
+        // the compressed file is only renamed into place once it is complete
+        Some("log") => {
+            is_finished_log(path)
+                && [Codec::Zstd, Codec::Gzip]
+                    .iter()
+                    .any(|codec| codec.compressed_path(path).exists())
+        }
+        _ => false,
+    };
+    if leftover {
+        tokio::fs::remove_file(path).await?;
+        info!("Removed the leftover file {}", path.display());
+    }
+    Ok(leftover)
+}
+
+/// Scan the milestone lines of a `.part` file, returning the length of its complete milestone lines which are
+/// parseable and in order, the milestone index following the last of them, and the number of dropped lines,
+/// including the torn one
+async fn scan_lines<R: AsyncBufRead + Unpin>(mut reader: R, from_ms_index: u32) -> anyhow::Result<(u64, u32, u32)> {
+    let mut line = Vec::new();
+    let mut to_ms_index = from_ms_index;
+    let mut len = 0;
+    // find the end of the last milestone line which is complete, parseable and in order
+    loop {
+        line.clear();
+        let n = reader.read_until(b'\n', &mut line).await?;
+        let complete = n > 0 && line.ends_with(b"\n");
+        let in_order = complete
+            && serde_json::from_slice::<MilestoneIndexOnly>(&line)
+                .map(|ms| ms.milestone_index == to_ms_index)
+                .unwrap_or(false);
+        if !in_order {
+            break;
+        }
+        len += n as u64;
+        to_ms_index += 1;
+    }
+    // count the dropped milestone lines, including the torn one
+    let mut dropped = 0;
+    if !line.is_empty() {
+        dropped += 1;
+        loop {
+            line.clear();
+            if reader.read_until(b'\n', &mut line).await? == 0 {
+                break;
+            }
+            dropped += 1;
+        }
+    }
+    Ok((len, to_ms_index, dropped))
+}
+
+/// The milestones lost with the dropped tail of a recovered log, which the sync table marks as logged before the next
+/// log file starts, as the tail may have been logged without ever reaching the disk. The milestones in the gaps
+/// between the logged ranges were never synced, so they aren't lost
+fn logged_lost(dropped: Range<u32>, next_start: Option<u32>, completed: &[Range<u32>]) -> Vec<Range<u32>> {
+    let bound = next_start.unwrap_or(u32::MAX).max(dropped.end);
+    let mut lost: Vec<Range<u32>> = completed
+        .iter()
+        .map(|range| range.start.max(dropped.start)..range.end.min(bound))
+        .filter(|range| !range.is_empty())
+        .collect();
+    lost.sort_by_key(|range| range.start);
+    lost
+}
+
+/// The first milestone of the logs of the given directory, including the uploaded ones which got removed locally
+async fn log_starts(dir_path: &PathBuf) -> anyhow::Result<Vec<u32>> {
+    let mut starts = Vec::new();
+    let mut dir = tokio::fs::read_dir(dir_path).await?;
+    while let Some(entry) = dir.next_entry().await? {
+        let path = entry.path();
+        let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        if let Some(from_ms_index) = name.strip_suffix(".part").and_then(|stem| stem.parse::<u32>().ok()) {
+            starts.push(from_ms_index);
+        } else if is_finished_log(&path) {
+            starts.extend(range_of(&path).map(|(from, _)| from));
+        }
+    }
+    if let Ok(manifest) = tokio::fs::read_to_string(dir_path.join(UPLOAD_MANIFEST)).await {
+        starts.extend(
+            manifest
+                .lines()
+                .filter_map(|line| serde_json::from_str::<UploadRecord>(line).ok())
+                .filter(|record| is_finished_log(Path::new(&record.file)))
+                .filter_map(|record| range_of(Path::new(&record.file)).map(|(from, _)| from)),
+        );
+    }
+    Ok(starts)
+}
+
+impl LogFile {
+    /// Truncate the `.part` file at the given path at its last complete milestone line, returning the truncated log
+    /// file along with the milestones of the dropped lines
+    async fn recover(path: &PathBuf, from_ms_index: u32) -> anyhow::Result<(Option<LogFile>, Range<u32>)> {
+        let file = OpenOptions::new().read(true).append(true).open(path).await?;
+        let size = file.metadata().await?.len();
+        let reader = BufReader::new(file.try_clone().await?);
+        let (len, to_ms_index, dropped) = scan_lines(reader, from_ms_index).await?;
+        // the dropped lines are lost at least, the tail which never got written is added once all the logs are known
+        let dropped = to_ms_index..to_ms_index + dropped;
+        if len < size {
+            warn!(
+                "Truncated {} from {} to {} bytes, dropping milestones {:?}",
+                path.display(),
+                size,
+                len,
+                dropped
+            );
+            file.set_len(len).await?;
+            file.sync_all().await?;
+        }
+        if len == 0 {
+            tokio::fs::remove_file(path).await?;
+            info!("Removed the empty log file {}", path.display());
+            return Ok((None, dropped));
+        }
+        let filename = format!("{}.part", from_ms_index);
+        info!(
+            "Recovered {}, with milestones {}..{}",
+            filename, from_ms_index, to_ms_index
+        );
+        Ok((
+            Some(LogFile {
+                len,
+                size: len,
+                filename,
+                from_ms_index,
+                to_ms_index,
+                upper_ms_limit: to_ms_index,
+                io: LogFileIo::Writer(file),
+                verifier: None,
+                skipped_lines: 0,
+                lines_to_skip: 0,
+                maybe_corrupted: false,
+                finished: false,
+            }),
+            dropped,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(content: &str, from_ms_index: u32) -> (u64, u32, u32) {
+        futures::executor::block_on(scan_lines(content.as_bytes(), from_ms_index)).unwrap()
+    }
+
+    #[test]
+    fn truncated_line_is_dropped_along_with_the_following_ones() {
+        let complete = "{\"milestone_index\":10}\n{\"milestone_index\":11}\n";
+        let content = format!(
+            "{}{{\"milestone_index\":12, \"mess\n{{\"milestone_index\":13}}\n",
+            complete
+        );
+        let (len, to_ms_index, dropped) = scan(&content, 10);
+        assert_eq!(len, complete.len() as u64);
+        assert_eq!(to_ms_index, 12);
+        assert_eq!(dropped, 2);
+        // the milestones logged past the dropped lines are lost too, up to the next log
+        assert_eq!(logged_lost(12..14, Some(20), &[30..40, 0..17]), vec![12..17]);
+        assert_eq!(logged_lost(12..14, Some(16), &[0..17]), vec![12..16]);
+        // the dropped lines which the sync table didn't log are still synced but unlogged, or never synced
+        assert_eq!(logged_lost(12..14, None, &[0..12]), vec![]);
+        assert_eq!(logged_lost(12..14, None, &[13..20, 0..12]), vec![13..20]);
+    }
+
+    #[test]
+    fn missing_tail_is_lost_up_to_the_highest_logged_milestone() {
+        // a torn last line without its newline
+        let complete = "{\"milestone_index\":10}\n";
+        let (len, to_ms_index, dropped) = scan(&format!("{}{{\"milestone_index\":11}}", complete), 10);
+        assert_eq!((len, to_ms_index, dropped), (complete.len() as u64, 11, 1));
+        // the lines which never reached the disk, while the sync table marks them as logged
+        let (len, to_ms_index, dropped) = scan(complete, 10);
+        assert_eq!((len, to_ms_index, dropped), (complete.len() as u64, 11, 0));
+        assert_eq!(logged_lost(11..11, None, &[0..25]), vec![11..25]);
+        // across several logged ranges, bounded by the next log, while the gap between them was never synced
+        assert_eq!(
+            logged_lost(11..11, Some(30), &[40..50, 20..28, 0..15]),
+            vec![11..15, 20..28]
+        );
+        // nothing is lost once the next log starts right away
+        assert_eq!(logged_lost(11..11, Some(11), &[0..25]), vec![]);
+        // nor if the sync table didn't log anything past the file
+        assert_eq!(logged_lost(11..11, None, &[0..11]), vec![]);
+    }
+}
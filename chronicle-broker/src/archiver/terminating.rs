@@ -15,11 +15,19 @@ impl<H: ChronicleBrokerScope> Terminating<BrokerHandle<H>> for Archiver {
         let _ = supervisor.as_mut().unwrap().send(event);
         // finialize in progress logs
         for log in self.logs.iter_mut() {
-            if let Err(e) = log.finish(&self.dir_path).await {
-                info!("Unable to finish in progress log file: {}, error: {}", log.filename, e);
-            } else {
-                info!("Finished in progress log file: {}", log.filename);
-            };
+            match log.finish(&self.dir_path, self.finish_options.clone()).await {
+                Ok(task) => {
+                    self.finishing.push(task);
+                    info!("Finished in progress log file: {}", log.filename);
+                }
+                Err(e) => info!("Unable to finish in progress log file: {}, error: {}", log.filename, e),
+            }
+        }
+        // wait for the manifests, compressions and uploads of the finished logs
+        for task in self.finishing.drain(..) {
+            if let Err(e) = task.await {
+                error!("Unable to complete a finished log file: {}", e);
+            }
         }
         _status
     }
@@ -9,7 +9,10 @@ use bee_message::{
     payload::Payload,
     prelude::TransactionId,
 };
-use chronicle_common::metrics::CONFIRMATION_TIME_COLLECTOR;
+use chronicle_common::metrics::{
+    CONFIRMATION_TIME_COLLECTOR,
+    DUPLICATE_MESSAGES,
+};
 use std::sync::Arc;
 
 #[async_trait::async_trait]
@@ -87,6 +90,13 @@ impl<H: ChronicleBrokerScope> EventLoop<BrokerHandle<H>> for Collector {
                     }
                 }
                 CollectorEvent::Message(message_id, mut message) => {
+                    // the metadata which arrived first must describe the message, otherwise it is dropped
+                    if let Some(metadata) = self.lru_msg_ref.peek(&message_id) {
+                        if let Err(e) = verify_metadata(&message_id, &message, metadata) {
+                            reject_message("collector", &self.get_name(), &e);
+                            self.lru_msg_ref.pop(&message_id);
+                        }
+                    }
                     // check if msg already in lru cache(if so then it's already presisted)
                     if let None = self.lru_msg.get(&message_id) {
                         // store message
@@ -96,6 +106,10 @@ impl<H: ChronicleBrokerScope> EventLoop<BrokerHandle<H>> for Collector {
                         // add it to the cache in order to not presist it again.
                         self.lru_msg
                             .put(message_id, (Some(std::time::Instant::now()), self.est_ms, message));
+                    } else {
+                        DUPLICATE_MESSAGES
+                            .with_label_values(&[&self.partition_id.to_string()])
+                            .inc();
                     }
                 }
                 CollectorEvent::MessageReferenced(metadata) => {
@@ -106,6 +120,11 @@ impl<H: ChronicleBrokerScope> EventLoop<BrokerHandle<H>> for Collector {
                     let ref_ms = metadata.referenced_by_milestone_index.as_ref().unwrap();
                     let _partition_id = (ref_ms % (self.collector_count as u32)) as u8;
                     let message_id = metadata.message_id;
+                    // the metadata must describe the collected message, if any
+                    if let Err(e) = self.verify_collected_metadata(&message_id, &metadata) {
+                        reject_message("collector", &self.get_name(), &e);
+                        continue;
+                    }
                     // set the ref_ms to be the most recent ref_ms
                     self.ref_ms.0 = *ref_ms;
                     // update the est_ms to be the most recent ref_ms+1
@@ -139,7 +158,7 @@ impl<H: ChronicleBrokerScope> EventLoop<BrokerHandle<H>> for Collector {
                             if let Some(solidifier_handle) = self.solidifier_handles.get(&_partition_id) {
                                 let full_message = FullMessage::new(message.clone(), metadata.clone());
                                 let full_msg_event = SolidifierEvent::Message(full_message);
-                                let _ = solidifier_handle.send(full_msg_event);
+                                let _ = solidifier_handle.send_async(full_msg_event).await;
                             };
                             // however the message_id might had been requested,
                             if let Some((requested_by_this_ms, _)) = self.pending_requests.remove(&message_id) {
@@ -204,7 +223,7 @@ impl<H: ChronicleBrokerScope> EventLoop<BrokerHandle<H>> for Collector {
                                         if let Some(solidifier_handle) = self.solidifier_handles.get(&solidifier_id) {
                                             let full_message = FullMessage::new(message.clone(), metadata.clone());
                                             let full_msg_event = SolidifierEvent::Message(full_message);
-                                            let _ = solidifier_handle.send(full_msg_event);
+                                            let _ = solidifier_handle.send_async(full_msg_event).await;
                                         }
                                         // make sure to insert the message if it's requested from syncer
                                         if created_by == CreatedBy::Syncer {
@@ -373,6 +392,18 @@ impl Collector {
         }
         Ok(())
     }
+    /// Verify that the metadata matches the collected message with the given id, if any
+    fn verify_collected_metadata(&self, message_id: &MessageId, metadata: &MessageMetadata) -> anyhow::Result<()> {
+        let collected = self
+            .lru_msg
+            .peek(message_id)
+            .map(|(_, _, message)| message)
+            .or_else(|| self.pending_requests.get(message_id).map(|(_, message)| message));
+        match collected {
+            Some(message) => verify_metadata(message_id, message, metadata),
+            None => Ok(()),
+        }
+    }
     /// Push the full message (both the message and message metadata) to a given partiion of the solidifier
     fn push_fullmsg_to_solidifier(&self, partition_id: u8, message: Message, metadata: MessageMetadata) {
         if let Some(solidifier_handle) = self.solidifier_handles.get(&partition_id) {
@@ -389,11 +420,13 @@ impl Collector {
             let _ = solidifier_handle.send(full_msg_event);
         };
     }
-    /// Get the `Chronicle` keyspace of a message
+    /// Get the `Chronicle` keyspace of a message, falling back to the default keyspace if the filter has no decision
     #[cfg(feature = "filter")]
-    fn get_keyspace_for_message(&self, message: &mut Message) -> ChronicleKeyspace {
-        let res = futures::executor::block_on(chronicle_filter::filter_messages(message));
-        ChronicleKeyspace::new(res.keyspace.into_owned())
+    fn get_keyspace_for_message(&self, message: &mut Message, metadata: Option<&MessageMetadata>) -> ChronicleKeyspace {
+        let metadata = metadata.and_then(|metadata| serde_json::to_vec(metadata).ok());
+        futures::executor::block_on(chronicle_filter::filter_messages(message, metadata.as_deref()))
+            .map(|res| ChronicleKeyspace::new(res.keyspace.into_owned()))
+            .unwrap_or_else(|| self.get_keyspace())
     }
     /// Get the Chronicle keyspace
     fn get_keyspace(&self) -> ChronicleKeyspace {
@@ -409,7 +442,7 @@ impl Collector {
         let ledger_inclusion_state;
 
         #[cfg(feature = "filter")]
-        let keyspace = self.get_keyspace_for_message(message);
+        let keyspace = self.get_keyspace_for_message(message, self.lru_msg_ref.peek(message_id));
         #[cfg(not(feature = "filter"))]
         let keyspace = self.get_keyspace();
         let metadata;
@@ -597,7 +630,7 @@ impl Collector {
         metadata: MessageMetadata,
     ) -> anyhow::Result<()> {
         #[cfg(feature = "filter")]
-        let keyspace = self.get_keyspace_for_message(&mut message);
+        let keyspace = self.get_keyspace_for_message(&mut message, Some(&metadata));
         #[cfg(not(feature = "filter"))]
         let keyspace = self.get_keyspace();
         let solidifier_handle = self.clone_solidifier_handle(*self.ref_ms);
@@ -826,7 +859,7 @@ impl Collector {
     fn insert<I, S, K, V>(&self, inherent_worker: &I, keyspace: &S, key: K, value: V) -> anyhow::Result<()>
     where
         I: Inherent,
-        S: 'static + Insert<K, V>,
+        S: 'static + InsertTable<K, V>,
         K: 'static + Send + Sync + Clone,
         V: 'static + Send + Sync + Clone,
     {
@@ -975,7 +1008,7 @@ pub struct SimpleWorker {
 trait Inherent {
     fn inherent_boxed<S, K, V>(&self, keyspace: S, key: K, value: V) -> Box<dyn Worker>
     where
-        S: 'static + Insert<K, V>,
+        S: 'static + InsertTable<K, V>,
         K: 'static + Send + Sync + Clone,
         V: 'static + Send + Sync + Clone;
 }
@@ -984,7 +1017,7 @@ trait Inherent {
 impl Inherent for SimpleWorker {
     fn inherent_boxed<S, K, V>(&self, keyspace: S, key: K, value: V) -> Box<dyn Worker>
     where
-        S: 'static + Insert<K, V>,
+        S: 'static + InsertTable<K, V>,
         K: 'static + Send + Sync + Clone,
         V: 'static + Send + Sync + Clone,
     {
@@ -996,7 +1029,7 @@ impl Inherent for SimpleWorker {
 impl Inherent for AtomicWorker {
     fn inherent_boxed<S, K, V>(&self, keyspace: S, key: K, value: V) -> Box<dyn Worker>
     where
-        S: 'static + Insert<K, V>,
+        S: 'static + InsertTable<K, V>,
         K: 'static + Send + Sync + Clone,
         V: 'static + Send + Sync + Clone,
     {
@@ -35,6 +35,7 @@ impl Collector {
                 .requester_id(id)
                 .api_endpoints(self.api_endpoints.iter().cloned().collect())
                 .retries_per_endpoint(self.retries_per_endpoint)
+                .endpoints_health(self.endpoints_health.clone())
                 .reqwest_client(reqwest_client)
                 .build();
             let (abort_handle, abort_registration) = futures::future::AbortHandle::new_pair();
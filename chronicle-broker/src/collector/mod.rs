@@ -5,6 +5,10 @@ use crate::{
     application::*,
     requester::*,
     solidifier::*,
+    verification::{
+        reject_message,
+        verify_metadata,
+    },
 };
 use anyhow::bail;
 use bee_message::{
@@ -47,6 +51,7 @@ builder!(CollectorBuilder {
     requester_count: u8,
     retries_per_query: usize,
     retries_per_endpoint: usize,
+    endpoints_health: EndpointsHealth,
     requesters_channels: Vec<(RequesterSender, RequesterReceiver)>,
     handle: CollectorHandle,
     storage_config: StorageConfig
@@ -83,7 +88,7 @@ pub enum AskCollector {
 /// CollectorHandle to be passed to siblings(feed sources) and the supervisor(in order to shutdown)
 #[derive(Clone)]
 pub struct CollectorHandle {
-    pub(crate) tx: tokio::sync::mpsc::UnboundedSender<CollectorEvent>,
+    pub(crate) tx: InboxSender<CollectorEvent>,
     pub(crate) requesters_senders: Vec<RequesterSender>,
 }
 
@@ -109,10 +114,10 @@ impl MessageIdPartitioner {
 }
 /// CollectorInbox is used to recv events
 pub struct CollectorInbox {
-    pub(crate) rx: tokio::sync::mpsc::UnboundedReceiver<CollectorEvent>,
+    pub(crate) rx: InboxReceiver<CollectorEvent>,
 }
 impl Deref for CollectorHandle {
-    type Target = tokio::sync::mpsc::UnboundedSender<CollectorEvent>;
+    type Target = InboxSender<CollectorEvent>;
 
     fn deref(&self) -> &Self::Target {
         &self.tx
@@ -126,7 +131,7 @@ impl DerefMut for CollectorHandle {
 }
 
 impl Deref for CollectorInbox {
-    type Target = tokio::sync::mpsc::UnboundedReceiver<CollectorEvent>;
+    type Target = InboxReceiver<CollectorEvent>;
 
     fn deref(&self) -> &Self::Target {
         &self.rx
@@ -188,6 +193,9 @@ pub struct Collector {
     pending_requests: HashMap<MessageId, (u32, Message)>,
     /// The double ended queue stores the api endpoints
     api_endpoints: VecDeque<Url>,
+    /// The health of the api endpoints, shared by all requesters
+    /// NOTE: used by requester
+    endpoints_health: EndpointsHealth,
     /// The http client
     reqwest_client: Client,
     /// The partition configure
@@ -242,6 +250,7 @@ impl Builder for CollectorBuilder {
             inbox: self.inbox.unwrap(),
             pending_requests: HashMap::new(),
             api_endpoints: self.api_endpoints.unwrap(),
+            endpoints_health: self.endpoints_health.expect("Collector expected endpoints health"),
             reqwest_client: self.reqwest_client.unwrap(),
             partition_config,
             default_keyspace,
@@ -0,0 +1,277 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! The archive log files are written as plain json lines, and can be compressed once they are finished.
+//! Readers pick the codec from the file extension, so plain and compressed logs can live in the same directory.
+
+use async_compression::tokio::{
+    bufread::{
+        GzipDecoder,
+        ZstdDecoder,
+    },
+    write::{
+        GzipEncoder,
+        ZstdEncoder,
+    },
+};
+use std::{
+    path::{
+        Path,
+        PathBuf,
+    },
+    pin::Pin,
+    sync::{
+        atomic::{
+            AtomicU64,
+            Ordering,
+        },
+        Arc,
+    },
+    task::{
+        Context,
+        Poll,
+    },
+};
+use tokio::{
+    fs::File,
+    io::{
+        AsyncBufRead,
+        AsyncBufReadExt,
+        AsyncRead,
+        AsyncWrite,
+        AsyncWriteExt,
+        BufReader,
+        ReadBuf,
+    },
+};
+
+/// The codec of a compressed log file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    /// Zstandard
+    Zstd,
+    /// Gzip
+    Gzip,
+}
+
+impl Codec {
+    /// The file extension appended to the compressed log files
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Codec::Zstd => "zst",
+            Codec::Gzip => "gz",
+        }
+    }
+    /// Get the codec of a log file from its extension, None for plain log files
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "zst" => Some(Codec::Zstd),
+            "gz" => Some(Codec::Gzip),
+            _ => None,
+        }
+    }
+    /// Append the codec extension to the given path, ie `0to10.log` becomes `0to10.log.zst`
+    pub fn compressed_path(&self, path: &Path) -> PathBuf {
+        let mut compressed_path = path.as_os_str().to_owned();
+        compressed_path.push(".");
+        compressed_path.push(self.extension());
+        compressed_path.into()
+    }
+}
+
+impl std::str::FromStr for Codec {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "zstd" | "zst" => Ok(Codec::Zstd),
+            "gzip" | "gz" => Ok(Codec::Gzip),
+            _ => anyhow::bail!("Unsupported compression: {}, expected zstd or gzip", s),
+        }
+    }
+}
+
+#[cfg(feature = "chronicle-common")]
+impl From<chronicle_common::config::LogCompression> for Codec {
+    fn from(compression: chronicle_common::config::LogCompression) -> Self {
+        match compression {
+            chronicle_common::config::LogCompression::Zstd => Codec::Zstd,
+            chronicle_common::config::LogCompression::Gzip => Codec::Gzip,
+        }
+    }
+}
+
+/// Compress the finished log file at the given path, and remove it once the compressed file is synced.
+/// The compressed file is written next to it with a `.tmp` suffix and renamed when complete,
+/// so an interrupted compression never leaves a truncated log behind.
+pub async fn compress_log(path: &Path, codec: Codec) -> anyhow::Result<PathBuf> {
+    let compressed_path = codec.compressed_path(path);
+    let tmp_path = compressed_path.with_extension(format!("{}.tmp", codec.extension()));
+    let mut reader = File::open(path).await?;
+    let mut encoder = LogEncoder::new(File::create(&tmp_path).await?, codec);
+    tokio::io::copy(&mut reader, encoder.writer()).await?;
+    encoder.finish().await?;
+    tokio::fs::rename(&tmp_path, &compressed_path).await?;
+    tokio::fs::remove_file(path).await?;
+    Ok(compressed_path)
+}
+
+/// Writes lines to a log file as one compressed frame.
+/// The frames appended to a compressed log are decoded one after the other, so a compressed log can be extended
+/// without being decompressed first
+pub enum LogEncoder {
+    /// Zstandard frame
+    Zstd(ZstdEncoder<File>),
+    /// Gzip member
+    Gzip(GzipEncoder<File>),
+}
+
+impl LogEncoder {
+    /// Start a new frame at the current position of the file
+    pub fn new(file: File, codec: Codec) -> Self {
+        match codec {
+            Codec::Zstd => LogEncoder::Zstd(ZstdEncoder::new(file)),
+            Codec::Gzip => LogEncoder::Gzip(GzipEncoder::new(file)),
+        }
+    }
+    fn writer(&mut self) -> &mut (dyn AsyncWrite + Send + Unpin) {
+        match self {
+            LogEncoder::Zstd(encoder) => encoder,
+            LogEncoder::Gzip(encoder) => encoder,
+        }
+    }
+    /// Compress the bytes into the frame
+    pub async fn write_all(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        self.writer().write_all(bytes).await
+    }
+    /// End the frame and sync the file
+    pub async fn finish(mut self) -> std::io::Result<()> {
+        self.writer().shutdown().await?;
+        let file = match self {
+            LogEncoder::Zstd(encoder) => encoder.into_inner(),
+            LogEncoder::Gzip(encoder) => encoder.into_inner(),
+        };
+        file.sync_all().await
+    }
+}
+
+/// Line reader over a plain or compressed log file, which keeps track of the consumed file bytes.
+pub struct LogReader {
+    lines: Box<dyn AsyncBufRead + Send + Unpin>,
+    codec: Option<Codec>,
+    /// The compressed bytes pulled out of the file by the decoder
+    compressed_bytes: Arc<AtomicU64>,
+    /// The bytes of the lines read so far
+    line_bytes: u64,
+}
+
+impl LogReader {
+    /// Create a line reader over the given file, decompressing it with the given codec if any
+    pub fn new(file: File, codec: Option<Codec>) -> Self {
+        Self::from_reader(file, codec)
+    }
+    /// Create a line reader over any byte stream, ie a log streamed over http
+    pub fn from_reader<R: AsyncRead + Send + Unpin + 'static>(reader: R, codec: Option<Codec>) -> Self {
+        let compressed_bytes = Arc::new(AtomicU64::new(0));
+        let lines: Box<dyn AsyncBufRead + Send + Unpin> = match codec {
+            None => Box::new(BufReader::new(reader)),
+            Some(codec) => {
+                let counted = BufReader::new(CountingReader {
+                    inner: reader,
+                    count: compressed_bytes.clone(),
+                });
+                // the appended logs hold several frames
+                match codec {
+                    Codec::Zstd => {
+                        let mut decoder = ZstdDecoder::new(counted);
+                        decoder.multiple_members(true);
+                        Box::new(BufReader::new(decoder))
+                    }
+                    Codec::Gzip => {
+                        let mut decoder = GzipDecoder::new(counted);
+                        decoder.multiple_members(true);
+                        Box::new(BufReader::new(decoder))
+                    }
+                }
+            }
+        };
+        Self {
+            lines,
+            codec,
+            compressed_bytes,
+            line_bytes: 0,
+        }
+    }
+    /// Open the log file at the given path, picking the codec from its extension
+    pub async fn open(path: &Path) -> std::io::Result<Self> {
+        let file = File::open(path).await?;
+        Ok(Self::new(file, Codec::from_path(path)))
+    }
+    /// Read the next line into the buffer, returning its uncompressed length, or 0 at the end of the file
+    pub async fn read_line(&mut self, buf: &mut String) -> std::io::Result<usize> {
+        let n = self.lines.read_line(buf).await?;
+        self.line_bytes += n as u64;
+        Ok(n)
+    }
+    /// Skip the given number of lines without decoding them, returning the lines skipped before the end of the file
+    pub async fn skip_lines(&mut self, count: u64) -> std::io::Result<u64> {
+        let mut line = Vec::new();
+        for skipped in 0..count {
+            line.clear();
+            let n = self.lines.read_until(b'\n', &mut line).await?;
+            if n == 0 {
+                return Ok(skipped);
+            }
+            self.line_bytes += n as u64;
+        }
+        Ok(count)
+    }
+    /// The codec of the underlying file
+    pub fn codec(&self) -> Option<Codec> {
+        self.codec
+    }
+    /// The file bytes consumed so far.
+    /// Note: for compressed files this grows by chunks, as the decoder pulls them from the file
+    pub fn consumed(&self) -> u64 {
+        match self.codec {
+            None => self.line_bytes,
+            Some(_) => self.compressed_bytes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Counts the bytes read from the inner reader
+struct CountingReader<R> {
+    inner: R,
+    count: Arc<AtomicU64>,
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for CountingReader<R> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        let filled = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = poll {
+            self.count
+                .fetch_add((buf.filled().len() - filled) as u64, Ordering::Relaxed);
+        }
+        poll
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skipped_lines_are_consumed() {
+        futures::executor::block_on(async {
+            let mut reader = LogReader::from_reader(&b"first\nsecond\nthird\n"[..], None);
+            assert_eq!(reader.skip_lines(2).await.unwrap(), 2);
+            assert_eq!(reader.consumed(), 13);
+            let mut line = String::new();
+            reader.read_line(&mut line).await.unwrap();
+            assert_eq!(line, "third\n");
+            // the end of the file stops the skipping
+            assert_eq!(reader.skip_lines(2).await.unwrap(), 0);
+        });
+    }
+}
@@ -12,6 +12,10 @@ use bee_message::{
 };
 
 use super::*;
+use chronicle_common::metrics::{
+    IMPORTED_BYTES,
+    IMPORTED_MILESTONES,
+};
 #[async_trait::async_trait]
 impl<H: ChronicleBrokerScope, T: ImportMode> EventLoop<BrokerHandle<H>> for Importer<T> {
     async fn event_loop(
@@ -45,6 +49,16 @@ impl<H: ChronicleBrokerScope, T: ImportMode> EventLoop<BrokerHandle<H>> for Impo
                                     .in_progress_milestones_data_bytes_size
                                     .remove(&milestone_index)
                                     .expect("Expected size-entry for a milestone data");
+                                IMPORTED_MILESTONES.inc();
+                                IMPORTED_BYTES.inc_by(ms_bytes_size as u64);
+                                supervisor
+                                    .send(BrokerEvent::ImportCheckpoint {
+                                        job_id: self.job_id,
+                                        path: self.file_path.clone(),
+                                        milestone_index,
+                                        bytes: ms_bytes_size as u64,
+                                    })
+                                    .ok();
                                 let skipped = false;
                                 Self::imported(
                                     supervisor,
@@ -54,6 +68,7 @@ impl<H: ChronicleBrokerScope, T: ImportMode> EventLoop<BrokerHandle<H>> for Impo
                                     milestone_index,
                                     ms_bytes_size,
                                     skipped,
+                                    self.throttle.rate(),
                                 );
                                 // check if we should process more
                                 if !self.service.is_stopping() {
@@ -68,6 +83,7 @@ impl<H: ChronicleBrokerScope, T: ImportMode> EventLoop<BrokerHandle<H>> for Impo
                                             error!("{}", e);
                                             Need::Abort
                                         })?;
+                                        self.wait_for_throttle().await;
                                     } else {
                                         // no more milestone data.
                                         if self.in_progress_milestones_data.is_empty() {
@@ -115,6 +131,8 @@ impl<H: ChronicleBrokerScope, T: ImportMode> EventLoop<BrokerHandle<H>> for Impo
                                 synced_record,
                                 self.retries_per_query,
                             );
+                            // the analytic record, then the sync record
+                            self.pending_rows += 2;
                             self.default_keyspace
                                 .insert_prepared(&Synckey, &analytic_record)
                                 .consistency(Consistency::One)
@@ -126,6 +144,7 @@ impl<H: ChronicleBrokerScope, T: ImportMode> EventLoop<BrokerHandle<H>> for Impo
                         self.in_progress_milestones_data
                             .insert(milestone_index, (iter, analytic_record));
                         // NOTE: we only delete it once we get Ok CqlResult
+                        self.wait_for_throttle().await;
                     }
                     ImporterEvent::Shutdown => {
                         self.service.update_status(ServiceStatus::Stopping);
@@ -167,8 +186,14 @@ impl<T: ImportMode> Importer<T> {
             MilestoneIndex(milestone_index),
             metadata.ledger_inclusion_state.clone(),
         )?;
+        // the payloads of the proof context aren't written, except the milestone itself
+        let is_proof_context = self.proof_context.remove(&message_id);
         // insert payload (if any)
-        if let Some(payload) = message.payload() {
+        if let Some(payload) = message
+            .payload()
+            .as_ref()
+            .filter(|payload| !is_proof_context || matches!(payload, Payload::Milestone(_)))
+        {
             self.insert_payload(
                 inherent_worker,
                 &message_id,
@@ -185,7 +210,7 @@ impl<T: ImportMode> Importer<T> {
     }
 
     fn insert_parents<I: Inherent>(
-        &self,
+        &mut self,
         inherent_worker: &I,
         message_id: &MessageId,
         parents: &[MessageId],
@@ -254,7 +279,7 @@ impl<T: ImportMode> Importer<T> {
         Ok(())
     }
     fn insert_index<I: Inherent>(
-        &self,
+        &mut self,
         inherent_worker: &I,
         message_id: &MessageId,
         index: Indexation,
@@ -368,7 +393,7 @@ impl<T: ImportMode> Importer<T> {
         Ok(())
     }
     fn insert_input<I: Inherent>(
-        &self,
+        &mut self,
         inherent_worker: &I,
         message_id: &MessageId,
         transaction_id: &TransactionId,
@@ -383,7 +408,7 @@ impl<T: ImportMode> Importer<T> {
         self.insert(inherent_worker, input_id, transaction_record)
     }
     fn insert_unlock<I: Inherent>(
-        &self,
+        &mut self,
         inherent_worker: &I,
         message_id: &MessageId,
         utxo_transaction_id: &TransactionId,
@@ -398,7 +423,7 @@ impl<T: ImportMode> Importer<T> {
         self.insert(inherent_worker, utxo_id, transaction_record)
     }
     fn insert_output<I: Inherent>(
-        &self,
+        &mut self,
         inherent_worker: &I,
         message_id: &MessageId,
         transaction_id: &TransactionId,
@@ -413,7 +438,7 @@ impl<T: ImportMode> Importer<T> {
         self.insert(inherent_worker, output_id, transaction_record)
     }
     fn insert_address<I: Inherent>(
-        &self,
+        &mut self,
         inherent_worker: &I,
         output: &Output,
         transaction_id: &TransactionId,
@@ -459,12 +484,12 @@ impl<T: ImportMode> Importer<T> {
         }
         Ok(())
     }
-    fn insert<I, K, V>(&self, inherent_worker: &I, key: K, value: V) -> anyhow::Result<()>
+    fn insert<I, K, V>(&mut self, inherent_worker: &I, key: K, value: V) -> anyhow::Result<()>
     where
         I: Inherent,
         K: 'static + Send + Clone,
         V: 'static + Send + Clone,
-        ChronicleKeyspace: Insert<K, V>,
+        ChronicleKeyspace: InsertTable<K, V>,
     {
         let req = self
             .default_keyspace
@@ -473,6 +498,7 @@ impl<T: ImportMode> Importer<T> {
             .build()?;
         let worker = inherent_worker.inherent_boxed(key, value);
         req.send_local(worker);
+        self.pending_rows += 1;
         Ok(())
     }
 }
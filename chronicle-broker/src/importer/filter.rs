@@ -0,0 +1,195 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use bee_message::payload::Payload;
+use std::collections::{
+    HashSet,
+    VecDeque,
+};
+
+impl<T> Importer<T> {
+    /// Keep the messages of the milestone data which match the message filter, along with the messages on their path
+    /// down from the milestone message. The path messages are kept as proof context, so their payloads aren't written.
+    /// Fails if the filter fails for any message, so the milestone isn't imported partially
+    pub(crate) fn retain_filtered(&mut self, milestone_data: &mut MilestoneData) -> anyhow::Result<()> {
+        let milestone_index = milestone_data.milestone_index();
+        let mut matching = HashSet::new();
+        for (message_id, FullMessage(message, metadata)) in milestone_data.messages.iter() {
+            if self.matches_filter(message_id, message, metadata)? {
+                matching.insert(*message_id);
+            }
+        }
+        let milestone_message_id = milestone_data
+            .messages
+            .iter()
+            .find(|(_, FullMessage(message, _))| match message.payload() {
+                Some(Payload::Milestone(milestone)) => *milestone.essence().index() == milestone_index,
+                _ => false,
+            })
+            .map(|(message_id, _)| *message_id);
+        if milestone_message_id.is_none() {
+            warn!("No milestone message in milestone data {}", milestone_index);
+        }
+        let context = retain_proven(
+            &mut milestone_data.messages,
+            milestone_message_id,
+            &matching,
+            |FullMessage(message, _)| &message.parents()[..],
+        );
+        debug!(
+            "Keeping {} matching messages of milestone {}, along with {} messages of their proof",
+            matching.len(),
+            milestone_index,
+            context.len()
+        );
+        self.proof_context.extend(context);
+        Ok(())
+    }
+    /// Whether the message filter pinned by the importer reaches a decision for the message
+    #[cfg(feature = "filter")]
+    fn matches_filter(
+        &self,
+        message_id: &MessageId,
+        message: &Message,
+        metadata: &MessageMetadata,
+    ) -> anyhow::Result<bool> {
+        let filter = self
+            .wasm_filter
+            .as_ref()
+            .ok_or_else(|| anyhow!("No message filter is loaded"))?;
+        let metadata = serde_json::to_vec(metadata)?;
+        let response = filter.filter(&message.pack_new(), &metadata).map_err(|e| {
+            anyhow!(
+                "Message filter {} failed for message {}: {}",
+                filter.path().display(),
+                message_id,
+                e
+            )
+        })?;
+        Ok(response.is_some())
+    }
+    /// Whether the message filter pinned by the importer reaches a decision for the message
+    #[cfg(not(feature = "filter"))]
+    fn matches_filter(
+        &self,
+        _message_id: &MessageId,
+        _message: &Message,
+        _metadata: &MessageMetadata,
+    ) -> anyhow::Result<bool> {
+        bail!("Built without the message filter")
+    }
+}
+
+/// Keep the matching messages along with the messages proving their inclusion, and return the latter, whose
+/// payloads aren't written. Without a milestone message, only the matching messages are kept
+fn retain_proven<V>(
+    messages: &mut HashMap<MessageId, V>,
+    milestone_message_id: Option<MessageId>,
+    matching: &HashSet<MessageId>,
+    parents: impl Fn(&V) -> &[MessageId],
+) -> HashSet<MessageId> {
+    let context = milestone_message_id
+        .map(|milestone_message_id| {
+            proof_context(milestone_message_id, matching, |message_id| {
+                messages.get(message_id).map(&parents)
+            })
+        })
+        .unwrap_or_default();
+    messages.retain(|message_id, _| matching.contains(message_id) || context.contains(message_id));
+    context
+}
+
+/// Collect the messages on the paths down from the milestone message to the matching messages, which prove their
+/// inclusion. The milestone message is always part of it, so the target keyspace holds the milestones, but the
+/// matching messages themselves aren't. `parents` returns the parents of a message of the milestone data, or `None`
+/// if the message isn't part of it
+fn proof_context<'a>(
+    milestone_message_id: MessageId,
+    matching: &HashSet<MessageId>,
+    parents: impl Fn(&MessageId) -> Option<&'a [MessageId]>,
+) -> HashSet<MessageId> {
+    // walk the parents breadth first, so every message remembers the approver it was first reached from
+    let mut approvers = HashMap::new();
+    let mut queue = VecDeque::new();
+    approvers.insert(milestone_message_id, milestone_message_id);
+    queue.push_back(milestone_message_id);
+    while let Some(message_id) = queue.pop_front() {
+        for parent_id in parents(&message_id).unwrap_or_default() {
+            if parents(parent_id).is_some() && !approvers.contains_key(parent_id) {
+                approvers.insert(*parent_id, message_id);
+                queue.push_back(*parent_id);
+            }
+        }
+    }
+    let mut context = HashSet::new();
+    context.insert(milestone_message_id);
+    for message_id in matching.iter() {
+        let mut current = *message_id;
+        while let Some(approver) = approvers.get(&current).copied() {
+            // stop at the milestone message, or at a path which is already kept
+            if approver == current || !context.insert(approver) {
+                break;
+            }
+            current = approver;
+        }
+    }
+    context.retain(|message_id| !matching.contains(message_id));
+    context
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(message_ids: &[MessageId]) -> HashSet<MessageId> {
+        message_ids.iter().copied().collect()
+    }
+
+    #[test]
+    fn proof_context_follows_the_shortest_paths() {
+        let id = |n: u8| MessageId::new([n; 32]);
+        let (milestone, a, b, c, d, e, f, outside) = (id(1), id(2), id(3), id(4), id(5), id(6), id(7), id(8));
+        // the milestone approves a and b, a approves c and d, b approves d and e, d approves f,
+        // and c, e and f approve a message confirmed by a previous milestone
+        let mut dag = HashMap::new();
+        dag.insert(milestone, vec![a, b]);
+        dag.insert(a, vec![c, d]);
+        dag.insert(b, vec![d, e]);
+        dag.insert(c, vec![outside]);
+        dag.insert(d, vec![f]);
+        dag.insert(e, vec![outside]);
+        dag.insert(f, vec![outside]);
+        let retain = |milestone_message_id: Option<MessageId>, matching: &[MessageId]| {
+            let mut messages = dag.clone();
+            let context = retain_proven(&mut messages, milestone_message_id, &set(matching), Vec::as_slice);
+            (messages.keys().copied().collect::<HashSet<_>>(), context)
+        };
+        // f is first reached through a and d, whose payloads are skipped, while the milestone payload is still written
+        assert_eq!(
+            retain(Some(milestone), &[f]),
+            (set(&[milestone, a, d, f]), set(&[milestone, a, d]))
+        );
+        // the paths share their messages
+        assert_eq!(
+            retain(Some(milestone), &[f, e]),
+            (set(&[milestone, a, b, d, e, f]), set(&[milestone, a, b, d]))
+        );
+        // the matching messages are kept for their own payloads, not as proof context
+        assert_eq!(
+            retain(Some(milestone), &[a, f]),
+            (set(&[milestone, a, d, f]), set(&[milestone, d]))
+        );
+        // only the milestone message is kept without matching messages
+        assert_eq!(retain(Some(milestone), &[]), (set(&[milestone]), set(&[milestone])));
+        // a matching milestone message leaves nothing to prove
+        assert_eq!(retain(Some(milestone), &[milestone]), (set(&[milestone]), set(&[])));
+        // the messages outside of the milestone data can't be proven
+        assert_eq!(
+            retain(Some(milestone), &[outside]),
+            (set(&[milestone]), set(&[milestone]))
+        );
+        // without a milestone message, the matching messages are kept without proof
+        assert_eq!(retain(None, &[f, e]), (set(&[e, f]), set(&[])));
+    }
+}
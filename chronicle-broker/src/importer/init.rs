@@ -15,10 +15,23 @@ impl<H: ChronicleBrokerScope, T: ImportMode> Init<BrokerHandle<H>> for Importer<
             self.service.update_status(ServiceStatus::Initializing);
             let event = BrokerEvent::Children(BrokerChild::Importer(self.service.clone(), Ok(()), self.parallelism));
             supervisor.send(event).ok();
-            let log_file = LogFile::try_from(self.file_path.clone()).map_err(|e| {
+            let log_file = match self.stream.take() {
+                Some(reader) => LogFile::from_stream(&self.file_path, reader),
+                None => LogFile::open(&self.file_path, self.position).await,
+            };
+            let mut log_file = log_file.map_err(|e| {
                 error!("Unable to create LogFile. Error: {}", e);
                 Need::Abort
             })?;
+            self.verify_manifest(&mut log_file).await.map_err(|e| {
+                error!("Unable to verify the manifest of LogFile. Error: {}", e);
+                Need::Abort
+            })?;
+            // binary logs skip straight to the import range through their index
+            log_file.seek_range(&self.import_range).await.map_err(|e| {
+                error!("Unable to seek the import range in LogFile. Error: {}", e);
+                Need::Abort
+            })?;
             let from = log_file.from_ms_index();
             let to = log_file.to_ms_index();
             self.log_file_size = log_file.len();
@@ -31,6 +44,7 @@ impl<H: ChronicleBrokerScope, T: ImportMode> Init<BrokerHandle<H>> for Importer<
                 ms_bytes_size: 0,
                 milestone_index: 0,
                 skipped: true,
+                rate: self.throttle.rate(),
             };
             // fetch sync data from the keyspace
             if self.resume {
@@ -60,6 +74,7 @@ impl<T: ImportMode> Importer<T> {
         for _ in 0..self.parallelism {
             if let Some(milestone_data) = self.next_milestone_data(supervisor).await? {
                 T::handle_milestone_data(milestone_data, self)?;
+                self.wait_for_throttle().await;
             } else {
                 self.eof = true;
                 break;
@@ -67,6 +82,43 @@ impl<T: ImportMode> Importer<T> {
         }
         Ok(())
     }
+    /// Verify the manifest signature of the log file, so its lines get verified against the manifest as they are read.
+    /// Logs without a manifest are only accepted if no trusted keys are configured
+    async fn verify_manifest(&self, log_file: &mut LogFile) -> anyhow::Result<()> {
+        let trusted_keys = &get_config_async().await.broker_config.log_manifest.trusted_keys;
+        // binary logs aren't covered by the manifests, so they can't be trusted
+        if self
+            .file_path
+            .extension()
+            .map(|ext| ext == BINARY_LOG_EXTENSION)
+            .unwrap_or(false)
+        {
+            ensure!(
+                trusted_keys.is_empty(),
+                "Binary LogFile {} can't be verified against the trusted keys",
+                self.file_path.display()
+            );
+            return Ok(());
+        }
+        let manifest = match self.file_path.to_str().filter(|_| is_http(&self.file_path)) {
+            Some(url) => fetch_manifest(&Url::parse(url)?).await?,
+            None => LogManifest::read(&self.file_path)?,
+        };
+        match manifest {
+            Some(manifest) => {
+                manifest.verify_signature(&self.file_path, trusted_keys)?;
+                log_file.verify_with(manifest, &self.file_path);
+            }
+            None => {
+                ensure!(trusted_keys.is_empty(), ManifestError::Missing(self.file_path.clone()));
+                warn!(
+                    "No manifest found for LogFile {}, it can't be verified",
+                    self.file_path.display()
+                );
+            }
+        }
+        Ok(())
+    }
     pub(crate) async fn next_milestone_data<H: ChronicleBrokerScope>(
         &mut self,
         supervisor: &BrokerHandle<H>,
@@ -78,11 +130,13 @@ impl<T: ImportMode> Importer<T> {
         let mut scan_budget: usize = 100;
         loop {
             let pre_len = log_file.len();
-            if let Some(milestone_data) = log_file.next().await? {
+            if let Some(mut milestone_data) = log_file.next().await? {
                 let milestone_index = milestone_data.milestone_index();
                 let not_in_import_range = !self.import_range.contains(&milestone_index);
                 let resume = self.resume && self.sync_data.completed.iter().any(|r| r.contains(&milestone_index));
-                if resume || not_in_import_range {
+                // the milestones checkpointed by the import job before it got paused or interrupted
+                let checkpointed = self.checkpoint.iter().any(|r| r.contains(&milestone_index));
+                if resume || checkpointed || not_in_import_range {
                     warn!(
                         "Skipping imported milestone data for milestone index: {}",
                         milestone_index
@@ -97,6 +151,7 @@ impl<T: ImportMode> Importer<T> {
                         milestone_index,
                         ms_bytes_size,
                         skipped,
+                        self.throttle.rate(),
                     );
                     // skip this synced milestone data
                     if scan_budget > 0 {
@@ -106,9 +161,32 @@ impl<T: ImportMode> Importer<T> {
                         tokio::task::yield_now().await;
                     }
                 } else {
+                    if let Some(milestone_keys) = self.milestone_keys.as_ref() {
+                        let milestone_payload = milestone_data
+                            .milestone
+                            .as_ref()
+                            .ok_or_else(|| anyhow!("No milestone payload in milestone data {}", milestone_index))?;
+                        if let Err(e) = verify_milestone_signatures(milestone_payload, milestone_keys) {
+                            alert!("Rejected imported milestone data {}: {}", milestone_index, e)
+                                .await
+                                .ok();
+                            bail!(e);
+                        }
+                    }
+                    for (message_id, full_message) in milestone_data.messages() {
+                        if let Err(e) = verify_full_message(full_message, message_id) {
+                            reject_message(&self.file_path.to_string_lossy(), &e);
+                            bail!("Invalid milestone data {}: {}", milestone_index, e);
+                        }
+                    }
                     let ms_bytes_size = (pre_len - log_file.len()) as usize;
                     self.in_progress_milestones_data_bytes_size
                         .insert(milestone_index, ms_bytes_size);
+                    self.pending_bytes += ms_bytes_size as u64;
+                    if self.filter.is_some() {
+                        self.retain_filtered(&mut milestone_data)
+                            .map_err(|e| anyhow!("Unable to filter milestone data {}: {}", milestone_index, e))?;
+                    }
                     return Ok(Some(milestone_data));
                 }
             } else {
@@ -124,6 +202,7 @@ impl<T: ImportMode> Importer<T> {
         milestone_index: u32,
         ms_bytes_size: usize,
         skipped: bool,
+        rate: ImportRate,
     ) {
         let importer_session = ImporterSession::ProgressBar {
             log_file_size,
@@ -132,6 +211,7 @@ impl<T: ImportMode> Importer<T> {
             ms_bytes_size,
             milestone_index,
             skipped,
+            rate,
         };
         supervisor.send(BrokerEvent::Importer(importer_session)).ok();
     }
@@ -0,0 +1,511 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use crate::manifest::range_of;
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+use tokio::io::AsyncWriteExt;
+
+/// The file of the logs directory which records the import jobs along with their checkpoints
+pub const IMPORT_JOBS_FILE: &str = "import_jobs.json";
+/// The minimum interval between two writes of the checkpoints
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5);
+/// The number of completed jobs kept, the older ones are pruned
+const MAX_COMPLETED_JOBS: usize = 100;
+
+/// The state of a log file of an import job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JobLogState {
+    /// The log waits for an importer
+    Pending,
+    /// An importer is importing the log
+    Running,
+    /// The log got imported
+    Done,
+    /// The importer of the log failed
+    Failed,
+}
+
+/// A log file of an import job, along with its checkpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobLog {
+    path: PathBuf,
+    state: JobLogState,
+    /// The imported milestones, as sorted and disjoint ranges
+    imported: Vec<Range<u32>>,
+    imported_bytes: u64,
+}
+
+/// An import job, which imports the log files of its path
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportJob {
+    id: u64,
+    /// The imported path, replaced by the local copy once a remote source is downloaded
+    path: PathBuf,
+    resume: bool,
+    import_range: Option<Range<u32>>,
+    import_type: ImportType,
+    #[serde(default)]
+    filter: Option<ImportFilter>,
+    state: ImportJobState,
+    /// The log files found so far
+    logs: Vec<JobLog>,
+}
+
+impl ImportJob {
+    /// The import topology of the job
+    pub fn topology(&self) -> BrokerTopology {
+        BrokerTopology::Import {
+            path: self.path.clone(),
+            resume: self.resume,
+            import_range: self.import_range.clone(),
+            import_type: self.import_type,
+            filter: self.filter.clone(),
+        }
+    }
+    /// The imported path of the job
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    /// The state of the job
+    pub fn state(&self) -> ImportJobState {
+        self.state
+    }
+    /// Whether the importers of the job are still running
+    pub fn is_stopping(&self) -> bool {
+        self.logs.iter().any(|log| log.state == JobLogState::Running)
+    }
+    /// The log file at the given path, if it got imported already
+    pub fn is_imported(&self, path: &Path) -> bool {
+        self.logs
+            .iter()
+            .any(|log| log.path == path && log.state == JobLogState::Done)
+    }
+    /// The imported milestones of the log file at the given path
+    pub fn checkpoint(&self, path: &Path) -> Vec<Range<u32>> {
+        self.logs
+            .iter()
+            .find(|log| log.path == path)
+            .map(|log| log.imported.clone())
+            .unwrap_or_default()
+    }
+    /// The position the log file at the given path can be read from, ie after the milestones imported from its
+    /// start
+    pub fn position(&self, path: &Path) -> LogPosition {
+        self.logs
+            .iter()
+            .find(|log| log.path == path)
+            .and_then(|log| match (log.imported.as_slice(), range_of(path)) {
+                ([imported], Some((from, _))) if imported.start == from => Some(LogPosition {
+                    line: (imported.end - imported.start) as u64,
+                    offset: log.imported_bytes,
+                }),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+    /// Report the job and its progress
+    pub fn report(&self) -> ImportJobReport {
+        let count = |state| self.logs.iter().filter(|log| log.state == state).count();
+        ImportJobReport {
+            id: self.id,
+            path: self.path.clone(),
+            import_range: self.import_range.clone(),
+            import_type: self.import_type,
+            filter: self.filter.clone(),
+            state: self.state,
+            logs: self.logs.len(),
+            imported_logs: count(JobLogState::Done),
+            failed_logs: count(JobLogState::Failed),
+            imported_milestones: self
+                .logs
+                .iter()
+                .flat_map(|log| log.imported.iter())
+                .map(|range| (range.end - range.start) as u64)
+                .sum(),
+            imported_bytes: self.logs.iter().map(|log| log.imported_bytes).sum(),
+        }
+    }
+    fn log_mut(&mut self, path: &Path) -> &mut JobLog {
+        match self.logs.iter().position(|log| log.path == path) {
+            Some(position) => &mut self.logs[position],
+            None => {
+                self.logs.push(JobLog {
+                    path: path.to_owned(),
+                    state: JobLogState::Pending,
+                    imported: Vec::new(),
+                    imported_bytes: 0,
+                });
+                self.logs.last_mut().unwrap()
+            }
+        }
+    }
+    /// Complete the running job once none of its logs is left to import
+    fn update_state(&mut self) {
+        if self.state != ImportJobState::Running
+            || self
+                .logs
+                .iter()
+                .any(|log| matches!(log.state, JobLogState::Pending | JobLogState::Running))
+        {
+            return;
+        }
+        if self.logs.iter().any(|log| log.state == JobLogState::Failed) {
+            self.state = ImportJobState::Failed;
+        } else {
+            self.state = ImportJobState::Completed;
+        }
+        info!("Import job {} is {:?}", self.id, self.state);
+    }
+}
+
+/// The import jobs tracked by the broker, persisted to the logs directory so they survive restarts
+pub struct ImportJobs {
+    file_path: Option<PathBuf>,
+    jobs: BTreeMap<u64, ImportJob>,
+    next_id: u64,
+    last_write: Instant,
+    dirty: bool,
+}
+
+impl ImportJobs {
+    /// Load the import jobs of the given logs directory, if any.
+    /// Without a logs directory, the jobs are only tracked in memory.
+    /// A jobs file which can't be loaded is kept with a `.corrupt` suffix, and the jobs start empty
+    pub fn load(logs_dir: Option<&PathBuf>) -> Self {
+        let file_path = logs_dir.map(|dir| dir.join(IMPORT_JOBS_FILE));
+        let mut jobs = match file_path.as_ref().map(|file_path| (file_path, Self::read(file_path))) {
+            Some((_, Ok(jobs))) => jobs,
+            Some((file_path, Err(e))) => {
+                error!("Unable to load the import jobs from {}: {}", file_path.display(), e);
+                let mut corrupt_path = file_path.clone().into_os_string();
+                corrupt_path.push(".corrupt");
+                if let Err(e) = std::fs::rename(file_path, &corrupt_path) {
+                    error!("Unable to move the import jobs aside: {}", e);
+                }
+                BTreeMap::new()
+            }
+            None => BTreeMap::new(),
+        };
+        // no importer survives the broker, so the logs left running got interrupted
+        for log in jobs.values_mut().flat_map(|job| job.logs.iter_mut()) {
+            if log.state == JobLogState::Running {
+                log.state = JobLogState::Pending;
+            }
+        }
+        let next_id = jobs.keys().next_back().map_or(1, |id| id + 1);
+        Self {
+            file_path,
+            jobs,
+            next_id,
+            last_write: Instant::now(),
+            dirty: false,
+        }
+    }
+    fn read(file_path: &Path) -> anyhow::Result<BTreeMap<u64, ImportJob>> {
+        match std::fs::read(file_path) {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+    /// Track a new job for the given import topology, and return its id
+    pub fn create(&mut self, topology: &BrokerTopology) -> Option<u64> {
+        if let BrokerTopology::Import {
+            path,
+            resume,
+            import_range,
+            import_type,
+            filter,
+        } = topology
+        {
+            let id = self.next_id;
+            self.next_id += 1;
+            self.jobs.insert(
+                id,
+                ImportJob {
+                    id,
+                    path: path.clone(),
+                    resume: *resume,
+                    import_range: import_range.clone(),
+                    import_type: *import_type,
+                    filter: filter.clone(),
+                    state: ImportJobState::Running,
+                    logs: Vec::new(),
+                },
+            );
+            self.dirty = true;
+            Some(id)
+        } else {
+            None
+        }
+    }
+    /// Get the job with the given id
+    pub fn get(&self, id: u64) -> Option<&ImportJob> {
+        self.jobs.get(&id)
+    }
+    /// Whether the job with the given id is running, so its logs get imported
+    pub fn is_running(&self, id: u64) -> bool {
+        self.get(id).map_or(false, |job| job.state == ImportJobState::Running)
+    }
+    /// The ids of the running jobs
+    pub fn running(&self) -> Vec<u64> {
+        self.jobs
+            .values()
+            .filter(|job| job.state == ImportJobState::Running)
+            .map(|job| job.id)
+            .collect()
+    }
+    /// The job which imports the log file at the given path
+    pub fn find_running_log(&self, path: &Path) -> Option<u64> {
+        self.jobs
+            .values()
+            .find(|job| {
+                job.logs
+                    .iter()
+                    .any(|log| log.path == path && log.state == JobLogState::Running)
+            })
+            .map(|job| job.id)
+    }
+    /// The paths of the logs the job is importing
+    pub fn running_logs(&self, id: u64) -> Vec<PathBuf> {
+        self.get(id)
+            .map(|job| {
+                job.logs
+                    .iter()
+                    .filter(|log| log.state == JobLogState::Running)
+                    .map(|log| log.path.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+    /// Continue the job from the local copy of its downloaded remote source
+    pub fn set_path(&mut self, id: u64, path: PathBuf) {
+        if let Some(job) = self.jobs.get_mut(&id) {
+            job.path = path;
+            self.dirty = true;
+        }
+    }
+    /// Set the state of the job
+    pub fn set_state(&mut self, id: u64, state: ImportJobState) {
+        if let Some(job) = self.jobs.get_mut(&id) {
+            job.state = state;
+            if state == ImportJobState::Running {
+                // the failed logs are imported again, from their checkpoints
+                for log in job.logs.iter_mut() {
+                    if log.state == JobLogState::Failed {
+                        log.state = JobLogState::Pending;
+                    }
+                }
+            }
+            self.dirty = true;
+        }
+    }
+    /// Set the state of the log file of the job, and update the state of the job accordingly
+    pub fn set_log_state(&mut self, id: u64, path: &Path, state: JobLogState) {
+        if let Some(job) = self.jobs.get_mut(&id) {
+            job.log_mut(path).state = state;
+            job.update_state();
+            self.dirty = true;
+        }
+    }
+    /// Complete the job if none of its logs is left to import, ie once its remaining logs turn out to be imported
+    pub fn refresh(&mut self, id: u64) {
+        if let Some(job) = self.jobs.get_mut(&id) {
+            job.update_state();
+            self.dirty = true;
+        }
+    }
+    /// Mark the job as failed, ie if its path holds no log file
+    pub fn fail(&mut self, id: u64) {
+        if let Some(job) = self.jobs.get_mut(&id) {
+            if job.state == ImportJobState::Running {
+                job.state = ImportJobState::Failed;
+                self.dirty = true;
+            }
+        }
+    }
+    /// Checkpoint the milestone imported from the log file of the job
+    pub fn imported(&mut self, id: u64, path: &Path, milestone_index: u32, bytes: u64) {
+        if let Some(job) = self.jobs.get_mut(&id) {
+            let log = job.log_mut(path);
+            insert_milestone(&mut log.imported, milestone_index);
+            log.imported_bytes += bytes;
+            self.dirty = true;
+        }
+    }
+    /// Report all the jobs
+    pub fn reports(&self) -> Vec<ImportJobReport> {
+        self.jobs.values().map(ImportJob::report).collect()
+    }
+    /// Prune the oldest completed jobs, so at most `MAX_COMPLETED_JOBS` of them are kept
+    fn prune(&mut self) {
+        let completed = self
+            .jobs
+            .values()
+            .filter(|job| job.state == ImportJobState::Completed)
+            .map(|job| job.id)
+            .collect::<Vec<_>>();
+        if completed.len() > MAX_COMPLETED_JOBS {
+            for id in completed[..completed.len() - MAX_COMPLETED_JOBS].iter() {
+                self.jobs.remove(id);
+            }
+        }
+    }
+    /// Write the jobs to the logs directory if they changed.
+    /// Unless forced, the checkpoints are written at most once per checkpoint interval
+    pub async fn save(&mut self, force: bool) {
+        if !self.dirty || (!force && self.last_write.elapsed() < CHECKPOINT_INTERVAL) {
+            return;
+        }
+        self.prune();
+        if let Some(file_path) = self.file_path.as_ref() {
+            if let Err(e) = Self::write(file_path, &self.jobs).await {
+                error!("Unable to write the import jobs to {}: {}", file_path.display(), e);
+                return;
+            }
+        }
+        self.dirty = false;
+        self.last_write = Instant::now();
+    }
+    /// Write the jobs next to the jobs file first, and rename it once synced, so a crash never leaves it half written
+    async fn write(file_path: &Path, jobs: &BTreeMap<u64, ImportJob>) -> anyhow::Result<()> {
+        let tmp_path = file_path.with_extension("json.tmp");
+        let mut file = tokio::fs::File::create(&tmp_path).await?;
+        file.write_all(&serde_json::to_vec_pretty(jobs)?).await?;
+        file.sync_all().await?;
+        tokio::fs::rename(&tmp_path, file_path).await?;
+        // sync the directory too, so the rename itself survives a crash
+        if let Some(dir) = file_path.parent() {
+            tokio::fs::File::open(dir).await?.sync_all().await?;
+        }
+        Ok(())
+    }
+}
+
+/// Insert the milestone index into the sorted and disjoint ranges, merging the adjacent ones
+fn insert_milestone(ranges: &mut Vec<Range<u32>>, milestone_index: u32) {
+    // the first range which ends at or after the milestone index
+    let position = ranges.partition_point(|range| range.end < milestone_index);
+    match ranges.get(position).cloned() {
+        Some(range) if range.contains(&milestone_index) => (),
+        Some(range) if range.end == milestone_index => {
+            ranges[position].end += 1;
+            if ranges
+                .get(position + 1)
+                .map_or(false, |next| next.start == milestone_index + 1)
+            {
+                ranges[position].end = ranges.remove(position + 1).end;
+            }
+        }
+        Some(range) if range.start == milestone_index + 1 => ranges[position].start = milestone_index,
+        _ => ranges.insert(position, milestone_index..milestone_index + 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn import_topology(path: &str) -> BrokerTopology {
+        BrokerTopology::Import {
+            path: PathBuf::from(path),
+            resume: true,
+            import_range: None,
+            import_type: ImportType::All,
+            filter: None,
+        }
+    }
+
+    #[test]
+    fn insert_milestone_merges_adjacent_ranges() {
+        let mut ranges = Vec::new();
+        insert_milestone(&mut ranges, 5);
+        assert_eq!(ranges, vec![5..6]);
+        // extend the range at its end and at its start
+        insert_milestone(&mut ranges, 6);
+        insert_milestone(&mut ranges, 4);
+        assert_eq!(ranges, vec![4..7]);
+        // the duplicates are ignored
+        insert_milestone(&mut ranges, 5);
+        insert_milestone(&mut ranges, 4);
+        assert_eq!(ranges, vec![4..7]);
+        // the disjoint milestones are kept sorted
+        insert_milestone(&mut ranges, 10);
+        insert_milestone(&mut ranges, 0);
+        insert_milestone(&mut ranges, 8);
+        assert_eq!(ranges, vec![0..1, 4..7, 8..9, 10..11]);
+        // filling a gap merges the ranges around it
+        insert_milestone(&mut ranges, 9);
+        assert_eq!(ranges, vec![0..1, 4..7, 8..11]);
+        insert_milestone(&mut ranges, 7);
+        assert_eq!(ranges, vec![0..1, 4..11]);
+        insert_milestone(&mut ranges, u32::MAX - 1);
+        assert_eq!(ranges, vec![0..1, 4..11, u32::MAX - 1..u32::MAX]);
+    }
+
+    #[test]
+    fn position_only_follows_an_imported_prefix() {
+        let mut jobs = ImportJobs::load(None);
+        let id = jobs.create(&import_topology("logs")).unwrap();
+        let path = Path::new("logs/10to20.log");
+        assert_eq!(jobs.get(id).unwrap().position(path), LogPosition::default());
+        jobs.imported(id, path, 10, 100);
+        jobs.imported(id, path, 11, 50);
+        assert_eq!(
+            jobs.get(id).unwrap().position(path),
+            LogPosition { line: 2, offset: 150 }
+        );
+        // a gap after the imported prefix leaves the log to be read from its start
+        jobs.imported(id, path, 13, 50);
+        assert_eq!(jobs.get(id).unwrap().position(path), LogPosition::default());
+        jobs.imported(id, path, 12, 50);
+        assert_eq!(
+            jobs.get(id).unwrap().position(path),
+            LogPosition { line: 4, offset: 250 }
+        );
+        // neither do the milestones imported away from the start of the log
+        let path = Path::new("logs/20to30.log");
+        jobs.imported(id, path, 25, 100);
+        assert_eq!(jobs.get(id).unwrap().position(path), LogPosition::default());
+    }
+
+    #[test]
+    fn completed_jobs_are_pruned() {
+        let mut jobs = ImportJobs::load(None);
+        let ids = (0..MAX_COMPLETED_JOBS + 10)
+            .map(|_| jobs.create(&import_topology("logs")).unwrap())
+            .collect::<Vec<_>>();
+        for id in ids.iter().skip(1) {
+            jobs.set_state(*id, ImportJobState::Completed);
+        }
+        jobs.prune();
+        // the running job is kept along with the latest completed ones
+        assert!(jobs.get(ids[0]).is_some());
+        assert!(ids[1..10].iter().all(|id| jobs.get(*id).is_none()));
+        assert!(ids[10..].iter().all(|id| jobs.get(*id).is_some()));
+    }
+
+    #[test]
+    fn corrupt_jobs_file_is_kept_aside() {
+        let dir = std::env::temp_dir().join(format!("chronicle-import-jobs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(IMPORT_JOBS_FILE), b"{\"1\": {").unwrap();
+        let mut jobs = ImportJobs::load(Some(&dir));
+        assert!(jobs.reports().is_empty());
+        assert_eq!(jobs.create(&import_topology("logs")), Some(1));
+        assert!(!dir.join(IMPORT_JOBS_FILE).exists());
+        assert_eq!(
+            std::fs::read(dir.join(format!("{}.corrupt", IMPORT_JOBS_FILE))).unwrap(),
+            b"{\"1\": {"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -8,7 +8,26 @@ use crate::{
         BrokerHandle,
         ChronicleBrokerScope,
     },
-    archiver::LogFile,
+    archiver::{
+        binary::BINARY_LOG_EXTENSION,
+        LogFile,
+        LogPosition,
+    },
+    manifest::{
+        LogManifest,
+        ManifestError,
+    },
+    monitor::count_failed_insert,
+    remote::{
+        fetch_manifest,
+        is_http,
+        RemoteLogReader,
+    },
+    verification::{
+        reject_message,
+        verify_full_message,
+        verify_milestone_signatures,
+    },
 };
 use bee_message::{
     output::Output,
@@ -18,7 +37,10 @@ use bee_message::{
     },
 };
 use chronicle_common::{
-    config::PartitionConfig,
+    config::{
+        MilestoneKeysConfig,
+        PartitionConfig,
+    },
     Synckey,
 };
 use chronicle_storage::access::SyncRecord;
@@ -27,7 +49,10 @@ use scylla_rs::{
     prelude::stage::ReporterHandle,
 };
 use std::{
-    collections::hash_map::IntoIter,
+    collections::{
+        hash_map::IntoIter,
+        HashSet,
+    },
     ops::{
         Deref,
         DerefMut,
@@ -38,8 +63,22 @@ use std::{
 };
 
 mod event_loop;
+mod filter;
 mod init;
+mod jobs;
 mod terminating;
+mod throttle;
+
+pub use jobs::{
+    ImportJob,
+    ImportJobs,
+    JobLogState,
+    IMPORT_JOBS_FILE,
+};
+pub use throttle::{
+    record_live_insert_error,
+    ImportThrottle,
+};
 
 /// Import all records to all tables
 pub struct All;
@@ -75,6 +114,8 @@ impl ImportMode for Analytics {
         })?;
         let milestone_index = milestone_data.milestone_index();
         let iterator = milestone_data.into_iter();
+        // the messages aren't inserted, so neither is their proof context
+        importer.proof_context.clear();
         importer.insert_analytic_record(&analytic_record)?;
         // note: iterator is not needed to presist analytic record in Analytics mode,
         // however we kept them for simplicty sake.
@@ -91,7 +132,13 @@ builder!(ImporterBuilder<T> {
     resume: bool,
     import_range: Range<u32>,
     parallelism: u8,
-    chronicle_id: u8
+    chronicle_id: u8,
+    throttle: ImportThrottle,
+    job_id: u64,
+    checkpoint: Vec<Range<u32>>,
+    position: LogPosition,
+    stream: RemoteLogReader,
+    filter: ImportFilter
 });
 
 /// Importer events
@@ -107,15 +154,17 @@ pub enum ImporterEvent {
 /// ImporterHandle to be passed to children(Inserter) and the supervisor(in order to shutdown)
 #[derive(Clone)]
 pub struct ImporterHandle {
-    pub(crate) tx: tokio::sync::mpsc::UnboundedSender<ImporterEvent>,
+    pub(crate) tx: InboxSender<ImporterEvent>,
+    /// The throttle shared by the importers, which the workers report their failed inserts to
+    pub(crate) throttle: ImportThrottle,
 }
 
 /// ImporterInbox is used to recv events
 pub struct ImporterInbox {
-    pub(crate) rx: tokio::sync::mpsc::UnboundedReceiver<ImporterEvent>,
+    pub(crate) rx: InboxReceiver<ImporterEvent>,
 }
 impl Deref for ImporterHandle {
-    type Target = tokio::sync::mpsc::UnboundedSender<ImporterEvent>;
+    type Target = InboxSender<ImporterEvent>;
 
     fn deref(&self) -> &Self::Target {
         &self.tx
@@ -129,7 +178,7 @@ impl DerefMut for ImporterHandle {
 }
 
 impl Deref for ImporterInbox {
-    type Target = tokio::sync::mpsc::UnboundedReceiver<ImporterEvent>;
+    type Target = InboxReceiver<ImporterEvent>;
 
     fn deref(&self) -> &Self::Target {
         &self.rx
@@ -170,6 +219,8 @@ pub struct Importer<T> {
     default_keyspace: ChronicleKeyspace,
     /// The partition configuration
     partition_config: PartitionConfig,
+    /// The coordinator keys to verify the imported milestones against
+    milestone_keys: Option<MilestoneKeysConfig>,
     /// The number of retires per query
     retries_per_query: usize,
     /// The chronicle id
@@ -180,6 +231,22 @@ pub struct Importer<T> {
     resume: bool,
     /// The range of requested milestones to import
     import_range: Range<u32>,
+    /// The import job of the log file
+    job_id: u64,
+    /// The milestones the import job already imported from the log file
+    checkpoint: Vec<Range<u32>>,
+    /// The position the log file is read from, ie after the milestones imported from its start
+    position: LogPosition,
+    /// The reader of the log file, if it's streamed over the broker websocket
+    stream: Option<RemoteLogReader>,
+    /// The filter the imported messages are re-routed through, if any
+    filter: Option<ImportFilter>,
+    /// The message filter pinned when the importer is built, so a filter reloaded meanwhile doesn't apply to part of
+    /// the log only
+    #[cfg(feature = "filter")]
+    wasm_filter: Option<std::sync::Arc<chronicle_filter::wasm::WasmFilter>>,
+    /// The messages only kept to prove the inclusion of the filtered ones, until they are inserted
+    proof_context: HashSet<MessageId>,
     /// The database sync data
     sync_data: SyncData,
     /// In progress milestones data
@@ -191,6 +258,12 @@ pub struct Importer<T> {
     inbox: ImporterInbox,
     /// The flag of end of file
     eof: bool,
+    /// The throttle shared by the importers
+    throttle: ImportThrottle,
+    /// The rows sent since the throttle was last acquired
+    pending_rows: u64,
+    /// The log bytes read since the throttle was last acquired
+    pending_bytes: u64,
     /// Import mode marker
     _mode: std::marker::PhantomData<T>,
 }
@@ -205,7 +278,7 @@ impl<T: ImportMode> Builder for ImporterBuilder<T> {
         // In order to use multiple keyspaces, the user must
         // use filters to determine where records go
         let config = chronicle_common::get_config();
-        let default_keyspace = ChronicleKeyspace::new(
+        let mut default_keyspace = ChronicleKeyspace::new(
             config
                 .storage_config
                 .keyspaces
@@ -213,9 +286,21 @@ impl<T: ImportMode> Builder for ImporterBuilder<T> {
                 .and_then(|keyspace| Some(keyspace.name.clone()))
                 .unwrap_or("permanode".to_owned()),
         );
+        // the filtered messages, along with the sync rows, are written into the target keyspace
+        if let Some(filter) = self.filter.as_ref() {
+            default_keyspace = ChronicleKeyspace::new(filter.keyspace.clone()).with_ttl(filter.ttl);
+        }
         let partition_config = config.storage_config.partition_config;
-        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
-        let handle = Some(ImporterHandle { tx });
+        // the milestones are verified against the keys of the keyspace they are written to
+        let milestone_keys = config.storage_config.milestone_keys(default_keyspace.name());
+        let (tx, rx) = crate::inbox::channel("importer", config.broker_config.inbox_capacity.importer);
+        let throttle = self
+            .throttle
+            .unwrap_or_else(|| ImportThrottle::new(config.broker_config.import_throttle.clone()));
+        let handle = Some(ImporterHandle {
+            tx,
+            throttle: throttle.clone(),
+        });
         let inbox = ImporterInbox { rx };
         let import_range = self.import_range.unwrap_or(Range {
             start: 1,
@@ -230,6 +315,7 @@ impl<T: ImportMode> Builder for ImporterBuilder<T> {
             to_ms: 0,
             default_keyspace,
             partition_config,
+            milestone_keys,
             parallelism: self.parallelism.unwrap_or(10),
             chronicle_id: self.chronicle_id.unwrap(),
             in_progress_milestones_data: HashMap::new(),
@@ -237,10 +323,24 @@ impl<T: ImportMode> Builder for ImporterBuilder<T> {
             retries_per_query: self.retries_per_query.unwrap_or(10),
             resume: self.resume.unwrap_or(true),
             import_range,
+            job_id: self.job_id.unwrap_or_default(),
+            checkpoint: self.checkpoint.unwrap_or_default(),
+            position: self.position.unwrap_or_default(),
+            stream: self.stream,
+            #[cfg(feature = "filter")]
+            wasm_filter: self
+                .filter
+                .as_ref()
+                .and_then(|_| chronicle_filter::wasm::current_filter()),
+            filter: self.filter,
+            proof_context: HashSet::new(),
             sync_data: SyncData::default(),
             handle,
             inbox,
             eof: false,
+            throttle,
+            pending_rows: 0,
+            pending_bytes: 0,
             _mode: std::marker::PhantomData::<T>,
         }
         .set_name()
@@ -251,6 +351,12 @@ impl<T> Importer<T> {
     pub(crate) fn clone_handle(&self) -> Option<ImporterHandle> {
         self.handle.clone()
     }
+    /// Wait for the throttle to allow the rows sent and the bytes read since the last call
+    pub(crate) async fn wait_for_throttle(&mut self) {
+        let rows = std::mem::take(&mut self.pending_rows);
+        let bytes = std::mem::take(&mut self.pending_bytes);
+        self.throttle.acquire(rows, bytes).await;
+    }
 }
 /// Implement `Name` trait of the Importer
 impl<T> Name for Importer<T> {
@@ -264,7 +370,7 @@ impl<T> Name for Importer<T> {
     }
 }
 impl Importer<Analytics> {
-    pub(crate) fn insert_analytic_record(&self, analytic_record: &AnalyticRecord) -> anyhow::Result<()> {
+    pub(crate) fn insert_analytic_record(&mut self, analytic_record: &AnalyticRecord) -> anyhow::Result<()> {
         if let Some(importer_handle) = self.handle.clone() {
             let keyspace = self.get_keyspace();
             let worker = AnalyzeWorker::boxed(
@@ -273,6 +379,7 @@ impl Importer<Analytics> {
                 analytic_record.clone(),
                 self.retries_per_query,
             );
+            self.pending_rows += 1;
             self.default_keyspace
                 .insert_prepared(&Synckey, analytic_record)
                 .consistency(Consistency::One)
@@ -298,7 +405,7 @@ impl<H: ChronicleBrokerScope, T: ImportMode> AknShutdown<Importer<T>> for Broker
 #[derive(Clone)]
 pub struct AtomicImporterWorker<S, K, V>
 where
-    S: 'static + Insert<K, V> + Insert<Synckey, SyncRecord>,
+    S: 'static + InsertTable<K, V> + InsertTable<Synckey, SyncRecord>,
     K: 'static + Send,
     V: 'static + Send,
 {
@@ -312,7 +419,7 @@ where
 /// An atomic importer handle
 pub struct AtomicImporterHandle<S>
 where
-    S: 'static + Insert<Synckey, SyncRecord>,
+    S: 'static + InsertTable<Synckey, SyncRecord>,
 {
     /// The importer handle
     pub(crate) handle: ImporterHandle,
@@ -328,7 +435,7 @@ where
 
 impl<S> AtomicImporterHandle<S>
 where
-    S: 'static + Insert<Synckey, SyncRecord>,
+    S: 'static + InsertTable<Synckey, SyncRecord>,
 {
     /// Create a new atomic importer handle with an importer handle, a keyspace, a milestone index, an atomic error
     /// indicator, and a number of retires
@@ -348,9 +455,9 @@ where
         }
     }
 }
-impl<S: Insert<K, V>, K, V> AtomicImporterWorker<S, K, V>
+impl<S: InsertTable<K, V>, K, V> AtomicImporterWorker<S, K, V>
 where
-    S: 'static + Insert<K, V> + Insert<Synckey, SyncRecord>,
+    S: 'static + InsertTable<K, V> + InsertTable<Synckey, SyncRecord>,
     K: 'static + Send,
     V: 'static + Send,
 {
@@ -376,7 +483,7 @@ where
 
 impl<S, K, V> Worker for AtomicImporterWorker<S, K, V>
 where
-    S: 'static + Insert<K, V> + Insert<Synckey, SyncRecord> + Insert<Synckey, AnalyticRecord>,
+    S: 'static + InsertTable<K, V> + InsertTable<Synckey, SyncRecord> + InsertTable<Synckey, AnalyticRecord>,
     K: 'static + Send + Clone,
     V: 'static + Send + Clone,
 {
@@ -394,6 +501,7 @@ where
                 return Ok(());
             }
         }
+        self.handle.handle.throttle.record_error();
         if self.retries > 0 {
             self.retries -= 1;
             // currently we assume all cql/worker errors are retryable, but we might change this in future
@@ -405,6 +513,7 @@ where
             tokio::spawn(async { req.send_global(self) });
         } else {
             // no more retries
+            count_failed_insert::<S, K, V>();
             self.handle.any_error.store(true, Ordering::Relaxed);
         }
         Ok(())
@@ -413,7 +522,7 @@ where
 
 impl<S> Drop for AtomicImporterHandle<S>
 where
-    S: 'static + Insert<Synckey, SyncRecord>,
+    S: 'static + InsertTable<Synckey, SyncRecord>,
 {
     fn drop(&mut self) {
         let any_error = self.any_error.load(Ordering::Relaxed);
@@ -430,7 +539,7 @@ where
 #[derive(Clone)]
 pub struct AnalyzeAndSyncWorker<S>
 where
-    S: 'static + Insert<Synckey, SyncRecord>,
+    S: 'static + InsertTable<Synckey, SyncRecord>,
 {
     /// The importer handle
     handle: ImporterHandle,
@@ -448,7 +557,7 @@ where
 
 impl<S> AnalyzeAndSyncWorker<S>
 where
-    S: 'static + Insert<Synckey, SyncRecord>,
+    S: 'static + InsertTable<Synckey, SyncRecord>,
 {
     /// Create a new sync worker with an importer handle, a keyspace, a `sync` table row (`SyncRecord`), and a number of
     /// retries
@@ -484,7 +593,7 @@ where
 /// Implement the Scylla `Worker` trait
 impl<S> Worker for AnalyzeAndSyncWorker<S>
 where
-    S: 'static + Insert<Synckey, SyncRecord> + Insert<Synckey, AnalyticRecord>,
+    S: 'static + InsertTable<Synckey, SyncRecord> + InsertTable<Synckey, AnalyticRecord>,
 {
     fn handle_response(mut self: Box<Self>, giveload: Vec<u8>) -> anyhow::Result<()> {
         Decoder::from(giveload.try_into()?).get_void()?;
@@ -528,6 +637,7 @@ where
                 return Ok(());
             }
         }
+        self.handle.throttle.record_error();
         if self.retries > 0 {
             self.retries -= 1;
             // currently we assume all cql/worker errors are retryable, but we might change this in future
@@ -550,6 +660,11 @@ where
             }
         } else {
             // no more retries
+            if self.analyzed {
+                count_failed_insert::<S, Synckey, SyncRecord>();
+            } else {
+                count_failed_insert::<S, Synckey, AnalyticRecord>();
+            }
             // respond with error
             let milestone_index = *self.synced_record.milestone_index;
             let _ = self.handle.send(ImporterEvent::CqlResult(Err(milestone_index)));
@@ -561,7 +676,7 @@ where
 /// A milestone data worker
 pub struct MilestoneDataWorker<S>
 where
-    S: 'static + Insert<Synckey, SyncRecord>,
+    S: 'static + InsertTable<Synckey, SyncRecord>,
 {
     /// The arced atomic importer handle for a given keyspace
     arc_handle: std::sync::Arc<AtomicImporterHandle<S>>,
@@ -569,7 +684,7 @@ where
 
 impl<S> MilestoneDataWorker<S>
 where
-    S: 'static + Insert<Synckey, SyncRecord>,
+    S: 'static + InsertTable<Synckey, SyncRecord>,
 {
     /// Create a new milestone data worker with an importer handle, a keyspace, a milestone index, and a number of
     /// retries
@@ -586,7 +701,7 @@ where
 pub(crate) trait Inherent {
     fn inherent_boxed<K, V>(&self, key: K, value: V) -> Box<dyn Worker>
     where
-        ChronicleKeyspace: 'static + Insert<K, V> + Insert<Synckey, SyncRecord>,
+        ChronicleKeyspace: 'static + InsertTable<K, V> + InsertTable<Synckey, SyncRecord>,
         K: 'static + Send + Clone,
         V: 'static + Send + Clone;
 }
@@ -596,7 +711,7 @@ pub(crate) trait Inherent {
 impl Inherent for MilestoneDataWorker<ChronicleKeyspace> {
     fn inherent_boxed<K, V>(&self, key: K, value: V) -> Box<dyn Worker>
     where
-        ChronicleKeyspace: 'static + Insert<K, V> + Insert<Synckey, SyncRecord>,
+        ChronicleKeyspace: 'static + InsertTable<K, V> + InsertTable<Synckey, SyncRecord>,
         K: 'static + Send + Clone,
         V: 'static + Send + Clone,
     {
@@ -608,7 +723,7 @@ impl Inherent for MilestoneDataWorker<ChronicleKeyspace> {
 #[derive(Clone)]
 pub struct AnalyzeWorker<S>
 where
-    S: 'static + Insert<Synckey, AnalyticRecord>,
+    S: 'static + InsertTable<Synckey, AnalyticRecord>,
 {
     /// The importer handle
     handle: ImporterHandle,
@@ -622,7 +737,7 @@ where
 
 impl<S> AnalyzeWorker<S>
 where
-    S: 'static + Insert<Synckey, AnalyticRecord>,
+    S: 'static + InsertTable<Synckey, AnalyticRecord>,
 {
     /// Create a new AnalyzeWorker with an importer handle, a keyspace, a `analytics` table row (`AnalyticRecord`), and
     /// a number of retries
@@ -644,7 +759,7 @@ where
 /// Implement the Scylla `Worker` trait
 impl<S> Worker for AnalyzeWorker<S>
 where
-    S: 'static + Insert<Synckey, AnalyticRecord>,
+    S: 'static + InsertTable<Synckey, AnalyticRecord>,
 {
     fn handle_response(self: Box<Self>, giveload: Vec<u8>) -> anyhow::Result<()> {
         Decoder::from(giveload.try_into()?).get_void()?;
@@ -663,6 +778,7 @@ where
                 return Ok(());
             }
         }
+        self.handle.throttle.record_error();
         if self.retries > 0 {
             self.retries -= 1;
             // currently we assume all cql/worker errors are retryable, but we might change this in future
@@ -675,6 +791,7 @@ where
             tokio::spawn(async { req.send_global(self) });
         } else {
             // no more retries
+            count_failed_insert::<S, Synckey, AnalyticRecord>();
             // respond with error
             let milestone_index = self.analytic_record.milestone_index();
             let _ = self.handle.send(ImporterEvent::CqlResult(Err(**milestone_index)));
@@ -0,0 +1,330 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use chronicle_common::config::ImportThrottleConfig;
+use std::{
+    sync::{
+        atomic::{
+            AtomicU64,
+            Ordering,
+        },
+        Arc,
+        Mutex,
+    },
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+/// The lowest fraction of the rate limits the adaptive backoff goes down to
+const MIN_BACKOFF: f64 = 0.05;
+/// The minimum time between two backoffs, so a burst of failed inserts only halves the rate once
+const BACKOFF_INTERVAL: Duration = Duration::from_secs(1);
+/// The time without failed inserts after which the backoff recovers by one step
+const RECOVERY_INTERVAL: Duration = Duration::from_secs(5);
+/// The fraction of the rate limits recovered per step
+const RECOVERY_STEP: f64 = 0.1;
+/// The length of the window the effective rate is measured over
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+/// The weight of the latest window in the effective rate
+const EWMA_ALPHA: f64 = 0.3;
+
+/// The failed inserts of the live ingestion, which the importers back off from as well
+static LIVE_INSERT_ERRORS: AtomicU64 = AtomicU64::new(0);
+
+/// Count a failed or timed out insert of the live ingestion, so the import throttle backs off
+pub fn record_live_insert_error() {
+    LIVE_INSERT_ERRORS.fetch_add(1, Ordering::Relaxed);
+}
+
+struct ThrottleState {
+    config: ImportThrottleConfig,
+    /// The fraction of the rate limits currently allowed
+    backoff: f64,
+    /// The rows rate the backoff applies to when no rows limit is configured, measured at the first failure
+    measured_limit: Option<f64>,
+    last_backoff: Option<Instant>,
+    last_recovery: Instant,
+    /// The failed inserts of the live ingestion seen so far
+    live_errors: u64,
+    /// The rows which can be written without waiting, negative once overdrawn
+    row_tokens: f64,
+    /// The bytes which can be imported without waiting, negative once overdrawn
+    byte_tokens: f64,
+    last_refill: Instant,
+    window_start: Instant,
+    window_rows: u64,
+    window_bytes: u64,
+    rate: ImportRate,
+}
+
+impl ThrottleState {
+    fn new(config: ImportThrottleConfig, now: Instant) -> Self {
+        Self {
+            config,
+            backoff: 1.0,
+            measured_limit: None,
+            last_backoff: None,
+            last_recovery: now,
+            live_errors: LIVE_INSERT_ERRORS.load(Ordering::Relaxed),
+            row_tokens: 0.0,
+            byte_tokens: 0.0,
+            last_refill: now,
+            window_start: now,
+            window_rows: 0,
+            window_bytes: 0,
+            rate: ImportRate::default(),
+        }
+    }
+    fn rows_limit(&self) -> Option<f64> {
+        self.config
+            .max_rows_per_sec
+            .map(|max| max as f64)
+            .or(self.measured_limit)
+            .map(|limit| limit * self.backoff)
+    }
+    fn bytes_limit(&self) -> Option<f64> {
+        self.config.max_bytes_per_sec.map(|max| max as f64 * self.backoff)
+    }
+    /// Back off if the live ingestion failed inserts since the last poll
+    fn poll_live_errors(&mut self, now: Instant) {
+        let live_errors = LIVE_INSERT_ERRORS.load(Ordering::Relaxed);
+        if live_errors != self.live_errors {
+            self.live_errors = live_errors;
+            self.back_off(now);
+        }
+    }
+    fn refill(&mut self, now: Instant) {
+        if self.backoff < 1.0 && now.duration_since(self.last_recovery) >= RECOVERY_INTERVAL {
+            self.backoff = (self.backoff + RECOVERY_STEP).min(1.0);
+            self.last_recovery = now;
+            if self.backoff >= 1.0 {
+                self.measured_limit = None;
+                info!("The import rate recovered from its backoff");
+            }
+        }
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.last_refill = now;
+        // the buckets hold at most one second worth of tokens, so idle importers can't burst
+        if let Some(limit) = self.rows_limit() {
+            self.row_tokens = (self.row_tokens + elapsed * limit).min(limit);
+        }
+        if let Some(limit) = self.bytes_limit() {
+            self.byte_tokens = (self.byte_tokens + elapsed * limit).min(limit);
+        }
+    }
+    /// Take the rows and bytes out of the buckets, and return the seconds to wait until they are refilled
+    fn take(&mut self, now: Instant, rows: u64, bytes: u64) -> f64 {
+        self.poll_live_errors(now);
+        self.refill(now);
+        self.measure(now, rows, bytes);
+        let mut wait: f64 = 0.0;
+        if let Some(limit) = self.rows_limit() {
+            self.row_tokens -= rows as f64;
+            wait = wait.max(-self.row_tokens / limit);
+        }
+        if let Some(limit) = self.bytes_limit() {
+            self.byte_tokens -= bytes as f64;
+            wait = wait.max(-self.byte_tokens / limit);
+        }
+        wait
+    }
+    fn back_off(&mut self, now: Instant) {
+        if !self.config.adaptive {
+            return;
+        }
+        self.last_recovery = now;
+        if self.last_backoff.map_or(false, |last_backoff| {
+            now.duration_since(last_backoff) < BACKOFF_INTERVAL
+        }) {
+            return;
+        }
+        if self.config.max_rows_per_sec.is_none() && self.measured_limit.is_none() {
+            // without a rows limit, back off from the rate measured so far
+            if self.rate.rows_per_sec <= 0.0 {
+                return;
+            }
+            self.measured_limit = Some(self.rate.rows_per_sec);
+            self.row_tokens = 0.0;
+        }
+        self.backoff = (self.backoff / 2.0).max(MIN_BACKOFF);
+        self.last_backoff = Some(now);
+        warn!(
+            "Failed inserts, backing off the import rate to {:.0}% of its limits",
+            self.backoff * 100.0
+        );
+    }
+    fn measure(&mut self, now: Instant, rows: u64, bytes: u64) {
+        self.window_rows += rows;
+        self.window_bytes += bytes;
+        let elapsed = now.duration_since(self.window_start);
+        if elapsed >= RATE_WINDOW {
+            let secs = elapsed.as_secs_f64();
+            // the previous rate decays once per window elapsed, so an idle spell isn't weighted as a single window
+            let decay = (1.0 - EWMA_ALPHA).powf(secs / RATE_WINDOW.as_secs_f64());
+            self.rate.rows_per_sec = (1.0 - decay) * (self.window_rows as f64 / secs) + decay * self.rate.rows_per_sec;
+            self.rate.bytes_per_sec =
+                (1.0 - decay) * (self.window_bytes as f64 / secs) + decay * self.rate.bytes_per_sec;
+            self.window_start = now;
+            self.window_rows = 0;
+            self.window_bytes = 0;
+        }
+    }
+}
+
+/// Token bucket limiting the rows and bytes written by all the importers together
+#[derive(Clone)]
+pub struct ImportThrottle {
+    state: Arc<Mutex<ThrottleState>>,
+}
+
+impl ImportThrottle {
+    /// Create a new import throttle with the given limits
+    pub fn new(config: ImportThrottleConfig) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(ThrottleState::new(config, Instant::now()))),
+        }
+    }
+    /// Replace the limits, the current backoff is kept if the new limits are still adaptive
+    pub fn configure(&self, config: ImportThrottleConfig) {
+        let mut state = self.state.lock().unwrap();
+        if !config.adaptive {
+            state.backoff = 1.0;
+            state.measured_limit = None;
+        }
+        state.config = config;
+    }
+    /// Take the written rows and imported bytes out of the buckets, and wait until the overdrawn tokens are refilled
+    pub async fn acquire(&self, rows: u64, bytes: u64) {
+        let wait = self.state.lock().unwrap().take(Instant::now(), rows, bytes);
+        if wait > 0.0 {
+            tokio::time::sleep(Duration::from_secs_f64(wait)).await;
+        }
+    }
+    /// Halve the rate after a failed or timed out insert, if the limits are adaptive
+    pub fn record_error(&self) {
+        self.state.lock().unwrap().back_off(Instant::now());
+    }
+    /// The effective rate of the importers, which decays while they are idle
+    pub fn rate(&self) -> ImportRate {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        state.poll_live_errors(now);
+        state.refill(now);
+        state.measure(now, 0, 0);
+        ImportRate {
+            backoff: state.backoff,
+            ..state.rate
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(max_rows_per_sec: Option<u32>, max_bytes_per_sec: Option<u64>, adaptive: bool) -> ImportThrottleConfig {
+        ImportThrottleConfig {
+            max_rows_per_sec,
+            max_bytes_per_sec,
+            adaptive,
+        }
+    }
+
+    #[test]
+    fn token_bucket() {
+        let start = Instant::now();
+        let mut state = ThrottleState::new(config(Some(100), Some(1000), false), start);
+        // the buckets start empty, so the first rows wait for their refill
+        assert_eq!(state.take(start, 50, 0), 0.5);
+        // the bytes limit waits longer than the rows limit
+        assert_eq!(state.take(start, 0, 2000), 2.0);
+        // the overdrawn tokens are paid off after two seconds
+        let later = start + Duration::from_secs(2);
+        assert_eq!(state.take(later, 100, 0), 0.0);
+        // an idle throttle refills at most one second worth of tokens
+        let idle = later + Duration::from_secs(60);
+        assert_eq!(state.take(idle, 100, 1000), 0.0);
+        assert_eq!(state.take(idle, 50, 0), 0.5);
+        // without limits there is nothing to wait for
+        let mut state = ThrottleState::new(config(None, None, false), start);
+        assert_eq!(state.take(start, u32::MAX as u64, u32::MAX as u64), 0.0);
+    }
+
+    #[test]
+    fn moving_rate() {
+        let start = Instant::now();
+        let mut state = ThrottleState::new(config(None, None, false), start);
+        state.measure(start + Duration::from_millis(500), 500, 5000);
+        assert_eq!(state.rate.rows_per_sec, 0.0);
+        state.measure(start + RATE_WINDOW, 500, 5000);
+        assert!((state.rate.rows_per_sec - 1000.0 * EWMA_ALPHA).abs() < 1e-6);
+        assert!((state.rate.bytes_per_sec - 10000.0 * EWMA_ALPHA).abs() < 1e-6);
+        // the rate decays while nothing is imported
+        let measured = state.rate.rows_per_sec;
+        state.measure(start + RATE_WINDOW * 11, 0, 0);
+        assert!(state.rate.rows_per_sec < measured * 0.1);
+    }
+
+    #[test]
+    fn backoff() {
+        let start = Instant::now();
+        // a disabled backoff ignores the failed inserts
+        let mut state = ThrottleState::new(config(Some(100), None, false), start);
+        state.back_off(start);
+        assert_eq!(state.backoff, 1.0);
+        let mut state = ThrottleState::new(config(Some(100), None, true), start);
+        state.back_off(start);
+        assert_eq!(state.backoff, 0.5);
+        assert_eq!(state.rows_limit(), Some(50.0));
+        // a burst of failed inserts halves the rate once
+        state.back_off(start + BACKOFF_INTERVAL / 2);
+        assert_eq!(state.backoff, 0.5);
+        let mut now = start + BACKOFF_INTERVAL;
+        for _ in 0..10 {
+            state.back_off(now);
+            now += BACKOFF_INTERVAL;
+        }
+        assert_eq!(state.backoff, MIN_BACKOFF);
+        // the rate recovers one step per recovery interval without failed inserts
+        state.refill(now + RECOVERY_INTERVAL);
+        assert!((state.backoff - (MIN_BACKOFF + RECOVERY_STEP)).abs() < 1e-9);
+        now += RECOVERY_INTERVAL * 20;
+        state.refill(now);
+        assert!((state.backoff - (MIN_BACKOFF + 2.0 * RECOVERY_STEP)).abs() < 1e-9);
+        for _ in 0..10 {
+            now += RECOVERY_INTERVAL;
+            state.refill(now);
+        }
+        assert_eq!(state.backoff, 1.0);
+        // the failed inserts of the live ingestion back off the importers too
+        record_live_insert_error();
+        assert_eq!(state.take(now, 0, 0), 0.0);
+        assert_eq!(state.backoff, 0.5);
+    }
+
+    #[test]
+    fn backoff_without_rows_limit() {
+        let start = Instant::now();
+        let mut state = ThrottleState::new(config(None, None, true), start);
+        // nothing to back off from until a rate got measured
+        state.back_off(start);
+        assert_eq!(state.backoff, 1.0);
+        assert_eq!(state.rows_limit(), None);
+        state.measure(start + RATE_WINDOW, 1000, 0);
+        let measured = state.rate.rows_per_sec;
+        let now = start + RATE_WINDOW;
+        state.back_off(now);
+        assert_eq!(state.rows_limit(), Some(measured * 0.5));
+        // the measured limit is dropped once the backoff recovered
+        let mut now = now;
+        for _ in 0..10 {
+            now += RECOVERY_INTERVAL;
+            state.refill(now);
+        }
+        assert_eq!(state.backoff, 1.0);
+        assert_eq!(state.rows_limit(), None);
+    }
+}
@@ -0,0 +1,195 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Actor inboxes, which bound the events of the feed sources.
+//!
+//! The feed sources and the requesters `send_async` their events, which waits until the inbox holds less than its
+//! capacity of them, so a slow actor pushes back on them instead of growing its queue without limit.
+//! The events the actors exchange with each other (and with their storage workers) are sent with `send`, which
+//! neither waits nor drops them: they are queued on top of the capacity, as a lost event would leave the milestone it
+//! belongs to pending for good.
+
+use super::*;
+use chronicle_common::metrics::{
+    prometheus::{
+        IntCounter,
+        IntGauge,
+    },
+    INBOX_DEPTH,
+    INBOX_DROPPED,
+};
+use std::sync::Arc;
+use tokio::sync::{
+    mpsc::{
+        error::SendError,
+        UnboundedReceiver,
+        UnboundedSender,
+    },
+    Semaphore,
+};
+
+/// An event along with whether it holds a slot of the inbox capacity
+struct Envelope<T> {
+    event: T,
+    bounded: bool,
+}
+
+/// The sending half of an actor inbox
+pub struct InboxSender<T> {
+    tx: UnboundedSender<Envelope<T>>,
+    /// The free slots of the inbox capacity
+    slots: Arc<Semaphore>,
+    depth: IntGauge,
+    dropped: IntCounter,
+}
+
+impl<T> Clone for InboxSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+            slots: self.slots.clone(),
+            depth: self.depth.clone(),
+            dropped: self.dropped.clone(),
+        }
+    }
+}
+
+impl<T> InboxSender<T> {
+    /// Send an event without waiting, on top of the inbox capacity.
+    /// It only fails if the receiving actor is gone
+    pub fn send(&self, event: T) -> Result<(), SendError<T>> {
+        self.enqueue(Envelope { event, bounded: false })
+    }
+    /// Send an event once the inbox holds less than its capacity of the events sent this way
+    pub async fn send_async(&self, event: T) -> Result<(), SendError<T>> {
+        match self.slots.acquire().await {
+            // the slot is given back once the event is received
+            Ok(slot) => slot.forget(),
+            Err(_) => return Err(SendError(event)),
+        }
+        self.enqueue(Envelope { event, bounded: true }).map_err(|e| {
+            // let the next waiting sender find out the actor is gone as well
+            self.slots.add_permits(1);
+            e
+        })
+    }
+    fn enqueue(&self, envelope: Envelope<T>) -> Result<(), SendError<T>> {
+        match self.tx.send(envelope) {
+            Ok(()) => {
+                self.depth.inc();
+                Ok(())
+            }
+            Err(SendError(envelope)) => {
+                self.dropped.inc();
+                Err(SendError(envelope.event))
+            }
+        }
+    }
+    /// Check if the receiving half was dropped
+    pub fn is_closed(&self) -> bool {
+        self.tx.is_closed()
+    }
+}
+
+/// The receiving half of an actor inbox
+pub struct InboxReceiver<T> {
+    rx: UnboundedReceiver<Envelope<T>>,
+    slots: Arc<Semaphore>,
+    capacity: usize,
+    depth: IntGauge,
+}
+
+impl<T> InboxReceiver<T> {
+    /// Receive the next event, making room for a new one
+    pub async fn recv(&mut self) -> Option<T> {
+        let Envelope { event, bounded } = self.rx.recv().await?;
+        self.depth.dec();
+        if bounded {
+            self.slots.add_permits(1);
+        }
+        Some(event)
+    }
+}
+
+impl<T> Drop for InboxReceiver<T> {
+    fn drop(&mut self) {
+        // wake the waiting senders, so they find out the actor is gone
+        self.rx.close();
+        self.slots.add_permits(self.capacity);
+    }
+}
+
+/// Create the inbox of the named actor, holding up to `capacity` events of the feed sources
+pub fn channel<T>(actor: &str, capacity: usize) -> (InboxSender<T>, InboxReceiver<T>) {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let slots = Arc::new(Semaphore::new(capacity));
+    let depth = INBOX_DEPTH.with_label_values(&[actor]);
+    depth.set(0);
+    let dropped = INBOX_DROPPED.with_label_values(&[actor]);
+    (
+        InboxSender {
+            tx,
+            slots: slots.clone(),
+            depth: depth.clone(),
+            dropped,
+        },
+        InboxReceiver {
+            rx,
+            slots,
+            capacity,
+            depth,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{
+        executor::block_on,
+        FutureExt,
+    };
+
+    #[test]
+    fn actor_events_are_queued_beyond_the_capacity() {
+        let (tx, mut rx) = channel("test_actor_events", 2);
+        for event in 0..5 {
+            assert!(tx.send(event).is_ok());
+        }
+        for event in 0..5 {
+            assert_eq!(block_on(rx.recv()), Some(event));
+        }
+    }
+
+    #[test]
+    fn feed_events_wait_for_room() {
+        let (tx, mut rx) = channel("test_feed_events", 2);
+        assert!(tx.send_async(0).now_or_never().unwrap().is_ok());
+        assert!(tx.send_async(1).now_or_never().unwrap().is_ok());
+        // the inbox is full of feed events, which don't hold up the actor events
+        assert!(tx.send_async(2).now_or_never().is_none());
+        assert!(tx.send(3).is_ok());
+        assert_eq!(block_on(rx.recv()), Some(0));
+        assert!(tx.send_async(2).now_or_never().unwrap().is_ok());
+        for event in [1, 3, 2] {
+            assert_eq!(block_on(rx.recv()), Some(event));
+        }
+        // the capacity is back once the feed events are received
+        assert!(tx.send_async(4).now_or_never().unwrap().is_ok());
+        assert!(tx.send_async(5).now_or_never().unwrap().is_ok());
+        assert!(tx.send_async(6).now_or_never().is_none());
+    }
+
+    #[test]
+    fn events_fail_once_the_actor_is_gone() {
+        let (tx, rx) = channel("test_actor_gone", 1);
+        assert!(tx.send_async(0).now_or_never().unwrap().is_ok());
+        let mut waiting = Box::pin(tx.send_async(1));
+        assert!((&mut waiting).now_or_never().is_none());
+        drop(rx);
+        assert!(tx.is_closed());
+        assert_eq!(block_on(waiting).unwrap_err().0, 1);
+        assert_eq!(tx.send(2).unwrap_err().0, 2);
+        assert_eq!(block_on(tx.send_async(3)).unwrap_err().0, 3);
+    }
+}
@@ -15,12 +15,30 @@ pub mod collector;
 /// The importer, which enables to import write-ahead-logs
 #[cfg(feature = "application")]
 pub mod importer;
+/// Bounded actor inboxes, which push back on the feed sources
+#[cfg(feature = "application")]
+pub mod inbox;
 /// The listener, which receives incoming connections
 #[cfg(feature = "application")]
 pub mod listener;
+/// The monitor endpoint, which serves the broker metrics
+#[cfg(feature = "application")]
+pub mod monitor;
 /// MQTT handler
 #[cfg(feature = "application")]
 pub mod mqtt;
+/// HTTP polling feed source
+#[cfg(feature = "application")]
+pub mod poller;
+/// Raw feed traffic recording
+#[cfg(feature = "application")]
+pub mod recording;
+/// Streaming of the archive logs from http(s) urls
+#[cfg(feature = "application")]
+pub mod remote;
+/// Feed recording replayer
+#[cfg(feature = "application")]
+pub mod replayer;
 /// Missing data requester
 #[cfg(feature = "application")]
 pub mod requester;
@@ -30,6 +48,12 @@ pub mod solidifier;
 /// Milestone syncer
 #[cfg(feature = "application")]
 pub mod syncer;
+/// Archive log uploader, which ships the finished logs to S3-compatible object storage
+#[cfg(feature = "application")]
+pub mod uploader;
+/// Milestone and message verification
+#[cfg(feature = "application")]
+pub mod verification;
 /// Websocket command router
 #[cfg(feature = "application")]
 pub mod websocket;
@@ -53,12 +77,20 @@ mod app {
         MessageId,
     };
     pub use chronicle_common::{
-        config::MqttType,
+        alert,
+        config::{
+            MqttConnectionConfig,
+            MqttType,
+        },
         get_config,
         get_config_async,
         SyncRange,
     };
     pub use chronicle_storage::access::*;
+    pub use inbox::{
+        InboxReceiver,
+        InboxSender,
+    };
     pub use log::*;
     pub use paho_mqtt::{
         AsyncClient,
@@ -86,6 +118,12 @@ mod app {
 #[cfg(feature = "application")]
 use app::*;
 
+#[cfg(any(feature = "merge", feature = "application"))]
+/// Compression of the finished archive files
+pub mod compression;
+#[cfg(any(feature = "merge", feature = "application"))]
+/// Tamper-evident manifests of the finished archive files
+pub mod manifest;
 #[cfg(feature = "merge")]
 /// Provide the archive file merger functionality;
 pub mod merge;
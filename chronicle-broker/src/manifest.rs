@@ -0,0 +1,563 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Manifests of the finished archive logs, which make any modification of a log after it got finished evident.
+//!
+//! The manifest of `<from>to<to>.log` is written next to it as `<from>to<to>.manifest`, and holds the range and the
+//! line count of the log, the SHA-256 of its uncompressed content, and a hash chain over its milestone lines: the link
+//! of a line is the SHA-256 of the previous link followed by the line, the first line being chained to 32 zero bytes.
+//! The first link which doesn't match points to the altered milestone. Manifests can be signed with an Ed25519 key.
+
+use crate::compression::LogReader;
+use anyhow::anyhow;
+use crypto::signatures::ed25519::{
+    PublicKey,
+    SecretKey,
+    Signature,
+    PUBLIC_KEY_LENGTH,
+    SECRET_KEY_LENGTH,
+    SIGNATURE_LENGTH,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use sha2::{
+    Digest,
+    Sha256,
+};
+use std::{
+    convert::TryInto,
+    path::{
+        Path,
+        PathBuf,
+    },
+    sync::Arc,
+};
+use thiserror::Error;
+use tokio::{
+    fs::File,
+    io::AsyncWriteExt,
+};
+
+/// The extension of the log manifests
+pub const MANIFEST_EXTENSION: &str = "manifest";
+/// The domain of the signed manifest digests
+const SIGNING_DOMAIN: &[u8] = b"chronicle-log-manifest";
+
+/// The manifest of a finished log file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LogManifest {
+    /// The first milestone index of the log
+    pub from_ms_index: u32,
+    /// The milestone index following the last one of the log
+    pub to_ms_index: u32,
+    /// The number of milestone lines
+    pub lines: u64,
+    /// The hex encoded SHA-256 of the uncompressed log
+    pub sha256: String,
+    /// The hex encoded hash chain links, one per milestone line
+    pub links: Vec<String>,
+    /// The operator signature, if the manifest is signed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<ManifestSignature>,
+}
+
+/// The Ed25519 signature of a manifest
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ManifestSignature {
+    /// The hex encoded public key of the signer
+    pub public_key: String,
+    /// The hex encoded signature of the manifest digest
+    pub signature: String,
+}
+
+#[derive(Error, Debug)]
+/// The ways a log file can fail to match its manifest
+pub enum ManifestError {
+    /// The log file has no manifest
+    #[error("No manifest found: {0}")]
+    Missing(PathBuf),
+    /// The range of the manifest differs from the log file name
+    #[error("Manifest range {} to {} doesn't match the file name: {path}", .range.start, .range.end)]
+    Range {
+        /// The range of the manifest
+        range: std::ops::Range<u32>,
+        /// The log file
+        path: PathBuf,
+    },
+    /// A milestone line doesn't match its hash chain link
+    #[error("Milestone {milestone} was altered: {path}")]
+    AlteredMilestone {
+        /// The altered milestone index
+        milestone: u32,
+        /// The log file
+        path: PathBuf,
+    },
+    /// The log file has a different number of lines
+    #[error("Found {found} milestone lines where the manifest expects {expected}: {path}")]
+    LineCount {
+        /// The lines found in the log file
+        found: u64,
+        /// The lines of the manifest
+        expected: u64,
+        /// The log file
+        path: PathBuf,
+    },
+    /// The checksum of the log file differs
+    #[error("Checksum mismatch: {0}")]
+    Checksum(PathBuf),
+    /// The manifest isn't signed while a trusted signature is required
+    #[error("Manifest is not signed: {0}")]
+    Unsigned(PathBuf),
+    /// The manifest is signed by a key which isn't trusted
+    #[error("Manifest is signed by the untrusted key {key}: {path}")]
+    UntrustedKey {
+        /// The hex encoded public key of the signer
+        key: String,
+        /// The log file
+        path: PathBuf,
+    },
+    /// The manifest signature is invalid
+    #[error("Invalid manifest signature: {0}")]
+    InvalidSignature(PathBuf),
+    /// The manifest or the log file couldn't be read
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// The SHA-256 of the whole log along with the hash chain over its lines
+struct HashChain {
+    content: Sha256,
+    link: [u8; 32],
+    lines: u64,
+}
+
+impl HashChain {
+    fn new() -> Self {
+        Self {
+            content: Sha256::new(),
+            link: [0; 32],
+            lines: 0,
+        }
+    }
+    /// Chain the next line and return its link
+    fn push(&mut self, line: &[u8]) -> [u8; 32] {
+        self.content.update(line);
+        let mut hasher = Sha256::new();
+        hasher.update(&self.link);
+        hasher.update(line);
+        self.link = hasher.finalize().into();
+        self.lines += 1;
+        self.link
+    }
+    fn sha256(self) -> String {
+        hex::encode(self.content.finalize())
+    }
+}
+
+/// Get the range of a log from its file name, ie 0to10.log, 0to10.log.zst or 0to10.manifest
+pub(crate) fn range_of(path: &Path) -> Option<(u32, u32)> {
+    let name = path.file_name()?.to_str()?;
+    let (from, to) = name.split('.').next()?.split_once("to")?;
+    Some((from.parse().ok()?, to.parse().ok()?))
+}
+
+/// Get the manifest path of the log file at the given path, ie 0to10.manifest for 0to10.log.zst
+pub fn manifest_path(log_path: &Path) -> PathBuf {
+    let name = log_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.split('.').next())
+        .unwrap_or_default();
+    log_path.with_file_name(format!("{}.{}", name, MANIFEST_EXTENSION))
+}
+
+/// Whether the path points to a log manifest, ie 0to10.manifest
+pub fn is_manifest(path: &Path) -> bool {
+    path.extension().map(|ext| ext == MANIFEST_EXTENSION).unwrap_or(false) && range_of(path).is_some()
+}
+
+/// Remove the manifest of the log file at the given path, if any.
+/// Note: this has to be done whenever the content of a log changes, ie when it gets merged
+pub async fn remove_manifest(log_path: &Path) -> std::io::Result<()> {
+    match tokio::fs::remove_file(manifest_path(log_path)).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+impl LogManifest {
+    /// Compute the manifest of the log file at the given path, compressed or not
+    pub async fn compute(log_path: &Path) -> anyhow::Result<Self> {
+        Self::compute_from(log_path, log_path).await
+    }
+    /// Compute the manifest of the log file at the given path from the content of another file, ie the part file it
+    /// is renamed from
+    pub async fn compute_from(content_path: &Path, log_path: &Path) -> anyhow::Result<Self> {
+        let (from_ms_index, to_ms_index) =
+            range_of(log_path).ok_or_else(|| anyhow!("Invalid log file name: {}", log_path.display()))?;
+        let mut reader = LogReader::open(content_path).await?;
+        let mut chain = HashChain::new();
+        let mut links = Vec::new();
+        let mut line = String::new();
+        while reader.read_line(&mut line).await? != 0 {
+            links.push(hex::encode(chain.push(line.as_bytes())));
+            line.clear();
+        }
+        Ok(Self {
+            from_ms_index,
+            to_ms_index,
+            lines: chain.lines,
+            sha256: chain.sha256(),
+            links,
+            signature: None,
+        })
+    }
+    /// Read the manifest of the log file at the given path, None if it has no manifest
+    pub fn read(log_path: &Path) -> anyhow::Result<Option<Self>> {
+        let path = manifest_path(log_path);
+        match std::fs::read(&path) {
+            Ok(bytes) => {
+                Ok(Some(serde_json::from_slice(&bytes).map_err(|e| {
+                    anyhow!("Malformed manifest {}: {}", path.display(), e)
+                })?))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+    /// Write the manifest next to the log file at the given path.
+    /// It's written with a `.tmp` suffix and renamed once synced, so a manifest is never truncated
+    pub async fn write(&self, log_path: &Path) -> anyhow::Result<PathBuf> {
+        let path = manifest_path(log_path);
+        let tmp_path = path.with_extension(format!("{}.tmp", MANIFEST_EXTENSION));
+        let mut file = File::create(&tmp_path).await?;
+        file.write_all(&serde_json::to_vec_pretty(self)?).await?;
+        file.sync_all().await?;
+        tokio::fs::rename(&tmp_path, &path).await?;
+        Ok(path)
+    }
+    /// The digest covered by the signature
+    fn digest(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(SIGNING_DOMAIN);
+        hasher.update(self.from_ms_index.to_le_bytes());
+        hasher.update(self.to_ms_index.to_le_bytes());
+        hasher.update(self.lines.to_le_bytes());
+        hasher.update(self.sha256.as_bytes());
+        for link in self.links.iter() {
+            hasher.update(link.as_bytes());
+        }
+        hasher.finalize().into()
+    }
+    /// Verify the manifest range matches the log file name and its signature, if any.
+    /// If trusted keys are provided, the manifest must be signed by one of them
+    pub fn verify_signature(&self, log_path: &Path, trusted_keys: &[String]) -> Result<(), ManifestError> {
+        if range_of(log_path) != Some((self.from_ms_index, self.to_ms_index)) {
+            return Err(ManifestError::Range {
+                range: self.from_ms_index..self.to_ms_index,
+                path: log_path.to_owned(),
+            });
+        }
+        let signature = match self.signature.as_ref() {
+            Some(signature) => signature,
+            None if trusted_keys.is_empty() => return Ok(()),
+            None => return Err(ManifestError::Unsigned(log_path.to_owned())),
+        };
+        if !trusted_keys.is_empty()
+            && !trusted_keys
+                .iter()
+                .any(|key| key.eq_ignore_ascii_case(&signature.public_key))
+        {
+            return Err(ManifestError::UntrustedKey {
+                key: signature.public_key.clone(),
+                path: log_path.to_owned(),
+            });
+        }
+        let invalid = || ManifestError::InvalidSignature(log_path.to_owned());
+        let public_key: [u8; PUBLIC_KEY_LENGTH] = hex::decode(&signature.public_key)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(invalid)?;
+        let public_key = PublicKey::try_from_bytes(public_key).map_err(|_| invalid())?;
+        let bytes: [u8; SIGNATURE_LENGTH] = hex::decode(&signature.signature)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(invalid)?;
+        if public_key.verify(&Signature::from_bytes(bytes), &self.digest()) {
+            Ok(())
+        } else {
+            Err(invalid())
+        }
+    }
+    /// Create a verifier of the lines of the log file at the given path against this manifest
+    pub fn verifier(self, log_path: &Path) -> ManifestVerifier {
+        self.verifier_from(log_path, 0)
+    }
+    /// Create a verifier of the lines of the log file at the given path, read after skipping the given lines.
+    /// The skipped lines are trusted as the manifest links them, so only the checksum of a whole log is verified
+    pub fn verifier_from(self, log_path: &Path, skipped_lines: u64) -> ManifestVerifier {
+        let mut chain = HashChain::new();
+        if skipped_lines > 0 {
+            if let Some(link) = self
+                .links
+                .get(skipped_lines as usize - 1)
+                .and_then(|link| hex::decode(link).ok())
+                .and_then(|link| link.try_into().ok())
+            {
+                chain.link = link;
+            }
+            chain.lines = skipped_lines;
+        }
+        ManifestVerifier {
+            manifest: self,
+            chain,
+            path: log_path.to_owned(),
+            whole: skipped_lines == 0,
+        }
+    }
+}
+
+/// Verifies the lines of a log file, as they are read, against its manifest
+pub struct ManifestVerifier {
+    manifest: LogManifest,
+    chain: HashChain,
+    path: PathBuf,
+    /// Whether the whole log is read, so its checksum can be verified
+    whole: bool,
+}
+
+impl ManifestVerifier {
+    /// Verify the next milestone line against its hash chain link
+    pub fn verify_line(&mut self, line: &[u8]) -> Result<(), ManifestError> {
+        let position = self.chain.lines as usize;
+        let link = hex::encode(self.chain.push(line));
+        match self.manifest.links.get(position) {
+            Some(expected) if expected.eq_ignore_ascii_case(&link) => Ok(()),
+            Some(_) => Err(ManifestError::AlteredMilestone {
+                milestone: self.manifest.from_ms_index + position as u32,
+                path: self.path.clone(),
+            }),
+            None => Err(ManifestError::LineCount {
+                found: self.chain.lines,
+                expected: self.manifest.lines,
+                path: self.path.clone(),
+            }),
+        }
+    }
+    /// Verify the line count and the checksum once all the lines got verified
+    pub fn finish(self) -> Result<(), ManifestError> {
+        if self.chain.lines != self.manifest.lines {
+            return Err(ManifestError::LineCount {
+                found: self.chain.lines,
+                expected: self.manifest.lines,
+                path: self.path,
+            });
+        }
+        if self.whole && !self.chain.sha256().eq_ignore_ascii_case(&self.manifest.sha256) {
+            return Err(ManifestError::Checksum(self.path));
+        }
+        Ok(())
+    }
+}
+
+/// Verify the log file at the given path against its manifest, reading it whole.
+/// If trusted keys are provided, the manifest must be signed by one of them
+pub async fn verify_log(log_path: &Path, trusted_keys: &[String]) -> Result<(), ManifestError> {
+    let manifest = LogManifest::read(log_path)?.ok_or_else(|| ManifestError::Missing(log_path.to_owned()))?;
+    manifest.verify_signature(log_path, trusted_keys)?;
+    let mut verifier = manifest.verifier(log_path);
+    let mut reader = LogReader::open(log_path).await.map_err(anyhow::Error::from)?;
+    let mut line = String::new();
+    while reader.read_line(&mut line).await.map_err(anyhow::Error::from)? != 0 {
+        verifier.verify_line(line.as_bytes())?;
+        line.clear();
+    }
+    verifier.finish()
+}
+
+/// Signs the manifests with the operator Ed25519 key
+#[derive(Clone)]
+pub struct ManifestSigner {
+    key: Arc<SecretKey>,
+}
+
+impl ManifestSigner {
+    /// Load the hex encoded secret key from the file at the given path
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let hex_key = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Unable to read the signing key {}: {}", path.display(), e))?;
+        let bytes: [u8; SECRET_KEY_LENGTH] = hex::decode(hex_key.trim())
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| {
+                anyhow!(
+                    "Invalid signing key {}, expected a hex encoded Ed25519 key",
+                    path.display()
+                )
+            })?;
+        let key =
+            SecretKey::from_le_bytes(bytes).map_err(|e| anyhow!("Invalid signing key {}: {:?}", path.display(), e))?;
+        Ok(Self { key: Arc::new(key) })
+    }
+    /// The hex encoded public key of the signer
+    pub fn public_key(&self) -> String {
+        hex::encode(self.key.public_key().to_compressed_bytes())
+    }
+    /// Sign the manifest
+    pub fn sign(&self, manifest: &mut LogManifest) {
+        let signature = self.key.sign(&manifest.digest());
+        manifest.signature.replace(ManifestSignature {
+            public_key: self.public_key(),
+            signature: hex::encode(signature.to_bytes()),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINES: [&str; 3] = [
+        "{\"milestone_index\":10}\n",
+        "{\"milestone_index\":11}\n",
+        "{\"milestone_index\":12}\n",
+    ];
+
+    fn manifest(lines: &[&str]) -> LogManifest {
+        let mut chain = HashChain::new();
+        let links = lines
+            .iter()
+            .map(|line| hex::encode(chain.push(line.as_bytes())))
+            .collect();
+        LogManifest {
+            from_ms_index: 10,
+            to_ms_index: 13,
+            lines: chain.lines,
+            sha256: chain.sha256(),
+            links,
+            signature: None,
+        }
+    }
+
+    fn verify(manifest: LogManifest, lines: &[&str]) -> Result<(), ManifestError> {
+        let mut verifier = manifest.verifier(Path::new("10to13.log"));
+        for line in lines {
+            verifier.verify_line(line.as_bytes())?;
+        }
+        verifier.finish()
+    }
+
+    fn signer(seed: u8) -> ManifestSigner {
+        ManifestSigner {
+            key: Arc::new(SecretKey::from_le_bytes([seed; SECRET_KEY_LENGTH]).unwrap()),
+        }
+    }
+
+    #[test]
+    fn hash_chain_links_every_line_to_the_previous_one() {
+        let manifest = manifest(&LINES);
+        assert_eq!(manifest.lines, 3);
+        assert_eq!(manifest.links.len(), 3);
+        // the same line chained after different lines gets a different link
+        let reordered = self::manifest(&[LINES[1], LINES[0], LINES[2]]);
+        assert_ne!(manifest.links[2], reordered.links[2]);
+        assert!(verify(manifest, &LINES).is_ok());
+    }
+
+    #[test]
+    fn tampered_log_points_to_the_altered_milestone() {
+        let tampered = [LINES[0], "{\"milestone_index\":99}\n", LINES[2]];
+        match verify(manifest(&LINES), &tampered) {
+            Err(ManifestError::AlteredMilestone { milestone, .. }) => assert_eq!(milestone, 11),
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn truncated_and_extended_logs_are_rejected() {
+        match verify(manifest(&LINES), &LINES[..2]) {
+            Err(ManifestError::LineCount { found, expected, .. }) => assert_eq!((found, expected), (2, 3)),
+            result => panic!("Unexpected result: {:?}", result),
+        }
+        let extended = [LINES[0], LINES[1], LINES[2], LINES[2]];
+        assert!(matches!(
+            verify(manifest(&LINES), &extended),
+            Err(ManifestError::LineCount {
+                found: 4,
+                expected: 3,
+                ..
+            })
+        ));
+        // a line truncated in place keeps the line count but alters the milestone
+        let truncated = [LINES[0], LINES[1], "{\"milestone_index\":1"];
+        assert!(matches!(
+            verify(manifest(&LINES), &truncated),
+            Err(ManifestError::AlteredMilestone { milestone: 12, .. })
+        ));
+    }
+
+    #[test]
+    fn verification_resumes_after_the_skipped_lines() {
+        let mut verifier = manifest(&LINES).verifier_from(Path::new("10to13.log"), 2);
+        assert!(verifier.verify_line(LINES[2].as_bytes()).is_ok());
+        assert!(verifier.finish().is_ok());
+        let mut verifier = manifest(&LINES).verifier_from(Path::new("10to13.log"), 1);
+        assert!(matches!(
+            verifier.verify_line(LINES[2].as_bytes()),
+            Err(ManifestError::AlteredMilestone { milestone: 11, .. })
+        ));
+    }
+
+    #[test]
+    fn signed_manifest_is_verified_against_the_trusted_keys() {
+        let signer = signer(1);
+        let path = Path::new("10to13.log");
+        let mut manifest = manifest(&LINES);
+        signer.sign(&mut manifest);
+        assert!(manifest.verify_signature(path, &[]).is_ok());
+        assert!(manifest.verify_signature(path, &[signer.public_key()]).is_ok());
+        assert!(manifest
+            .verify_signature(path, &[signer.public_key().to_uppercase()])
+            .is_ok());
+        // the range must match the file name
+        assert!(matches!(
+            manifest.verify_signature(Path::new("10to14.log"), &[]),
+            Err(ManifestError::Range { .. })
+        ));
+    }
+
+    #[test]
+    fn wrong_key_unsigned_and_altered_manifests_are_rejected() {
+        let path = Path::new("10to13.log");
+        let trusted = signer(1);
+        let other = signer(2);
+        let mut manifest = manifest(&LINES);
+        assert!(matches!(
+            manifest.verify_signature(path, &[trusted.public_key()]),
+            Err(ManifestError::Unsigned(_))
+        ));
+        other.sign(&mut manifest);
+        assert!(matches!(
+            manifest.verify_signature(path, &[trusted.public_key()]),
+            Err(ManifestError::UntrustedKey { .. })
+        ));
+        // a manifest rewritten for a tampered log no longer matches its signature
+        trusted.sign(&mut manifest);
+        let mut altered = manifest.clone();
+        altered.links[1] = self::manifest(&[LINES[0], "{\"milestone_index\":99}\n"]).links[1].clone();
+        assert!(matches!(
+            altered.verify_signature(path, &[trusted.public_key()]),
+            Err(ManifestError::InvalidSignature(_))
+        ));
+        // as does a signature claimed by the trusted key but made by another one
+        let mut forged = manifest.clone();
+        other.sign(&mut forged);
+        forged.signature.as_mut().unwrap().public_key = trusted.public_key();
+        assert!(matches!(
+            forged.verify_signature(path, &[trusted.public_key()]),
+            Err(ManifestError::InvalidSignature(_))
+        ));
+    }
+}
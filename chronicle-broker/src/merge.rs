@@ -1,7 +1,22 @@
 // Copyright 2021 IOTA Stiftung
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::MilestoneData;
+use crate::{
+    compression::{
+        compress_log,
+        Codec,
+        LogEncoder,
+        LogReader,
+    },
+    manifest::{
+        remove_manifest,
+        verify_log,
+        LogManifest,
+        ManifestError,
+        ManifestSigner,
+    },
+    MilestoneData,
+};
 use anyhow::{
     anyhow,
     bail,
@@ -19,7 +34,10 @@ use std::{
         DerefMut,
         Range,
     },
-    path::PathBuf,
+    path::{
+        Path,
+        PathBuf,
+    },
 };
 use thiserror::Error;
 use tokio::{
@@ -28,10 +46,8 @@ use tokio::{
         OpenOptions,
     },
     io::{
-        AsyncBufReadExt,
         AsyncSeekExt,
         AsyncWriteExt,
-        BufReader,
     },
 };
 
@@ -54,6 +70,8 @@ enum LogFileError {
     #[error("File exceeds max file size of {max}: {path}")]
     TooBig { max: u64, path: PathBuf },
     #[error(transparent)]
+    Manifest(#[from] ManifestError),
+    #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
 
@@ -89,6 +107,14 @@ impl LogFileError {
                 "This file exceeds the requested maximum file size.
                 The merger will skip this file."
             }
+            LogFileError::Manifest(ManifestError::Missing(_)) => {
+                "This file has no manifest, so it can't be proven unmodified.
+                Manifests are written when the archiver finishes a file, and when the merger writes one."
+            }
+            LogFileError::Manifest(_) => {
+                "This file doesn't match its manifest, it was modified after being finished.
+                It should be restored from a backup or fetched again."
+            }
             LogFileError::Other(_) => "An unknown error occurred.",
         }
     }
@@ -150,30 +176,62 @@ struct LogFile {
     file_path: PathBuf,
     file: File,
     len: u64,
+    /// The codec of a compressed log file
+    codec: Option<Codec>,
+    /// The frame the lines appended to a compressed log file are written to
+    encoder: Option<LogEncoder>,
+    /// The codec to compress the file with once it is closed
+    compress: Option<Codec>,
+    /// Whether to write the manifest of the file once it is closed
+    write_manifest: bool,
+    /// The signer of the manifest
+    signer: Option<ManifestSigner>,
     pub err: bool,
     pub finalized: bool,
 }
 
 impl LogFile {
     pub fn new(start: u32, end: u32, file_path: PathBuf, file: File, len: u64) -> Self {
+        let codec = Codec::from_path(&file_path);
+        // the extension ahead of the codec one, ie fin for 0to10.log.fin.zst
+        let ext = match codec {
+            Some(_) => file_path.with_extension("").extension().map(ToOwned::to_owned),
+            None => file_path.extension().map(ToOwned::to_owned),
+        };
         Self {
             file,
             len,
             start,
             end,
-            err: file_path.extension().map(|ext| ext == "err").unwrap_or(false),
-            finalized: file_path.extension().map(|ext| ext == "fin").unwrap_or(false),
+            codec,
+            encoder: None,
+            compress: None,
+            write_manifest: false,
+            signer: None,
+            err: ext.as_ref().map(|ext| ext == "err").unwrap_or(false),
+            finalized: ext.as_ref().map(|ext| ext == "fin").unwrap_or(false),
             file_path,
         }
     }
     pub fn len(&self) -> u64 {
         self.len
     }
-    /// Append a new line to the active log file
+    /// Append a new line to the active log file.
+    /// Note: the lines appended to a compressed log count with their uncompressed length
     pub async fn append_line(&mut self, line: &String) -> anyhow::Result<()> {
         let bytes = line.as_bytes();
-        // append to the file
-        if let Err(e) = self.file.write_all(bytes).await {
+        // append to the file, within a new frame if it is compressed
+        let res = match self.codec {
+            Some(codec) => {
+                if self.encoder.is_none() {
+                    self.encoder
+                        .replace(LogEncoder::new(self.file.try_clone().await?, codec));
+                }
+                self.encoder.as_mut().unwrap().write_all(bytes).await
+            }
+            None => self.file.write_all(bytes).await,
+        };
+        if let Err(e) = res {
             bail!(
                 "Unable to append milestone data line into the log file: {:?}, error: {:?}",
                 self.file_path,
@@ -213,11 +271,17 @@ impl LogFile {
                     pb.set_message(format!("Validating {}", self.file_path.to_string_lossy()));
                 }
                 let path = self.file_path.clone();
-                let reader = BufReader::new(&mut self.file);
+                // the cloned handle shares the cursor, which is rewound below
+                let file = self.file.try_clone().await.map_err(|e| anyhow!(e))?;
+                let mut reader = LogReader::new(file, self.codec);
                 let mut est_idx = self.start;
-                let mut lines = reader.lines();
+                let mut line = String::new();
                 let mut extra = 0;
-                while let Some(line) = lines.next_line().await.map_err(|e| anyhow!(e))? {
+                loop {
+                    line.clear();
+                    if reader.read_line(&mut line).await.map_err(|e| anyhow!(e))? == 0 {
+                        break;
+                    }
                     // If we've exceeded our claimed range, just add up the extras
                     if est_idx >= self.end {
                         extra += 1;
@@ -269,7 +333,7 @@ impl LogFile {
 
                     est_idx += 1;
                     if let Some(pb) = progress_bar.as_mut() {
-                        pb.inc(line.as_bytes().len() as u64);
+                        pb.set_position(reader.consumed());
                     }
                 }
                 if extra > 0 {
@@ -288,10 +352,16 @@ impl LogFile {
         Ok(())
     }
 
-    async fn close(&mut self) -> anyhow::Result<()> {
+    /// Close the log file, renaming it after its range and status.
+    /// The written files then get their manifest, and get compressed if asked
+    async fn close(mut self) -> anyhow::Result<()> {
+        if let Some(encoder) = self.encoder.take() {
+            encoder.finish().await?;
+        }
         self.file.flush().await?;
-        if self.file.metadata().await.is_ok() {
-            let new_path = self.file_path.parent().unwrap().join(&format!(
+        // the consumed and the empty files are removed already
+        if tokio::fs::metadata(&self.file_path).await.is_ok() {
+            let mut new_path = self.file_path.parent().unwrap().join(&format!(
                 "{}to{}.{}",
                 self.start,
                 self.end,
@@ -303,20 +373,28 @@ impl LogFile {
                     "log"
                 }
             ));
+            if let Some(codec) = self.codec {
+                new_path = codec.compressed_path(&new_path);
+            }
             if self.file_path != new_path {
-                tokio::fs::rename(&self.file_path, new_path).await?;
+                tokio::fs::rename(&self.file_path, &new_path).await?;
+            }
+            if self.write_manifest && !self.err {
+                let mut manifest = LogManifest::compute(&new_path).await?;
+                if let Some(signer) = self.signer.as_ref() {
+                    signer.sign(&mut manifest);
+                }
+                manifest.write(&new_path).await?;
+            }
+            // the compressed logs keep their codec
+            if let (Some(codec), None, false) = (self.compress, self.codec, self.err) {
+                compress_log(&new_path, codec).await?;
             }
         }
         Ok(())
     }
 }
 
-impl std::ops::Drop for LogFile {
-    fn drop(&mut self) {
-        tokio::task::block_in_place(|| futures::executor::block_on(self.close())).unwrap();
-    }
-}
-
 /// Sorted log paths with start and end milestones
 #[derive(Default)]
 pub struct LogPaths(Vec<(u32, u32, PathBuf)>);
@@ -337,25 +415,26 @@ impl LogPaths {
             Err(_) => None,
         };
         if let Some(dir) = logs_dir.to_str() {
-            let paths = glob::glob(&format!("{}/*to*.log", dir))
-                .unwrap()
-                .filter_map(split_filename)
-                .chain(
-                    glob::glob(&format!("{}/*.log.active", dir))
-                        .unwrap()
-                        .filter_map(split_filename),
-                );
-            let mut paths = if include_finalized {
-                paths
-                    .chain(
-                        glob::glob(&format!("{}/*to*.log.fin", dir))
-                            .unwrap()
-                            .filter_map(split_filename),
+            let mut patterns = vec!["*to*.log", "*.log.active"];
+            if include_finalized {
+                patterns.push("*to*.log.fin");
+            }
+            let mut paths = patterns
+                .into_iter()
+                .flat_map(|pattern| {
+                    // the compressed logs, ie 0to10.log.zst or 0.log.active.zst
+                    std::iter::once(pattern.to_owned()).chain(
+                        [Codec::Zstd, Codec::Gzip]
+                            .iter()
+                            .map(move |codec| format!("{}.{}", pattern, codec.extension())),
                     )
-                    .collect::<Vec<_>>()
-            } else {
-                paths.collect::<Vec<_>>()
-            };
+                })
+                .flat_map(|pattern| {
+                    glob::glob(&format!("{}/{}", dir, pattern))
+                        .unwrap()
+                        .filter_map(split_filename)
+                })
+                .collect::<Vec<_>>();
             paths.sort_unstable_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
             Ok(Self(paths))
         } else {
@@ -363,8 +442,9 @@ impl LogPaths {
         }
     }
 
-    /// Perform validation of the logs defined by these paths
-    pub async fn validate(self, max_log_size: u64, progress_bar: bool) -> anyhow::Result<()> {
+    /// Perform validation of the logs defined by these paths, including their manifests.
+    /// If trusted keys are provided, the manifests must be signed by one of them
+    pub async fn validate(self, max_log_size: u64, progress_bar: bool, trusted_keys: &[String]) -> anyhow::Result<()> {
         let mut progress_bar = progress_bar.then(|| {
             let style = ProgressStyle::default_bar()
                 .template(
@@ -402,9 +482,23 @@ impl LogPaths {
                 Self::handle_err(&mut progress_bar, e)?;
             }
             let mut log = LogFile::new(start, end, path.clone(), file, len);
+            // the validated files are only read, so they are left as they are
             if let Err(e) = log.verify(ValidationLevel::Full, &mut progress_bar).await {
                 Self::handle_err(&mut progress_bar, e)?;
             }
+            // the active files of an interrupted merge have no manifest yet
+            if !path.to_string_lossy().contains(".log.active") {
+                match verify_log(&path, trusted_keys).await {
+                    Ok(()) => (),
+                    // the logs finished before the manifests were introduced are only reported
+                    Err(e @ ManifestError::Missing(_)) if trusted_keys.is_empty() => {
+                        if let Some(pb) = progress_bar.as_ref() {
+                            pb.println(format!("Validation Warning: {}", e));
+                        }
+                    }
+                    Err(e) => Self::handle_err(&mut progress_bar, e.into())?,
+                }
+            }
             prev_end = Some(end);
         }
         Ok(())
@@ -448,6 +542,9 @@ pub struct Merger {
     validation_level: ValidationLevel,
     exit_on_val_err: bool,
     include_finalized: bool,
+    compression: Option<Codec>,
+    signer: Option<ManifestSigner>,
+    trusted_keys: Vec<String>,
 }
 
 impl Merger {
@@ -460,6 +557,7 @@ impl Merger {
         validation_level: ValidationLevel,
         exit_on_val_err: bool,
         include_finalized: bool,
+        compression: Option<Codec>,
     ) -> anyhow::Result<Self> {
         let progress_bar = progress_bar.then(|| {
             let style = ProgressStyle::default_bar()
@@ -478,9 +576,24 @@ impl Merger {
             validation_level,
             exit_on_val_err,
             include_finalized,
+            compression,
+            signer: None,
+            trusted_keys: Vec::new(),
         })
     }
 
+    /// Sign the manifests of the written log files with the given signer
+    pub fn sign_manifests(mut self, signer: ManifestSigner) -> Self {
+        self.signer.replace(signer);
+        self
+    }
+
+    /// Only merge the log files whose manifests are signed by one of the given keys
+    pub fn trusted_keys(mut self, trusted_keys: Vec<String>) -> Self {
+        self.trusted_keys = trusted_keys;
+        self
+    }
+
     /// Begin cleaning up (merging) log files using the Merger instance
     pub async fn cleanup(mut self) -> anyhow::Result<()> {
         if let Some(pb) = self.progress_bar.as_mut() {
@@ -489,6 +602,17 @@ impl Merger {
             pb.println(format!(" - backup: {}", self.backup_dir.is_some()));
             pb.println(format!(" - exit on validation err: {}", self.exit_on_val_err));
             pb.println(format!(" - include finalized: {}", self.include_finalized));
+            pb.println(format!(
+                " - compression: {}",
+                self.compression.map(|codec| codec.extension()).unwrap_or("none")
+            ));
+            pb.println(format!(
+                " - signed manifests: {}",
+                self.signer
+                    .as_ref()
+                    .map(|signer| signer.public_key())
+                    .unwrap_or_else(|| "no".to_owned())
+            ));
         }
         if let Some(pb) = self.progress_bar.as_mut() {
             pb.println(format!("Gathering log files from {}", self.logs_dir.to_string_lossy()));
@@ -519,13 +643,15 @@ impl Merger {
                 {
                     match e {
                         LogFileError::EmptyFile(_) => {
-                            tokio::fs::remove_file(path).await?;
+                            tokio::fs::remove_file(&writer.file_path).await?;
+                            remove_manifest(&path).await?;
                         }
                         _ => {
                             writer.err = true;
                             self.handle_error(e, writer.len())?;
                         }
                     }
+                    writer.close().await?;
                 } else {
                     res = Some(writer);
                     break;
@@ -549,7 +675,8 @@ impl Merger {
                     if let Some(pb) = self.progress_bar.as_ref() {
                         pb.println(format!("Found gap in logs from {} to {}", writer.end, start));
                     }
-                    writer = self.open_write(&path, start, end).await?;
+                    let next_writer = self.open_write(&path, start, end).await?;
+                    std::mem::replace(&mut writer, next_writer).close().await?;
                     if let Err(e) = writer
                         .verify(
                             match self.validation_level {
@@ -562,7 +689,8 @@ impl Merger {
                     {
                         match e {
                             LogFileError::EmptyFile(_) => {
-                                tokio::fs::remove_file(path).await?;
+                                tokio::fs::remove_file(&writer.file_path).await?;
+                                remove_manifest(&path).await?;
                             }
                             _ => {
                                 writer.err = true;
@@ -572,6 +700,7 @@ impl Merger {
                     }
                 }
             }
+            writer.close().await?;
         } else {
             if let Some(pb) = self.progress_bar.as_ref() {
                 pb.println("No valid log files to merge");
@@ -585,6 +714,36 @@ impl Merger {
 
     async fn merge(&mut self, start: u32, end: u32, path: PathBuf, mut active: LogFile) -> anyhow::Result<LogFile> {
         let mut consumed_file = self.open_read(&path, start, end).await?;
+        let consumed = self
+            .consume(&mut consumed_file, start, end, path.clone(), &mut active)
+            .await;
+        // the consumed file is only renamed if it turned out invalid
+        consumed_file.close().await?;
+        match consumed {
+            Ok(true) => {
+                active.close().await?;
+                // We already validated the file, so it's not needed here
+                // Just return the new active file
+                self.open_write(&path, start, end).await
+            }
+            Ok(false) => Ok(active),
+            Err(e) => {
+                active.close().await?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Append the lines of the consumed file to the active one.
+    /// Returns true if the consumed file has to become the active file as it is
+    async fn consume(
+        &mut self,
+        consumed_file: &mut LogFile,
+        start: u32,
+        end: u32,
+        path: PathBuf,
+        active: &mut LogFile,
+    ) -> anyhow::Result<bool> {
         let total_bytes = consumed_file.len();
         if let Err(e) = consumed_file
             .verify(self.validation_level, &mut self.progress_bar)
@@ -593,15 +752,17 @@ impl Merger {
             match e {
                 LogFileError::EmptyFile(_) => {
                     tokio::fs::remove_file(&path).await?;
+                    remove_manifest(&path).await?;
                 }
                 _ => {
-                    return self.handle_error(e, total_bytes).map(|_| active);
+                    return self.handle_error(e, total_bytes).map(|_| false);
                 }
             }
-            return Ok(active);
+            return Ok(false);
         }
 
-        let mut buf_reader = BufReader::new(&mut consumed_file.file);
+        let file = consumed_file.file.try_clone().await?;
+        let mut reader = LogReader::new(file, consumed_file.codec);
         let mut line_buffer = String::new();
         let mut milestone_index = start;
         let mut total_read_bytes = 0;
@@ -611,15 +772,16 @@ impl Merger {
             pb.set_message(format!("Consuming {}", path.to_string_lossy()));
         }
         loop {
-            match buf_reader.read_line(&mut line_buffer).await {
+            match reader.read_line(&mut line_buffer).await {
                 Ok(bytes) => {
-                    total_read_bytes += bytes as u64;
+                    total_read_bytes = reader.consumed();
                     let ms_line = std::mem::take(&mut line_buffer);
                     if bytes == 0 {
                         // if let Some(pb) = self.progress_bar.as_mut() {
                         //    pb.println(format!("Removing log file {}", path.to_string_lossy()));
                         //}
                         tokio::fs::remove_file(&path).await?;
+                        remove_manifest(&path).await?;
                         break;
                     } else {
                         // Perform validation if JIT is enabled or we are looking at an overlapping milestone
@@ -630,18 +792,18 @@ impl Merger {
                                 if idx < start || idx >= end {
                                     consumed_file.err = true;
                                     let err = LogFileError::OutsideMilestone { milestone: idx, path };
-                                    return self.handle_error(err, total_bytes - total_read_bytes).map(|_| active);
+                                    return self.handle_error(err, total_bytes - total_read_bytes).map(|_| false);
                                 } else if milestone_index < idx {
                                     consumed_file.err = true;
                                     let err = LogFileError::MissingMilestones {
                                         range: milestone_index..idx,
                                         path,
                                     };
-                                    return self.handle_error(err, total_bytes - total_read_bytes).map(|_| active);
+                                    return self.handle_error(err, total_bytes - total_read_bytes).map(|_| false);
                                 } else if milestone_index > idx {
                                     consumed_file.err = true;
                                     let err = LogFileError::DuplicateMilestone { milestone: idx, path };
-                                    return self.handle_error(err, total_bytes - total_read_bytes).map(|_| active);
+                                    return self.handle_error(err, total_bytes - total_read_bytes).map(|_| false);
                                 }
                             } else {
                                 consumed_file.err = true;
@@ -649,7 +811,7 @@ impl Merger {
                                     milestone: milestone_index,
                                     path,
                                 };
-                                return self.handle_error(err, total_bytes - total_read_bytes).map(|_| active);
+                                return self.handle_error(err, total_bytes - total_read_bytes).map(|_| false);
                             }
                         }
                         // We can fit this line in the writer file
@@ -664,7 +826,7 @@ impl Merger {
                                 active.append_line(&ms_line).await?;
                             }
                             if let Some(pb) = self.progress_bar.as_mut() {
-                                pb.inc(bytes as u64);
+                                pb.set_position(total_read_bytes);
                             }
 
                         // Adding this line would go over our limit
@@ -674,31 +836,27 @@ impl Merger {
                             //}
                             active.finalized = true;
                             // If we read more than just a single line from the file
-                            if total_read_bytes != bytes as u64 {
+                            if milestone_index != start {
                                 // Create a new file to funnel the remainder of the milestones to
-                                active = self.create_active(milestone_index).await?;
+                                let next_active = self.create_active(milestone_index).await?;
+                                std::mem::replace(active, next_active).close().await?;
                                 // Add the line we just read
                                 active.append_line(&ms_line).await?;
 
                             // Otherwise we shouldn't copy it line-by-line, just set the active file
                             } else {
-                                // Drop our reader file so we can access it as a writer
-                                drop(consumed_file);
-                                // Drop the writer ahead of reassignment so we don't conflict names
-                                drop(active);
-                                // We already validated the file above, so it's not needed here
-                                // Just return the new active file
-                                return Ok(self.open_write(&path, start, end).await?);
+                                // The active file gets closed ahead of reassignment so we don't conflict names
+                                return Ok(true);
                             }
                             if let Some(pb) = self.progress_bar.as_mut() {
-                                pb.inc(bytes as u64);
+                                pb.set_position(total_read_bytes);
                             }
                         }
                     }
                     milestone_index += 1;
                 }
                 Err(e) => {
-                    return self.handle_error(e, total_bytes - total_read_bytes).map(|_| active);
+                    return self.handle_error(e, total_bytes - total_read_bytes).map(|_| false);
                 }
             }
         }
@@ -710,19 +868,19 @@ impl Merger {
                         range: milestone_index..end,
                         path,
                     };
-                    return self.handle_error(err, total_bytes - total_read_bytes).map(|_| active);
+                    return self.handle_error(err, total_bytes - total_read_bytes).map(|_| false);
                 } else if milestone_index > end {
                     consumed_file.err = true;
                     let err = LogFileError::ExtraMilestones {
                         num: milestone_index - end,
                         path,
                     };
-                    return self.handle_error(err, total_bytes - total_read_bytes).map(|_| active);
+                    return self.handle_error(err, total_bytes - total_read_bytes).map(|_| false);
                 }
             }
             _ => (),
         }
-        Ok(active)
+        Ok(false)
     }
 
     fn handle_error<E: Display + Into<anyhow::Error>>(&mut self, err: E, inc_bytes: u64) -> anyhow::Result<()> {
@@ -741,11 +899,19 @@ impl Merger {
         // if let Some(pb) = self.progress_bar.as_mut() {
         //    pb.println(format!("Opening file for writes: {}", file_path.to_string_lossy()));
         //}
+        self.verify_manifest(file_path).await?;
         let active_file_path = self.logs_dir.join(&format!("{}.log.active", start));
         // Copy the file to the backup first, if asked
         if let Some(ref dir) = self.backup_dir {
             tokio::fs::copy(file_path, dir.join(file_path.file_name().unwrap())).await?;
         }
+        // the written file gets a new manifest once it is closed
+        remove_manifest(file_path).await?;
+        // compressed logs keep their codec, the appended lines go to a new frame
+        let active_file_path = match Codec::from_path(file_path) {
+            Some(codec) => codec.compressed_path(&active_file_path),
+            None => active_file_path,
+        };
         tokio::fs::rename(file_path, &active_file_path).await?;
         let active_file = OpenOptions::new()
             .read(true)
@@ -753,13 +919,18 @@ impl Merger {
             .open(&active_file_path)
             .await?;
         let active_len = active_file.metadata().await?.len();
-        Ok(LogFile::new(start, end, active_file_path, active_file, active_len))
+        let mut active = LogFile::new(start, end, active_file_path, active_file, active_len);
+        active.compress = self.compression;
+        active.write_manifest = true;
+        active.signer = self.signer.clone();
+        Ok(active)
     }
 
     async fn open_read(&mut self, file_path: &PathBuf, start: u32, end: u32) -> anyhow::Result<LogFile> {
         // if let Some(pb) = self.progress_bar.as_mut() {
         //    pb.println(format!("Opening file for reads: {}", file_path.to_string_lossy()));
         //}
+        self.verify_manifest(file_path).await?;
         // Copy the file to the backup first, if asked
         if let Some(ref dir) = self.backup_dir {
             tokio::fs::copy(file_path, dir.join(file_path.file_name().unwrap())).await?;
@@ -769,6 +940,25 @@ impl Merger {
         Ok(LogFile::new(start, end, file_path.clone(), file, len))
     }
 
+    /// Verify the log file against its manifest before it gets rewritten or removed along with its manifest.
+    /// The logs finished before the manifests were introduced are only accepted if no trusted keys are set
+    async fn verify_manifest(&mut self, file_path: &Path) -> anyhow::Result<()> {
+        // the active files of an interrupted merge have no manifest yet
+        if file_path.to_string_lossy().contains(".log.active") {
+            return Ok(());
+        }
+        match verify_log(file_path, &self.trusted_keys).await {
+            Ok(()) => Ok(()),
+            Err(e @ ManifestError::Missing(_)) if self.trusted_keys.is_empty() => {
+                if let Some(pb) = self.progress_bar.as_ref() {
+                    pb.println(format!("Validation Warning: {}", e));
+                }
+                Ok(())
+            }
+            Err(e) => bail!("Aborted the merge of {}: {}", file_path.display(), e),
+        }
+    }
+
     async fn create_active(&mut self, milestone_index: u32) -> anyhow::Result<LogFile> {
         let file_path = self.logs_dir.join(&format!("{}.log.active", milestone_index));
         let file: File = OpenOptions::new()
@@ -784,6 +974,10 @@ impl Merger {
                 )
             })?;
         let len = file.metadata().await?.len();
-        Ok(LogFile::new(milestone_index, milestone_index, file_path, file, len))
+        let mut active = LogFile::new(milestone_index, milestone_index, file_path, file, len);
+        active.compress = self.compression;
+        active.write_manifest = true;
+        active.signer = self.signer.clone();
+        Ok(active)
     }
 }
@@ -0,0 +1,121 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! A minimal http endpoint which serves the metrics of the broker, whether the API is running or not.
+
+use super::*;
+use chronicle_common::metrics::{
+    prometheus::{
+        self,
+        Encoder,
+        TextEncoder,
+    },
+    CQL_INSERT_ERRORS,
+    REGISTRY,
+};
+use futures::future::{
+    AbortHandle,
+    Abortable,
+};
+use std::net::SocketAddr;
+use tokio::{
+    io::{
+        AsyncReadExt,
+        AsyncWriteExt,
+    },
+    net::{
+        TcpListener,
+        TcpStream,
+    },
+};
+
+/// The largest request head the monitor accepts
+const MAX_REQUEST_HEAD_SIZE: usize = 8 * 1024;
+
+/// MonitorHandle to stop the monitor endpoint
+pub struct MonitorHandle {
+    abort_handle: AbortHandle,
+}
+
+impl MonitorHandle {
+    /// Stop serving the monitor endpoint
+    pub fn stop(self) {
+        self.abort_handle.abort();
+    }
+}
+
+/// The monitor endpoint
+pub struct Monitor {
+    listener: TcpListener,
+}
+
+impl Monitor {
+    /// Bind the monitor endpoint to the given address and serve it in the background
+    pub async fn spawn(address: SocketAddr) -> anyhow::Result<MonitorHandle> {
+        let listener = TcpListener::bind(address).await?;
+        info!("Serving the broker metrics on http://{}/metrics", address);
+        let (abort_handle, abort_registration) = AbortHandle::new_pair();
+        tokio::spawn(Abortable::new(Self { listener }.serve(), abort_registration));
+        Ok(MonitorHandle { abort_handle })
+    }
+    async fn serve(self) {
+        loop {
+            match self.listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(async move {
+                        if let Err(e) = Self::respond(stream).await {
+                            debug!("Unable to respond to monitor request: {}", e);
+                        }
+                    });
+                }
+                Err(e) => warn!("Unable to accept monitor connection: {}", e),
+            }
+        }
+    }
+    async fn respond(mut stream: TcpStream) -> anyhow::Result<()> {
+        let path = Self::read_request_path(&mut stream).await?;
+        let (status, content_type, body) = match path.as_str() {
+            "/metrics" => ("200 OK", TextEncoder::new().format_type().to_owned(), Self::metrics()?),
+            _ => ("404 Not Found", "text/plain".to_owned(), b"Not Found".to_vec()),
+        };
+        let head = format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            status,
+            content_type,
+            body.len()
+        );
+        stream.write_all(head.as_bytes()).await?;
+        stream.write_all(&body).await?;
+        stream.shutdown().await?;
+        Ok(())
+    }
+    /// Read the request head and return the path of its request line, ie `GET /metrics HTTP/1.1`
+    async fn read_request_path(stream: &mut TcpStream) -> anyhow::Result<String> {
+        let mut head = Vec::new();
+        let mut buffer = [0; 1024];
+        while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+            ensure!(head.len() < MAX_REQUEST_HEAD_SIZE, "Request head too large");
+            let read = stream.read(&mut buffer).await?;
+            ensure!(read > 0, "Connection closed before the end of the request head");
+            head.extend_from_slice(&buffer[..read]);
+        }
+        let request_line = String::from_utf8_lossy(&head);
+        let mut parts = request_line.split_whitespace();
+        ensure!(parts.next() == Some("GET"), "Unsupported request method");
+        let path = parts.next().ok_or_else(|| anyhow!("No path in request line"))?;
+        // ignore the query string
+        Ok(path.split('?').next().unwrap_or(path).to_owned())
+    }
+    fn metrics() -> anyhow::Result<Vec<u8>> {
+        let encoder = TextEncoder::new();
+        let mut buffer = Vec::new();
+        encoder.encode(&REGISTRY.gather(), &mut buffer)?;
+        encoder.encode(&prometheus::gather(), &mut buffer)?;
+        Ok(buffer)
+    }
+}
+
+/// Count an insert which failed for good, by the table it inserts into
+pub(crate) fn count_failed_insert<S: InsertTable<K, V>, K, V>() {
+    CQL_INSERT_ERRORS.with_label_values(&[S::TABLE]).inc();
+}
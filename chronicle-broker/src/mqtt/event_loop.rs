@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use super::*;
+use chronicle_common::metrics::MQTT_RECEIVED;
 
 #[async_trait::async_trait]
 impl<H: ChronicleBrokerScope> EventLoop<BrokerHandle<H>> for Mqtt<Messages> {
@@ -14,16 +15,26 @@ impl<H: ChronicleBrokerScope> EventLoop<BrokerHandle<H>> for Mqtt<Messages> {
         self.service.update_status(ServiceStatus::Running);
         let event = BrokerEvent::Children(BrokerChild::Mqtt(self.service.clone(), None, status));
         let _ = supervisor.as_mut().unwrap().send(event);
+        let received = MQTT_RECEIVED.with_label_values(&[&self.get_name()]);
         let inbox = self.inbox.as_mut().unwrap();
         while let Some(msg_opt) = inbox.stream.next().await {
             if let Some(msg) = msg_opt {
-                if let Ok(msg) = Message::unpack(&mut msg.payload()) {
-                    let (message_id, _) = msg.id();
-                    // partitioning based on first byte of the message_id
-                    let collector_partition_id = self.partitioner.partition_id(&message_id);
-                    if let Some(collector_handle) = self.collectors_handles.get(&collector_partition_id) {
-                        let _ = collector_handle.send(CollectorEvent::Message(message_id, msg));
+                received.inc();
+                if let Some(recorder) = self.recorder.as_ref() {
+                    recorder.record(Messages::name(), msg.payload());
+                }
+                match Message::unpack(&mut msg.payload()) {
+                    Ok(msg) => {
+                        let (message_id, _) = msg.id();
+                        // partitioning based on first byte of the message_id
+                        let collector_partition_id = self.partitioner.partition_id(&message_id);
+                        if let Some(collector_handle) = self.collectors_handles.get(&collector_partition_id) {
+                            let _ = collector_handle
+                                .send_async(CollectorEvent::Message(message_id, msg))
+                                .await;
+                        }
                     }
+                    Err(e) => reject_message("mqtt", &self.get_name(), &anyhow!("Invalid message: {:?}", e)),
                 };
             } else {
                 warn!("Mqtt: {}, lost connection", self.get_name());
@@ -45,15 +56,61 @@ impl<H: ChronicleBrokerScope> EventLoop<BrokerHandle<H>> for Mqtt<MessagesRefere
         self.service.update_status(ServiceStatus::Running);
         let event = BrokerEvent::Children(BrokerChild::Mqtt(self.service.clone(), None, status));
         let _ = supervisor.as_mut().unwrap().send(event);
+        let received = MQTT_RECEIVED.with_label_values(&[&self.get_name()]);
         let inbox = self.inbox.as_mut().unwrap();
         while let Some(msg_ref_opt) = inbox.stream.next().await {
             if let Some(msg_ref) = msg_ref_opt {
-                if let Ok(msg_ref) = serde_json::from_str::<MessageMetadata>(&msg_ref.payload_str()) {
-                    // partitioning based on first byte of the message_id
-                    let collector_partition_id = self.partitioner.partition_id(&msg_ref.message_id);
-                    if let Some(collector_handle) = self.collectors_handles.get(&collector_partition_id) {
-                        let _ = collector_handle.send(CollectorEvent::MessageReferenced(msg_ref));
+                received.inc();
+                if let Some(recorder) = self.recorder.as_ref() {
+                    recorder.record(MessagesReferenced::name(), msg_ref.payload());
+                }
+                match serde_json::from_str::<MessageMetadata>(&msg_ref.payload_str()) {
+                    Ok(msg_ref) => {
+                        // partitioning based on first byte of the message_id
+                        let collector_partition_id = self.partitioner.partition_id(&msg_ref.message_id);
+                        if let Some(collector_handle) = self.collectors_handles.get(&collector_partition_id) {
+                            let _ = collector_handle
+                                .send_async(CollectorEvent::MessageReferenced(msg_ref))
+                                .await;
+                        }
                     }
+                    Err(e) => reject_message("mqtt", &self.get_name(), &anyhow!("Invalid message metadata: {}", e)),
+                };
+            } else {
+                warn!("Mqtt: {}, lost connection", self.get_name());
+                return Err(Need::Restart);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl<H: ChronicleBrokerScope> EventLoop<BrokerHandle<H>> for Mqtt<LatestMilestone> {
+    async fn event_loop(
+        &mut self,
+        status: Result<(), Need>,
+        supervisor: &mut Option<BrokerHandle<H>>,
+    ) -> Result<(), Need> {
+        status?;
+        self.service.update_status(ServiceStatus::Running);
+        let event = BrokerEvent::Children(BrokerChild::Mqtt(self.service.clone(), None, status));
+        let supervisor = supervisor.as_mut().unwrap();
+        let _ = supervisor.send(event);
+        let received = MQTT_RECEIVED.with_label_values(&[&self.get_name()]);
+        let inbox = self.inbox.as_mut().unwrap();
+        while let Some(latest_opt) = inbox.stream.next().await {
+            if let Some(latest) = latest_opt {
+                received.inc();
+                if let Some(recorder) = self.recorder.as_ref() {
+                    recorder.record(LatestMilestone::name(), latest.payload());
+                }
+                if let Ok(latest) = serde_json::from_str::<LatestMilestonePayload>(&latest.payload_str()) {
+                    let network_tip = NetworkTip {
+                        milestone_index: latest.index,
+                        timestamp: latest.timestamp,
+                    };
+                    let _ = supervisor.send(BrokerEvent::NetworkTip(network_tip));
                 };
             } else {
                 warn!("Mqtt: {}, lost connection", self.get_name());
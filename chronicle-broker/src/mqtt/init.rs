@@ -10,7 +10,7 @@ impl<T: Topic, H: ChronicleBrokerScope> Init<BrokerHandle<H>> for Mqtt<T> {
         // create async client
         let random_id: u64 = rand::random();
         let create_opts = CreateOptionsBuilder::new()
-            .server_uri(&self.url.as_str()[..])
+            .server_uri(MqttConnectionConfig::server_uri(&self.url))
             .client_id(&format!("{}|{}", self.get_name(), random_id))
             .persistence(None)
             .finalize();
@@ -19,12 +19,10 @@ impl<T: Topic, H: ChronicleBrokerScope> Init<BrokerHandle<H>> for Mqtt<T> {
             Need::Abort
         })?;
         info!("Created AsyncClient: {}", &self.url.to_string());
-        let conn_opts = paho_mqtt::ConnectOptionsBuilder::new()
-            .keep_alive_interval(Duration::from_secs(120))
-            .mqtt_version(paho_mqtt::MQTT_VERSION_3_1_1)
-            .clean_session(false)
-            .connect_timeout(Duration::from_secs(60))
-            .finalize();
+        let conn_opts = self.connection.connect_options(&self.url).map_err(|e| {
+            error!("Invalid connection settings: {}, error: {}", &self.url.as_str(), e);
+            Need::Abort
+        })?;
         let mut arc_client = std::sync::Arc::new(client);
         let arced_client = std::sync::Arc::get_mut(&mut arc_client).unwrap();
         let stream = arced_client.get_stream(self.stream_capacity);
@@ -41,8 +39,8 @@ impl<T: Topic, H: ChronicleBrokerScope> Init<BrokerHandle<H>> for Mqtt<T> {
             Need::Restart
         })?;
         info!("Connected AsyncClient: {}", &self.url.as_str());
-        // subscribe to T::name() topic with T::qos()
-        arced_client.subscribe(T::name(), T::qos()).await.map_err(|e| {
+        // subscribe to T::name() topic with the configured qos
+        arced_client.subscribe(T::name(), self.qos).await.map_err(|e| {
             error!(
                 "Unable to subscribe AsyncClient: {}, topic: {}, error: {}",
                 &self.url.as_str(),
@@ -7,13 +7,12 @@ use super::{
         CollectorHandle,
         MessageIdPartitioner,
     },
+    recording::FeedRecorderHandle,
+    verification::reject_message,
     *,
 };
 use futures::stream::StreamExt;
-use std::{
-    collections::HashMap,
-    time::Duration,
-};
+use std::collections::HashMap;
 
 mod event_loop;
 mod init;
@@ -24,7 +23,10 @@ builder!(MqttBuilder<T> {
     url: Url,
     topic: T,
     collectors_handles: HashMap<u8, CollectorHandle>,
-    stream_capacity: usize
+    stream_capacity: usize,
+    qos: i32,
+    connection: MqttConnectionConfig,
+    recorder: FeedRecorderHandle
 });
 
 /// MqttHandle to be passed to the supervisor in order to shutdown
@@ -51,6 +53,9 @@ pub struct Mqtt<T> {
     service: Service,
     url: Url,
     stream_capacity: usize,
+    qos: i32,
+    connection: MqttConnectionConfig,
+    recorder: Option<FeedRecorderHandle>,
     collectors_handles: HashMap<u8, CollectorHandle>,
     partitioner: MessageIdPartitioner,
     handle: Option<MqttHandle>,
@@ -70,6 +75,8 @@ pub enum Topics {
     Messages,
     /// Messages Referenced topic
     MessagesReferenced,
+    /// Latest Milestone topic
+    LatestMilestone,
 }
 
 impl TryFrom<&str> for Topics {
@@ -78,6 +85,7 @@ impl TryFrom<&str> for Topics {
         match value {
             "messages" => Ok(Topics::Messages),
             "messages/referenced" => Ok(Topics::MessagesReferenced),
+            "milestones/latest" => Ok(Topics::LatestMilestone),
             _ => Err(format!("Unsupported topic: {}", value).into()),
         }
     }
@@ -87,8 +95,6 @@ impl TryFrom<&str> for Topics {
 pub trait Topic: Send + 'static {
     /// MQTT Topic name
     fn name() -> &'static str;
-    /// MQTT Quality of service
-    fn qos() -> i32;
 }
 
 /// Mqtt Messages topic
@@ -96,10 +102,7 @@ pub(crate) struct Messages;
 
 impl Topic for Messages {
     fn name() -> &'static str {
-        "messages"
-    }
-    fn qos() -> i32 {
-        0
+        MqttType::Messages.topic()
     }
 }
 
@@ -108,10 +111,7 @@ pub(crate) struct MessagesReferenced;
 
 impl Topic for MessagesReferenced {
     fn name() -> &'static str {
-        "messages/referenced"
-    }
-    fn qos() -> i32 {
-        0
+        MqttType::MessagesReferenced.topic()
     }
 }
 
@@ -120,15 +120,20 @@ pub(crate) struct LatestMilestone;
 
 impl Topic for LatestMilestone {
     fn name() -> &'static str {
-        "milestones/latest"
-    }
-    fn qos() -> i32 {
-        0
+        MqttType::LatestMilestone.topic()
     }
 }
 
+/// The payload published on the "milestones/latest" topic
+#[derive(Deserialize)]
+pub(crate) struct LatestMilestonePayload {
+    pub(crate) index: u32,
+    pub(crate) timestamp: u64,
+}
+
 impl<H: ChronicleBrokerScope> ActorBuilder<BrokerHandle<H>> for MqttBuilder<Messages> {}
 impl<H: ChronicleBrokerScope> ActorBuilder<BrokerHandle<H>> for MqttBuilder<MessagesReferenced> {}
+impl<H: ChronicleBrokerScope> ActorBuilder<BrokerHandle<H>> for MqttBuilder<LatestMilestone> {}
 
 /// implementation of builder
 impl<T: Topic> Builder for MqttBuilder<T> {
@@ -142,6 +147,9 @@ impl<T: Topic> Builder for MqttBuilder<T> {
             collectors_handles,
             partitioner: MessageIdPartitioner::new(collector_count),
             stream_capacity: self.stream_capacity.unwrap_or(10000),
+            qos: self.qos.unwrap_or(0),
+            connection: self.connection.unwrap_or_default(),
+            recorder: self.recorder,
             handle: None,
             inbox: None,
             _topic: self.topic.unwrap(),
@@ -0,0 +1,115 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use chronicle_common::Wrapper;
+use std::{
+    collections::{
+        HashSet,
+        VecDeque,
+    },
+    str::FromStr,
+};
+
+#[async_trait::async_trait]
+impl<H: ChronicleBrokerScope> EventLoop<BrokerHandle<H>> for Poller {
+    async fn event_loop(
+        &mut self,
+        status: Result<(), Need>,
+        supervisor: &mut Option<BrokerHandle<H>>,
+    ) -> Result<(), Need> {
+        status?;
+        self.service.update_status(ServiceStatus::Running);
+        let event = BrokerEvent::Children(BrokerChild::Poller(self.service.clone(), Ok(())));
+        let supervisor = supervisor.as_mut().unwrap();
+        let _ = supervisor.send(event);
+        loop {
+            let result = self.poll().await;
+            // report only the transitions between running and degraded
+            if result.is_err() != self.service.is_degraded() {
+                if let Err(e) = result {
+                    warn!("Poller: {}, failed to poll: {}", self.get_name(), e);
+                    self.service.update_status(ServiceStatus::Degraded);
+                } else {
+                    self.service.update_status(ServiceStatus::Running);
+                }
+                let event = BrokerEvent::Children(BrokerChild::Poller(self.service.clone(), Ok(())));
+                let _ = supervisor.send(event);
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+}
+
+impl Poller {
+    /// Feed the cones of all the milestones confirmed since the last poll
+    async fn poll(&mut self) -> anyhow::Result<()> {
+        let confirmed_milestone_index = self.request_confirmed_milestone_index().await?;
+        // start from the current confirmed milestone, the syncer takes care of anything older
+        let mut next = *self.next.get_or_insert(confirmed_milestone_index);
+        while next <= confirmed_milestone_index {
+            self.feed_milestone_cone(next).await?;
+            next += 1;
+            self.next.replace(next);
+        }
+        Ok(())
+    }
+    /// Walk the cone of the given milestone and push its messages and metadata to the collectors,
+    /// stopping at messages referenced by older milestones
+    async fn feed_milestone_cone(&mut self, milestone_index: u32) -> anyhow::Result<()> {
+        let milestone = self.request_milestone(milestone_index).await?;
+        let milestone_message_id = MessageId::from_str(&milestone.message_id)?;
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(milestone_message_id);
+        while let Some(message_id) = queue.pop_front() {
+            if !visited.insert(message_id) || message_id == MessageId::null() {
+                continue;
+            }
+            let (message, metadata) = self.request_message_and_metadata(message_id).await?;
+            if metadata.referenced_by_milestone_index != Some(milestone_index) {
+                continue;
+            }
+            queue.extend(message.parents().iter().cloned());
+            let collector_partition_id = self.partitioner.partition_id(&message_id);
+            if let Some(collector_handle) = self.collectors_handles.get(&collector_partition_id) {
+                let _ = collector_handle
+                    .send_async(CollectorEvent::Message(message_id, message))
+                    .await;
+                let _ = collector_handle
+                    .send_async(CollectorEvent::MessageReferenced(metadata))
+                    .await;
+            }
+        }
+        Ok(())
+    }
+    async fn request_confirmed_milestone_index(&self) -> anyhow::Result<u32> {
+        let info = self.request::<NodeInfo>("info").await?;
+        Ok(info.confirmed_milestone_index)
+    }
+    async fn request_milestone(&self, milestone_index: u32) -> anyhow::Result<MilestoneResponse> {
+        self.request(&format!("milestones/{}", milestone_index)).await
+    }
+    async fn request_message_and_metadata(&self, message_id: MessageId) -> anyhow::Result<(Message, MessageMetadata)> {
+        let message_dto = self.request::<MessageDto>(&format!("messages/{}", message_id)).await?;
+        let message = Message::try_from(&message_dto).map_err(|e| anyhow!("Invalid message {}: {}", message_id, e))?;
+        let metadata = self
+            .request::<MessageMetadata>(&format!("messages/{}/metadata", message_id))
+            .await?;
+        verify_message(&message, &message_id)
+            .and_then(|_| verify_metadata(&message_id, &message, &metadata))
+            .map_err(|e| {
+                reject_message("poller", self.url.as_str(), &e);
+                e
+            })?;
+        Ok((message, metadata))
+    }
+    async fn request<T: serde::de::DeserializeOwned>(&self, path: &str) -> anyhow::Result<T> {
+        let url = self.url.join(path)?;
+        let response = self.api_client.get(url.clone()).send().await?;
+        if !response.status().is_success() {
+            bail!("Received {} requesting {}", response.status(), url);
+        }
+        Ok(response.json::<JsonData<T>>().await?.into_inner())
+    }
+}
@@ -0,0 +1,14 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+#[async_trait::async_trait]
+impl<H: ChronicleBrokerScope> Init<BrokerHandle<H>> for Poller {
+    async fn init(&mut self, status: Result<(), Need>, supervisor: &mut Option<BrokerHandle<H>>) -> Result<(), Need> {
+        self.service.update_status(ServiceStatus::Initializing);
+        let event = BrokerEvent::Children(BrokerChild::Poller(self.service.clone(), Ok(())));
+        let _ = supervisor.as_mut().unwrap().send(event);
+        status
+    }
+}
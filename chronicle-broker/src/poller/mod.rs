@@ -0,0 +1,129 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use super::{
+    collector::{
+        CollectorEvent,
+        CollectorHandle,
+        MessageIdPartitioner,
+    },
+    verification::{
+        reject_message,
+        verify_message,
+        verify_metadata,
+    },
+    *,
+};
+use bee_rest_api::types::{
+    dtos::MessageDto,
+    responses::MilestoneResponse,
+};
+use chronicle_common::config::ApiClient;
+use futures::future::AbortHandle;
+use std::{
+    collections::HashMap,
+    time::Duration,
+};
+
+mod event_loop;
+mod init;
+mod terminating;
+
+// Poller builder
+builder!(PollerBuilder {
+    url: Url,
+    api_client: ApiClient,
+    poll_interval: Duration,
+    collectors_handles: HashMap<u8, CollectorHandle>
+});
+
+/// PollerHandle to be passed to the supervisor in order to shutdown/abort the poller
+#[derive(Clone)]
+pub struct PollerHandle {
+    abort_handle: AbortHandle,
+}
+
+impl PollerHandle {
+    /// Create a new poller handle
+    pub fn new(abort_handle: AbortHandle) -> Self {
+        Self { abort_handle }
+    }
+}
+
+impl Shutdown for PollerHandle {
+    fn shutdown(self) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        // abortable actor just require abort()
+        self.abort_handle.abort();
+        None
+    }
+}
+
+/// Poller state
+pub struct Poller {
+    service: Service,
+    url: Url,
+    api_client: ApiClient,
+    poll_interval: Duration,
+    collectors_handles: HashMap<u8, CollectorHandle>,
+    partitioner: MessageIdPartitioner,
+    /// The next milestone index to be polled
+    next: Option<u32>,
+}
+
+impl Poller {
+    pub(crate) fn clone_service(&self) -> Service {
+        self.service.clone()
+    }
+}
+
+/// The subset of the node info the poller relies on
+#[derive(Deserialize)]
+struct NodeInfo {
+    #[serde(rename = "confirmedMilestoneIndex")]
+    confirmed_milestone_index: u32,
+}
+
+impl<H: ChronicleBrokerScope> ActorBuilder<BrokerHandle<H>> for PollerBuilder {}
+
+/// implementation of builder
+impl Builder for PollerBuilder {
+    type State = Poller;
+    fn build(self) -> Self::State {
+        let collectors_handles = self.collectors_handles.expect("Expected collectors handles");
+        let collector_count = collectors_handles.len() as u8;
+        Self::State {
+            service: Service::new(),
+            url: self.url.unwrap(),
+            api_client: self.api_client.expect("Expected api client"),
+            poll_interval: self.poll_interval.unwrap_or(Duration::from_secs(1)),
+            collectors_handles,
+            partitioner: MessageIdPartitioner::new(collector_count),
+            next: None,
+        }
+        .set_name()
+    }
+}
+
+/// impl name of the Poller
+impl Name for Poller {
+    fn set_name(mut self) -> Self {
+        let name = format!("poller@{}", self.url.as_str());
+        self.service.update_name(name);
+        self
+    }
+    fn get_name(&self) -> String {
+        self.service.get_name()
+    }
+}
+
+#[async_trait::async_trait]
+impl<H: ChronicleBrokerScope> AknShutdown<Poller> for BrokerHandle<H> {
+    async fn aknowledge_shutdown(self, mut _state: Poller, status: Result<(), Need>) {
+        _state.service.update_status(ServiceStatus::Stopped);
+        let event = BrokerEvent::Children(BrokerChild::Poller(_state.service.clone(), status));
+        let _ = self.send(event);
+    }
+}
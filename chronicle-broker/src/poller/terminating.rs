@@ -0,0 +1,18 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+#[async_trait::async_trait]
+impl<H: ChronicleBrokerScope> Terminating<BrokerHandle<H>> for Poller {
+    async fn terminating(
+        &mut self,
+        _status: Result<(), Need>,
+        _supervisor: &mut Option<BrokerHandle<H>>,
+    ) -> Result<(), Need> {
+        self.service.update_status(ServiceStatus::Stopping);
+        let event = BrokerEvent::Children(BrokerChild::Poller(self.service.clone(), _status));
+        let _ = _supervisor.as_mut().unwrap().send(event);
+        _status
+    }
+}
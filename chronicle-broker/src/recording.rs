@@ -0,0 +1,232 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! A recording is a directory of `.feed` files, named after the time (in ms) they were created,
+//! each one holding a sequence of records:
+//! - the topic length (u16 BE) followed by the topic
+//! - the time (in ms since the unix epoch) the payload was received (u64 BE)
+//! - the payload length (u32 BE) followed by the raw payload
+
+use super::*;
+use chronicle_common::config::FeedRecordingConfig;
+use std::{
+    io::ErrorKind,
+    path::{
+        Path,
+        PathBuf,
+    },
+    time::{
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+use tokio::{
+    fs::{
+        File,
+        OpenOptions,
+    },
+    io::{
+        AsyncReadExt,
+        AsyncWriteExt,
+        BufReader,
+        BufWriter,
+    },
+    sync::mpsc::{
+        UnboundedReceiver,
+        UnboundedSender,
+    },
+};
+
+/// The extension of the recording files
+pub const RECORDING_EXTENSION: &str = "feed";
+
+/// A raw payload received from a feed source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeedRecord {
+    /// The topic the payload was published on
+    pub topic: String,
+    /// The time in ms since the unix epoch the payload was received
+    pub timestamp_ms: u64,
+    /// The raw payload
+    pub payload: Vec<u8>,
+}
+
+impl FeedRecord {
+    /// Create a new record, timestamped now
+    pub fn new(topic: &str, payload: &[u8]) -> Self {
+        Self {
+            topic: topic.to_owned(),
+            timestamp_ms: now_ms(),
+            payload: payload.to_vec(),
+        }
+    }
+    fn encoded_len(&self) -> u64 {
+        (2 + self.topic.len() + 8 + 4 + self.payload.len()) as u64
+    }
+}
+
+/// FeedRecorderHandle is cloned into every feed source which should be recorded.
+/// The recorder flushes and stops once all its handles are dropped.
+#[derive(Clone)]
+pub struct FeedRecorderHandle {
+    tx: UnboundedSender<FeedRecord>,
+}
+
+impl FeedRecorderHandle {
+    /// Record a raw payload received on the given topic
+    pub fn record(&self, topic: &str, payload: &[u8]) {
+        let _ = self.tx.send(FeedRecord::new(topic, payload));
+    }
+}
+
+/// Appends the records to rotating files
+pub struct FeedRecorder {
+    directory: PathBuf,
+    max_file_size: u64,
+    max_files: Option<usize>,
+    file: Option<(BufWriter<File>, u64)>,
+    rx: UnboundedReceiver<FeedRecord>,
+}
+
+impl FeedRecorder {
+    /// Spawn a recorder with the given config and return its handle
+    pub async fn spawn(config: &FeedRecordingConfig) -> anyhow::Result<FeedRecorderHandle> {
+        let directory = PathBuf::from(&config.directory);
+        tokio::fs::create_dir_all(&directory).await?;
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let recorder = Self {
+            directory,
+            max_file_size: config.max_file_size,
+            max_files: config.max_files,
+            file: None,
+            rx,
+        };
+        tokio::spawn(recorder.run());
+        Ok(FeedRecorderHandle { tx })
+    }
+    async fn run(mut self) {
+        info!("Recording the feed traffic to {:?}", self.directory);
+        while let Some(record) = self.rx.recv().await {
+            if let Err(e) = self.write(record).await {
+                error!("Unable to record feed traffic: {}", e);
+                continue;
+            }
+            // drain whatever is already queued before flushing
+            while let Ok(record) = self.rx.try_recv() {
+                if let Err(e) = self.write(record).await {
+                    error!("Unable to record feed traffic: {}", e);
+                }
+            }
+            if let Some((file, _)) = self.file.as_mut() {
+                if let Err(e) = file.flush().await {
+                    error!("Unable to flush feed recording: {}", e);
+                }
+            }
+        }
+        if let Some((mut file, _)) = self.file.take() {
+            let _ = file.flush().await;
+        }
+        info!("Stopped recording the feed traffic to {:?}", self.directory);
+    }
+    async fn write(&mut self, record: FeedRecord) -> anyhow::Result<()> {
+        let len = record.encoded_len();
+        if self
+            .file
+            .as_ref()
+            .map_or(true, |(_, size)| *size + len > self.max_file_size)
+        {
+            self.rotate().await?;
+        }
+        let (file, size) = self.file.as_mut().unwrap();
+        file.write_u16(record.topic.len() as u16).await?;
+        file.write_all(record.topic.as_bytes()).await?;
+        file.write_u64(record.timestamp_ms).await?;
+        file.write_u32(record.payload.len() as u32).await?;
+        file.write_all(&record.payload).await?;
+        *size += len;
+        Ok(())
+    }
+    async fn rotate(&mut self) -> anyhow::Result<()> {
+        if let Some((mut file, _)) = self.file.take() {
+            file.flush().await?;
+        }
+        let file_path = self.directory.join(format!("{:020}.{}", now_ms(), RECORDING_EXTENSION));
+        let file = OpenOptions::new().append(true).create(true).open(&file_path).await?;
+        let size = file.metadata().await?.len();
+        self.file.replace((BufWriter::new(file), size));
+        if let Some(max_files) = self.max_files {
+            let mut files = recording_files(&self.directory).await?;
+            while files.len() > max_files {
+                let oldest = files.remove(0);
+                info!("Removing old feed recording {:?}", oldest);
+                tokio::fs::remove_file(oldest).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads the records of a recording file in order
+pub struct FeedReader {
+    path: PathBuf,
+    file: BufReader<File>,
+}
+
+impl FeedReader {
+    /// Open the recording file at the given path
+    pub async fn open<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref().to_owned();
+        let file = BufReader::new(File::open(&path).await?);
+        Ok(Self { path, file })
+    }
+    /// Read the next record, or None once the end of the file is reached.
+    /// A truncated trailing record (ie the recorder crashed midway) is treated as the end of the file.
+    pub async fn next_record(&mut self) -> anyhow::Result<Option<FeedRecord>> {
+        match self.read_record().await {
+            Ok(record) => Ok(Some(record)),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => Ok(None),
+            Err(e) => bail!("Unable to read feed recording {:?}: {}", self.path, e),
+        }
+    }
+    async fn read_record(&mut self) -> std::io::Result<FeedRecord> {
+        let topic_len = self.file.read_u16().await?;
+        let mut topic = vec![0; topic_len as usize];
+        self.file.read_exact(&mut topic).await?;
+        let timestamp_ms = self.file.read_u64().await?;
+        let payload_len = self.file.read_u32().await?;
+        let mut payload = vec![0; payload_len as usize];
+        self.file.read_exact(&mut payload).await?;
+        let topic = String::from_utf8(topic).map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e))?;
+        Ok(FeedRecord {
+            topic,
+            timestamp_ms,
+            payload,
+        })
+    }
+}
+
+/// List the recording files of a directory (or the file itself) in the order they were recorded
+pub async fn recording_files<P: AsRef<Path>>(path: P) -> anyhow::Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    if path.is_file() {
+        return Ok(vec![path.to_owned()]);
+    }
+    let mut files = Vec::new();
+    let mut dir = tokio::fs::read_dir(path).await?;
+    while let Some(entry) = dir.next_entry().await? {
+        let file_path = entry.path();
+        if file_path.is_file() && file_path.extension().map_or(false, |ext| ext == RECORDING_EXTENSION) {
+            files.push(file_path);
+        }
+    }
+    // the file names are zero padded creation times
+    files.sort();
+    Ok(files)
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_millis() as u64
+}
@@ -0,0 +1,397 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Streams the archive logs from http(s) urls, so they are imported without being downloaded first.
+//! A dropped connection is resumed with a range request at the first byte which wasn't received yet, which is only
+//! honored if the log didn't change in the meantime, as validated by its ETag.
+//! Also pipes the logs streamed by the clients over the broker websocket, ie from their stdin.
+
+use super::*;
+use crate::manifest::{
+    manifest_path,
+    LogManifest,
+};
+use reqwest::{
+    header::{
+        HeaderMap,
+        HeaderValue,
+        CONTENT_RANGE,
+        ETAG,
+        IF_RANGE,
+        LAST_MODIFIED,
+        RANGE,
+    },
+    Client,
+    RequestBuilder,
+    Response,
+    StatusCode,
+};
+use std::{
+    path::Path,
+    pin::Pin,
+    sync::{
+        Arc,
+        Mutex,
+    },
+    task::{
+        Context,
+        Poll,
+    },
+    time::Duration,
+};
+use tokio::io::{
+    AsyncRead,
+    AsyncWriteExt,
+    DuplexStream,
+    ReadBuf,
+};
+
+/// The buffer between the http connection and the log reader
+const PIPE_CAPACITY: usize = 1024 * 1024;
+/// The reconnections per dropped connection, before the stream fails
+const MAX_RECONNECTS: u32 = 5;
+/// The time to establish a connection
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// The time to wait for the response head or the next chunk of the body, before the connection counts as dropped
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+/// The scheme of the logs streamed over the broker websocket, ie stream://<id>/0to10.log
+const STREAM_SCHEME: &str = "stream://";
+
+/// Whether the path holds an http(s) url, ie http://archive/0to10.log
+pub fn is_http(path: &Path) -> bool {
+    path.to_str()
+        .map(|path| path.starts_with("http://") || path.starts_with("https://"))
+        .unwrap_or(false)
+}
+
+/// Whether the path holds a log streamed over the broker websocket, ie stream://<id>/0to10.log
+pub fn is_stream(path: &Path) -> bool {
+    path.to_str()
+        .map(|path| path.starts_with(STREAM_SCHEME))
+        .unwrap_or(false)
+}
+
+/// The path of the log with the given name streamed over the broker websocket under the given id
+pub fn stream_path(id: &str, name: &str) -> PathBuf {
+    PathBuf::from(format!("{}{}/{}", STREAM_SCHEME, id, name))
+}
+
+/// The id of the log streamed over the broker websocket at the given path
+pub fn stream_id(path: &Path) -> Option<&str> {
+    path.to_str()?.strip_prefix(STREAM_SCHEME)?.split('/').next()
+}
+
+/// The http client, which gives up on unreachable servers
+fn client() -> anyhow::Result<Client> {
+    Ok(Client::builder().connect_timeout(CONNECT_TIMEOUT).build()?)
+}
+
+/// Reader over the body of a remote log, which is pumped in the background
+pub struct RemoteLogReader {
+    pipe: DuplexStream,
+    /// Why the background pump failed, if it did, so a failure isn't mistaken for the end of the log
+    error: Arc<Mutex<Option<String>>>,
+}
+
+impl RemoteLogReader {
+    /// Start streaming the log at the given url from the given byte offset, returning its reader along with its
+    /// remaining size if the server provided it
+    pub async fn open(url: &Url, offset: u64) -> anyhow::Result<(Self, Option<u64>)> {
+        let client = client()?;
+        let response = send_request(range_request(&client, url, offset, None), url).await?;
+        check_response(response.status(), response.headers(), url, offset, None)?;
+        let size = response.content_length();
+        let (writer, pipe) = tokio::io::duplex(PIPE_CAPACITY);
+        let error = Arc::new(Mutex::new(None));
+        tokio::spawn(pump(client, url.clone(), response, offset, writer, error.clone()));
+        Ok((Self { pipe, error }, size))
+    }
+    /// Create the reader of a log streamed over the broker websocket, along with the writer its frames go to
+    pub fn channel() -> (StreamedLogWriter, Self) {
+        let (writer, pipe) = tokio::io::duplex(PIPE_CAPACITY);
+        let error = Arc::new(Mutex::new(None));
+        let writer = StreamedLogWriter {
+            pipe: writer,
+            error: error.clone(),
+            finished: false,
+        };
+        (writer, Self { pipe, error })
+    }
+}
+
+/// Writes the frames of a log streamed over the broker websocket to its reader.
+/// Dropping it before the stream is finished fails the reader, so a lost connection isn't mistaken for the end of the
+/// log
+pub struct StreamedLogWriter {
+    pipe: DuplexStream,
+    error: Arc<Mutex<Option<String>>>,
+    finished: bool,
+}
+
+impl StreamedLogWriter {
+    /// Write the next frame of the log, waiting for the reader to catch up
+    pub async fn write(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        self.pipe.write_all(bytes).await
+    }
+    /// End the log once its last frame is written
+    pub async fn finish(mut self) -> std::io::Result<()> {
+        self.finished = true;
+        self.pipe.shutdown().await
+    }
+}
+
+impl Drop for StreamedLogWriter {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.error
+                .lock()
+                .unwrap()
+                .replace("The log stream got interrupted before its end".to_owned());
+        }
+    }
+}
+
+impl AsyncRead for RemoteLogReader {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        let filled = buf.filled().len();
+        match Pin::new(&mut self.pipe).poll_read(cx, buf) {
+            // the pipe only ends once the pump is done, successfully or not
+            Poll::Ready(Ok(())) if buf.filled().len() == filled => match self.error.lock().unwrap().as_ref() {
+                Some(e) => Poll::Ready(Err(std::io::Error::new(std::io::ErrorKind::Other, e.clone()))),
+                None => Poll::Ready(Ok(())),
+            },
+            poll => poll,
+        }
+    }
+}
+
+/// Request the log at the given url, starting at the given byte offset.
+/// If a validator of the content streamed so far is provided, the range is only served if the log didn't change
+fn range_request(client: &Client, url: &Url, offset: u64, validator: Option<&HeaderValue>) -> RequestBuilder {
+    let mut request = client.get(url.clone());
+    if offset > 0 {
+        request = request.header(RANGE, format!("bytes={}-", offset));
+        if let Some(validator) = validator {
+            request = request.header(IF_RANGE, validator.clone());
+        }
+    }
+    request
+}
+
+/// Send the request, failing on the transient errors only, ie timeouts, dropped connections or overloaded servers
+async fn send_request(request: RequestBuilder, url: &Url) -> anyhow::Result<Response> {
+    let response = tokio::time::timeout(READ_TIMEOUT, request.send())
+        .await
+        .map_err(|_| anyhow!("Timed out requesting {}", url))??;
+    ensure!(
+        !response.status().is_server_error(),
+        "Unable to fetch {}, status code: {}",
+        url,
+        response.status()
+    );
+    Ok(response)
+}
+
+/// Check that the response serves the log from the requested byte offset, and that a resumed log didn't change
+fn check_response(
+    status: StatusCode,
+    headers: &HeaderMap,
+    url: &Url,
+    offset: u64,
+    validator: Option<&HeaderValue>,
+) -> anyhow::Result<()> {
+    match status {
+        StatusCode::PARTIAL_CONTENT if offset > 0 => {
+            let content_range = headers
+                .get(CONTENT_RANGE)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default();
+            ensure!(
+                content_range.starts_with(&format!("bytes {}-", offset)),
+                "{} served the range {} instead of the one starting at byte {}",
+                url,
+                content_range,
+                offset
+            );
+            Ok(())
+        }
+        status if status.is_success() && offset == 0 => Ok(()),
+        status if status.is_success() && validator.is_some() => {
+            bail!(
+                "{} changed since it got streamed, unable to resume at byte {}",
+                url,
+                offset
+            )
+        }
+        status if status.is_success() => bail!(
+            "{} doesn't support range requests, unable to resume at byte {}",
+            url,
+            offset
+        ),
+        status => bail!("Unable to fetch {}, status code: {}", url, status),
+    }
+}
+
+/// The validator of the served log, ie its ETag, or its modification date if it has none.
+/// Weak ETags can't validate a range, so they are ignored
+fn validator(headers: &HeaderMap) -> Option<HeaderValue> {
+    headers
+        .get(ETAG)
+        .filter(|etag| !etag.as_bytes().starts_with(b"W/"))
+        .or_else(|| headers.get(LAST_MODIFIED))
+        .cloned()
+}
+
+/// Write the body of the log to the pipe in the background, and record why it failed, if it did
+async fn pump(
+    client: Client,
+    url: Url,
+    response: Response,
+    offset: u64,
+    mut writer: DuplexStream,
+    error: Arc<Mutex<Option<String>>>,
+) {
+    if let Err(e) = stream_body(&client, &url, response, offset, &mut writer).await {
+        warn!("Stopped streaming {}: {}", url, e);
+        // set before the pipe gets closed, so the reader doesn't mistake the failure for the end of the log
+        error.lock().unwrap().replace(e.to_string());
+    }
+}
+
+/// Write the body of the log to the pipe, resuming the dropped connections where they stopped
+async fn stream_body(
+    client: &Client,
+    url: &Url,
+    mut response: Response,
+    offset: u64,
+    writer: &mut DuplexStream,
+) -> anyhow::Result<()> {
+    let validator = validator(response.headers());
+    let mut received = offset;
+    let mut reconnects = 0;
+    loop {
+        let chunk = match tokio::time::timeout(READ_TIMEOUT, response.chunk()).await {
+            Ok(chunk) => chunk.map_err(anyhow::Error::from),
+            Err(_) => Err(anyhow!("No data received for {:?}", READ_TIMEOUT)),
+        };
+        match chunk {
+            Ok(Some(chunk)) => {
+                writer.write_all(&chunk).await?;
+                received += chunk.len() as u64;
+                reconnects = 0;
+            }
+            Ok(None) => break,
+            Err(mut e) => {
+                // the failed reconnections count against the same limit as the dropped connections
+                response = loop {
+                    if reconnects == MAX_RECONNECTS {
+                        bail!(e);
+                    }
+                    reconnects += 1;
+                    warn!(
+                        "Lost the connection to {} at byte {}, reconnect {}/{}: {}",
+                        url, received, reconnects, MAX_RECONNECTS, e
+                    );
+                    tokio::time::sleep(Duration::from_secs(2u64.pow(reconnects))).await;
+                    match send_request(range_request(client, url, received, validator.as_ref()), url).await {
+                        Ok(resumed) => break resumed,
+                        Err(request_error) => e = request_error,
+                    }
+                };
+                check_response(response.status(), response.headers(), url, received, validator.as_ref())?;
+            }
+        }
+    }
+    writer.shutdown().await?;
+    Ok(())
+}
+
+/// Download the log at the given url into the given file, ie a binary log which is read through its index
+pub async fn download_log(url: &Url, dest: &Path) -> anyhow::Result<()> {
+    let (mut reader, _) = RemoteLogReader::open(url, 0).await?;
+    let mut file = tokio::fs::File::create(dest).await?;
+    tokio::io::copy(&mut reader, &mut file).await?;
+    file.sync_all().await?;
+    Ok(())
+}
+
+/// Fetch the manifest of the log at the given url, ie http://archive/0to10.manifest for http://archive/0to10.log.zst.
+/// Returns None if the server has no manifest for it
+pub async fn fetch_manifest(url: &Url) -> anyhow::Result<Option<LogManifest>> {
+    let mut manifest_url = url.clone();
+    manifest_url.set_path(&manifest_path(Path::new(url.path())).to_string_lossy());
+    let response = tokio::time::timeout(READ_TIMEOUT, client()?.get(manifest_url.clone()).send())
+        .await
+        .map_err(|_| anyhow!("Timed out requesting {}", manifest_url))??;
+    match response.status() {
+        StatusCode::NOT_FOUND => Ok(None),
+        status if status.is_success() => {
+            let manifest = serde_json::from_slice(&response.bytes().await?)
+                .map_err(|e| anyhow!("Malformed manifest {}: {}", manifest_url, e))?;
+            Ok(Some(manifest))
+        }
+        status => bail!("Unable to fetch {}, status code: {}", manifest_url, status),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url() -> Url {
+        Url::parse("http://archive/0to10.log").unwrap()
+    }
+
+    fn headers(entries: &[(reqwest::header::HeaderName, &'static str)]) -> HeaderMap {
+        entries
+            .iter()
+            .map(|(name, value)| (name.clone(), HeaderValue::from_static(value)))
+            .collect()
+    }
+
+    #[test]
+    fn dropped_connections_resume_with_a_validated_range() {
+        let client = Client::new();
+        let etag = HeaderValue::from_static("\"v1\"");
+        let request = range_request(&client, &url(), 0, None).build().unwrap();
+        assert!(request.headers().get(RANGE).is_none());
+        let request = range_request(&client, &url(), 100, Some(&etag)).build().unwrap();
+        assert_eq!(request.headers().get(RANGE).unwrap(), "bytes=100-");
+        assert_eq!(request.headers().get(IF_RANGE).unwrap(), &etag);
+        // the range is requested unconditionally without a validator
+        let request = range_request(&client, &url(), 100, None).build().unwrap();
+        assert!(request.headers().get(IF_RANGE).is_none());
+    }
+
+    #[test]
+    fn resumed_ranges_are_checked() {
+        let etag = HeaderValue::from_static("\"v1\"");
+        let check = |status: StatusCode, headers: &HeaderMap, offset: u64, validator: Option<&HeaderValue>| {
+            check_response(status, headers, &url(), offset, validator).is_ok()
+        };
+        let partial = headers(&[(CONTENT_RANGE, "bytes 100-199/200")]);
+        assert!(check(StatusCode::PARTIAL_CONTENT, &partial, 100, Some(&etag)));
+        // another range than the requested one
+        assert!(!check(StatusCode::PARTIAL_CONTENT, &partial, 150, Some(&etag)));
+        assert!(!check(StatusCode::PARTIAL_CONTENT, &HeaderMap::new(), 100, Some(&etag)));
+        // the whole log is served once it changed, or if the server ignores the ranges
+        assert!(!check(StatusCode::OK, &HeaderMap::new(), 100, Some(&etag)));
+        assert!(!check(StatusCode::OK, &HeaderMap::new(), 100, None));
+        assert!(check(StatusCode::OK, &HeaderMap::new(), 0, None));
+        assert!(!check(StatusCode::NOT_FOUND, &HeaderMap::new(), 0, None));
+    }
+
+    #[test]
+    fn only_strong_etags_validate_the_ranges() {
+        let last_modified = "Wed, 21 Oct 2015 07:28:00 GMT";
+        assert_eq!(
+            validator(&headers(&[(ETAG, "\"v1\""), (LAST_MODIFIED, last_modified)])).unwrap(),
+            "\"v1\""
+        );
+        assert_eq!(
+            validator(&headers(&[(ETAG, "W/\"v1\""), (LAST_MODIFIED, last_modified)])).unwrap(),
+            last_modified
+        );
+        assert!(validator(&headers(&[(ETAG, "W/\"v1\"")])).is_none());
+    }
+}
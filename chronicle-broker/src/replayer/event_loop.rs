@@ -0,0 +1,94 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+#[async_trait::async_trait]
+impl<H: ChronicleBrokerScope> EventLoop<BrokerHandle<H>> for Replayer {
+    async fn event_loop(
+        &mut self,
+        status: Result<(), Need>,
+        supervisor: &mut Option<BrokerHandle<H>>,
+    ) -> Result<(), Need> {
+        status?;
+        self.service.update_status(ServiceStatus::Running);
+        let event = BrokerEvent::Children(BrokerChild::Replayer(self.service.clone(), Ok(())));
+        let supervisor = supervisor.as_mut().unwrap();
+        let _ = supervisor.send(event);
+        let files = recording_files(&self.path).await.map_err(|e| {
+            error!("Replayer: {}, unable to list recording files: {}", self.get_name(), e);
+            Need::Abort
+        })?;
+        let mut previous_timestamp_ms = None;
+        for file_path in files {
+            info!("Replayer: {}, replaying {:?}", self.get_name(), file_path);
+            let mut reader = FeedReader::open(&file_path).await.map_err(|e| {
+                error!("Replayer: {}, unable to open {:?}: {}", self.get_name(), file_path, e);
+                Need::Abort
+            })?;
+            while let Some(record) = reader.next_record().await.map_err(|e| {
+                error!("Replayer: {}, {}", self.get_name(), e);
+                Need::Abort
+            })? {
+                // keep the original spacing between the records, scaled by the speed factor
+                if let (Some(speed), Some(previous_timestamp_ms)) = (self.speed, previous_timestamp_ms) {
+                    let delay_ms = record.timestamp_ms.saturating_sub(previous_timestamp_ms) as f64 / speed;
+                    if delay_ms >= 1.0 {
+                        tokio::time::sleep(Duration::from_secs_f64(delay_ms / 1000.0)).await;
+                    }
+                }
+                previous_timestamp_ms = Some(record.timestamp_ms);
+                self.replay(record, supervisor);
+            }
+        }
+        info!(
+            "Replayer: {}, finished replaying {} records",
+            self.get_name(),
+            self.replayed
+        );
+        Ok(())
+    }
+}
+
+impl Replayer {
+    /// Push a recorded payload to the collectors, the same way the Mqtt of its topic would
+    fn replay<H: ChronicleBrokerScope>(&mut self, record: FeedRecord, supervisor: &BrokerHandle<H>) {
+        match Topics::try_from(record.topic.as_str()) {
+            Ok(Topics::Messages) => {
+                if let Ok(message) = Message::unpack(&mut record.payload.as_slice()) {
+                    let (message_id, _) = message.id();
+                    let collector_partition_id = self.partitioner.partition_id(&message_id);
+                    if let Some(collector_handle) = self.collectors_handles.get(&collector_partition_id) {
+                        let _ = collector_handle
+                            .send_async(CollectorEvent::Message(message_id, message))
+                            .await;
+                    }
+                }
+            }
+            Ok(Topics::MessagesReferenced) => {
+                if let Ok(metadata) = serde_json::from_slice::<MessageMetadata>(&record.payload) {
+                    let collector_partition_id = self.partitioner.partition_id(&metadata.message_id);
+                    if let Some(collector_handle) = self.collectors_handles.get(&collector_partition_id) {
+                        let _ = collector_handle
+                            .send_async(CollectorEvent::MessageReferenced(metadata))
+                            .await;
+                    }
+                }
+            }
+            Ok(Topics::LatestMilestone) => {
+                if let Ok(latest) = serde_json::from_slice::<LatestMilestonePayload>(&record.payload) {
+                    let network_tip = NetworkTip {
+                        milestone_index: latest.index,
+                        timestamp: latest.timestamp,
+                    };
+                    let _ = supervisor.send(BrokerEvent::NetworkTip(network_tip));
+                }
+            }
+            Err(e) => {
+                warn!("Replayer: {}, skipping record: {}", self.get_name(), e);
+                return;
+            }
+        }
+        self.replayed += 1;
+    }
+}
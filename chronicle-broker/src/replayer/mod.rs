@@ -0,0 +1,119 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use super::{
+    collector::{
+        CollectorEvent,
+        CollectorHandle,
+        MessageIdPartitioner,
+    },
+    mqtt::{
+        LatestMilestonePayload,
+        Topics,
+    },
+    recording::{
+        recording_files,
+        FeedReader,
+        FeedRecord,
+    },
+    *,
+};
+use futures::future::AbortHandle;
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    time::Duration,
+};
+
+mod event_loop;
+mod init;
+mod terminating;
+
+// Replayer builder
+builder!(ReplayerBuilder {
+    path: PathBuf,
+    speed: f64,
+    collectors_handles: HashMap<u8, CollectorHandle>
+});
+
+/// ReplayerHandle to be passed to the supervisor in order to shutdown/abort the replayer
+#[derive(Clone)]
+pub struct ReplayerHandle {
+    abort_handle: AbortHandle,
+}
+
+impl ReplayerHandle {
+    /// Create a new replayer handle
+    pub fn new(abort_handle: AbortHandle) -> Self {
+        Self { abort_handle }
+    }
+}
+
+impl Shutdown for ReplayerHandle {
+    fn shutdown(self) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        // abortable actor just require abort()
+        self.abort_handle.abort();
+        None
+    }
+}
+
+/// Replayer state
+pub struct Replayer {
+    service: Service,
+    path: PathBuf,
+    /// The speed factor relative to the recording, None replays as fast as possible
+    speed: Option<f64>,
+    collectors_handles: HashMap<u8, CollectorHandle>,
+    partitioner: MessageIdPartitioner,
+    replayed: usize,
+}
+
+impl Replayer {
+    pub(crate) fn clone_service(&self) -> Service {
+        self.service.clone()
+    }
+}
+
+impl<H: ChronicleBrokerScope> ActorBuilder<BrokerHandle<H>> for ReplayerBuilder {}
+
+/// implementation of builder
+impl Builder for ReplayerBuilder {
+    type State = Replayer;
+    fn build(self) -> Self::State {
+        let collectors_handles = self.collectors_handles.expect("Expected collectors handles");
+        let collector_count = collectors_handles.len() as u8;
+        Self::State {
+            service: Service::new(),
+            path: self.path.unwrap(),
+            speed: self.speed.filter(|speed| *speed > 0.0),
+            collectors_handles,
+            partitioner: MessageIdPartitioner::new(collector_count),
+            replayed: 0,
+        }
+        .set_name()
+    }
+}
+
+/// impl name of the Replayer
+impl Name for Replayer {
+    fn set_name(mut self) -> Self {
+        let name = format!("replayer@{}", self.path.to_string_lossy());
+        self.service.update_name(name);
+        self
+    }
+    fn get_name(&self) -> String {
+        self.service.get_name()
+    }
+}
+
+#[async_trait::async_trait]
+impl<H: ChronicleBrokerScope> AknShutdown<Replayer> for BrokerHandle<H> {
+    async fn aknowledge_shutdown(self, mut _state: Replayer, status: Result<(), Need>) {
+        _state.service.update_status(ServiceStatus::Stopped);
+        let event = BrokerEvent::Children(BrokerChild::Replayer(_state.service.clone(), status));
+        let _ = self.send(event);
+    }
+}
@@ -3,8 +3,13 @@
 
 use super::*;
 use bee_message::Message;
-use chronicle_common::Wrapper;
+use chronicle_common::{
+    metrics::REQUESTER_RETRIES,
+    Wrapper,
+};
+use reqwest::StatusCode;
 use serde_json::Value;
+use std::time::Instant;
 
 #[async_trait::async_trait]
 impl EventLoop<CollectorHandle> for Requester {
@@ -31,8 +36,21 @@ impl EventLoop<CollectorHandle> for Requester {
                     RequesterTopology::AddEndpoint(url) => {
                         info!("Trying to AddEndpoint: {}", url);
                         if self.api_endpoints.iter().all(|u| u != &url) {
+                            self.api_endpoints.push_front(url.clone());
+                            if self.add_api_client(url.clone(), None) {
+                                info!("AddedEndpoint: {}", url);
+                            }
+                        }
+                        self.shuffle();
+                    }
+                    RequesterTopology::AddAuthenticatedEndpoint { url, auth } => {
+                        info!("Trying to AddEndpoint: {}", url);
+                        // replace the client of a known endpoint, as its credentials may have changed
+                        if self.api_endpoints.iter().all(|u| u != &url) {
+                            self.api_endpoints.push_front(url.clone());
+                        }
+                        if self.add_api_client(url.clone(), Some(&auth)) {
                             info!("AddedEndpoint: {}", url);
-                            self.api_endpoints.push_front(url);
                         }
                         self.shuffle();
                     }
@@ -41,6 +59,8 @@ impl EventLoop<CollectorHandle> for Requester {
                         if let Some(p) = self.api_endpoints.iter().position(|u| u == &url) {
                             info!("RemovedEndpoint: {}", url);
                             self.api_endpoints.remove(p);
+                            self.api_clients.remove(&url);
+                            self.raw_fallback.remove(&url);
                         }
                     }
                 },
@@ -59,29 +79,31 @@ impl Requester {
         try_ms_index: u32,
     ) {
         let mut retries = self.retries;
-        loop {
-            if retries > 0 {
-                if let Some(remote_url) = self.api_endpoints.pop_front() {
-                    if let Ok(full_message) = self.request_message_and_metadata(&remote_url, message_id).await {
-                        self.respond_to_collector(collector_handle, try_ms_index, Some(message_id), Some(full_message));
-                        self.api_endpoints.push_front(remote_url);
-                        break;
-                    } else {
-                        self.api_endpoints.push_back(remote_url);
-                        retries -= 1;
-                        // keep retrying, but yield to keep the system responsive
-                        tokio::task::yield_now().await;
-                        continue;
+        while retries > 0 {
+            match self.endpoints_health.select(self.api_endpoints.iter(), Instant::now()) {
+                Selection::Endpoint(remote_url, attempt) => {
+                    let start = Instant::now();
+                    let result = self.request_message_and_metadata(&remote_url, message_id).await;
+                    let end = Instant::now();
+                    attempt.report(end, end.duration_since(start), Self::outcome(&result));
+                    if let Ok(full_message) = result {
+                        self.respond_to_collector(collector_handle, try_ms_index, Some(message_id), Some(full_message))
+                            .await;
+                        return;
                     }
-                } else {
-                    self.respond_to_collector(collector_handle, try_ms_index, None, None);
-                    break;
-                };
-            } else {
-                self.respond_to_collector(collector_handle, try_ms_index, None, None);
-                break;
+                    retries -= 1;
+                    if retries > 0 {
+                        REQUESTER_RETRIES.with_label_values(&[remote_url.as_str()]).inc();
+                    }
+                    // keep retrying, but yield to keep the system responsive
+                    tokio::task::yield_now().await;
+                }
+                Selection::Wait(duration) => tokio::time::sleep(duration).await,
+                Selection::Unavailable => break,
             }
         }
+        self.respond_to_collector(collector_handle, try_ms_index, None, None)
+            .await;
     }
     async fn request_milestone_message_with_retries(
         &mut self,
@@ -89,36 +111,44 @@ impl Requester {
         milestone_index: u32,
     ) {
         let mut retries = self.retries;
-        loop {
-            if retries > 0 {
-                if let Some(remote_url) = self.api_endpoints.pop_front() {
-                    if let Ok(full_message) = self.request_milestone_message(&remote_url, milestone_index).await {
+        while retries > 0 {
+            match self.endpoints_health.select(self.api_endpoints.iter(), Instant::now()) {
+                Selection::Endpoint(remote_url, attempt) => {
+                    let start = Instant::now();
+                    let result = self.request_milestone_message(&remote_url, milestone_index).await;
+                    let end = Instant::now();
+                    attempt.report(end, end.duration_since(start), Self::outcome(&result));
+                    if let Ok(full_message) = result {
                         self.respond_to_collector(
                             collector_handle,
                             milestone_index,
                             Some(full_message.metadata().message_id),
                             Some(full_message),
-                        );
-                        self.api_endpoints.push_front(remote_url);
-                        break;
-                    } else {
-                        self.api_endpoints.push_back(remote_url);
-                        retries -= 1;
-                        // keep retrying, but yield to keep the system responsive
-                        tokio::task::yield_now().await;
-                        continue;
+                        )
+                        .await;
+                        return;
                     }
-                } else {
-                    self.respond_to_collector(collector_handle, milestone_index, None, None);
-                    break;
-                };
-            } else {
-                self.respond_to_collector(collector_handle, milestone_index, None, None);
-                break;
+                    retries -= 1;
+                    if retries > 0 {
+                        REQUESTER_RETRIES.with_label_values(&[remote_url.as_str()]).inc();
+                    }
+                    // keep retrying, but yield to keep the system responsive
+                    tokio::task::yield_now().await;
+                }
+                Selection::Wait(duration) => tokio::time::sleep(duration).await,
+                Selection::Unavailable => break,
             }
         }
+        self.respond_to_collector(collector_handle, milestone_index, None, None)
+            .await;
+    }
+    fn outcome(result: &Result<FullMessage, RequestOutcome>) -> RequestOutcome {
+        match result {
+            Ok(_) => RequestOutcome::Success,
+            Err(outcome) => *outcome,
+        }
     }
-    fn respond_to_collector(
+    async fn respond_to_collector(
         &self,
         collector_handle: &CollectorHandle,
         ms_index: u32,
@@ -127,88 +157,143 @@ impl Requester {
     ) {
         let collector_event =
             CollectorEvent::MessageAndMeta(self.requester_id, ms_index, opt_message_id, opt_full_message);
-        let _ = collector_handle.send(collector_event);
+        let _ = collector_handle.send_async(collector_event).await;
+    }
+    fn api_client(&self, remote_url: &Url) -> Result<&ApiClient, RequestOutcome> {
+        self.api_clients.get(remote_url).ok_or_else(|| {
+            error!("No client for endpoint: {}", remote_url);
+            RequestOutcome::Failure
+        })
     }
-    async fn request_milestone_message(&mut self, remote_url: &Url, milestone_index: u32) -> Result<FullMessage, ()> {
-        let get_milestone_url = remote_url.join(&format!("milestones/{}", milestone_index)).unwrap();
-        let milestone_response = self
-            .reqwest_client
-            .get(get_milestone_url)
-            .send()
+    async fn request_milestone_message(
+        &mut self,
+        remote_url: &Url,
+        milestone_index: u32,
+    ) -> Result<FullMessage, RequestOutcome> {
+        let get_milestone_url = Self::join(remote_url, &format!("milestones/{}", milestone_index))?;
+        let milestone_response = self.send(remote_url, get_milestone_url, "milestone").await?;
+        let milestone = milestone_response
+            .json::<JsonData<MilestoneResponse>>()
             .await
-            .map_err(|e| error!("Error sending request for milestone: {}", e));
-        if let Ok(milestone_response) = milestone_response {
-            if milestone_response.status().is_success() {
-                let milestone = milestone_response
-                    .json::<JsonData<MilestoneResponse>>()
-                    .await
-                    .map_err(|e| error!("Error deserializing milestone: {}", e));
-                if let Ok(milestone) = milestone {
-                    let milestone = milestone.into_inner();
-                    let message_id = MessageId::from_str(&milestone.message_id).expect("Expected message_id as string");
-                    return self.request_message_and_metadata(remote_url, message_id).await;
+            .map_err(|e| {
+                error!("Error deserializing milestone: {}", e);
+                RequestOutcome::Failure
+            })?
+            .into_inner();
+        let message_id = MessageId::from_str(&milestone.message_id).map_err(|e| {
+            error!("Invalid milestone message id {}: {:?}", milestone.message_id, e);
+            RequestOutcome::Failure
+        })?;
+        self.request_message_and_metadata(remote_url, message_id).await
+    }
+    async fn request_message_and_metadata(
+        &mut self,
+        remote_url: &Url,
+        message_id: MessageId,
+    ) -> Result<FullMessage, RequestOutcome> {
+        // the metadata tells whether the node has the message at all, before downloading it
+        let metadata = self.request_metadata(remote_url, message_id).await?;
+        if metadata.referenced_by_milestone_index.is_none() {
+            // the node is lagging behind, it does not know the message got referenced yet
+            return Err(RequestOutcome::NotFound);
+        }
+        let message = if self.raw_fallback.serves_raw(remote_url, Instant::now()) {
+            match self.request_message_raw(remote_url, message_id).await {
+                Err(RequestOutcome::NotFound) => {
+                    // the node has the metadata but not the raw message, it may not serve raw messages
+                    if self.raw_fallback.missed(remote_url, Instant::now()) {
+                        warn!(
+                            "Endpoint: {}, does not serve raw messages, falling back to json",
+                            remote_url
+                        );
+                    }
+                    self.request_message_json(remote_url, message_id).await?
                 }
-            } else {
-                if !milestone_response.status().is_success() {
-                    let url = milestone_response.url().clone();
-                    let err = milestone_response.json::<Value>().await;
-                    error!("Received error requesting milestone from {}:\n {:#?}", url, err);
+                result => {
+                    let message = result?;
+                    self.raw_fallback.served(remote_url);
+                    message
                 }
             }
+        } else {
+            self.request_message_json(remote_url, message_id).await?
+        };
+        let full_message = FullMessage::new(message, metadata);
+        if let Err(e) = verify_full_message(&full_message, &message_id) {
+            reject_message(remote_url.as_str(), &e);
+            return Err(RequestOutcome::Failure);
         }
-        Err(())
+        Ok(full_message)
     }
-    async fn request_message_and_metadata(
-        &mut self,
+    async fn request_metadata(
+        &self,
         remote_url: &Url,
         message_id: MessageId,
-    ) -> Result<FullMessage, ()> {
-        let get_message_url = remote_url.join(&format!("messages/{}", message_id)).unwrap();
-        let get_metadata_url = remote_url.join(&format!("messages/{}/metadata", message_id)).unwrap();
-        let message_response = self
-            .reqwest_client
-            .get(get_message_url)
-            .send()
+    ) -> Result<MessageMetadata, RequestOutcome> {
+        let get_metadata_url = Self::join(remote_url, &format!("messages/{}/metadata", message_id))?;
+        let metadata_response = self.send(remote_url, get_metadata_url, "metadata").await?;
+        let metadata = metadata_response
+            .json::<JsonData<MessageMetadata>>()
             .await
-            .map_err(|e| error!("Error sending request for message: {}", e));
-        let metadata_response = self
-            .reqwest_client
-            .get(get_metadata_url)
-            .send()
+            .map_err(|e| {
+                error!("Error deserializing metadata: {}", e);
+                RequestOutcome::Failure
+            })?
+            .into_inner();
+        Ok(metadata)
+    }
+    async fn request_message_raw(&self, remote_url: &Url, message_id: MessageId) -> Result<Message, RequestOutcome> {
+        let get_message_url = Self::join(remote_url, &format!("messages/{}/raw", message_id))?;
+        let message_response = self.send(remote_url, get_message_url, "raw message").await?;
+        let bytes = message_response.bytes().await.map_err(|e| {
+            error!("Error receiving raw message: {}", e);
+            RequestOutcome::Failure
+        })?;
+        Message::unpack(&mut bytes.as_ref()).map_err(|e| {
+            error!("Error unpacking raw message {}: {:?}", message_id, e);
+            RequestOutcome::Failure
+        })
+    }
+    async fn request_message_json(&self, remote_url: &Url, message_id: MessageId) -> Result<Message, RequestOutcome> {
+        let get_message_url = Self::join(remote_url, &format!("messages/{}", message_id))?;
+        let message_response = self.send(remote_url, get_message_url, "message").await?;
+        let message_dto = message_response
+            .json::<JsonData<MessageDto>>()
             .await
-            .map_err(|e| error!("Error sending request for metadata: {}", e));
-        if let (Ok(message_response), Ok(metadata_response)) = (message_response, metadata_response) {
-            if message_response.status().is_success() && metadata_response.status().is_success() {
-                let message = message_response
-                    .json::<JsonData<MessageDto>>()
-                    .await
-                    .map_err(|e| error!("Error deserializing message: {}", e));
-                let metadata = metadata_response
-                    .json::<JsonData<MessageMetadata>>()
-                    .await
-                    .map_err(|e| error!("Error deserializing metadata: {}", e));
-                if let (Ok(message), Ok(metadata)) = (message, metadata) {
-                    let message_dto = message.into_inner();
-                    let message = Message::try_from(&message_dto).unwrap();
-                    let metadata = metadata.into_inner();
-                    if metadata.referenced_by_milestone_index.is_some() {
-                        let full_message = FullMessage::new(message, metadata);
-                        return Ok(full_message);
-                    }
-                }
+            .map_err(|e| {
+                error!("Error deserializing message: {}", e);
+                RequestOutcome::Failure
+            })?
+            .into_inner();
+        Message::try_from(&message_dto).map_err(|e| {
+            error!("Error converting message {}: {:?}", message_id, e);
+            RequestOutcome::Failure
+        })
+    }
+    /// Send a GET request to the endpoint, mapping any unsuccessful response to its outcome
+    async fn send(&self, remote_url: &Url, url: Url, what: &str) -> Result<reqwest::Response, RequestOutcome> {
+        let response = self.api_client(remote_url)?.get(url).send().await.map_err(|e| {
+            error!("Error sending request for {}: {}", what, e);
+            RequestOutcome::Failure
+        })?;
+        if response.status().is_success() {
+            Ok(response)
+        } else {
+            let not_found = response.status() == StatusCode::NOT_FOUND;
+            let url = response.url().clone();
+            let err = response.json::<Value>().await;
+            error!("Received error requesting {} from {}:\n {:#?}", what, url, err);
+            if not_found {
+                Err(RequestOutcome::NotFound)
             } else {
-                if !message_response.status().is_success() {
-                    let url = message_response.url().clone();
-                    let err = message_response.json::<Value>().await;
-                    error!("Received error requesting message from {}:\n {:#?}", url, err);
-                }
-                if !metadata_response.status().is_success() {
-                    let url = metadata_response.url().clone();
-                    let err = metadata_response.json::<Value>().await;
-                    error!("Received error requesting metadata from {}:\n {:#?}", url, err);
-                }
+                Err(RequestOutcome::Failure)
             }
         }
-        Err(())
+    }
+    fn join(remote_url: &Url, path: &str) -> Result<Url, RequestOutcome> {
+        remote_url.join(path).map_err(|e| {
+            error!("Invalid request url {}{}: {}", remote_url, path, e);
+            RequestOutcome::Failure
+        })
     }
 }
@@ -0,0 +1,346 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use chronicle_common::{
+    config::EndpointHealthConfig,
+    metrics::{
+        ENDPOINT_CIRCUIT_OPEN,
+        ENDPOINT_HEALTH_SCORE,
+        ENDPOINT_LATENCY,
+        ENDPOINT_REQUESTS,
+    },
+};
+use std::{
+    sync::{
+        Arc,
+        Mutex,
+    },
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+/// The weight of the latest request in the moving averages
+const EWMA_ALPHA: f64 = 0.2;
+/// The length of the rate limit window
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(1);
+
+/// The outcome of a request to an api endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestOutcome {
+    /// The endpoint responded with the requested data
+    Success,
+    /// The endpoint responded, but did not have data it should have (ie pruned or unsynced node)
+    NotFound,
+    /// The request failed or timed out, or the endpoint responded with an error or invalid data
+    Failure,
+}
+
+impl RequestOutcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            RequestOutcome::Success => "success",
+            RequestOutcome::NotFound => "not_found",
+            RequestOutcome::Failure => "failure",
+        }
+    }
+}
+
+/// The endpoint picked for the next request
+pub enum Selection {
+    /// Send the request to this endpoint, and report its outcome through the attempt
+    Endpoint(Url, Attempt),
+    /// All the available endpoints are rate limited, retry after the given duration
+    Wait(Duration),
+    /// All the endpoints are failing
+    Unavailable,
+}
+
+/// A request sent to an endpoint picked by `EndpointsHealth::select`.
+/// If it is the trial of a half open endpoint and gets dropped without a report (ie the request got aborted), the trial
+/// ends with it, so the endpoint gets tried again by a later request
+pub struct Attempt {
+    health: EndpointsHealth,
+    url: Url,
+    trial: bool,
+}
+
+impl Attempt {
+    /// Update the health of the endpoint with the outcome of the request
+    pub fn report(mut self, now: Instant, latency: Duration, outcome: RequestOutcome) {
+        self.trial = false;
+        self.health.report(&self.url, now, latency, outcome);
+    }
+}
+
+impl Drop for Attempt {
+    fn drop(&mut self) {
+        if self.trial {
+            if let Some(health) = self.health.endpoints.lock().unwrap().get_mut(&self.url) {
+                health.trial_in_flight = false;
+            }
+        }
+    }
+}
+
+struct EndpointHealth {
+    latency_ms: f64,
+    error_rate: f64,
+    not_found_rate: f64,
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+    trial_in_flight: bool,
+    window_start: Instant,
+    window_requests: u32,
+}
+
+impl EndpointHealth {
+    fn new(now: Instant) -> Self {
+        Self {
+            latency_ms: 0.0,
+            error_rate: 0.0,
+            not_found_rate: 0.0,
+            consecutive_failures: 0,
+            open_until: None,
+            trial_in_flight: false,
+            window_start: now,
+            window_requests: 0,
+        }
+    }
+    fn score(&self) -> f64 {
+        // not found responses are cheap and may be transient, so they weigh less than failures
+        (1.0 - self.error_rate) * (1.0 - 0.5 * self.not_found_rate) * 1000.0 / (1000.0 + self.latency_ms)
+    }
+    fn circuit(&self, now: Instant) -> CircuitState {
+        match self.open_until {
+            Some(open_until) if now < open_until => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+            None => CircuitState::Closed,
+        }
+    }
+}
+
+/// The health of the api endpoints, shared by all the requesters
+#[derive(Clone)]
+pub struct EndpointsHealth {
+    config: EndpointHealthConfig,
+    endpoints: Arc<Mutex<HashMap<Url, EndpointHealth>>>,
+}
+
+impl EndpointsHealth {
+    /// Create a new endpoints health registry
+    pub fn new(config: EndpointHealthConfig) -> Self {
+        Self {
+            config,
+            endpoints: Default::default(),
+        }
+    }
+    /// Pick the healthiest of the given endpoints which is neither failing nor rate limited
+    pub fn select<'a>(&self, candidates: impl Iterator<Item = &'a Url>, now: Instant) -> Selection {
+        let mut endpoints = self.endpoints.lock().unwrap();
+        let mut best: Option<(&Url, f64)> = None;
+        let mut wait: Option<Duration> = None;
+        for url in candidates {
+            let health = endpoints.entry(url.clone()).or_insert_with(|| EndpointHealth::new(now));
+            match health.circuit(now) {
+                CircuitState::Open => continue,
+                CircuitState::HalfOpen if health.trial_in_flight => continue,
+                _ => (),
+            }
+            if let Some(max_requests_per_sec) = self.config.max_requests_per_sec {
+                if now.duration_since(health.window_start) >= RATE_LIMIT_WINDOW {
+                    health.window_start = now;
+                    health.window_requests = 0;
+                } else if health.window_requests >= max_requests_per_sec {
+                    let retry_after = RATE_LIMIT_WINDOW - now.duration_since(health.window_start);
+                    wait = Some(wait.map_or(retry_after, |wait| wait.min(retry_after)));
+                    continue;
+                }
+            }
+            let score = health.score();
+            if best.map_or(true, |(_, best_score)| score > best_score) {
+                best.replace((url, score));
+            }
+        }
+        match (best, wait) {
+            (Some((url, _)), _) => {
+                let health = endpoints.get_mut(url).unwrap();
+                health.window_requests += 1;
+                let trial = health.circuit(now) == CircuitState::HalfOpen;
+                health.trial_in_flight |= trial;
+                let attempt = Attempt {
+                    health: self.clone(),
+                    url: url.clone(),
+                    trial,
+                };
+                Selection::Endpoint(url.clone(), attempt)
+            }
+            (None, Some(wait)) => Selection::Wait(wait),
+            (None, None) => Selection::Unavailable,
+        }
+    }
+    /// Update the health of an endpoint with the outcome of a request
+    fn report(&self, url: &Url, now: Instant, latency: Duration, outcome: RequestOutcome) {
+        let mut endpoints = self.endpoints.lock().unwrap();
+        let health = endpoints.entry(url.clone()).or_insert_with(|| EndpointHealth::new(now));
+        let latency_ms = latency.as_secs_f64() * 1000.0;
+        health.latency_ms = if health.latency_ms == 0.0 {
+            latency_ms
+        } else {
+            EWMA_ALPHA * latency_ms + (1.0 - EWMA_ALPHA) * health.latency_ms
+        };
+        let (failed, not_found) = match outcome {
+            RequestOutcome::Success => (0.0, 0.0),
+            RequestOutcome::NotFound => (0.0, 1.0),
+            RequestOutcome::Failure => (1.0, 0.0),
+        };
+        health.error_rate = EWMA_ALPHA * failed + (1.0 - EWMA_ALPHA) * health.error_rate;
+        health.not_found_rate = EWMA_ALPHA * not_found + (1.0 - EWMA_ALPHA) * health.not_found_rate;
+        health.trial_in_flight = false;
+        if let RequestOutcome::Failure = outcome {
+            health.consecutive_failures += 1;
+            if health.consecutive_failures >= self.config.failure_threshold {
+                let exponent = (health.consecutive_failures - self.config.failure_threshold).min(16);
+                let backoff_ms = self
+                    .config
+                    .base_backoff_ms
+                    .saturating_mul(1 << exponent)
+                    .min(self.config.max_backoff_ms);
+                if health.open_until.is_none() {
+                    warn!("Opening the circuit of endpoint: {}, for {} ms", url, backoff_ms);
+                }
+                health.open_until.replace(now + Duration::from_millis(backoff_ms));
+            }
+        } else {
+            if health.open_until.take().is_some() {
+                info!("Closing the circuit of endpoint: {}", url);
+            }
+            health.consecutive_failures = 0;
+        }
+        let endpoint = url.as_str();
+        ENDPOINT_REQUESTS.with_label_values(&[endpoint, outcome.as_str()]).inc();
+        ENDPOINT_LATENCY.with_label_values(&[endpoint]).set(health.latency_ms);
+        ENDPOINT_HEALTH_SCORE.with_label_values(&[endpoint]).set(health.score());
+        ENDPOINT_CIRCUIT_OPEN
+            .with_label_values(&[endpoint])
+            .set(health.open_until.is_some() as i64);
+    }
+    /// Forget about a removed endpoint
+    pub fn remove(&self, url: &Url) {
+        if self.endpoints.lock().unwrap().remove(url).is_some() {
+            let endpoint = url.as_str();
+            let _ = ENDPOINT_LATENCY.remove_label_values(&[endpoint]);
+            let _ = ENDPOINT_HEALTH_SCORE.remove_label_values(&[endpoint]);
+            let _ = ENDPOINT_CIRCUIT_OPEN.remove_label_values(&[endpoint]);
+        }
+    }
+    /// The health of all the known endpoints at the given time
+    pub fn report_all(&self, now: Instant) -> Vec<EndpointHealthReport> {
+        self.endpoints
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(url, health)| EndpointHealthReport {
+                url: url.clone(),
+                score: health.score(),
+                latency_ms: health.latency_ms,
+                error_rate: health.error_rate,
+                not_found_rate: health.not_found_rate,
+                consecutive_failures: health.consecutive_failures,
+                circuit: health.circuit(now),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn health() -> (EndpointsHealth, Url) {
+        let config = EndpointHealthConfig {
+            failure_threshold: 2,
+            base_backoff_ms: 50,
+            max_backoff_ms: 50,
+            max_requests_per_sec: None,
+        };
+        (
+            EndpointsHealth::new(config),
+            Url::parse("http://localhost:14265/api/v1/").unwrap(),
+        )
+    }
+
+    fn circuit(health: &EndpointsHealth, now: Instant) -> CircuitState {
+        health.report_all(now)[0].circuit
+    }
+
+    fn select(health: &EndpointsHealth, url: &Url, now: Instant) -> Option<Attempt> {
+        match health.select(std::iter::once(url), now) {
+            Selection::Endpoint(_, attempt) => Some(attempt),
+            _ => None,
+        }
+    }
+
+    fn fail(health: &EndpointsHealth, url: &Url, now: Instant) {
+        select(health, url, now)
+            .expect("Expected the endpoint to be selected")
+            .report(now, Duration::from_millis(10), RequestOutcome::Failure);
+    }
+
+    #[test]
+    fn circuit_transitions() {
+        let (health, url) = health();
+        let start = Instant::now();
+        fail(&health, &url, start);
+        assert_eq!(circuit(&health, start), CircuitState::Closed);
+        fail(&health, &url, start);
+        assert_eq!(circuit(&health, start), CircuitState::Open);
+        assert!(select(&health, &url, start).is_none());
+        // the circuit stays open for the whole backoff
+        let backoff_end = start + Duration::from_millis(50);
+        assert_eq!(
+            circuit(&health, backoff_end - Duration::from_millis(1)),
+            CircuitState::Open
+        );
+        assert_eq!(circuit(&health, backoff_end), CircuitState::HalfOpen);
+        let trial = select(&health, &url, backoff_end).expect("Expected a trial request");
+        // a single trial request at a time
+        assert!(select(&health, &url, backoff_end).is_none());
+        trial.report(backoff_end, Duration::from_millis(10), RequestOutcome::Success);
+        assert_eq!(circuit(&health, backoff_end), CircuitState::Closed);
+        assert!(select(&health, &url, backoff_end).is_some());
+    }
+
+    #[test]
+    fn failed_trial_reopens_the_circuit() {
+        let (health, url) = health();
+        let start = Instant::now();
+        fail(&health, &url, start);
+        fail(&health, &url, start);
+        let trial_time = start + Duration::from_millis(60);
+        fail(&health, &url, trial_time);
+        assert_eq!(circuit(&health, trial_time), CircuitState::Open);
+        assert!(select(&health, &url, trial_time).is_none());
+        // the backoff starts over from the failed trial
+        assert_eq!(
+            circuit(&health, trial_time + Duration::from_millis(50)),
+            CircuitState::HalfOpen
+        );
+    }
+
+    #[test]
+    fn aborted_trial() {
+        let (health, url) = health();
+        let start = Instant::now();
+        fail(&health, &url, start);
+        fail(&health, &url, start);
+        let trial_time = start + Duration::from_millis(60);
+        let trial = select(&health, &url, trial_time).expect("Expected a trial request");
+        assert!(select(&health, &url, trial_time).is_none());
+        drop(trial);
+        assert_eq!(circuit(&health, trial_time), CircuitState::HalfOpen);
+        assert!(select(&health, &url, trial_time).is_some());
+    }
+}
@@ -2,16 +2,23 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use super::*;
+use chronicle_common::config::ApiConnectionConfig;
 use rand::{
     prelude::SliceRandom,
     thread_rng,
 };
-use std::iter::FromIterator;
+use std::{
+    iter::FromIterator,
+    time::Duration,
+};
 
 #[async_trait::async_trait]
 impl Init<CollectorHandle> for Requester {
     async fn init(&mut self, status: Result<(), Need>, supervisor: &mut Option<CollectorHandle>) -> Result<(), Need> {
         self.service.update_status(ServiceStatus::Initializing);
+        for url in self.api_endpoints.clone() {
+            self.add_api_client(url, None);
+        }
         self.shuffle();
         let event = CollectorEvent::Internal(Internal::Service(self.service.clone()));
         let _ = supervisor.as_mut().expect("Expected Collector handle").send(event);
@@ -20,6 +27,29 @@ impl Init<CollectorHandle> for Requester {
 }
 
 impl Requester {
+    /// Build the client of the given api endpoint with the given credentials, or its configured ones,
+    /// an endpoint whose client cannot be built is dropped
+    pub(crate) fn add_api_client(&mut self, url: Url, auth: Option<&ApiConnectionConfig>) -> bool {
+        let config = get_config();
+        let api_client = match auth {
+            Some(auth) => auth.api_client(
+                &self.reqwest_client,
+                Duration::from_secs(config.broker_config.request_timeout_secs),
+            ),
+            None => config.broker_config.api_client(&url, &self.reqwest_client),
+        };
+        match api_client {
+            Ok(api_client) => {
+                self.api_clients.insert(url, api_client);
+                true
+            }
+            Err(e) => {
+                error!("Unable to build the client of endpoint: {}, error: {}", url, e);
+                self.api_endpoints.retain(|u| u != &url);
+                false
+            }
+        }
+    }
     /// shuffle the api_endpoints
     pub(crate) fn shuffle(&mut self) {
         let mut vec_api_endpoints = self.api_endpoints.iter().map(|e| e.clone()).collect::<Vec<_>>();
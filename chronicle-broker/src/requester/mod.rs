@@ -6,12 +6,17 @@ use super::{
         CollectorHandle,
         Internal,
     },
+    verification::{
+        reject_message,
+        verify_full_message,
+    },
     *,
 };
 use bee_rest_api::types::{
     dtos::MessageDto,
     responses::MilestoneResponse,
 };
+use chronicle_common::config::ApiClient;
 use reqwest::Client;
 use std::{
     collections::VecDeque,
@@ -24,9 +29,14 @@ use std::{
 use url::Url;
 
 mod event_loop;
+mod health;
 mod init;
+mod raw_fallback;
 mod terminating;
 
+pub use health::*;
+use raw_fallback::RawFallback;
+
 /// Requester Tokio handle
 pub type RequesterSender = tokio::sync::mpsc::UnboundedSender<RequesterEvent>;
 /// Requester Tokio inbox
@@ -38,7 +48,8 @@ builder!(RequesterBuilder {
     inbox: RequesterInbox,
     api_endpoints: VecDeque<Url>,
     reqwest_client: Client,
-    retries_per_endpoint: usize
+    retries_per_endpoint: usize,
+    endpoints_health: EndpointsHealth
 });
 pub(crate) type RequesterId = u8;
 
@@ -125,6 +136,10 @@ pub struct Requester {
     requester_id: u8,
     inbox: RequesterInbox,
     api_endpoints: VecDeque<Url>,
+    api_clients: HashMap<Url, ApiClient>,
+    /// The endpoints which do not serve raw messages
+    raw_fallback: RawFallback,
+    endpoints_health: EndpointsHealth,
     reqwest_client: Client,
     retries: usize,
 }
@@ -144,6 +159,11 @@ impl Builder for RequesterBuilder {
             inbox: self.inbox.unwrap(),
             requester_id: self.requester_id.unwrap(),
             api_endpoints,
+            api_clients: HashMap::new(),
+            raw_fallback: RawFallback::default(),
+            endpoints_health: self
+                .endpoints_health
+                .unwrap_or_else(|| EndpointsHealth::new(Default::default())),
             reqwest_client: self.reqwest_client.unwrap(),
             retries,
         }
@@ -0,0 +1,88 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use std::time::{
+    Duration,
+    Instant,
+};
+
+/// The consecutive raw message misses after which an endpoint is requested in json only
+const RAW_MISS_THRESHOLD: u32 = 3;
+/// How long an endpoint is requested in json only, before it gets probed for raw messages again
+const RAW_REPROBE_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Tracks the endpoints which do not serve raw messages.
+/// A node which has the metadata of a message but not its raw bytes may be catching up, so an endpoint is only
+/// requested in json after consecutive misses, and gets probed for raw messages again once the interval elapsed
+#[derive(Default)]
+pub(crate) struct RawFallback {
+    endpoints: HashMap<Url, RawMisses>,
+}
+
+#[derive(Default)]
+struct RawMisses {
+    consecutive: u32,
+    json_only_until: Option<Instant>,
+}
+
+impl RawFallback {
+    /// Whether the raw message should be requested from the endpoint
+    pub(crate) fn serves_raw(&mut self, url: &Url, now: Instant) -> bool {
+        match self.endpoints.get(url).and_then(|misses| misses.json_only_until) {
+            Some(json_only_until) if now < json_only_until => false,
+            Some(_) => {
+                // probe the endpoint again from scratch
+                self.endpoints.remove(url);
+                true
+            }
+            None => true,
+        }
+    }
+    /// Count a raw message the endpoint did not have, and tell whether it is now requested in json only
+    pub(crate) fn missed(&mut self, url: &Url, now: Instant) -> bool {
+        let misses = self.endpoints.entry(url.clone()).or_default();
+        misses.consecutive += 1;
+        if misses.consecutive >= RAW_MISS_THRESHOLD && misses.json_only_until.is_none() {
+            misses.json_only_until.replace(now + RAW_REPROBE_INTERVAL);
+            true
+        } else {
+            false
+        }
+    }
+    /// The endpoint served a raw message
+    pub(crate) fn served(&mut self, url: &Url) {
+        self.endpoints.remove(url);
+    }
+    /// Forget about a removed endpoint
+    pub(crate) fn remove(&mut self, url: &Url) {
+        self.endpoints.remove(url);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_only_after_consecutive_misses() {
+        let mut fallback = RawFallback::default();
+        let url = Url::parse("http://localhost:14265/api/v1/").unwrap();
+        let start = Instant::now();
+        // a single miss is transient
+        assert!(!fallback.missed(&url, start));
+        assert!(fallback.serves_raw(&url, start));
+        // a served raw message resets the misses
+        fallback.served(&url);
+        assert!(!fallback.missed(&url, start));
+        assert!(!fallback.missed(&url, start));
+        assert!(fallback.missed(&url, start));
+        assert!(!fallback.serves_raw(&url, start));
+        assert!(!fallback.serves_raw(&url, start + RAW_REPROBE_INTERVAL - Duration::from_secs(1)));
+        // the endpoint gets probed again once the interval elapsed
+        let reprobe = start + RAW_REPROBE_INTERVAL;
+        assert!(fallback.serves_raw(&url, reprobe));
+        assert!(!fallback.missed(&url, reprobe));
+        assert!(fallback.serves_raw(&url, reprobe));
+    }
+}
@@ -5,6 +5,7 @@ use super::*;
 use bee_message::prelude::MilestoneIndex;
 use chronicle_common::{
     alert,
+    metrics::SOLIDIFICATION_TIME,
     Synckey,
 };
 
@@ -58,8 +59,9 @@ impl<H: ChronicleBrokerScope> EventLoop<BrokerHandle<H>> for Solidifier {
                                     }
                                 }
                                 CqlResult::SyncedMilestone(milestone_index) => {
-                                    // Inform syncer (maybe it wants to update the dashboard or something)
+                                    // Inform syncer, in order to keep the sync lag up to date
                                     info!("Synced this milestone {}", milestone_index);
+                                    let _ = self.syncer_handle.send(SyncerEvent::Synced(milestone_index));
                                 }
                                 CqlResult::AnalyzedMilestone(milestone_index) => {
                                     if let Some(in_database) = self.in_database.get_mut(&milestone_index) {
@@ -113,6 +115,17 @@ impl<H: ChronicleBrokerScope> EventLoop<BrokerHandle<H>> for Solidifier {
                     });
                 }
                 SolidifierEvent::Milestone(milestone_message) => {
+                    if let Some(milestone_keys) = self.milestone_keys.as_ref() {
+                        if let Err(e) = verify_milestone_signatures(&milestone_message.1, milestone_keys) {
+                            error!("solidifier_id: {}, rejected milestone: {}", self.partition_id, e);
+                            let milestone_index = milestone_message.1.essence().index().0;
+                            self.reject_milestone(
+                                milestone_index,
+                                e.context(format!("invalid milestone message {}", milestone_message.0)),
+                            );
+                            continue;
+                        }
+                    }
                     self.handle_milestone_msg(milestone_message).unwrap_or_else(|e| {
                         error!("{}", e);
                     });
@@ -255,6 +268,11 @@ impl Solidifier {
             .milestones_data
             .remove(&milestone_index)
             .expect("Expected milestone data for milestone_index");
+        if let Err(e) = verify_inclusion_merkle_proof(&milestone_data) {
+            self.reject_milestone_data(milestone_index, e);
+            return Ok(());
+        }
+        SOLIDIFICATION_TIME.observe(milestone_data.created_at.elapsed().as_secs_f64());
         let analytic_record = milestone_data.get_analytic_record()?;
         self.insert_analytic(milestone_index, analytic_record)?;
         // Update in_database
@@ -287,6 +305,13 @@ impl Solidifier {
             .milestones_data
             .remove(&milestone_index)
             .expect("Expected milestone data for milestone_index");
+        if let Err(e) = verify_inclusion_merkle_proof(&milestone_data) {
+            self.reject_milestone_data(milestone_index, e);
+            // tell syncer to skip this atm
+            let _ = self.syncer_handle.send(SyncerEvent::Unreachable(milestone_index));
+            return Ok(());
+        }
+        SOLIDIFICATION_TIME.observe(milestone_data.created_at.elapsed().as_secs_f64());
         let analytic_record = milestone_data.get_analytic_record()?;
         self.insert_analytic(milestone_index, analytic_record)?;
         // Update in_database
@@ -303,6 +328,26 @@ impl Solidifier {
         let _ = self.syncer_handle.send(syncer_event);
         Ok(())
     }
+    /// Reject the milestone whose milestone message failed the verification, along with the data collected for it
+    fn reject_milestone(&mut self, milestone_index: u32, error: anyhow::Error) {
+        let milestone_data = self.milestones_data.remove(&milestone_index);
+        self.reject_milestone_data(milestone_index, error);
+        if milestone_data.map_or(false, |milestone_data| milestone_data.created_by == CreatedBy::Syncer) {
+            // tell syncer to skip this atm
+            let _ = self.syncer_handle.send(SyncerEvent::Unreachable(milestone_index));
+        }
+    }
+    fn reject_milestone_data(&mut self, milestone_index: u32, error: anyhow::Error) {
+        // never mark it as synced, neither log it, and do not accept it again atm
+        self.in_database.remove(&milestone_index);
+        self.unreachable.put(milestone_index, ());
+        tokio::spawn(alert!(
+            "solidifier_id: {}, rejected the cone of milestone {}: {}",
+            self.partition_id,
+            milestone_index,
+            error
+        ));
+    }
     fn handle_in_database(&mut self, milestone_index: u32) -> anyhow::Result<()> {
         self.in_database.remove(&milestone_index);
         self.lru_in_database.put(milestone_index, ());
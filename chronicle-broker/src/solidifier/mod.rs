@@ -11,13 +11,20 @@ use super::{
         CollectorHandle,
         MessageIdPartitioner,
     },
+    importer::record_live_insert_error,
+    monitor::count_failed_insert,
     syncer::{
         SyncerEvent,
         SyncerHandle,
     },
+    verification::{
+        verify_inclusion_merkle_proof,
+        verify_milestone_signatures,
+    },
     *,
 };
 use bee_message::prelude::MilestonePayload;
+use chronicle_common::config::MilestoneKeysConfig;
 
 use std::{
     ops::{
@@ -44,7 +51,8 @@ builder!(SolidifierBuilder {
     gap_start: u32,
     retries: u16,
     collector_handles: HashMap<u8, CollectorHandle>,
-    collector_count: u8
+    collector_count: u8,
+    milestone_keys: MilestoneKeysConfig
 });
 
 /// A milestone message payload
@@ -130,14 +138,14 @@ pub enum CqlResult {
 /// SolidifierHandle
 #[derive(Clone)]
 pub struct SolidifierHandle {
-    pub(crate) tx: tokio::sync::mpsc::UnboundedSender<SolidifierEvent>,
+    pub(crate) tx: InboxSender<SolidifierEvent>,
 }
 /// SolidifierInbox is used to recv events
 pub struct SolidifierInbox {
-    pub(crate) rx: tokio::sync::mpsc::UnboundedReceiver<SolidifierEvent>,
+    pub(crate) rx: InboxReceiver<SolidifierEvent>,
 }
 impl Deref for SolidifierHandle {
-    type Target = tokio::sync::mpsc::UnboundedSender<SolidifierEvent>;
+    type Target = InboxSender<SolidifierEvent>;
 
     fn deref(&self) -> &Self::Target {
         &self.tx
@@ -151,7 +159,7 @@ impl DerefMut for SolidifierHandle {
 }
 
 impl Deref for SolidifierInbox {
-    type Target = tokio::sync::mpsc::UnboundedReceiver<SolidifierEvent>;
+    type Target = InboxReceiver<SolidifierEvent>;
 
     fn deref(&self) -> &Self::Target {
         &self.rx
@@ -194,6 +202,7 @@ pub struct Solidifier {
     gap_start: u32,
     expected: u32,
     retries: u16,
+    milestone_keys: Option<MilestoneKeysConfig>,
     handle: SolidifierHandle,
     inbox: SolidifierInbox,
 }
@@ -223,6 +232,7 @@ impl Builder for SolidifierBuilder {
             gap_start: self.gap_start.unwrap(),
             expected: 0,
             retries: self.retries.unwrap_or(100),
+            milestone_keys: self.milestone_keys,
             handle: self.handle.unwrap(),
             inbox: self.inbox.unwrap(),
         }
@@ -255,7 +265,7 @@ impl<H: ChronicleBrokerScope> AknShutdown<Solidifier> for BrokerHandle<H> {
 #[derive(Clone)]
 pub struct AtomicSolidifierWorker<S, K, V>
 where
-    S: 'static + Insert<K, V>,
+    S: 'static + InsertTable<K, V>,
     K: 'static + Send,
     V: 'static + Send,
 {
@@ -289,9 +299,9 @@ impl AtomicSolidifierHandle {
         }
     }
 }
-impl<S: Insert<K, V>, K, V> AtomicSolidifierWorker<S, K, V>
+impl<S: InsertTable<K, V>, K, V> AtomicSolidifierWorker<S, K, V>
 where
-    S: 'static + Insert<K, V>,
+    S: 'static + InsertTable<K, V>,
     K: 'static + Send,
     V: 'static + Send,
 {
@@ -319,7 +329,7 @@ where
 
 impl<S, K, V> Worker for AtomicSolidifierWorker<S, K, V>
 where
-    S: 'static + Insert<K, V>,
+    S: 'static + InsertTable<K, V>,
     K: 'static + Send + Clone,
     V: 'static + Send + Clone,
 {
@@ -336,6 +346,7 @@ where
                 handle_insert_unprepared_error(&self, &self.keyspace, &self.key, &self.value, id, reporter)?;
             }
         }
+        record_live_insert_error();
         if self.retries > 0 {
             self.retries -= 1;
             // currently we assume all cql/worker errors are retryable, but we might change this in future
@@ -355,6 +366,7 @@ where
             }
         } else {
             // no more retries
+            count_failed_insert::<S, K, V>();
             self.handle.any_error.store(true, Ordering::Relaxed);
         }
         Ok(())
@@ -378,7 +390,7 @@ impl Drop for AtomicSolidifierHandle {
 #[derive(Clone)]
 pub struct SyncedMilestoneWorker<S, K, V>
 where
-    S: 'static + Insert<K, V>,
+    S: 'static + InsertTable<K, V>,
     K: 'static + Send,
     V: 'static + Send,
 {
@@ -390,9 +402,9 @@ where
     retries: u16,
 }
 
-impl<S: Insert<K, V>, K, V> SyncedMilestoneWorker<S, K, V>
+impl<S: InsertTable<K, V>, K, V> SyncedMilestoneWorker<S, K, V>
 where
-    S: 'static + Insert<K, V>,
+    S: 'static + InsertTable<K, V>,
     K: 'static + Send,
     V: 'static + Send,
 {
@@ -422,7 +434,7 @@ where
 
 impl<S, K, V> Worker for SyncedMilestoneWorker<S, K, V>
 where
-    S: 'static + Insert<K, V>,
+    S: 'static + InsertTable<K, V>,
     K: 'static + Send + Clone,
     V: 'static + Send + Clone,
 {
@@ -448,6 +460,7 @@ where
                 handle_insert_unprepared_error(&self, &self.keyspace, &self.key, &self.value, id, reporter)?;
             }
         }
+        record_live_insert_error();
         if self.retries > 0 {
             self.retries -= 1;
             // currently we assume all cql/worker errors are retryable, but we might change this in future
@@ -468,6 +481,7 @@ where
             }
         } else {
             // no more retries
+            count_failed_insert::<S, K, V>();
             // respond with error
             let synced_ms = CqlResult::SyncedMilestone(self.milestone_index);
             let _ = self.handle.send(SolidifierEvent::CqlResult(Err(synced_ms)));
@@ -480,7 +494,7 @@ where
 #[derive(Clone)]
 pub struct AnalyzedMilestoneWorker<S, K, V>
 where
-    S: 'static + Insert<K, V>,
+    S: 'static + InsertTable<K, V>,
     K: 'static + Send,
     V: 'static + Send,
 {
@@ -492,9 +506,9 @@ where
     retries: u16,
 }
 
-impl<S: Insert<K, V>, K, V> AnalyzedMilestoneWorker<S, K, V>
+impl<S: InsertTable<K, V>, K, V> AnalyzedMilestoneWorker<S, K, V>
 where
-    S: 'static + Insert<K, V>,
+    S: 'static + InsertTable<K, V>,
     K: 'static + Send,
     V: 'static + Send,
 {
@@ -524,7 +538,7 @@ where
 
 impl<S, K, V> Worker for AnalyzedMilestoneWorker<S, K, V>
 where
-    S: 'static + Insert<K, V>,
+    S: 'static + InsertTable<K, V>,
     K: 'static + Send + Clone,
     V: 'static + Send + Clone,
 {
@@ -550,6 +564,7 @@ where
                 handle_insert_unprepared_error(&self, &self.keyspace, &self.key, &self.value, id, reporter)?;
             }
         }
+        record_live_insert_error();
         if self.retries > 0 {
             self.retries -= 1;
             // currently we assume all cql/worker errors are retryable, but we might change this in future
@@ -570,6 +585,7 @@ where
             }
         } else {
             // no more retries
+            count_failed_insert::<S, K, V>();
             // respond with error
             let analyzed_ms = CqlResult::AnalyzedMilestone(self.milestone_index);
             let _ = self.handle.send(SolidifierEvent::CqlResult(Err(analyzed_ms)));
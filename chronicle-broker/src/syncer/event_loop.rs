@@ -2,7 +2,13 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use super::*;
-use chronicle_common::alert;
+use chronicle_common::{
+    alert,
+    metrics::{
+        LATEST_SYNCED_MILESTONE,
+        SYNC_LAG,
+    },
+};
 
 #[async_trait::async_trait]
 impl<H: ChronicleBrokerScope> EventLoop<BrokerHandle<H>> for Syncer {
@@ -47,7 +53,11 @@ impl<H: ChronicleBrokerScope> EventLoop<BrokerHandle<H>> for Syncer {
                     }
                 }
                 SyncerEvent::MilestoneData(milestone_data) => {
+                    let syncing = self.active.is_some();
                     self.handle_milestone_data(milestone_data).await;
+                    if syncing {
+                        self.report_progress(_supervisor);
+                    }
                 }
                 SyncerEvent::Unreachable(milestone_index) => {
                     self.pending -= 1;
@@ -58,6 +68,22 @@ impl<H: ChronicleBrokerScope> EventLoop<BrokerHandle<H>> for Syncer {
                     ).await.ok();
                     self.handle_skip();
                     self.trigger_process_more();
+                    self.report_progress(_supervisor);
+                }
+                SyncerEvent::Synced(milestone_index) => {
+                    if milestone_index > self.latest_synced {
+                        self.latest_synced = milestone_index;
+                        LATEST_SYNCED_MILESTONE.set(milestone_index as i64);
+                        self.update_sync_lag();
+                    }
+                }
+                SyncerEvent::NetworkTip(network_tip) => {
+                    self.network_tip.replace(network_tip);
+                    self.update_sync_lag();
+                }
+                SyncerEvent::Backfill(backfill) => {
+                    info!("Syncer backfill changed to: {:?}", backfill);
+                    self.backfill = backfill;
                 }
                 SyncerEvent::Shutdown => break,
             }
@@ -67,6 +93,49 @@ impl<H: ChronicleBrokerScope> EventLoop<BrokerHandle<H>> for Syncer {
 }
 
 impl Syncer {
+    /// Count a processed milestone and publish the progress of the sync session over the broker websocket
+    fn report_progress<H: ChronicleBrokerScope>(&mut self, supervisor: &mut Option<BrokerHandle<H>>) {
+        let now = Instant::now();
+        let due = self.progress.count(now);
+        let session = if let Some(active) = self.active.as_ref() {
+            if !due {
+                return;
+            }
+            let (range, include_unlogged) = match active {
+                Active::Complete(range) => (range, true),
+                Active::FillGaps(range) => (range, false),
+            };
+            let (remaining, remaining_gaps) = self.sync_data.remaining(include_unlogged, self.highest);
+            self.progress.session(
+                now,
+                self.initial_gap_start..self.initial_gap_end,
+                remaining + range.len() as u32 + self.pending,
+                remaining_gaps + 1,
+            )
+        } else {
+            // the session processed all the gaps of its sync data
+            let processed = std::mem::take(&mut self.progress).processed;
+            info!("Sync session finished, processed {} milestones", processed);
+            SyncerSession::Finish { processed }
+        };
+        self.progress.reported.replace(now);
+        if let Some(supervisor) = supervisor.as_mut() {
+            supervisor.send(BrokerEvent::Syncer(session)).ok();
+        }
+    }
+    /// Update the number of milestones between the network tip and the latest synced milestone
+    fn update_sync_lag(&mut self) {
+        if let Some(network_tip) = self.network_tip.as_ref() {
+            if self.latest_synced != 0 {
+                let sync_lag = network_tip.milestone_index.saturating_sub(self.latest_synced);
+                if self.sync_lag != Some(sync_lag) {
+                    debug!("Sync lag: {} milestones behind the network tip", sync_lag);
+                }
+                self.sync_lag.replace(sync_lag);
+                SYNC_LAG.set(sync_lag as i64);
+            }
+        }
+    }
     async fn update_sync(&mut self) {
         if self.eof {
             if let Some(sync_range) = self.sync_range.as_ref() {
@@ -291,73 +360,97 @@ impl Syncer {
             self.process_more();
         }
     }
-    pub(crate) fn complete(&mut self) {
-        // start from the lowest uncomplete
-        if let Some(mut gap) = self.sync_data.take_lowest_uncomplete() {
-            // ensure gap.end != i32::MAX
-            if !gap.end.eq(&(i32::MAX as u32)) {
-                info!("Completing the gap {:?}", gap);
-                // set next to be the start
-                self.next = gap.start;
-                self.initial_gap_start = self.next;
-                self.initial_gap_end = gap.end;
-                self.active.replace(Active::Complete(gap));
-                self.trigger_process_more();
+    /// Take the next range to process according to the backfill config, below the highest milestone which got
+    /// observed by the syncer
+    fn take_next_range(&mut self, include_unlogged: bool) -> Option<std::ops::Range<u32>> {
+        // the milestones from the highest one on can't be synced yet, so they stay in the sync data for later
+        let unreachable = self.sync_data.take_gaps_from(self.highest);
+        let range = self.take_reachable_range(include_unlogged, &unreachable);
+        for gap in unreachable {
+            self.sync_data.put_back_gap(gap);
+        }
+        range
+    }
+    fn take_reachable_range(
+        &mut self,
+        include_unlogged: bool,
+        unreachable: &[std::ops::Range<u32>],
+    ) -> Option<std::ops::Range<u32>> {
+        // the priority ranges come first
+        while let Some(priority_range) = self.backfill.priority_ranges.first() {
+            let priority_range = priority_range.from..priority_range.to;
+            if let Some(range) = self.sync_data.take_overlap(&priority_range, include_unlogged) {
+                info!("Prioritizing the range {:?}", range);
+                return Some(range);
+            } else if unreachable
+                .iter()
+                .any(|gap| gap.start < priority_range.end && priority_range.start < gap.end)
+            {
+                // the rest of the priority range waits for the syncer to observe it
+                break;
             } else {
-                // fill this with the gap.start up to self.highest
-                // this is the last gap in our sync data
-                // First we ensure highest is larger than gap.start
-                if self.highest > gap.start {
-                    // set next to be the start
-                    self.next = gap.start;
-                    self.initial_gap_start = self.next;
-                    // update the end of the gap
-                    gap.end = self.highest;
-                    self.initial_gap_end = gap.end;
-                    info!("Completing the last gap {:?}", gap);
-                    self.active.replace(Active::Complete(gap));
-                    self.trigger_process_more();
+                info!("Finished the priority range {:?}", priority_range);
+                self.backfill.priority_ranges.remove(0);
+            }
+        }
+        let newest = match self.backfill.strategy {
+            BackfillStrategy::OldestFirst => false,
+            BackfillStrategy::NewestFirst => true,
+            BackfillStrategy::Interleaved(_) => {
+                self.newest_next = !self.newest_next;
+                !self.newest_next
+            }
+        };
+        // the ranges taken along with the adjacent ones may mix gaps and unlogged ranges
+        let uncomplete = (self.sync_data.gaps.clone(), self.sync_data.synced_but_unlogged.clone());
+        let range = match (newest, include_unlogged) {
+            (false, true) => self.sync_data.take_lowest_uncomplete(),
+            (false, false) => self.sync_data.take_lowest_gap(),
+            (true, true) => self.sync_data.take_highest_uncomplete(),
+            (true, false) => self.sync_data.take_highest_gap(),
+        }?;
+        if let BackfillStrategy::Interleaved(window) = self.backfill.strategy {
+            // only take a window of the range, the rest waits for its turn as it was
+            if range.len() > window as usize {
+                let window_range = if newest {
+                    (range.end - window)..range.end
                 } else {
-                    info!("There are no more gaps neither unlogged in the current sync data");
-                    self.trigger_process_more();
-                }
+                    range.start..(range.start + window)
+                };
+                let (gaps, synced_but_unlogged) = uncomplete;
+                self.sync_data.gaps = gaps;
+                self.sync_data.synced_but_unlogged = synced_but_unlogged;
+                self.sync_data.cut(&window_range, include_unlogged);
+                return Some(window_range);
             }
+        }
+        Some(range)
+    }
+    pub(crate) fn complete(&mut self) {
+        // start from the next uncomplete according to the backfill config
+        if let Some(gap) = self.take_next_range(true) {
+            info!("Completing the gap {:?}", gap);
+            // set next to be the start
+            self.next = gap.start;
+            self.initial_gap_start = self.next;
+            self.initial_gap_end = gap.end;
+            self.active.replace(Active::Complete(gap));
+            self.trigger_process_more();
         } else {
             info!("There are no more gaps neither unlogged in the current sync data");
             self.trigger_process_more();
         }
     }
     pub(crate) fn fill_gaps(&mut self) {
-        // start from the lowest gap
-        if let Some(mut gap) = self.sync_data.take_lowest_gap() {
-            // ensure gap.end != i32::MAX
-            if !gap.end.eq(&(i32::MAX as u32)) {
-                info!("Filling the gap {:?}", gap);
-                // set next to be the start
-                self.next = gap.start;
-                self.initial_gap_start = self.next;
-                self.initial_gap_end = gap.end;
-                self.active.replace(Active::FillGaps(gap));
-                self.trigger_process_more();
-            } else {
-                // fill this with the gap.start up to self.highest
-                // this is the last gap in our sync data
-                // First we ensure highest is larger than gap.start
-                if self.highest > gap.start {
-                    info!("Filling the last gap {:?}", gap);
-                    // set next to be the start
-                    self.next = gap.start;
-                    self.initial_gap_start = self.next;
-                    // update the end of the gap
-                    gap.end = self.highest;
-                    self.initial_gap_end = gap.end;
-                    self.active.replace(Active::FillGaps(gap));
-                    self.trigger_process_more();
-                } else {
-                    info!("There are no more gaps in the current sync data");
-                    self.trigger_process_more();
-                }
-            }
+        // start from the next gap according to the backfill config
+        if let Some(gap) = self.take_next_range(false) {
+            info!("Filling the gap {:?}", gap);
+            // set next to be the start
+            self.next = gap.start;
+            self.initial_gap_start = self.next;
+            self.initial_gap_end = gap.end;
+            self.active.replace(Active::FillGaps(gap));
+            self.trigger_process_more();
         } else {
             info!("There are no more gaps in the current sync data");
             self.trigger_process_more();
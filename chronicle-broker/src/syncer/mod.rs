@@ -11,25 +11,38 @@ use super::{
     },
     *,
 };
-use chronicle_common::Wrapper;
+use chronicle_common::{
+    config::{
+        BackfillConfig,
+        BackfillStrategy,
+    },
+    Wrapper,
+};
 use chronicle_storage::keyspaces::ChronicleKeyspace;
 use std::{
     ops::{
         Deref,
         DerefMut,
     },
-    time::Duration,
+    time::{
+        Duration,
+        Instant,
+    },
 };
 use tokio::sync::oneshot::Sender;
 mod event_loop;
 mod init;
 mod terminating;
 
+/// The minimum interval between two sync progress reports
+const PROGRESS_REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
 // Syncer builder
 builder!(SyncerBuilder {
     sync_data: SyncData,
     update_sync_data_every: Duration,
     sync_range: SyncRange,
+    backfill: BackfillConfig,
     solidifier_handles: HashMap<u8, SolidifierHandle>,
     parallelism: u8,
     archiver_handle: ArchiverHandle,
@@ -47,6 +60,12 @@ pub enum SyncerEvent {
     MilestoneData(MilestoneData),
     /// Notify of an unreachable cluster
     Unreachable(u32),
+    /// Notify of a milestone index which got synced
+    Synced(u32),
+    /// Notify of the latest milestone of the network
+    NetworkTip(NetworkTip),
+    /// Change the backfill strategy and priority ranges, starting with the next gap
+    Backfill(BackfillConfig),
     /// Shutdown the syncer
     Shutdown,
 }
@@ -67,11 +86,11 @@ pub enum AskSyncer {
 /// Syncer handle
 #[derive(Clone)]
 pub struct SyncerHandle {
-    pub(crate) tx: tokio::sync::mpsc::UnboundedSender<SyncerEvent>,
+    pub(crate) tx: InboxSender<SyncerEvent>,
 }
 
 impl Deref for SyncerHandle {
-    type Target = tokio::sync::mpsc::UnboundedSender<SyncerEvent>;
+    type Target = InboxSender<SyncerEvent>;
 
     fn deref(&self) -> &Self::Target {
         &self.tx
@@ -86,11 +105,11 @@ impl DerefMut for SyncerHandle {
 
 /// SyncerInbox is used to recv requests from collector
 pub struct SyncerInbox {
-    pub(crate) rx: tokio::sync::mpsc::UnboundedReceiver<SyncerEvent>,
+    pub(crate) rx: InboxReceiver<SyncerEvent>,
 }
 
 impl Deref for SyncerInbox {
-    type Target = tokio::sync::mpsc::UnboundedReceiver<SyncerEvent>;
+    type Target = InboxReceiver<SyncerEvent>;
 
     fn deref(&self) -> &Self::Target {
         &self.rx
@@ -120,6 +139,8 @@ pub struct Syncer {
     update_sync_data_every: Duration,
     keyspace: ChronicleKeyspace,
     sync_range: Option<SyncRange>,
+    backfill: BackfillConfig,
+    newest_next: bool,
     solidifier_handles: HashMap<u8, SolidifierHandle>,
     solidifier_count: u8,
     parallelism: u8,
@@ -128,6 +149,9 @@ pub struct Syncer {
     archiver_handle: Option<ArchiverHandle>,
     milestones_data: std::collections::BinaryHeap<Ascending<MilestoneData>>,
     highest: u32,
+    latest_synced: u32,
+    network_tip: Option<NetworkTip>,
+    sync_lag: Option<u32>,
     pending: u32,
     eof: bool,
     next: u32,
@@ -135,6 +159,7 @@ pub struct Syncer {
     initial_gap_start: u32,
     initial_gap_end: u32,
     prev_closed_log_filename: u32,
+    progress: Progress,
     oneshot: Option<Sender<u32>>,
     handle: SyncerHandle,
     inbox: SyncerInbox,
@@ -164,6 +189,8 @@ impl Builder for SyncerBuilder {
             solidifier_handles,
             solidifier_count,
             sync_range: self.sync_range,
+            backfill: self.backfill.unwrap_or_default(),
+            newest_next: true,
             keyspace,
             update_sync_data_every: self
                 .update_sync_data_every
@@ -174,6 +201,9 @@ impl Builder for SyncerBuilder {
             archiver_handle: self.archiver_handle,
             milestones_data: std::collections::BinaryHeap::new(),
             highest: 0,
+            latest_synced: 0,
+            network_tip: None,
+            sync_lag: None,
             pending: solidifier_count as u32,
             next: 0,
             eof: false,
@@ -181,6 +211,7 @@ impl Builder for SyncerBuilder {
             initial_gap_start: 0,
             initial_gap_end: 0,
             prev_closed_log_filename: 0,
+            progress: Progress::default(),
             oneshot: self.oneshot,
             handle: self.handle.unwrap(),
             inbox: self.inbox.unwrap(),
@@ -193,6 +224,54 @@ enum Active {
     Complete(std::ops::Range<u32>),
     FillGaps(std::ops::Range<u32>),
 }
+
+/// The progress of the current sync session
+#[derive(Default)]
+struct Progress {
+    started: Option<Instant>,
+    processed: u32,
+    reported: Option<Instant>,
+}
+
+impl Progress {
+    /// Count a processed milestone, and tell whether the progress is due to be reported
+    fn count(&mut self, now: Instant) -> bool {
+        self.started.get_or_insert(now);
+        self.processed += 1;
+        self.reported.map_or(true, |reported| {
+            now.duration_since(reported) >= PROGRESS_REPORT_INTERVAL
+        })
+    }
+    /// The progress of the session, along with its throughput and the estimated time left to process the remaining
+    /// milestones
+    fn session(
+        &self,
+        now: Instant,
+        current_range: std::ops::Range<u32>,
+        remaining_milestones: u32,
+        remaining_gaps: usize,
+    ) -> SyncerSession {
+        let elapsed = self
+            .started
+            .map(|started| now.duration_since(started).as_secs_f64())
+            .unwrap_or_default();
+        let milestones_per_minute = if elapsed > 0.0 {
+            self.processed as f64 * 60.0 / elapsed
+        } else {
+            0.0
+        };
+        let eta_secs =
+            (milestones_per_minute > 0.0).then(|| (remaining_milestones as f64 * 60.0 / milestones_per_minute) as u64);
+        SyncerSession::Progress {
+            current_range,
+            processed: self.processed,
+            remaining_milestones,
+            remaining_gaps,
+            milestones_per_minute,
+            eta_secs,
+        }
+    }
+}
 /// impl name of the Syncer
 impl Name for Syncer {
     fn set_name(mut self) -> Self {
@@ -260,3 +339,58 @@ impl std::cmp::PartialEq for Ascending<MilestoneData> {
     }
 }
 impl std::cmp::Eq for Ascending<MilestoneData> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_reports_once_per_interval() {
+        let mut progress = Progress::default();
+        let started = Instant::now();
+        assert!(progress.count(started));
+        progress.reported.replace(started);
+        assert!(!progress.count(started + Duration::from_secs(1)));
+        assert!(progress.count(started + PROGRESS_REPORT_INTERVAL));
+        assert_eq!(progress.processed, 3);
+    }
+
+    #[test]
+    fn progress_throughput_and_eta() {
+        let mut progress = Progress::default();
+        let started = Instant::now();
+        for _ in 0..10 {
+            progress.count(started);
+        }
+        match progress.session(started + Duration::from_secs(60), 100..200, 20, 2) {
+            SyncerSession::Progress {
+                current_range,
+                processed,
+                remaining_milestones,
+                remaining_gaps,
+                milestones_per_minute,
+                eta_secs,
+            } => {
+                assert_eq!(current_range, 100..200);
+                assert_eq!(processed, 10);
+                assert_eq!(remaining_milestones, 20);
+                assert_eq!(remaining_gaps, 2);
+                assert!((milestones_per_minute - 10.0).abs() < 1e-9);
+                assert_eq!(eta_secs, Some(120));
+            }
+            session => panic!("Unexpected session: {:?}", session),
+        }
+        // without elapsed time the throughput is unknown
+        match progress.session(started, 100..200, 20, 2) {
+            SyncerSession::Progress {
+                milestones_per_minute,
+                eta_secs,
+                ..
+            } => {
+                assert_eq!(milestones_per_minute, 0.0);
+                assert_eq!(eta_secs, None);
+            }
+            session => panic!("Unexpected session: {:?}", session),
+        }
+    }
+}
@@ -28,6 +28,7 @@ use std::{
     collections::HashMap,
     ops::Range,
     path::PathBuf,
+    time::Instant,
 };
 use url::Url;
 
@@ -45,6 +46,10 @@ pub enum ChronicleBrokerThrough {
     Shutdown,
     /// Alter the topology of the broker app
     Topology(BrokerTopology),
+    /// Ask the broker app for the health of its api endpoints
+    EndpointsHealth,
+    /// List or control the import jobs of the broker app
+    ImportJobs(ImportJobCommand),
     /// Exit the broker app
     ExitProgram,
 }
@@ -60,6 +65,10 @@ pub enum BrokerTopology {
     RemoveMqttMessages(Url),
     /// Remove a MQTT Messages Referenced feed source
     RemoveMqttMessagesReferenced(Url),
+    /// Add new MQTT Latest Milestone feed source
+    AddMqttLatestMilestone(Url),
+    /// Remove a MQTT Latest Milestone feed source
+    RemoveMqttLatestMilestone(Url),
     /// Import a log file using the given url
     Import {
         /// File or dir path which supposed to contain LogFiles
@@ -70,13 +79,70 @@ pub enum BrokerTopology {
         import_range: Option<Range<u32>>,
         /// The type of import requested
         import_type: ImportType,
+        /// Run the imported messages through the message filter, and write the matching ones into another keyspace
+        #[serde(default)]
+        filter: Option<ImportFilter>,
     },
     /// Add Endpoint
     Requesters(RequesterTopology),
+    /// Reload the WebAssembly filter module, optionally from a new path
+    ReloadFilter(Option<PathBuf>),
+    /// Replay a feed recording through the collectors
+    Replay {
+        /// Recording file or dir path
+        path: PathBuf,
+        /// The speed factor relative to the recording, None replays as fast as possible
+        speed: Option<f64>,
+    },
+    /// Change the backfill strategy and priority ranges of the syncer
+    #[cfg(feature = "sync")]
+    Backfill(chronicle_common::config::BackfillConfig),
+    /// Change the write rate limits of the importers
+    #[cfg(feature = "chronicle-common")]
+    ImportThrottle(chronicle_common::config::ImportThrottleConfig),
+}
+
+/// The latest milestone announced by the network
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct NetworkTip {
+    /// The latest milestone index
+    pub milestone_index: u32,
+    /// The latest milestone timestamp
+    pub timestamp: u64,
+}
+
+/// The circuit state of an api endpoint
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CircuitState {
+    /// The endpoint receives requests
+    Closed,
+    /// The endpoint failed repeatedly and is skipped until its backoff expires
+    Open,
+    /// The backoff expired and a single trial request decides whether to close the circuit again
+    HalfOpen,
+}
+
+/// The health of an api endpoint, as reported through the broker websocket
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct EndpointHealthReport {
+    /// The api endpoint
+    pub url: Url,
+    /// The health score, from 0 (unusable) to 1 (healthy)
+    pub score: f64,
+    /// Moving average of the latency in milliseconds
+    pub latency_ms: f64,
+    /// Moving average of the rate of failed requests
+    pub error_rate: f64,
+    /// Moving average of the rate of requests for data the endpoint did not have
+    pub not_found_rate: f64,
+    /// The number of consecutive failures
+    pub consecutive_failures: u32,
+    /// The circuit state
+    pub circuit: CircuitState,
 }
 
 /// Import types
-#[derive(Deserialize, Serialize, Debug, Copy, Clone)]
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, Eq)]
 pub enum ImportType {
     /// Import everything
     All,
@@ -84,11 +150,86 @@ pub enum ImportType {
     Analytics,
 }
 
+/// Re-route the imported milestones through the configured message filter.
+/// Only the messages the filter reaches a decision for are written, along with the messages
+/// on their path to the milestone so their inclusion can still be proven
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ImportFilter {
+    /// The keyspace the matching messages and the `sync` rows are written to
+    pub keyspace: String,
+    /// The time-to-live of the written messages, in seconds, the `milestones` and `sync` rows never expire
+    pub ttl: Option<u32>,
+}
+
+/// Commands to list and control the import jobs tracked by the broker
+#[derive(Deserialize, Serialize, Debug, Copy, Clone)]
+pub enum ImportJobCommand {
+    /// List the import jobs along with their progress
+    List,
+    /// Stop the importers of the job, keeping its checkpoints
+    Pause(u64),
+    /// Continue a paused or failed job from its checkpoints
+    Resume(u64),
+    /// Stop the importers of the job and drop its remaining logs
+    Cancel(u64),
+}
+
+/// The state of an import job
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ImportJobState {
+    /// The logs of the job are being imported, or wait for parallelism
+    Running,
+    /// The job got paused, and continues from its checkpoints once resumed
+    Paused,
+    /// All the logs of the job got imported
+    Completed,
+    /// Some logs of the job failed, it can be resumed to retry them
+    Failed,
+    /// The job got cancelled
+    Cancelled,
+}
+
+/// An import job, as reported through the broker websocket
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ImportJobReport {
+    /// The job id
+    pub id: u64,
+    /// The imported file, directory or url
+    pub path: PathBuf,
+    /// The requested range of milestones
+    pub import_range: Option<Range<u32>>,
+    /// The type of import
+    pub import_type: ImportType,
+    /// The filter the imported messages are re-routed through, if any
+    #[serde(default)]
+    pub filter: Option<ImportFilter>,
+    /// The state of the job
+    pub state: ImportJobState,
+    /// The number of log files found so far
+    pub logs: usize,
+    /// The number of log files which got imported
+    pub imported_logs: usize,
+    /// The number of log files which failed
+    pub failed_logs: usize,
+    /// The number of milestones imported so far
+    pub imported_milestones: u64,
+    /// The log bytes imported so far
+    pub imported_bytes: u64,
+}
+
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 /// Requester topology used by admins to add/remove IOTA api endpoints
 pub enum RequesterTopology {
     /// Add new Api Endpoint
     AddEndpoint(Url),
+    /// Add new Api Endpoint with its own connection settings (ie credentials), which replace its configured ones.
+    /// They are only kept by the running requesters, and never written to the config
+    AddAuthenticatedEndpoint {
+        /// The api endpoint
+        url: Url,
+        /// The connection settings of the endpoint
+        auth: chronicle_common::config::ApiConnectionConfig,
+    },
     /// Remove existing Api Endpoint
     RemoveEndpoint(Url),
 }
@@ -101,6 +242,9 @@ pub struct MilestoneData {
     pub(crate) messages: HashMap<MessageId, FullMessage>,
     pub(crate) pending: HashMap<MessageId, ()>,
     pub(crate) created_by: CreatedBy,
+    /// When the solidification of this milestone started
+    #[serde(skip, default = "Instant::now")]
+    pub(crate) created_at: Instant,
 }
 
 impl MilestoneData {
@@ -112,6 +256,7 @@ impl MilestoneData {
             messages: HashMap::new(),
             pending: HashMap::new(),
             created_by,
+            created_at: Instant::now(),
         }
     }
     /// Get the milestone index from this milestone data
@@ -229,6 +374,27 @@ impl From<CreatedBy> for u8 {
     }
 }
 
+/// The write rate of the importers, measured by their shared throttle
+#[derive(Deserialize, Serialize, Debug, Copy, Clone, PartialEq)]
+pub struct ImportRate {
+    /// Moving average of the rows written per second
+    pub rows_per_sec: f64,
+    /// Moving average of the log bytes imported per second
+    pub bytes_per_sec: f64,
+    /// The fraction of the rate limits currently allowed, lowered by the adaptive backoff
+    pub backoff: f64,
+}
+
+impl Default for ImportRate {
+    fn default() -> Self {
+        Self {
+            rows_per_sec: 0.0,
+            bytes_per_sec: 0.0,
+            backoff: 1.0,
+        }
+    }
+}
+
 /// Enum used by importer to keep the sockets up to date with most recent progress.
 #[derive(Deserialize, Serialize, Debug)]
 pub enum ImporterSession {
@@ -246,6 +412,9 @@ pub enum ImporterSession {
         milestone_index: u32,
         /// Identify whether it skipped/resume the milestone_index or imported.
         skipped: bool,
+        /// The effective write rate of the importers
+        #[serde(default)]
+        rate: ImportRate,
     },
     /// Finish the progress bar with message
     Finish {
@@ -256,6 +425,13 @@ pub enum ImporterSession {
         /// Finish the progress bar using this msg.
         msg: String,
     },
+    /// The broker tracks the requested import as the given job
+    Job {
+        /// The job id
+        id: u64,
+        /// The imported file, directory or url
+        path: PathBuf,
+    },
     /// Return error
     PathError {
         /// Invalid dir or file path
@@ -267,6 +443,31 @@ pub enum ImporterSession {
     Close,
 }
 
+/// Enum used by syncer to keep the sockets up to date with its progress.
+#[derive(Deserialize, Serialize, Debug)]
+pub enum SyncerSession {
+    /// The progress of the active sync session
+    Progress {
+        /// The gap range the syncer is working on
+        current_range: Range<u32>,
+        /// Milestones processed since the session started
+        processed: u32,
+        /// Milestones left to process, including the current range
+        remaining_milestones: u32,
+        /// Gap ranges left to process, including the current range
+        remaining_gaps: usize,
+        /// The average throughput of the session
+        milestones_per_minute: f64,
+        /// Estimated seconds until the session completes, if the throughput is known
+        eta_secs: Option<u64>,
+    },
+    /// The syncer processed all the gaps of its sync data
+    Finish {
+        /// Milestones processed by the session
+        processed: u32,
+    },
+}
+
 #[cfg(feature = "sync")]
 pub use sync::*;
 #[cfg(feature = "sync")]
@@ -414,6 +615,156 @@ mod sync {
                 Ok(sync_data)
             }
         }
+        /// Count the milestones and ranges left to process, capping the open ended gap at the highest milestone
+        pub(crate) fn remaining(&self, include_unlogged: bool, highest: u32) -> (u32, usize) {
+            let unlogged = self.synced_but_unlogged.iter().filter(|_| include_unlogged);
+            self.gaps
+                .iter()
+                .chain(unlogged)
+                .fold((0, 0), |(milestones, ranges), range| {
+                    let end = range.end.min(highest.max(range.start));
+                    (milestones + (end - range.start), ranges + 1)
+                })
+        }
+        /// Move the completed milestones of the given range to the synced but unlogged ones, ie once their logs got
+        /// lost, so they get logged again
+        pub(crate) fn mark_unlogged(&mut self, range: &Range<u32>) {
+            let mut completed = Vec::with_capacity(self.completed.len() + 1);
+            for r in self.completed.drain(..) {
+                let (start, end) = (r.start.max(range.start), r.end.min(range.end));
+                if start < end {
+                    // keep the descending order
+                    if end < r.end {
+                        completed.push(end..r.end);
+                    }
+                    if r.start < start {
+                        completed.push(r.start..start);
+                    }
+                    self.synced_but_unlogged.push(start..end);
+                } else {
+                    completed.push(r);
+                }
+            }
+            self.completed = completed;
+            self.synced_but_unlogged.sort_by(|a, b| b.start.cmp(&a.start));
+            self.synced_but_unlogged.dedup_by(|lower, upper| {
+                // merge the adjacent ranges
+                if lower.end == upper.start {
+                    upper.start = lower.start;
+                    true
+                } else {
+                    false
+                }
+            });
+        }
+        /// Takes the highest gap from the sync_data
+        pub fn take_highest_gap(&mut self) -> Option<Range<u32>> {
+            (!self.gaps.is_empty()).then(|| self.gaps.remove(0))
+        }
+        /// Takes the highest unlogged or gap from the sync_data
+        pub fn take_highest_gap_or_unlogged(&mut self) -> Option<Range<u32>> {
+            match (self.gaps.first(), self.synced_but_unlogged.first()) {
+                (Some(gap), Some(unlogged)) => {
+                    if gap.start > unlogged.start {
+                        Some(self.gaps.remove(0))
+                    } else {
+                        Some(self.synced_but_unlogged.remove(0))
+                    }
+                }
+                (Some(_), None) => Some(self.gaps.remove(0)),
+                (None, Some(_)) => Some(self.synced_but_unlogged.remove(0)),
+                _ => None,
+            }
+        }
+        /// Takes the highest uncomplete(mixed range for unlogged and gap) from the sync_data
+        pub fn take_highest_uncomplete(&mut self) -> Option<Range<u32>> {
+            let mut post_range = self.take_highest_gap_or_unlogged()?;
+            while let Some(prev_range) = self.get_highest_gap_or_unlogged() {
+                if prev_range.end.eq(&post_range.start) {
+                    post_range.start = prev_range.start;
+                    let _ = self.take_highest_gap_or_unlogged();
+                } else {
+                    break;
+                }
+            }
+            Some(post_range)
+        }
+        fn get_highest_gap_or_unlogged(&self) -> Option<&Range<u32>> {
+            match (self.gaps.first(), self.synced_but_unlogged.first()) {
+                (Some(gap), Some(unlogged)) => {
+                    if gap.start > unlogged.start {
+                        Some(gap)
+                    } else {
+                        Some(unlogged)
+                    }
+                }
+                (gap, unlogged) => gap.or(unlogged),
+            }
+        }
+        /// Takes the part of the lowest gap (or unlogged range if included) which overlaps with the given range,
+        /// the rest of it stays in the sync_data
+        pub fn take_overlap(&mut self, range: &Range<u32>, include_unlogged: bool) -> Option<Range<u32>> {
+            let candidates = if include_unlogged {
+                vec![&mut self.gaps, &mut self.synced_but_unlogged]
+            } else {
+                vec![&mut self.gaps]
+            };
+            for ranges in candidates {
+                if let Some(i) = ranges.iter().rposition(|r| r.start < range.end && range.start < r.end) {
+                    let r = ranges.remove(i);
+                    let overlap = r.start.max(range.start)..r.end.min(range.end);
+                    // keep the descending order
+                    if r.start < overlap.start {
+                        ranges.insert(i, r.start..overlap.start);
+                    }
+                    if overlap.end < r.end {
+                        ranges.insert(i, overlap.end..r.end);
+                    }
+                    return Some(overlap);
+                }
+            }
+            None
+        }
+        /// Remove the given range from the gaps (and the unlogged ranges if included), keeping the rest of them
+        pub fn cut(&mut self, range: &Range<u32>, include_unlogged: bool) {
+            while self.take_overlap(range, include_unlogged).is_some() {}
+        }
+        /// Take the parts of the gaps from the given milestone index on, ie the ones which can't be synced yet
+        pub fn take_gaps_from(&mut self, milestone_index: u32) -> Vec<Range<u32>> {
+            let mut taken = Vec::new();
+            // the gaps are in descending order
+            while let Some(gap) = self.gaps.first_mut() {
+                if gap.end <= milestone_index {
+                    break;
+                } else if gap.start >= milestone_index {
+                    taken.push(self.gaps.remove(0));
+                } else {
+                    taken.push(milestone_index..gap.end);
+                    gap.end = milestone_index;
+                    break;
+                }
+            }
+            taken
+        }
+        /// Put back a range into the gaps, merging it with the adjacent ones
+        pub fn put_back_gap(&mut self, range: Range<u32>) {
+            let i = self
+                .gaps
+                .iter()
+                .position(|gap| gap.start < range.start)
+                .unwrap_or(self.gaps.len());
+            let merges_lower = self.gaps.get(i).map_or(false, |lower| lower.end == range.start);
+            let merges_upper = i > 0 && self.gaps[i - 1].start == range.end;
+            match (merges_lower, merges_upper) {
+                (true, true) => {
+                    let upper = self.gaps.remove(i - 1);
+                    self.gaps[i - 1].end = upper.end;
+                }
+                (true, false) => self.gaps[i].end = range.end,
+                (false, true) => self.gaps[i - 1].start = range.start,
+                (false, false) => self.gaps.insert(i, range),
+            }
+        }
         /// Takes the lowest gap from the sync_data
         pub fn take_lowest_gap(&mut self) -> Option<Range<u32>> {
             self.gaps.pop()
@@ -686,3 +1037,85 @@ mod analytic {
         }
     }
 }
+
+#[cfg(all(test, feature = "sync"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sync_data_remaining() {
+        let sync_data = SyncData {
+            completed: vec![60..70],
+            synced_but_unlogged: vec![70..75],
+            // the open ended gap above the highest synced milestone comes first
+            gaps: vec![100..i32::MAX as u32, 50..60],
+        };
+        assert_eq!(sync_data.remaining(true, 120), (20 + 10 + 5, 3));
+        assert_eq!(sync_data.remaining(false, 120), (20 + 10, 2));
+        // a highest milestone below the open ended gap leaves nothing to sync in it
+        assert_eq!(sync_data.remaining(false, 90), (10, 2));
+        assert_eq!(SyncData::default().remaining(true, 120), (0, 0));
+    }
+
+    fn sync_data() -> SyncData {
+        SyncData {
+            completed: vec![90..100, 40..50],
+            synced_but_unlogged: vec![80..90, 30..40],
+            gaps: vec![100..i32::MAX as u32, 50..80, 10..30],
+        }
+    }
+
+    #[test]
+    fn take_overlap() {
+        let mut sync_data = sync_data();
+        // the lowest overlapping gap comes first, and the rest of it stays
+        assert_eq!(sync_data.take_overlap(&(20..60), false), Some(20..30));
+        assert_eq!(sync_data.gaps, vec![100..i32::MAX as u32, 50..80, 10..20]);
+        assert_eq!(sync_data.take_overlap(&(20..60), false), Some(50..60));
+        assert_eq!(sync_data.gaps, vec![100..i32::MAX as u32, 60..80, 10..20]);
+        assert_eq!(sync_data.take_overlap(&(20..60), false), None);
+        // the unlogged ranges are only taken if included
+        assert_eq!(sync_data.take_overlap(&(20..60), true), Some(30..40));
+        assert_eq!(sync_data.synced_but_unlogged, vec![80..90]);
+    }
+
+    #[test]
+    fn take_highest_uncomplete() {
+        let mut sync_data = sync_data();
+        assert_eq!(sync_data.take_highest_uncomplete(), Some(100..i32::MAX as u32));
+        // the adjacent gaps and unlogged ranges are merged
+        assert_eq!(sync_data.take_highest_uncomplete(), Some(50..90));
+        assert_eq!(sync_data.take_highest_uncomplete(), Some(10..40));
+        assert_eq!(sync_data.take_highest_uncomplete(), None);
+        assert_eq!(sync_data.completed, vec![90..100, 40..50]);
+    }
+
+    #[test]
+    fn put_back_gap() {
+        let mut sync_data = sync_data();
+        let highest = sync_data.take_highest_gap().unwrap();
+        sync_data.put_back_gap(highest.clone());
+        assert_eq!(sync_data.gaps, sync_data().gaps);
+        // the adjacent gaps are merged back
+        sync_data.cut(&(55..60), false);
+        assert_eq!(sync_data.gaps, vec![100..i32::MAX as u32, 60..80, 50..55, 10..30]);
+        sync_data.put_back_gap(55..60);
+        assert_eq!(sync_data.gaps, sync_data().gaps);
+        sync_data.cut(&(60..80), false);
+        sync_data.put_back_gap(70..80);
+        assert_eq!(sync_data.gaps, vec![100..i32::MAX as u32, 70..80, 50..60, 10..30]);
+        sync_data.put_back_gap(0..5);
+        assert_eq!(sync_data.gaps.last(), Some(&(0..5)));
+    }
+
+    #[test]
+    fn take_gaps_from() {
+        let mut sync_data = sync_data();
+        assert_eq!(sync_data.take_gaps_from(60), vec![100..i32::MAX as u32, 60..80]);
+        assert_eq!(sync_data.gaps, vec![50..60, 10..30]);
+        for gap in vec![100..i32::MAX as u32, 60..80] {
+            sync_data.put_back_gap(gap);
+        }
+        assert_eq!(sync_data.gaps, sync_data().gaps);
+    }
+}
@@ -0,0 +1,369 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use crate::manifest::range_of;
+
+#[async_trait::async_trait]
+impl<H: ChronicleBrokerScope> EventLoop<BrokerHandle<H>> for Uploader {
+    async fn event_loop(
+        &mut self,
+        status: Result<(), Need>,
+        supervisor: &mut Option<BrokerHandle<H>>,
+    ) -> Result<(), Need> {
+        status?;
+        self.service.update_status(ServiceStatus::Running);
+        let event = BrokerEvent::Children(BrokerChild::Uploader(self.service.clone(), Ok(())));
+        let _ = supervisor.as_mut().unwrap().send(event);
+        let mut sweep = tokio::time::interval(RETENTION_SWEEP_INTERVAL);
+        loop {
+            self.upload_backlog().await;
+            tokio::select! {
+                event = self.inbox.rx.recv() => match event {
+                    Some(event) => self.handle_event(event),
+                    None => break,
+                },
+                _ = sweep.tick() => self.sweep().await,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The canonical form of the path, so the held logs match the logs of the directory however they were named
+fn canonical(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+impl Uploader {
+    fn handle_event(&mut self, event: UploaderEvent) {
+        match event {
+            UploaderEvent::Upload(path) => self.backlog.push(path),
+            UploaderEvent::Hold(path) => {
+                self.held.insert(canonical(&path));
+            }
+            UploaderEvent::Release(path) => {
+                self.held.remove(&canonical(&path));
+            }
+        }
+    }
+    /// Handle the events queued while uploading or sleeping between the retries, so the holds are known before a sweep
+    fn drain_events(&mut self) {
+        while let Ok(event) = self.inbox.rx.try_recv() {
+            self.handle_event(event);
+        }
+    }
+    /// Upload the queued logs in order, along with the ones queued meanwhile
+    async fn upload_backlog(&mut self) {
+        while !self.backlog.is_empty() {
+            for path in std::mem::take(&mut self.backlog) {
+                self.upload(path).await;
+            }
+        }
+    }
+    async fn upload(&mut self, path: PathBuf) {
+        let file_name = match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) => name.to_owned(),
+            None => return,
+        };
+        if self.manifest.iter().any(|record| record.file == file_name) {
+            return;
+        }
+        let key = match self.config.prefix.as_str() {
+            "" => file_name.clone(),
+            prefix if prefix.ends_with('/') => format!("{}{}", prefix, file_name),
+            prefix => format!("{}/{}", prefix, file_name),
+        };
+        let mut retries = 0;
+        loop {
+            // ie the log got compressed or merged in the meantime, its new file gets queued on its own
+            if !path.exists() {
+                debug!(
+                    "Skipped the upload of the no longer existing LogFile {}",
+                    path.display()
+                );
+                return;
+            }
+            match self.put(&path, &key).await {
+                Ok(size) => {
+                    let record = UploadRecord {
+                        file: file_name,
+                        key,
+                        size,
+                        uploaded_at: SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .unwrap_or_default()
+                            .as_secs(),
+                    };
+                    info!(
+                        "Uploaded LogFile {} to {}/{}",
+                        path.display(),
+                        self.config.bucket,
+                        record.key
+                    );
+                    if let Err(e) = self.record(&record).await {
+                        error!(
+                            "Unable to record the upload of {} in the manifest: {}",
+                            path.display(),
+                            e
+                        );
+                    }
+                    self.manifest.push(record);
+                    if is_finished_log(&path) {
+                        self.remove_superseded(&path).await;
+                    }
+                    self.sweep().await;
+                    return;
+                }
+                Err(e) if retries < self.config.retries => {
+                    retries += 1;
+                    warn!(
+                        "Unable to upload LogFile {}, retry {}/{}: {}",
+                        path.display(),
+                        retries,
+                        self.config.retries,
+                        e
+                    );
+                    tokio::time::sleep(Duration::from_secs(2u64.pow(retries.min(6) as u32))).await;
+                }
+                Err(e) => {
+                    alert!(
+                        "Unable to upload LogFile {} to the S3 bucket {}: {}",
+                        path.display(),
+                        self.config.bucket,
+                        e
+                    )
+                    .await
+                    .ok();
+                    return;
+                }
+            }
+        }
+    }
+    async fn put(&self, path: &Path, key: &str) -> anyhow::Result<u64> {
+        let mut file = File::open(path).await?;
+        let size = file.metadata().await?.len();
+        let status = self
+            .bucket
+            .put_object_stream(&mut file, key)
+            .await
+            .map_err(|e| anyhow!("{}", e))?;
+        ensure!(status == 200, "Unexpected status code: {}", status);
+        Ok(size)
+    }
+    /// Append the upload record to the manifest
+    async fn record(&self, record: &UploadRecord) -> anyhow::Result<()> {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        let mut manifest = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(self.logs_dir.join(UPLOAD_MANIFEST))
+            .await?;
+        manifest.write_all(line.as_bytes()).await?;
+        manifest.sync_data().await?;
+        Ok(())
+    }
+    /// Rewrite the whole manifest, next to it first so a crash never leaves it half written
+    async fn rewrite_manifest(&self) -> anyhow::Result<()> {
+        let path = self.logs_dir.join(UPLOAD_MANIFEST);
+        let tmp = path.with_extension("manifest.tmp");
+        let mut lines = String::new();
+        for record in self.manifest.iter() {
+            lines.push_str(&serde_json::to_string(record)?);
+            lines.push('\n');
+        }
+        let mut file = File::create(&tmp).await?;
+        file.write_all(lines.as_bytes()).await?;
+        file.sync_all().await?;
+        tokio::fs::rename(&tmp, &path).await?;
+        Ok(())
+    }
+    /// Remove the uploaded objects which the uploaded log supersedes, ie the logs merged into it along with their
+    /// manifests
+    async fn remove_superseded(&mut self, path: &Path) {
+        let (superseded, manifest) = partition_superseded(std::mem::take(&mut self.manifest), path);
+        self.manifest = manifest;
+        if superseded.is_empty() {
+            return;
+        }
+        for record in superseded {
+            match self.bucket.delete_object(&record.key).await {
+                Ok((_, status)) if status == 204 || status == 200 => {
+                    info!("Removed the superseded object {}/{}", self.config.bucket, record.key)
+                }
+                Ok((_, status)) => {
+                    warn!(
+                        "Unable to remove the superseded object {}/{}, status code: {}",
+                        self.config.bucket, record.key, status
+                    );
+                    self.manifest.push(record);
+                }
+                Err(e) => {
+                    warn!(
+                        "Unable to remove the superseded object {}/{}: {}",
+                        self.config.bucket, record.key, e
+                    );
+                    self.manifest.push(record);
+                }
+            }
+        }
+        if let Err(e) = self.rewrite_manifest().await {
+            error!("Unable to rewrite the upload manifest: {}", e);
+        }
+    }
+    /// Remove the uploaded logs which are past their local retention, unless they are held by an import job
+    async fn sweep(&mut self) {
+        self.drain_events();
+        if let Some(retention) = self.config.local_retention_secs {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            for path in expired_logs(&self.manifest, &self.logs_dir, &self.held, retention, now) {
+                match tokio::fs::remove_file(&path).await {
+                    Ok(()) => info!(
+                        "Removed the uploaded LogFile {} past its local retention",
+                        path.display()
+                    ),
+                    Err(e) => warn!("Unable to remove the uploaded LogFile {}: {}", path.display(), e),
+                }
+            }
+        }
+    }
+}
+
+/// Split the upload records into the ones superseded by the uploaded log at the given path, ie the logs merged into it
+/// along with their manifests, and the kept ones
+fn partition_superseded(manifest: Vec<UploadRecord>, path: &Path) -> (Vec<UploadRecord>, Vec<UploadRecord>) {
+    let (from, to) = match range_of(path) {
+        Some(range) => range,
+        None => return (Vec::new(), manifest),
+    };
+    let kept = [
+        path.file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default()
+            .to_owned(),
+        manifest_path(path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default()
+            .to_owned(),
+    ];
+    manifest.into_iter().partition(|record| {
+        let file = Path::new(&record.file);
+        !kept.contains(&record.file)
+            && (is_finished_log(file) || is_manifest(file))
+            && range_of(file).map_or(false, |(start, end)| from <= start && end <= to)
+    })
+}
+
+/// The local uploaded logs which are past their retention at the given time, except the ones held by an import job
+fn expired_logs(
+    manifest: &[UploadRecord],
+    logs_dir: &Path,
+    held: &HashSet<PathBuf>,
+    retention: u64,
+    now: u64,
+) -> Vec<PathBuf> {
+    manifest
+        .iter()
+        .filter(|record| record.uploaded_at + retention <= now)
+        .map(|record| logs_dir.join(&record.file))
+        .filter(|path| path.exists())
+        .filter(|path| {
+            let canonical_path = canonical(path);
+            let is_held = held.iter().any(|p| canonical_path.starts_with(p));
+            if is_held {
+                debug!("Kept the uploaded LogFile {} held by an import job", path.display());
+            }
+            !is_held
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(file: &str, uploaded_at: u64) -> UploadRecord {
+        UploadRecord {
+            file: file.to_owned(),
+            key: format!("logs/{}", file),
+            size: 0,
+            uploaded_at,
+        }
+    }
+
+    fn files(records: &[UploadRecord]) -> Vec<&str> {
+        records.iter().map(|record| record.file.as_str()).collect()
+    }
+
+    #[test]
+    fn merged_logs_are_superseded() {
+        let manifest = [
+            "0to10.log.zst",
+            "0to10.manifest",
+            "10to20.log",
+            "0to20.log.zst",
+            "0to20.manifest",
+            "15to25.log.zst",
+            "20to30.log.zst",
+            UPLOAD_MANIFEST,
+        ]
+        .iter()
+        .map(|file| record(file, 0))
+        .collect::<Vec<_>>();
+        let (superseded, kept) = partition_superseded(manifest.clone(), Path::new("/logs/0to20.log.zst"));
+        assert_eq!(files(&superseded), ["0to10.log.zst", "0to10.manifest", "10to20.log"]);
+        // the merged log itself, its manifest, and the logs reaching past it
+        assert_eq!(
+            files(&kept),
+            [
+                "0to20.log.zst",
+                "0to20.manifest",
+                "15to25.log.zst",
+                "20to30.log.zst",
+                UPLOAD_MANIFEST
+            ]
+        );
+        // nothing is superseded by a file without a range
+        let (superseded, kept) = partition_superseded(manifest.clone(), Path::new("/logs/merged.log"));
+        assert!(superseded.is_empty());
+        assert_eq!(kept.len(), manifest.len());
+    }
+
+    #[test]
+    fn sweep_keeps_the_recent_and_held_logs() {
+        let dir = std::env::temp_dir().join(format!("chronicle-uploader-sweep-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("held")).unwrap();
+        for file in ["0to10.log", "10to20.log", "20to30.log", "held/30to40.log"].iter() {
+            std::fs::write(dir.join(file), b"").unwrap();
+        }
+        let manifest = [
+            record("0to10.log", 0),
+            record("10to20.log", 0),
+            record("20to30.log", 90),
+            record("held/30to40.log", 0),
+            // removed already
+            record("40to50.log", 0),
+        ];
+        let mut held = HashSet::new();
+        // a held log, and a held directory
+        held.insert(canonical(&dir.join("10to20.log")));
+        held.insert(canonical(&dir.join("held")));
+        assert_eq!(
+            expired_logs(&manifest, &dir, &held, 100, 150),
+            vec![dir.join("0to10.log")]
+        );
+        assert_eq!(
+            expired_logs(&manifest, &dir, &held, 100, 190),
+            vec![dir.join("0to10.log"), dir.join("20to30.log")]
+        );
+        // once released
+        held.clear();
+        assert_eq!(expired_logs(&manifest, &dir, &held, 100, 150).len(), 3);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,68 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+
+#[async_trait::async_trait]
+impl<H: ChronicleBrokerScope> Init<BrokerHandle<H>> for Uploader {
+    async fn init(&mut self, status: Result<(), Need>, supervisor: &mut Option<BrokerHandle<H>>) -> Result<(), Need> {
+        self.service.update_status(ServiceStatus::Initializing);
+        let event = BrokerEvent::Children(BrokerChild::Uploader(self.service.clone(), Ok(())));
+        let _ = supervisor.as_mut().expect("Uploader expected BrokerHandle").send(event);
+        if let Err(e) = self.queue_backlog().await {
+            error!("Unable to read the uploaded logs of {}: {}", self.logs_dir.display(), e);
+            return Err(Need::Abort);
+        }
+        info!(
+            "Uploading the finished logs to the S3 bucket {}{}",
+            self.config.bucket,
+            self.config
+                .endpoint
+                .as_ref()
+                .map(|endpoint| format!(" at {}", endpoint))
+                .unwrap_or_default()
+        );
+        status
+    }
+}
+
+impl Uploader {
+    /// Read the manifest and queue the finished logs which are missing from it, ie the ones finished before a restart
+    /// or whose upload failed
+    async fn queue_backlog(&mut self) -> anyhow::Result<()> {
+        tokio::fs::create_dir_all(&self.logs_dir).await?;
+        self.manifest = Self::read_manifest(&self.logs_dir).await?;
+        let uploaded = self
+            .manifest
+            .iter()
+            .map(|record| record.file.as_str())
+            .collect::<HashSet<_>>();
+        let mut dir = tokio::fs::read_dir(&self.logs_dir).await?;
+        while let Some(entry) = dir.next_entry().await? {
+            let path = entry.path();
+            let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+            if (is_finished_log(&path) || is_manifest(&path)) && !uploaded.contains(name) {
+                self.backlog.push(path);
+            }
+        }
+        Ok(())
+    }
+    async fn read_manifest(logs_dir: &Path) -> anyhow::Result<Vec<UploadRecord>> {
+        let manifest = match tokio::fs::read_to_string(logs_dir.join(UPLOAD_MANIFEST)).await {
+            Ok(manifest) => manifest,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => bail!(e),
+        };
+        Ok(manifest
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(record) => Some(record),
+                Err(e) => {
+                    warn!("Skipped malformed upload manifest line: {}, error: {}", line, e);
+                    None
+                }
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,355 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! Ships the finished archive logs to S3-compatible object storage, and fetches them back for imports.
+
+use super::*;
+use crate::{
+    application::{
+        BrokerChild,
+        BrokerEvent,
+        BrokerHandle,
+        ChronicleBrokerScope,
+    },
+    archiver::is_finished_log,
+    manifest::{
+        is_manifest,
+        manifest_path,
+        MANIFEST_EXTENSION,
+    },
+};
+use chronicle_common::config::ArchiveUploadConfig;
+use s3::{
+    bucket::Bucket,
+    creds::Credentials,
+    region::Region,
+};
+use std::{
+    collections::HashSet,
+    ops::{
+        Deref,
+        DerefMut,
+    },
+    path::Path,
+    time::{
+        Duration,
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+use tokio::{
+    fs::{
+        File,
+        OpenOptions,
+    },
+    io::AsyncWriteExt,
+    sync::mpsc::{
+        UnboundedReceiver,
+        UnboundedSender,
+    },
+};
+
+mod event_loop;
+mod init;
+mod terminating;
+
+/// The file of the logs directory which records the uploaded logs
+pub const UPLOAD_MANIFEST: &str = "uploads.manifest";
+/// The interval between the sweeps of the uploaded logs past their local retention
+const RETENTION_SWEEP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// An uploaded log, as recorded by a line of the upload manifest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadRecord {
+    /// The file name of the log
+    pub file: String,
+    /// The object key of the log
+    pub key: String,
+    /// The size of the log in bytes
+    pub size: u64,
+    /// When the log got uploaded, in seconds since the unix epoch
+    pub uploaded_at: u64,
+}
+
+/// Build the client of the given bucket, with the region and credentials of the upload config
+pub fn bucket(config: &ArchiveUploadConfig, name: &str) -> anyhow::Result<Bucket> {
+    let credentials = match (config.access_key.as_deref(), config.secret_key.as_deref()) {
+        (Some(access_key), Some(secret_key)) => Credentials::new(Some(access_key), Some(secret_key), None, None, None),
+        _ => Credentials::default(),
+    }
+    .map_err(|e| anyhow!("Invalid S3 credentials: {}", e))?;
+    let bucket = match config.endpoint.as_ref() {
+        Some(endpoint) => {
+            let region = Region::Custom {
+                region: config.region.clone(),
+                endpoint: endpoint.clone(),
+            };
+            Bucket::new_with_path_style(name, region, credentials)
+        }
+        None => {
+            let region = config
+                .region
+                .parse::<Region>()
+                .map_err(|e| anyhow!("Invalid S3 region {}: {}", config.region, e))?;
+            Bucket::new(name, region, credentials)
+        }
+    }
+    .map_err(|e| anyhow!("Unable to create the S3 bucket client for {}: {}", name, e))?;
+    Ok(bucket)
+}
+
+/// Download the log, or the logs under the prefix, of the given `s3://<bucket>/<key>` url into the directory.
+/// The logs already downloaded with the same size are not downloaded again.
+/// Returns the path to import, which is the downloaded log or the directory of the downloaded logs
+pub async fn download(url: &Url, config: &ArchiveUploadConfig, dir: &Path) -> anyhow::Result<PathBuf> {
+    ensure!(
+        url.scheme() == "s3",
+        "Unsupported source {}, expected s3://<bucket>/<key>",
+        url
+    );
+    let bucket_name = url.host_str().ok_or_else(|| anyhow!("No bucket in {}", url))?;
+    let bucket = bucket(config, bucket_name)?;
+    let key = url.path().trim_start_matches('/');
+    let selection = DownloadSelection::new(key);
+    let objects = bucket
+        .list(selection.prefix().to_owned(), None)
+        .await
+        .map_err(|e| anyhow!("Unable to list {}: {}", url, e))?
+        .into_iter()
+        .flat_map(|result| result.contents)
+        .filter(|object| selection.contains(&object.key))
+        .collect::<Vec<_>>();
+    ensure!(
+        objects.iter().any(|object| is_finished_log(Path::new(&object.key))),
+        "No logs found at {}",
+        url
+    );
+    let dest_dir = dir.join(bucket_name).join(if key.is_empty() {
+        "root".to_owned()
+    } else {
+        key.replace('/', "_")
+    });
+    tokio::fs::create_dir_all(&dest_dir).await?;
+    for object in objects.iter() {
+        let file_name = Path::new(&object.key)
+            .file_name()
+            .ok_or_else(|| anyhow!("Invalid object key {}", object.key))?;
+        let dest = dest_dir.join(file_name);
+        if let Ok(metadata) = tokio::fs::metadata(&dest).await {
+            if metadata.len() == object.size {
+                continue;
+            }
+        }
+        // download next to the destination, so a partial download is never imported
+        let partial = dest.with_extension("download");
+        let mut file = File::create(&partial).await?;
+        let status = bucket
+            .get_object_stream(&object.key, &mut file)
+            .await
+            .map_err(|e| anyhow!("Unable to download {}: {}", object.key, e))?;
+        ensure!(
+            status == 200,
+            "Unable to download {}, status code: {}",
+            object.key,
+            status
+        );
+        file.sync_all().await?;
+        tokio::fs::rename(&partial, &dest).await?;
+        info!("Downloaded {} from {}", object.key, bucket_name);
+    }
+    if selection.single_log {
+        Ok(dest_dir.join(Path::new(key).file_name().unwrap_or_default()))
+    } else {
+        Ok(dest_dir)
+    }
+}
+
+/// The objects of a download, either a single log along with its manifest, or the logs under a prefix along with
+/// their manifests, so the downloaded logs get verified when imported
+struct DownloadSelection<'a> {
+    key: &'a str,
+    manifest_key: String,
+    single_log: bool,
+}
+
+impl<'a> DownloadSelection<'a> {
+    fn new(key: &'a str) -> Self {
+        Self {
+            key,
+            manifest_key: manifest_path(Path::new(key)).to_string_lossy().into_owned(),
+            single_log: is_finished_log(Path::new(key)),
+        }
+    }
+    /// The prefix to list, a single log is listed along with its manifest, ie dir/0to10 for dir/0to10.log.zst and
+    /// dir/0to10.manifest
+    fn prefix(&self) -> &str {
+        if self.single_log {
+            self.manifest_key
+                .trim_end_matches(MANIFEST_EXTENSION)
+                .trim_end_matches('.')
+        } else {
+            self.key
+        }
+    }
+    /// Whether the listed object gets downloaded
+    fn contains(&self, object_key: &str) -> bool {
+        if self.single_log {
+            object_key == self.key || object_key == self.manifest_key
+        } else {
+            is_finished_log(Path::new(object_key)) || is_manifest(Path::new(object_key))
+        }
+    }
+}
+
+// Uploader builder
+builder!(UploaderBuilder {
+    config: ArchiveUploadConfig,
+    bucket: Bucket,
+    logs_dir: PathBuf
+});
+
+/// Uploader events
+pub enum UploaderEvent {
+    /// Upload the finished log, or log manifest, at the given path
+    Upload(PathBuf),
+    /// Keep the log at the given path past its local retention, as long as it gets imported
+    Hold(PathBuf),
+    /// Release the log at the given path once it got imported
+    Release(PathBuf),
+}
+
+/// UploaderHandle to queue the finished logs for upload
+#[derive(Clone)]
+pub struct UploaderHandle {
+    pub(crate) tx: UnboundedSender<UploaderEvent>,
+}
+
+impl UploaderHandle {
+    /// Queue the finished log, or log manifest, at the given path for upload
+    pub fn upload(&self, path: PathBuf) {
+        self.tx.send(UploaderEvent::Upload(path)).ok();
+    }
+}
+
+impl Deref for UploaderHandle {
+    type Target = UnboundedSender<UploaderEvent>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.tx
+    }
+}
+
+impl DerefMut for UploaderHandle {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.tx
+    }
+}
+
+/// The uploader stops once all the handles are dropped and its queue is drained
+impl Shutdown for UploaderHandle {
+    fn shutdown(self) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        None
+    }
+}
+
+/// UploaderInbox is used to recv the logs to upload
+pub struct UploaderInbox {
+    pub(crate) rx: UnboundedReceiver<UploaderEvent>,
+}
+
+/// The uploader, which uploads the queued logs one at a time
+pub struct Uploader {
+    service: Service,
+    config: ArchiveUploadConfig,
+    bucket: Bucket,
+    logs_dir: PathBuf,
+    manifest: Vec<UploadRecord>,
+    /// The finished logs which are missing from the manifest, queued at startup, or queued while uploading
+    backlog: Vec<PathBuf>,
+    /// The logs held by the import jobs, which are kept past their local retention
+    held: HashSet<PathBuf>,
+    handle: Option<UploaderHandle>,
+    inbox: UploaderInbox,
+}
+
+impl Uploader {
+    /// Take the held uploader handle, leaving None in its place
+    pub fn take_handle(&mut self) -> Option<UploaderHandle> {
+        self.handle.take()
+    }
+}
+
+impl<H: ChronicleBrokerScope> ActorBuilder<BrokerHandle<H>> for UploaderBuilder {}
+
+/// implementation of builder
+impl Builder for UploaderBuilder {
+    type State = Uploader;
+    fn build(self) -> Self::State {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        Self::State {
+            service: Service::new(),
+            config: self.config.expect("Expected archive upload config"),
+            bucket: self.bucket.expect("Expected bucket"),
+            logs_dir: self.logs_dir.expect("Expected logs directory"),
+            manifest: Vec::new(),
+            backlog: Vec::new(),
+            held: HashSet::new(),
+            handle: Some(UploaderHandle { tx }),
+            inbox: UploaderInbox { rx },
+        }
+        .set_name()
+    }
+}
+
+/// impl name of the Uploader
+impl Name for Uploader {
+    fn set_name(mut self) -> Self {
+        self.service.update_name("Uploader".to_string());
+        self
+    }
+    fn get_name(&self) -> String {
+        self.service.get_name()
+    }
+}
+
+#[async_trait::async_trait]
+impl<H: ChronicleBrokerScope> AknShutdown<Uploader> for BrokerHandle<H> {
+    async fn aknowledge_shutdown(self, mut _state: Uploader, status: Result<(), Need>) {
+        _state.service.update_status(ServiceStatus::Stopped);
+        let event = BrokerEvent::Children(BrokerChild::Uploader(_state.service.clone(), status));
+        let _ = self.send(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn download_selects_the_log_along_with_its_manifest() {
+        let selection = DownloadSelection::new("dir/0to10.log.zst");
+        assert!(selection.single_log);
+        assert_eq!(selection.prefix(), "dir/0to10");
+        assert!(selection.contains("dir/0to10.log.zst"));
+        assert!(selection.contains("dir/0to10.manifest"));
+        // the other logs sharing the listed prefix
+        assert!(!selection.contains("dir/0to100.log.zst"));
+        assert!(!selection.contains("dir/0to100.manifest"));
+    }
+
+    #[test]
+    fn download_selects_the_logs_under_the_prefix() {
+        let selection = DownloadSelection::new("dir/");
+        assert!(!selection.single_log);
+        assert_eq!(selection.prefix(), "dir/");
+        assert!(selection.contains("dir/0to10.log"));
+        assert!(selection.contains("dir/sub/10to20.log.zst"));
+        assert!(selection.contains("dir/10to20.manifest"));
+        assert!(!selection.contains("dir/20.part"));
+        assert!(!selection.contains(&format!("dir/{}", UPLOAD_MANIFEST)));
+        assert_eq!(DownloadSelection::new("").prefix(), "");
+    }
+}
@@ -0,0 +1,268 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use bee_message::prelude::MilestonePayload;
+use chronicle_common::{
+    config::MilestoneKeysConfig,
+    metrics::REJECTED_MESSAGES,
+};
+use crypto::hashes::{
+    blake2b::Blake2b256,
+    Digest,
+};
+use std::collections::HashSet;
+
+/// Every how many rejected messages from the same source an alert is raised
+const REJECTION_ALERT_INTERVAL: u64 = 10;
+/// The prefix of the merkle tree leaf hashes
+const LEAF_HASH_PREFIX: u8 = 0;
+/// The prefix of the merkle tree node hashes
+const NODE_HASH_PREFIX: u8 = 1;
+
+/// Verify that the milestone essence is signed by at least `public_key_count` of the coordinator keys
+/// applicable to its index
+pub fn verify_milestone_signatures(
+    milestone_payload: &MilestonePayload,
+    milestone_keys: &MilestoneKeysConfig,
+) -> anyhow::Result<()> {
+    let milestone_index = milestone_payload.essence().index().0;
+    let applicable_public_keys = milestone_keys.applicable_public_keys(milestone_index);
+    ensure!(
+        applicable_public_keys.len() >= milestone_keys.public_key_count,
+        "Only {} public keys are applicable to milestone {}, while {} are required",
+        applicable_public_keys.len(),
+        milestone_index,
+        milestone_keys.public_key_count
+    );
+    milestone_payload
+        .validate(&applicable_public_keys, milestone_keys.public_key_count)
+        .map_err(|e| anyhow!("Invalid milestone {}: {:?}", milestone_index, e))
+}
+
+/// Verify that the messages of a completed milestone cone hash to the inclusion merkle proof of its milestone
+pub fn verify_inclusion_merkle_proof(milestone_data: &MilestoneData) -> anyhow::Result<()> {
+    let milestone_index = milestone_data.milestone_index();
+    let milestone_payload = milestone_data
+        .milestone
+        .as_ref()
+        .ok_or_else(|| anyhow!("No milestone payload in milestone data {}", milestone_index))?;
+    let included_messages = white_flag_ordered_included_messages(milestone_data, milestone_payload.essence().parents());
+    let merkle_root = merkle_root(&included_messages);
+    ensure!(
+        merkle_root.as_slice() == milestone_payload.essence().merkle_proof(),
+        "The inclusion merkle root {} of milestone {} does not match its proof {}",
+        hex::encode(&merkle_root),
+        milestone_index,
+        hex::encode(milestone_payload.essence().merkle_proof())
+    );
+    Ok(())
+}
+
+/// Collect the ids of the messages of the milestone cone whose transactions were included in the ledger,
+/// in white-flag order
+fn white_flag_ordered_included_messages(
+    milestone_data: &MilestoneData,
+    milestone_parents: &[MessageId],
+) -> Vec<MessageId> {
+    let milestone_index = milestone_data.milestone_index();
+    // messages outside of the cone were confirmed by previous milestones
+    white_flag_order(milestone_parents, |message_id| {
+        milestone_data
+            .messages()
+            .get(message_id)
+            .filter(|full_message| full_message.ref_ms() == Some(milestone_index))
+            .map(|full_message| {
+                (
+                    &full_message.message().parents()[..],
+                    matches!(
+                        full_message.metadata().ledger_inclusion_state,
+                        Some(LedgerInclusionState::Included)
+                    ),
+                )
+            })
+    })
+}
+
+/// Traverse the cone in post-order, visiting the parents in the order they are referenced,
+/// and collect the ids of the included messages. `cone` returns the parents of a message of the cone
+/// along with whether it is included, or `None` if the message is outside of the cone
+fn white_flag_order<'a>(
+    milestone_parents: &[MessageId],
+    cone: impl Fn(&MessageId) -> Option<(&'a [MessageId], bool)>,
+) -> Vec<MessageId> {
+    let mut visited = HashSet::new();
+    let mut included_messages = Vec::new();
+    for milestone_parent in milestone_parents {
+        let mut stack = vec![*milestone_parent];
+        while let Some(message_id) = stack.last().copied() {
+            let (parents, included) = match cone(&message_id) {
+                Some(message) if !visited.contains(&message_id) => message,
+                _ => {
+                    stack.pop();
+                    continue;
+                }
+            };
+            let unvisited_parent = parents
+                .iter()
+                .find(|parent| !visited.contains(*parent) && cone(parent).is_some());
+            if let Some(parent) = unvisited_parent {
+                stack.push(*parent);
+                continue;
+            }
+            if included {
+                included_messages.push(message_id);
+            }
+            visited.insert(message_id);
+            stack.pop();
+        }
+    }
+    included_messages
+}
+
+/// Compute the merkle tree root of the message ids, as specified by the white-flag RFC
+fn merkle_root(message_ids: &[MessageId]) -> Vec<u8> {
+    let mut hasher = Blake2b256::new();
+    match message_ids {
+        [] => (),
+        [message_id] => {
+            hasher.update([LEAF_HASH_PREFIX]);
+            hasher.update(message_id);
+        }
+        _ => {
+            // split at the largest power of two smaller than the number of ids
+            let split = 1 << (usize::BITS - (message_ids.len() - 1).leading_zeros() - 1);
+            hasher.update([NODE_HASH_PREFIX]);
+            hasher.update(merkle_root(&message_ids[..split]));
+            hasher.update(merkle_root(&message_ids[split..]));
+        }
+    }
+    hasher.finalize().to_vec()
+}
+
+/// Verify that a message hashes to the expected id, and return it.
+/// Note: the message itself is syntactically validated when it is unpacked or converted from its dto.
+pub fn verify_message(message: &Message, expected_message_id: &MessageId) -> anyhow::Result<MessageId> {
+    let (message_id, _) = message.id();
+    ensure!(
+        &message_id == expected_message_id,
+        "Expected message {}, got message {}",
+        expected_message_id,
+        message_id
+    );
+    Ok(message_id)
+}
+
+/// Verify that the metadata belongs to the message with the given id
+pub fn verify_metadata(message_id: &MessageId, message: &Message, metadata: &MessageMetadata) -> anyhow::Result<()> {
+    ensure!(
+        &metadata.message_id == message_id,
+        "Expected metadata of message {}, got metadata of message {}",
+        message_id,
+        metadata.message_id
+    );
+    ensure!(
+        metadata.parent_message_ids.as_slice() == &message.parents()[..],
+        "The parents of message {} disagree with its metadata",
+        message_id
+    );
+    Ok(())
+}
+
+/// Verify that both the message and its metadata match the expected message id
+pub fn verify_full_message(full_message: &FullMessage, expected_message_id: &MessageId) -> anyhow::Result<()> {
+    let message_id = verify_message(full_message.message(), expected_message_id)?;
+    verify_metadata(&message_id, full_message.message(), full_message.metadata())
+}
+
+/// Count a message rejected from the given source by the kind of the source, ie `mqtt`, `poller` or `collector`, so
+/// the metric labels stay bounded whatever the sources are, and alert if the sources keep sending bad data
+pub fn reject_message(source_kind: &'static str, source: &str, error: &anyhow::Error) {
+    warn!("Rejected message from {}: {}", source, error);
+    let rejected = REJECTED_MESSAGES.with_label_values(&[source_kind]);
+    rejected.inc();
+    if rejected.get() % REJECTION_ALERT_INTERVAL == 0 {
+        tokio::spawn(alert!(
+            "Rejected {} messages from the {} sources so far, last one from {}: {}",
+            rejected.get(),
+            source_kind,
+            source,
+            error
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn message_id(hex: &str) -> MessageId {
+        MessageId::from_str(hex).unwrap()
+    }
+
+    fn message_ids(hex: &[&str]) -> Vec<MessageId> {
+        hex.iter().map(|hex| message_id(hex)).collect()
+    }
+
+    #[test]
+    fn merkle_root_of_no_message() {
+        // the blake2b-256 hash of an empty input
+        assert_eq!(
+            hex::encode(merkle_root(&[])),
+            "0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8"
+        );
+    }
+
+    #[test]
+    fn merkle_root_of_a_single_message() {
+        let message_ids = message_ids(&["52fdfc072182654f163f5f0f9a621d729566c74d10037c4d7bbb0407d1e2c649"]);
+        assert_eq!(
+            hex::encode(merkle_root(&message_ids)),
+            "3d1399c64ff0ae6a074afa4cd2ce4eab8d5c499c1da6afdd1d84b7447cc00544"
+        );
+    }
+
+    #[test]
+    fn merkle_root_of_the_rfc_example() {
+        // the 7 message ids of the white-flag RFC example, which is not a power of two
+        let message_ids = message_ids(&[
+            "52fdfc072182654f163f5f0f9a621d729566c74d10037c4d7bbb0407d1e2c649",
+            "81855ad8681d0d86d1e91e00167939cb6694d2c422acd208a0072939487f6999",
+            "eb9d18a44784045d87f3c67cf22746e995af5a25367951baa2ff6cd471c483f1",
+            "5fb90badb37c5821b6d95526a41a9504680b4e7c8b763a1b1d49d4955c848621",
+            "6325253fec738dd7a9e28bf921119c160f0702448615bbda08313f6a8eb668d2",
+            "0bf5059875921e668a5bdf2c7fc4844592d2572bcd0668d2d6c52f5054e2d083",
+            "6bf84c7174cb7476364cc3dbd968b0f7172ed85794bb358b0c3b525da1786f9f",
+        ]);
+        assert_eq!(
+            hex::encode(merkle_root(&message_ids)),
+            "bf67ce7ba23e8c0951b5abaec4f5524360d2c26d971ff226d3359fa70cdb0beb"
+        );
+        assert_eq!(
+            hex::encode(merkle_root(&message_ids[..3])),
+            "0533872a888af35b5f18d1e616445084f5a6a0bf3de52ccb1b67ffee830099b7"
+        );
+    }
+
+    #[test]
+    fn white_flag_ordering() {
+        let id = |n: u8| MessageId::new([n; 32]);
+        let (a, b, c, d, e, outside) = (id(1), id(2), id(3), id(4), id(5), id(6));
+        // the milestone references a and b, and c and d approve a message confirmed by a previous milestone
+        let mut cone = HashMap::new();
+        cone.insert(a, (vec![c, d], false));
+        cone.insert(b, (vec![d, e], true));
+        cone.insert(c, (vec![outside], true));
+        cone.insert(d, (vec![outside], true));
+        cone.insert(e, (vec![c], true));
+        let lookup = |message_id: &MessageId| {
+            cone.get(message_id)
+                .map(|(parents, included)| (parents.as_slice(), *included))
+        };
+        // the parents come first, in the order they are referenced, and the conflicting a is left out
+        assert_eq!(white_flag_order(&[a, b], lookup), vec![c, d, e, b]);
+        assert_eq!(white_flag_order(&[b, a], lookup), vec![d, c, e, b]);
+        assert!(white_flag_order(&[outside], lookup).is_empty());
+    }
+}
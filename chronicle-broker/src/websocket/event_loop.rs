@@ -23,6 +23,30 @@ impl<H: ChronicleBrokerScope> EventLoop<BrokerHandle<H>> for Websocket {
                     let event = BrokerEvent::Passthrough(apps_events);
                     supervisor.as_mut().unwrap().send(event).map_err(|_| Need::Abort)?;
                 }
+                Message::Binary(bytes) => match std::mem::replace(&mut self.stream, StreamState::Done) {
+                    StreamState::Idle => {
+                        let id = String::from_utf8(bytes).map_err(|_| Need::Abort)?;
+                        let (writer, reader) = RemoteLogReader::channel();
+                        self.stream = StreamState::Streaming(writer);
+                        let event = BrokerEvent::StreamedLog(id, reader);
+                        supervisor.as_mut().unwrap().send(event).map_err(|_| Need::Abort)?;
+                    }
+                    StreamState::Streaming(writer) if bytes.is_empty() => {
+                        if let Err(e) = writer.finish().await {
+                            warn!(
+                                "Websocket: {}, unable to finish the streamed log: {}",
+                                self.get_name(),
+                                e
+                            );
+                        }
+                    }
+                    StreamState::Streaming(mut writer) => match writer.write(&bytes).await {
+                        Ok(()) => self.stream = StreamState::Streaming(writer),
+                        // the importer of the log stopped, the remaining frames are dropped
+                        Err(e) => warn!("Websocket: {}, stopped streaming the log: {}", self.get_name(), e),
+                    },
+                    StreamState::Done => (),
+                },
                 Message::Close(_) => {
                     self.service.update_status(ServiceStatus::Stopping);
                     let event = BrokerEvent::Children(BrokerChild::Websocket(self.service.clone(), None));
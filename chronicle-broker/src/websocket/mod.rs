@@ -2,6 +2,10 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use super::*;
+use crate::remote::{
+    RemoteLogReader,
+    StreamedLogWriter,
+};
 use futures::{
     stream::{
         SplitSink,
@@ -36,6 +40,19 @@ pub struct Websocket {
     peer: SocketAddr,
     ws_rx: WsRx,
     opt_ws_tx: Option<WsTx>,
+    /// The log the client streams in binary frames, ie from its stdin: the first frame holds its id, the following
+    /// ones its content, up to an empty frame
+    stream: StreamState,
+}
+
+/// The state of the log streamed by the client
+enum StreamState {
+    /// Waiting for the frame holding the id of the log
+    Idle,
+    /// Writing the content of the log
+    Streaming(StreamedLogWriter),
+    /// Done, or the importer stopped reading it
+    Done,
 }
 
 impl<H: ChronicleBrokerScope> ActorBuilder<BrokerHandle<H>> for WebsocketdBuilder {}
@@ -50,6 +67,7 @@ impl Builder for WebsocketdBuilder {
             peer: self.peer.unwrap(),
             ws_rx,
             opt_ws_tx: Some(ws_tx),
+            stream: StreamState::Idle,
         }
         .set_name()
     }
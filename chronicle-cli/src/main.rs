@@ -10,17 +10,30 @@ use chronicle::{
     SocketMsg,
 };
 use chronicle_broker::{
+    archiver::{
+        binary::{
+            binary_to_json,
+            json_to_binary,
+            BINARY_LOG_EXTENSION,
+        },
+        is_finished_log,
+        MAX_LOG_SIZE,
+    },
+    compression::Codec,
+    manifest::ManifestSigner,
     merge::{
         LogPaths,
         Merger,
         ValidationLevel,
     },
+    remote::stream_path,
     BrokerSocketMsg,
     BrokerTopology,
     ChronicleBrokerThrough,
     *,
 };
 use chronicle_common::config::{
+    ImportThrottleConfig,
     MqttType,
     VersionedConfig,
 };
@@ -34,24 +47,32 @@ use futures::{
     StreamExt,
 };
 use indicatif::{
+    HumanBytes,
+    HumanDuration,
     ProgressBar,
     ProgressStyle,
 };
 use regex::Regex;
 use scylla_rs::prelude::ScyllaThrough;
 use std::{
+    net::SocketAddr,
     path::{
         Path,
         PathBuf,
     },
     process::Command,
+    time::Duration,
 };
+use tokio::io::AsyncReadExt;
 use tokio_tungstenite::{
     connect_async,
     tungstenite::Message,
 };
 use url::Url;
 
+/// The size of the binary frames stdin is streamed in
+const STDIN_FRAME_SIZE: usize = 64 * 1024;
+
 #[tokio::main]
 async fn main() {
     process().await.unwrap();
@@ -136,6 +157,7 @@ async fn process() -> anyhow::Result<()> {
         }
         ("nodes", Some(matches)) => nodes(matches).await?,
         ("brokers", Some(matches)) => brokers(matches).await?,
+        ("sync", Some(matches)) => sync(matches).await?,
         ("archive", Some(matches)) => archive(matches).await?,
         _ => (),
     }
@@ -209,7 +231,12 @@ async fn brokers<'a>(matches: &ArgMatches<'a>) -> anyhow::Result<()> {
                     )?));
                     messages.push(Message::text(serde_json::to_string(
                         &BrokerSocketMsg::ChronicleBroker(ChronicleBrokerThrough::Topology(
-                            BrokerTopology::AddMqttMessagesReferenced(mqtt_address),
+                            BrokerTopology::AddMqttMessagesReferenced(mqtt_address.clone()),
+                        )),
+                    )?));
+                    messages.push(Message::text(serde_json::to_string(
+                        &BrokerSocketMsg::ChronicleBroker(ChronicleBrokerThrough::Topology(
+                            BrokerTopology::AddMqttLatestMilestone(mqtt_address),
                         )),
                     )?));
                 }
@@ -228,7 +255,13 @@ async fn brokers<'a>(matches: &ArgMatches<'a>) -> anyhow::Result<()> {
                     .broker_config
                     .mqtt_brokers
                     .get_mut(&MqttType::MessagesReferenced)
-                    .map(|m| m.extend(mqtt_addresses));
+                    .map(|m| m.extend(mqtt_addresses.clone()));
+                config
+                    .broker_config
+                    .mqtt_brokers
+                    .entry(MqttType::LatestMilestone)
+                    .or_default()
+                    .extend(mqtt_addresses);
                 config.save(None).expect("Failed to save config!");
             }
         }
@@ -248,7 +281,12 @@ async fn brokers<'a>(matches: &ArgMatches<'a>) -> anyhow::Result<()> {
                         ChronicleBrokerThrough::Topology(BrokerTopology::RemoveMqttMessages(mqtt_address.clone())),
                     ))?));
                     messages.push(Message::text(serde_json::to_string(&SocketMsg::Broker(
-                        ChronicleBrokerThrough::Topology(BrokerTopology::RemoveMqttMessagesReferenced(mqtt_address)),
+                        ChronicleBrokerThrough::Topology(BrokerTopology::RemoveMqttMessagesReferenced(
+                            mqtt_address.clone(),
+                        )),
+                    ))?));
+                    messages.push(Message::text(serde_json::to_string(&SocketMsg::Broker(
+                        ChronicleBrokerThrough::Topology(BrokerTopology::RemoveMqttLatestMilestone(mqtt_address)),
                     ))?));
                 }
                 let (mut stream, _) =
@@ -266,6 +304,15 @@ async fn brokers<'a>(matches: &ArgMatches<'a>) -> anyhow::Result<()> {
                     .broker_config
                     .mqtt_brokers
                     .get_mut(&MqttType::MessagesReferenced)
+                    .map(|m| {
+                        mqtt_addresses.clone().for_each(|u| {
+                            m.remove(&u);
+                        })
+                    });
+                config
+                    .broker_config
+                    .mqtt_brokers
+                    .get_mut(&MqttType::LatestMilestone)
                     .map(|m| {
                         mqtt_addresses.for_each(|u| {
                             m.remove(&u);
@@ -274,6 +321,28 @@ async fn brokers<'a>(matches: &ArgMatches<'a>) -> anyhow::Result<()> {
                 config.save(None).expect("Failed to save config!");
             }
         }
+        ("replay", Some(subcommand)) => {
+            let mut path = PathBuf::from(
+                subcommand
+                    .value_of("path")
+                    .ok_or_else(|| anyhow!("No recording path received!"))?,
+            );
+            if path.is_relative() {
+                if let Some(feed_recording) = config.broker_config.feed_recording.as_ref() {
+                    path = Path::new(&feed_recording.directory).join(path);
+                }
+            }
+            let speed = if subcommand.is_present("fast") {
+                None
+            } else {
+                Some(subcommand.value_of("speed").unwrap_or("1").parse::<f64>()?)
+            };
+            let message = Message::text(serde_json::to_string(&SocketMsg::Broker(
+                ChronicleBrokerThrough::Topology(BrokerTopology::Replay { path, speed }),
+            ))?);
+            let (mut stream, _) = connect_async(Url::parse(&format!("ws://{}/", config.websocket_address))?).await?;
+            stream.send(message).await?;
+        }
         _ => (),
     }
     if matches.is_present("list") {
@@ -292,13 +361,107 @@ async fn brokers<'a>(matches: &ArgMatches<'a>) -> anyhow::Result<()> {
     Ok(())
 }
 
+async fn sync<'a>(matches: &ArgMatches<'a>) -> anyhow::Result<()> {
+    let config = VersionedConfig::load(None)?.verify().await?;
+    match matches.subcommand() {
+        ("watch", Some(_subcommand)) => {
+            let sty = ProgressStyle::default_bar()
+                .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}")
+                .progress_chars("##-");
+            let pb = ProgressBar::new(0);
+            pb.set_style(sty);
+            pb.set_message("waiting for the syncer");
+            let (mut stream, _) = connect_async(Url::parse(&format!("ws://{}/", config.websocket_address))?).await?;
+            while let Some(msg) = stream.next().await {
+                match msg {
+                    Ok(Message::Text(ref s)) => {
+                        if let Ok(json) = serde_json::from_str::<serde_json::Value>(s) {
+                            if let Some(service_json) = json.get("ChronicleBroker").cloned() {
+                                if let Ok(session) = serde_json::from_value::<SyncerSession>(service_json) {
+                                    match session {
+                                        SyncerSession::Progress {
+                                            current_range,
+                                            processed,
+                                            remaining_milestones,
+                                            remaining_gaps,
+                                            milestones_per_minute,
+                                            eta_secs,
+                                        } => {
+                                            pb.set_length((processed + remaining_milestones) as u64);
+                                            pb.set_position(processed as u64);
+                                            pb.set_message(format!(
+                                                "range: {}..{}, {:.1} milestones/min, {} gaps left, ETA: {}",
+                                                current_range.start,
+                                                current_range.end,
+                                                milestones_per_minute,
+                                                remaining_gaps,
+                                                eta_secs
+                                                    .map(|secs| HumanDuration(Duration::from_secs(secs)).to_string())
+                                                    .unwrap_or_else(|| "unknown".to_owned())
+                                            ));
+                                        }
+                                        SyncerSession::Finish { processed } => {
+                                            pb.finish_with_message(format!("done, synced {} milestones", processed));
+                                            break;
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    Ok(Message::Close(c)) => {
+                        if let Some(c) = c {
+                            println!("Closed connection: {}", c);
+                        }
+                        break;
+                    }
+                    Ok(_) => (),
+                    Err(e) => {
+                        println!("Error received from Chronicle: {}", e);
+                        break;
+                    }
+                }
+            }
+        }
+        _ => (),
+    }
+    Ok(())
+}
+
 async fn archive<'a>(matches: &ArgMatches<'a>) -> anyhow::Result<()> {
     let config = VersionedConfig::load(None)?.verify().await?;
     match matches.subcommand() {
         ("import", Some(subcommand)) => {
-            let dir = subcommand.value_of("directory").unwrap_or("");
+            let mut dir = subcommand.value_of("directory").unwrap_or("").to_owned();
+            // stdin is streamed to the broker over the websocket, under a unique id
+            let mut stream_id = None;
+            if dir == "-" {
+                let name = subcommand
+                    .value_of("name")
+                    .ok_or_else(|| anyhow!("The log file name is required to import from stdin!"))?;
+                if !is_finished_log(Path::new(name)) || name.ends_with(&format!(".{}", BINARY_LOG_EXTENSION)) {
+                    bail!(
+                        "Invalid log file name: {}, expected ie 0to10.log or 0to10.log.zst",
+                        name
+                    );
+                }
+                let id = format!(
+                    "{}-{}",
+                    std::process::id(),
+                    std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)?
+                        .as_nanos()
+                );
+                dir = stream_path(&id, name).to_string_lossy().into_owned();
+                stream_id.replace(id);
+            }
+            let dir = dir.as_str();
             let mut path = PathBuf::from(dir);
-            if path.is_relative() {
+            // s3 sources are downloaded by the broker, and http(s) sources and stdin are streamed by it
+            let is_remote = Url::parse(dir)
+                .map(|url| matches!(url.scheme(), "s3" | "http" | "https" | "stream"))
+                .unwrap_or(false);
+            if path.is_relative() && !is_remote {
                 if let Some(logs_dir) = config.broker_config.logs_dir.as_ref() {
                     path = Path::new(&logs_dir).join(path);
                 }
@@ -328,14 +491,23 @@ async fn archive<'a>(matches: &ArgMatches<'a>) -> anyhow::Result<()> {
                 is_file,
                 range
             );
-            if is_url {
-                panic!("URL imports are not currently supported!");
+            if is_url && !is_remote {
+                bail!("Unsupported url scheme, expected s3, http or https");
             }
             let import_type = if subcommand.is_present("analytics") {
                 ImportType::Analytics
             } else {
                 ImportType::All
             };
+            let filter = subcommand
+                .value_of("keyspace")
+                .map(|keyspace| -> anyhow::Result<ImportFilter> {
+                    Ok(ImportFilter {
+                        keyspace: keyspace.to_owned(),
+                        ttl: subcommand.value_of("ttl").map(str::parse).transpose()?,
+                    })
+                })
+                .transpose()?;
             let sty = ProgressStyle::default_bar()
                 .template(
                     "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} {msg} ({eta})",
@@ -344,17 +516,29 @@ async fn archive<'a>(matches: &ArgMatches<'a>) -> anyhow::Result<()> {
             let mut active_progress_bars: std::collections::HashMap<(u32, u32), ()> = std::collections::HashMap::new();
             let pb = ProgressBar::new(0);
             pb.set_style(sty.clone());
-            let (mut stream, _) = connect_async(Url::parse(&format!("ws://{}/", config.websocket_address))?).await?;
-            stream
-                .send(Message::text(serde_json::to_string(&SocketMsg::Broker(
-                    ChronicleBrokerThrough::Topology(BrokerTopology::Import {
-                        path,
-                        resume,
-                        import_range: Some(range),
-                        import_type,
-                    }),
-                ))?))
-                .await?;
+            let (stream, _) = connect_async(Url::parse(&format!("ws://{}/", config.websocket_address))?).await?;
+            let (mut sink, mut stream) = stream.split();
+            // the id of the streamed log goes first, so the broker knows it by the time the import starts
+            if let Some(id) = stream_id.as_ref() {
+                sink.send(Message::binary(id.as_bytes())).await?;
+            }
+            sink.send(Message::text(serde_json::to_string(&SocketMsg::Broker(
+                ChronicleBrokerThrough::Topology(BrokerTopology::Import {
+                    path,
+                    resume,
+                    import_range: Some(range),
+                    import_type,
+                    filter,
+                }),
+            ))?))
+            .await?;
+            if stream_id.is_some() {
+                tokio::spawn(async move {
+                    if let Err(e) = stream_stdin(&mut sink).await {
+                        println!("Unable to stream stdin: {}", e);
+                    }
+                });
+            }
             while let Some(msg) = stream.next().await {
                 match msg {
                     Ok(msg) => {
@@ -373,6 +557,7 @@ async fn archive<'a>(matches: &ArgMatches<'a>) -> anyhow::Result<()> {
                                                     ms_bytes_size,
                                                     milestone_index,
                                                     skipped,
+                                                    rate,
                                                 } => {
                                                     if let Some(()) = active_progress_bars.get_mut(&(from_ms, to_ms)) {
                                                         // advance the pb
@@ -383,8 +568,12 @@ async fn archive<'a>(matches: &ArgMatches<'a>) -> anyhow::Result<()> {
                                                             skipped_or_imported = "imported"
                                                         }
                                                         pb.set_message(format!(
-                                                            "{}to{}.log: {} #{}",
-                                                            from_ms, to_ms, skipped_or_imported, milestone_index
+                                                            "{}to{}.log: {} #{} ({})",
+                                                            from_ms,
+                                                            to_ms,
+                                                            skipped_or_imported,
+                                                            milestone_index,
+                                                            format_rate(&rate)
                                                         ));
                                                         pb.inc(ms_bytes_size as u64);
                                                     } else {
@@ -397,8 +586,12 @@ async fn archive<'a>(matches: &ArgMatches<'a>) -> anyhow::Result<()> {
                                                             skipped_or_imported = "imported"
                                                         }
                                                         pb.set_message(format!(
-                                                            "{}to{}.log: {} #{}",
-                                                            from_ms, to_ms, skipped_or_imported, milestone_index
+                                                            "{}to{}.log: {} #{} ({})",
+                                                            from_ms,
+                                                            to_ms,
+                                                            skipped_or_imported,
+                                                            milestone_index,
+                                                            format_rate(&rate)
                                                         ));
                                                         pb.inc(ms_bytes_size as u64);
                                                         active_progress_bars.insert((from_ms, to_ms), ());
@@ -413,6 +606,11 @@ async fn archive<'a>(matches: &ArgMatches<'a>) -> anyhow::Result<()> {
                                                         pb.println(m);
                                                     }
                                                 }
+                                                ImporterSession::Job { id, path } => pb.println(format!(
+                                                    "Import job {}: {} (see `archive jobs`)",
+                                                    id,
+                                                    path.display()
+                                                )),
                                                 ImporterSession::PathError { path, msg } => {
                                                     pb.println(format!("ErrorPath: {:?}, msg: {:?}", path, msg))
                                                 }
@@ -447,11 +645,127 @@ async fn archive<'a>(matches: &ArgMatches<'a>) -> anyhow::Result<()> {
         }
         ("cleanup", Some(matches)) => cleanup_archive(matches).await?,
         ("validate", Some(_matches)) => validate_archive().await?,
+        ("convert", Some(matches)) => convert_archive(matches).await?,
+        ("throttle", Some(matches)) => {
+            let import_throttle = ImportThrottleConfig {
+                max_rows_per_sec: matches.value_of("rows-per-sec").map(str::parse).transpose()?,
+                max_bytes_per_sec: matches.value_of("bytes-per-sec").map(str::parse).transpose()?,
+                adaptive: !matches.is_present("no-adaptive"),
+            };
+            import_throttle.verify()?;
+            let message = Message::text(serde_json::to_string(&SocketMsg::Broker(
+                ChronicleBrokerThrough::Topology(BrokerTopology::ImportThrottle(import_throttle)),
+            ))?);
+            let (mut stream, _) = connect_async(Url::parse(&format!("ws://{}/", config.websocket_address))?).await?;
+            stream.send(message).await?;
+        }
+        ("jobs", Some(matches)) => import_jobs(matches, config.websocket_address).await?,
         _ => (),
     }
     Ok(())
 }
 
+/// List the import jobs of the broker, or pause, resume or cancel one of them
+async fn import_jobs<'a>(matches: &ArgMatches<'a>, websocket_address: SocketAddr) -> anyhow::Result<()> {
+    let job_id = |arg| matches.value_of(arg).map(str::parse::<u64>).transpose();
+    let command = if let Some(id) = job_id("pause")? {
+        ImportJobCommand::Pause(id)
+    } else if let Some(id) = job_id("resume")? {
+        ImportJobCommand::Resume(id)
+    } else if let Some(id) = job_id("cancel")? {
+        ImportJobCommand::Cancel(id)
+    } else {
+        ImportJobCommand::List
+    };
+    let (mut stream, _) = connect_async(Url::parse(&format!("ws://{}/", websocket_address))?).await?;
+    stream
+        .send(Message::text(serde_json::to_string(&SocketMsg::Broker(
+            ChronicleBrokerThrough::ImportJobs(command),
+        ))?))
+        .await?;
+    // the broker answers every socket, so skip the other messages
+    while let Some(msg) = stream.next().await {
+        if let Message::Text(s) = msg? {
+            let json = match serde_json::from_str::<serde_json::Value>(&s)?
+                .get("ChronicleBroker")
+                .cloned()
+            {
+                Some(json) => json,
+                None => continue,
+            };
+            if let ImportJobCommand::List = command {
+                if let Ok(reports) = serde_json::from_value::<Vec<ImportJobReport>>(json) {
+                    if reports.is_empty() {
+                        println!("No import jobs");
+                    }
+                    for report in reports.iter() {
+                        println!("{}", format_job(report));
+                    }
+                    break;
+                }
+            } else if let Ok(result) = serde_json::from_value::<Result<ImportJobReport, String>>(json) {
+                match result {
+                    Ok(report) => println!("{}", format_job(&report)),
+                    Err(e) => bail!(e),
+                }
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Format the import job along with its progress
+fn format_job(report: &ImportJobReport) -> String {
+    let mut formatted = format!(
+        "#{} {:?} {}: {}/{} logs, {} milestones, {}",
+        report.id,
+        report.state,
+        report.path.display(),
+        report.imported_logs,
+        report.logs,
+        report.imported_milestones,
+        HumanBytes(report.imported_bytes)
+    );
+    if report.failed_logs > 0 {
+        formatted.push_str(&format!(", {} failed logs", report.failed_logs));
+    }
+    if let Some(range) = report.import_range.as_ref() {
+        formatted.push_str(&format!(", range: {:?}", range));
+    }
+    formatted
+}
+
+/// Format the effective import rate, along with the backoff if the importers are backing off
+fn format_rate(rate: &ImportRate) -> String {
+    let mut formatted = format!(
+        "{:.0} rows/s, {}/s",
+        rate.rows_per_sec,
+        HumanBytes(rate.bytes_per_sec as u64)
+    );
+    if rate.backoff < 1.0 {
+        formatted.push_str(&format!(", backing off to {:.0}%", rate.backoff * 100.0));
+    }
+    formatted
+}
+
+/// Stream the stdin to the broker in binary frames, followed by an empty frame once it ends.
+/// Note: stdin can't be streamed twice, so the import fails if the connection to the broker is lost
+async fn stream_stdin<S>(sink: &mut S) -> anyhow::Result<()>
+where
+    S: futures::Sink<Message, Error = tokio_tungstenite::tungstenite::Error> + Unpin,
+{
+    let mut stdin = tokio::io::stdin();
+    let mut buffer = vec![0; STDIN_FRAME_SIZE];
+    loop {
+        let read = stdin.read(&mut buffer).await?;
+        sink.send(Message::binary(&buffer[..read])).await?;
+        if read == 0 {
+            return Ok(());
+        }
+    }
+}
+
 async fn cleanup_archive<'a>(matches: &ArgMatches<'a>) -> anyhow::Result<()> {
     let backup_logs = !matches.is_present("no-backup");
     let val_level = matches
@@ -470,6 +784,7 @@ async fn cleanup_archive<'a>(matches: &ArgMatches<'a>) -> anyhow::Result<()> {
         .unwrap_or_default();
     let exit_on_val_err = !matches.is_present("no-exit-on-val-err");
     let include_finalized = matches.is_present("include-finalized");
+    let compression = matches.value_of("compress").map(str::parse::<Codec>).transpose()?;
     let config = VersionedConfig::load(None)?.verify().await?;
     let logs_dir;
     let max_log_size = config.broker_config.max_log_size.clone().unwrap_or(u32::MAX as u64);
@@ -479,7 +794,7 @@ async fn cleanup_archive<'a>(matches: &ArgMatches<'a>) -> anyhow::Result<()> {
         println!("No LogsDir in the config, Chronicle is running without archiver");
         return Ok(());
     }
-    Merger::new(
+    let mut merger = Merger::new(
         logs_dir,
         max_log_size,
         backup_logs,
@@ -487,9 +802,13 @@ async fn cleanup_archive<'a>(matches: &ArgMatches<'a>) -> anyhow::Result<()> {
         val_level,
         exit_on_val_err,
         include_finalized,
+        compression,
     )?
-    .cleanup()
-    .await?;
+    .trusted_keys(config.broker_config.log_manifest.trusted_keys.clone());
+    if let Some(signing_key_path) = config.broker_config.log_manifest.signing_key_path.as_ref() {
+        merger = merger.sign_manifests(ManifestSigner::load(Path::new(signing_key_path))?);
+    }
+    merger.cleanup().await?;
     Ok(())
 }
 
@@ -503,5 +822,83 @@ async fn validate_archive() -> anyhow::Result<()> {
         println!("No LogsDir in the config, Chronicle is running without archiver");
         return Ok(());
     }
-    LogPaths::new(&logs_dir, true)?.validate(max_log_size, true).await
+    LogPaths::new(&logs_dir, true)?
+        .validate(max_log_size, true, &config.broker_config.log_manifest.trusted_keys)
+        .await
+}
+
+async fn convert_archive<'a>(matches: &ArgMatches<'a>) -> anyhow::Result<()> {
+    let to_binary = matches.value_of("to") == Some("binary");
+    let config = VersionedConfig::load(None)?.verify().await?;
+    let max_log_size = config.broker_config.max_log_size.unwrap_or(MAX_LOG_SIZE);
+    let path = match matches.value_of("path") {
+        Some(path) => PathBuf::from(path),
+        None => {
+            if let Some(dir) = config.broker_config.logs_dir.as_ref() {
+                PathBuf::from(dir)
+            } else {
+                println!("No LogsDir in the config, Chronicle is running without archiver");
+                return Ok(());
+            }
+        }
+    };
+    let mut files = Vec::new();
+    if path.is_dir() {
+        let mut dir = tokio::fs::read_dir(&path).await?;
+        while let Some(entry) = dir.next_entry().await? {
+            files.push(entry.path());
+        }
+    } else {
+        files.push(path);
+    }
+    files.sort();
+    for src in files {
+        let name = match src.file_name().and_then(|name| name.to_str()) {
+            Some(name) => name,
+            None => continue,
+        };
+        // ie 0to10.log, 0to10.log.zst or 0to10.bin
+        let stem = name.split('.').next().unwrap_or_default();
+        if !stem.contains("to") {
+            continue;
+        }
+        let is_binary = name == format!("{}.{}", stem, BINARY_LOG_EXTENSION);
+        let is_json = [".log", ".log.fin", ".log.zst", ".log.gz"]
+            .iter()
+            .any(|ext| name == format!("{}{}", stem, ext));
+        let dest = match (to_binary, is_json, is_binary) {
+            (true, true, _) => src.with_file_name(format!("{}.{}", stem, BINARY_LOG_EXTENSION)),
+            (false, _, true) => src.with_file_name(format!("{}.log", stem)),
+            _ => continue,
+        };
+        if dest.exists() {
+            println!("Skipping {}, {} already exists", src.display(), dest.display());
+            continue;
+        }
+        let converted = if to_binary {
+            json_to_binary(&src, &dest).await
+        } else {
+            binary_to_json(&src, &dest, max_log_size).await
+        };
+        match converted {
+            Ok(count) => {
+                // the source is set aside, so the milestones don't get imported twice from the same directory
+                let mut converted_src = src.clone().into_os_string();
+                converted_src.push(".converted");
+                tokio::fs::rename(&src, &converted_src).await?;
+                println!(
+                    "Converted {} milestones from {} to {}, the source is kept as {}",
+                    count,
+                    src.display(),
+                    dest.display(),
+                    Path::new(&converted_src).display()
+                )
+            }
+            Err(e) => {
+                tokio::fs::remove_file(&dest).await.ok();
+                bail!("Unable to convert {}: {}", src.display(), e);
+            }
+        }
+    }
+    Ok(())
 }
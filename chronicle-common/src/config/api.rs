@@ -3,8 +3,18 @@
 
 use super::*;
 /// Configuration for the Chronicle API
-#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, Clone)]
-pub struct ApiConfig {}
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct ApiConfig {
+    /// The sync lag (in milestones) above which the broker is reported as not ready
+    pub max_sync_lag: u32,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self { max_sync_lag: 10 }
+    }
+}
 
 impl ApiConfig {
     /// Verify that the api config is valid
@@ -5,13 +5,22 @@ use super::*;
 use log::warn;
 use paho_mqtt::{
     AsyncClient,
+    ConnectOptions,
+    ConnectOptionsBuilder,
     CreateOptionsBuilder,
+    SslOptionsBuilder,
+};
+use reqwest::{
+    Certificate,
+    Client,
+    Identity,
+    RequestBuilder,
 };
-use reqwest::Client;
 use serde_json::Value;
 use std::{
     collections::HashSet,
     net::SocketAddr,
+    time::Duration,
 };
 use url::Url;
 
@@ -21,14 +30,35 @@ pub struct BrokerConfig {
     /// The websocket listener address
     #[serde(deserialize_with = "super::deserialize_socket_addr")]
     pub websocket_address: SocketAddr,
+    /// The address the broker serves its metrics on, regardless of whether the API is running
+    #[serde(default, deserialize_with = "super::deserialize_optional_socket_addr")]
+    pub metrics_address: Option<SocketAddr>,
     /// MQTT addresses the broker will use as feed sources separated by type
     pub mqtt_brokers: HashMap<MqttType, HashSet<Url>>,
+    /// MQTT connection settings per address, addresses without an entry use the defaults
+    #[serde(default)]
+    pub mqtt_connections: HashMap<Url, MqttConnectionConfig>,
     /// Mqtt stream capacity
     pub mqtt_stream_capacity: usize,
+    /// The capacities of the actor inboxes
+    #[serde(default)]
+    pub inbox_capacity: InboxCapacityConfig,
+    /// MQTT quality of service per feed source type, defaults to 0 (at most once)
+    #[serde(default)]
+    pub mqtt_qos: HashMap<MqttType, i32>,
     /// API endpoints the broker will use to request missing data
     pub api_endpoints: HashSet<Url>,
+    /// API connection settings per endpoint, endpoints without an entry use the defaults
+    #[serde(default)]
+    pub api_connections: HashMap<Url, ApiConnectionConfig>,
+    /// HTTP polling feed sources, for nodes which cannot be reached over MQTT
+    #[serde(default)]
+    pub polling_feed: PollingFeedConfig,
     /// Retries per api endpoint.
     pub retries_per_endpoint: usize,
+    /// Circuit breaking and rate limiting of the api endpoints
+    #[serde(default)]
+    pub endpoint_health: EndpointHealthConfig,
     /// Retries per scylla query.
     pub retries_per_query: usize,
     /// Defines the total number of concurrent collectors and solidifiers
@@ -41,14 +71,479 @@ pub struct BrokerConfig {
     /// - Importer(s) uses this to define the maximum number of concurrent milestone data and messages
     /// - Syncer(worker which fills gaps) uses this to define the maximum number of solidify requests/milestone data.
     pub parallelism: u8,
+    /// The write rate limits shared by the importers
+    #[serde(default)]
+    pub import_throttle: ImportThrottleConfig,
     /// Desired range of milestone indexes to sync if missing
     pub sync_range: Option<SyncRange>,
+    /// The order in which the syncer fills the gaps of the sync range
+    #[serde(default)]
+    pub backfill: BackfillConfig,
     /// Complete gaps interval in seconds
     pub complete_gaps_interval_secs: u64,
     /// Archive directory
     pub logs_dir: Option<String>,
     /// The maximum log file size
     pub max_log_size: Option<u64>,
+    /// Compress the finished log files
+    #[serde(default)]
+    pub log_compression: Option<LogCompression>,
+    /// Upload the finished log files to S3-compatible object storage
+    #[serde(default)]
+    pub archive_upload: Option<ArchiveUploadConfig>,
+    /// Manifests of the finished log files
+    #[serde(default)]
+    pub log_manifest: LogManifestConfig,
+    /// WebAssembly filter module used to route messages
+    #[serde(default)]
+    pub wasm_filter: Option<WasmFilterConfig>,
+    /// Record the raw MQTT feed traffic to rotating files, to be replayed later
+    #[serde(default)]
+    pub feed_recording: Option<FeedRecordingConfig>,
+}
+
+/// Raw feed recording config
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct FeedRecordingConfig {
+    /// The directory the recordings are written to
+    pub directory: String,
+    /// The size in bytes after which a recording file is rotated
+    pub max_file_size: u64,
+    /// The maximum number of recording files to keep, the oldest ones are removed first
+    pub max_files: Option<usize>,
+}
+
+impl Default for FeedRecordingConfig {
+    fn default() -> Self {
+        Self {
+            directory: "chronicle/recordings/".to_owned(),
+            max_file_size: 64 * 1024 * 1024,
+            max_files: None,
+        }
+    }
+}
+
+/// Upload of the finished log files to S3-compatible object storage
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct ArchiveUploadConfig {
+    /// The bucket the logs are uploaded to
+    pub bucket: String,
+    /// The region of the bucket
+    pub region: String,
+    /// The endpoint of a S3-compatible storage (ie `http://localhost:9000` for MinIO), addressed path-style
+    pub endpoint: Option<String>,
+    /// The access key, the `AWS_ACCESS_KEY_ID` environment variable or the aws profile are used if not provided
+    pub access_key: Option<String>,
+    /// The secret key, the `AWS_SECRET_ACCESS_KEY` environment variable or the aws profile are used if not provided
+    pub secret_key: Option<String>,
+    /// The prefix of the uploaded object keys
+    pub prefix: String,
+    /// Retries per upload
+    pub retries: usize,
+    /// How long the uploaded logs are kept locally in seconds, they are kept forever if not provided
+    pub local_retention_secs: Option<u64>,
+}
+
+impl Default for ArchiveUploadConfig {
+    fn default() -> Self {
+        Self {
+            bucket: "chronicle-archive".to_owned(),
+            region: "us-east-1".to_owned(),
+            endpoint: None,
+            access_key: None,
+            secret_key: None,
+            prefix: String::new(),
+            retries: 5,
+            local_retention_secs: None,
+        }
+    }
+}
+
+/// Manifests of the finished log files, which prove the logs weren't altered since they were finished
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct LogManifestConfig {
+    /// Path to the file holding the hex encoded Ed25519 secret key the manifests are signed with
+    pub signing_key_path: Option<String>,
+    /// Hex encoded Ed25519 public keys trusted to sign the manifests.
+    /// If provided, the logs without a manifest signed by one of them are rejected by the importer
+    pub trusted_keys: Vec<String>,
+}
+
+/// WebAssembly filter module config
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct WasmFilterConfig {
+    /// Path to the `.wasm` module
+    pub module_path: String,
+    /// The fuel (roughly, the number of wasm instructions) the module may consume per message
+    pub fuel_per_call: u64,
+    /// The maximum size in bytes the module's memory may grow to per message
+    pub max_memory_bytes: usize,
+}
+
+impl Default for WasmFilterConfig {
+    fn default() -> Self {
+        Self {
+            module_path: "filter.wasm".to_owned(),
+            fuel_per_call: 10_000_000,
+            max_memory_bytes: 16 * 1024 * 1024,
+        }
+    }
+}
+
+/// MQTT connection settings
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct MqttConnectionConfig {
+    /// The user name used to authenticate
+    pub username: Option<String>,
+    /// The password used to authenticate
+    pub password: Option<String>,
+    /// The PEM file containing the trusted CA certificates
+    pub ca_path: Option<String>,
+    /// The PEM file containing the client certificate chain
+    pub client_cert_path: Option<String>,
+    /// The PEM file containing the client private key (if not included in the client certificate file)
+    pub client_key_path: Option<String>,
+    /// The keep alive interval in seconds
+    pub keep_alive_secs: u64,
+    /// Whether to start a clean session on connect
+    pub clean_session: bool,
+    /// The connect timeout in seconds
+    pub connect_timeout_secs: u64,
+}
+
+impl Default for MqttConnectionConfig {
+    fn default() -> Self {
+        Self {
+            username: None,
+            password: None,
+            ca_path: None,
+            client_cert_path: None,
+            client_key_path: None,
+            keep_alive_secs: 120,
+            clean_session: false,
+            connect_timeout_secs: 60,
+        }
+    }
+}
+
+impl MqttConnectionConfig {
+    /// Get the paho server uri of a feed source address, `mqtts://` is mapped to `ssl://`
+    pub fn server_uri(url: &Url) -> String {
+        match url.scheme() {
+            "mqtts" => url.as_str().replacen("mqtts://", "ssl://", 1),
+            "mqtt" => url.as_str().replacen("mqtt://", "tcp://", 1),
+            _ => url.as_str().to_owned(),
+        }
+    }
+
+    /// Whether the feed source address requires a TLS connection
+    pub fn is_secure(url: &Url) -> bool {
+        matches!(url.scheme(), "ssl" | "mqtts" | "wss")
+    }
+
+    /// Build the paho connect options for the given feed source address
+    pub fn connect_options(&self, url: &Url) -> anyhow::Result<ConnectOptions> {
+        let mut builder = ConnectOptionsBuilder::new();
+        builder
+            .keep_alive_interval(std::time::Duration::from_secs(self.keep_alive_secs))
+            .mqtt_version(paho_mqtt::MQTT_VERSION_3_1_1)
+            .clean_session(self.clean_session)
+            .connect_timeout(std::time::Duration::from_secs(self.connect_timeout_secs));
+        if let Some(username) = self.username.as_ref() {
+            builder.user_name(username);
+        }
+        if let Some(password) = self.password.as_ref() {
+            builder.password(password);
+        }
+        let has_tls_settings =
+            self.ca_path.is_some() || self.client_cert_path.is_some() || self.client_key_path.is_some();
+        if Self::is_secure(url) {
+            let mut ssl_builder = SslOptionsBuilder::new();
+            if let Some(ca_path) = self.ca_path.as_ref() {
+                ssl_builder
+                    .trust_store(ca_path)
+                    .map_err(|e| anyhow!("Invalid CA file {} for {}: {}", ca_path, url, e))?;
+            }
+            if let Some(client_cert_path) = self.client_cert_path.as_ref() {
+                ssl_builder
+                    .key_store(client_cert_path)
+                    .map_err(|e| anyhow!("Invalid client certificate {} for {}: {}", client_cert_path, url, e))?;
+            }
+            if let Some(client_key_path) = self.client_key_path.as_ref() {
+                ssl_builder
+                    .private_key(client_key_path)
+                    .map_err(|e| anyhow!("Invalid client key {} for {}: {}", client_key_path, url, e))?;
+            }
+            builder.ssl_options(ssl_builder.finalize());
+        } else if has_tls_settings {
+            bail!(
+                "TLS settings provided for {}, which does not use a secure scheme (ssl://, mqtts:// or wss://)",
+                url
+            );
+        }
+        Ok(builder.finalize())
+    }
+}
+
+/// API endpoint connection settings
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Default)]
+#[serde(default)]
+pub struct ApiConnectionConfig {
+    /// The bearer token (ie JWT) sent with every request
+    pub bearer_token: Option<String>,
+    /// The user name used to authenticate with basic auth
+    pub username: Option<String>,
+    /// The password used to authenticate with basic auth
+    pub password: Option<String>,
+    /// The PEM file containing the trusted CA certificates
+    pub ca_path: Option<String>,
+    /// The PEM file containing the client certificate chain
+    pub client_cert_path: Option<String>,
+    /// The PEM file containing the client private key (if not included in the client certificate file)
+    pub client_key_path: Option<String>,
+}
+
+impl ApiConnectionConfig {
+    /// Whether the endpoint requires its own TLS settings, and so its own http client
+    pub fn has_tls(&self) -> bool {
+        self.ca_path.is_some() || self.client_cert_path.is_some() || self.client_key_path.is_some()
+    }
+    /// Build the client used to request the endpoint, sharing the given one if no TLS settings are needed
+    pub fn api_client(&self, shared_client: &Client, timeout: Duration) -> anyhow::Result<ApiClient> {
+        ensure!(
+            self.bearer_token.is_none() || self.username.is_none(),
+            "Both a bearer token and basic auth are provided"
+        );
+        ensure!(
+            self.password.is_none() || self.username.is_some(),
+            "A password is provided without a username"
+        );
+        let client = if self.has_tls() {
+            let mut builder = Client::builder().timeout(timeout);
+            if let Some(ca_path) = self.ca_path.as_ref() {
+                let ca = std::fs::read(ca_path).map_err(|e| anyhow!("Unable to read {}: {}", ca_path, e))?;
+                builder = builder.add_root_certificate(Certificate::from_pem(&ca)?);
+            }
+            match (self.client_cert_path.as_ref(), self.client_key_path.as_ref()) {
+                (Some(client_cert_path), client_key_path) => {
+                    let mut pem = std::fs::read(client_cert_path)
+                        .map_err(|e| anyhow!("Unable to read {}: {}", client_cert_path, e))?;
+                    if let Some(client_key_path) = client_key_path {
+                        pem.push(b'\n');
+                        pem.extend(
+                            std::fs::read(client_key_path)
+                                .map_err(|e| anyhow!("Unable to read {}: {}", client_key_path, e))?,
+                        );
+                    }
+                    builder = builder.use_rustls_tls().identity(Identity::from_pem(&pem)?);
+                }
+                (None, Some(_)) => bail!("A client key is provided without a client certificate"),
+                (None, None) => (),
+            }
+            builder.build()?
+        } else {
+            shared_client.clone()
+        };
+        Ok(ApiClient {
+            client,
+            connection: self.clone(),
+        })
+    }
+}
+
+/// Http client which authenticates its requests with the connection settings of an api endpoint
+#[derive(Debug, Clone)]
+pub struct ApiClient {
+    client: Client,
+    connection: ApiConnectionConfig,
+}
+
+impl ApiClient {
+    /// Start an authenticated GET request
+    pub fn get(&self, url: Url) -> RequestBuilder {
+        let request = self.client.get(url);
+        if let Some(bearer_token) = self.connection.bearer_token.as_ref() {
+            request.bearer_auth(bearer_token)
+        } else if let Some(username) = self.connection.username.as_ref() {
+            request.basic_auth(username, self.connection.password.as_ref())
+        } else {
+            request
+        }
+    }
+}
+
+/// HTTP polling feed source config
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct PollingFeedConfig {
+    /// API endpoints which are always polled for new milestones
+    pub endpoints: HashSet<Url>,
+    /// Poll the `api_endpoints` for new milestones while all MQTT feeds are down
+    pub fallback: bool,
+    /// The interval between polls in milliseconds
+    pub interval_ms: u64,
+}
+
+impl Default for PollingFeedConfig {
+    fn default() -> Self {
+        Self {
+            endpoints: HashSet::new(),
+            fallback: true,
+            interval_ms: 1000,
+        }
+    }
+}
+
+/// Api endpoint circuit breaker and rate limit config
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct EndpointHealthConfig {
+    /// The number of consecutive failures after which an endpoint stops receiving requests
+    pub failure_threshold: u32,
+    /// How long a failing endpoint is skipped for the first time, doubled on every further failure
+    pub base_backoff_ms: u64,
+    /// The upper bound of the backoff
+    pub max_backoff_ms: u64,
+    /// The maximum number of requests per second sent to a single endpoint, unlimited if not set
+    pub max_requests_per_sec: Option<u32>,
+}
+
+impl Default for EndpointHealthConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            base_backoff_ms: 1000,
+            max_backoff_ms: 60 * 1000,
+            max_requests_per_sec: None,
+        }
+    }
+}
+
+/// Write rate limits shared by all the importers, which can be changed at runtime through the broker websocket
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct ImportThrottleConfig {
+    /// The maximum number of rows written per second, unlimited if not set
+    pub max_rows_per_sec: Option<u32>,
+    /// The maximum number of log bytes imported per second, unlimited if not set
+    pub max_bytes_per_sec: Option<u64>,
+    /// Lower the rate when the inserts fail or time out, and raise it back gradually once they succeed again
+    pub adaptive: bool,
+}
+
+impl Default for ImportThrottleConfig {
+    fn default() -> Self {
+        Self {
+            max_rows_per_sec: None,
+            max_bytes_per_sec: None,
+            adaptive: true,
+        }
+    }
+}
+
+impl ImportThrottleConfig {
+    /// Verify that the import throttle config is valid
+    pub fn verify(&self) -> anyhow::Result<()> {
+        ensure!(
+            self.max_rows_per_sec != Some(0),
+            "Error verifying import throttle, zero max_rows_per_sec provided!"
+        );
+        ensure!(
+            self.max_bytes_per_sec != Some(0),
+            "Error verifying import throttle, zero max_bytes_per_sec provided!"
+        );
+        Ok(())
+    }
+}
+
+/// The number of events from the feed sources and requesters an actor inbox holds,
+/// before the senders have to wait for the actor to catch up
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct InboxCapacityConfig {
+    /// The capacity of every collector inbox
+    pub collector: usize,
+    /// The capacity of every solidifier inbox
+    pub solidifier: usize,
+    /// The capacity of the archiver inbox
+    pub archiver: usize,
+    /// The capacity of the syncer inbox
+    pub syncer: usize,
+    /// The capacity of every importer inbox
+    pub importer: usize,
+}
+
+impl Default for InboxCapacityConfig {
+    fn default() -> Self {
+        Self {
+            collector: 10000,
+            solidifier: 10000,
+            archiver: 1000,
+            syncer: 1000,
+            importer: 1000,
+        }
+    }
+}
+
+/// The compression of the finished archive log files
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum LogCompression {
+    /// Zstandard, written as `.log.zst`
+    Zstd,
+    /// Gzip, written as `.log.gz`
+    Gzip,
+}
+
+/// The order in which the syncer fills the gaps of its sync data
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum BackfillStrategy {
+    /// Fill the oldest gap first
+    OldestFirst,
+    /// Fill the newest gap first, so the recent history becomes queryable before the old one
+    NewestFirst,
+    /// Alternate between the newest and the oldest gaps, filling at most the given window of milestones at a time
+    Interleaved(u32),
+}
+
+impl Default for BackfillStrategy {
+    fn default() -> Self {
+        BackfillStrategy::OldestFirst
+    }
+}
+
+/// Backfill configuration of the syncer, which can be changed at runtime through the broker websocket
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct BackfillConfig {
+    /// The order in which the gaps are filled
+    pub strategy: BackfillStrategy,
+    /// Ranges which are filled before any other gap, in the given order
+    pub priority_ranges: Vec<SyncRange>,
+}
+
+impl BackfillConfig {
+    /// Verify that the backfill config is valid
+    pub fn verify(&self) -> anyhow::Result<()> {
+        if let BackfillStrategy::Interleaved(window) = self.strategy {
+            ensure!(
+                window > 0,
+                "Error verifying backfill strategy, zero interleaved window provided!"
+            );
+        }
+        for range in self.priority_ranges.iter() {
+            ensure!(
+                range.from < range.to,
+                "Error verifying backfill priority range {}..{}, from must be lower than to!",
+                range.from,
+                range.to
+            );
+        }
+        Ok(())
+    }
 }
 
 /// Enumerated MQTT feed source type
@@ -58,6 +553,19 @@ pub enum MqttType {
     Messages,
     /// Receives Referenced notifications
     MessagesReferenced,
+    /// Receives the latest milestone index and timestamp
+    LatestMilestone,
+}
+
+impl MqttType {
+    /// The MQTT topic which the feed sources of this type subscribe to
+    pub fn topic(&self) -> &'static str {
+        match self {
+            MqttType::Messages => "messages",
+            MqttType::MessagesReferenced => "messages/referenced",
+            MqttType::LatestMilestone => "milestones/latest",
+        }
+    }
 }
 
 impl Default for BrokerConfig {
@@ -71,7 +579,9 @@ impl Default for BrokerConfig {
             retries_per_query: 100,
             complete_gaps_interval_secs: 60 * 60,
             websocket_address: ([127, 0, 0, 1], 9000).into(),
+            metrics_address: Some(([127, 0, 0, 1], 9100).into()),
             mqtt_stream_capacity: 10000,
+            inbox_capacity: Default::default(),
             mqtt_brokers: hashmap! {
                 MqttType::Messages => hashset![
                     url::Url::parse("tcp://api.hornet-0.testnet.chrysalis2.com:1883").unwrap(),
@@ -80,16 +590,36 @@ impl Default for BrokerConfig {
                 MqttType::MessagesReferenced => hashset![
                     url::Url::parse("tcp://api.hornet-0.testnet.chrysalis2.com:1883").unwrap(),
                     url::Url::parse("tcp://api.hornet-1.testnet.chrysalis2.com:1883").unwrap(),
+                ],
+                MqttType::LatestMilestone => hashset![
+                    url::Url::parse("tcp://api.hornet-0.testnet.chrysalis2.com:1883").unwrap(),
+                    url::Url::parse("tcp://api.hornet-1.testnet.chrysalis2.com:1883").unwrap(),
                 ]
             },
+            mqtt_connections: HashMap::new(),
+            mqtt_qos: hashmap! {
+                MqttType::Messages => 0,
+                MqttType::MessagesReferenced => 0,
+                MqttType::LatestMilestone => 0,
+            },
             api_endpoints: hashset![
                 url::Url::parse("https://api.hornet-0.testnet.chrysalis2.com/api/v1").unwrap(),
                 url::Url::parse("https://api.hornet-1.testnet.chrysalis2.com/api/v1").unwrap(),
             ]
             .into(),
+            api_connections: HashMap::new(),
+            polling_feed: Default::default(),
+            endpoint_health: Default::default(),
+            import_throttle: Default::default(),
             sync_range: Some(Default::default()),
+            backfill: Default::default(),
             logs_dir: Some("chronicle/logs/".to_owned()),
             max_log_size: Some(4 * 1024 * 1024 * 1024),
+            log_compression: None,
+            archive_upload: None,
+            log_manifest: Default::default(),
+            wasm_filter: None,
+            feed_recording: None,
         }
     }
 }
@@ -99,22 +629,47 @@ impl BrokerConfig {
     pub async fn verify(&mut self) -> anyhow::Result<()> {
         for mqtt_broker in self.mqtt_brokers.values().flatten() {
             let random_id: u64 = rand::random();
+            self.mqtt_connection(mqtt_broker)
+                .connect_options(mqtt_broker)
+                .map_err(|e| anyhow!("Error verifying mqtt broker {}: {}", mqtt_broker, e))?;
             let create_opts = CreateOptionsBuilder::new()
-                .server_uri(mqtt_broker.as_str())
+                .server_uri(MqttConnectionConfig::server_uri(mqtt_broker))
                 .client_id(&format!("{}|{}", "verifier", random_id))
                 .persistence(None)
                 .finalize();
             let _client = AsyncClient::new(create_opts)
                 .map_err(|e| anyhow!("Error verifying mqtt broker {}: {}", mqtt_broker, e))?;
         }
+        for (mqtt_type, qos) in self.mqtt_qos.iter() {
+            ensure!(
+                (0..=2).contains(qos),
+                "Error verifying mqtt qos for {:?}, {} provided!\nPlease provide 0, 1 or 2",
+                mqtt_type,
+                qos
+            );
+        }
         let client = Client::new();
         self.api_endpoints = self
             .api_endpoints
             .drain()
             .filter_map(|endpoint| Self::adjust_api_endpoint(endpoint))
             .collect();
-        for endpoint in self.api_endpoints.iter() {
-            Self::verify_endpoint(&client, endpoint).await?
+        self.api_connections = self
+            .api_connections
+            .drain()
+            .filter_map(|(endpoint, connection)| Self::adjust_api_endpoint(endpoint).map(|e| (e, connection)))
+            .collect();
+        self.polling_feed.endpoints = self
+            .polling_feed
+            .endpoints
+            .drain()
+            .filter_map(|endpoint| Self::adjust_api_endpoint(endpoint))
+            .collect();
+        for endpoint in self.polling_feed.endpoints.iter().chain(self.api_endpoints.iter()) {
+            let api_client = self
+                .api_client(endpoint, &client)
+                .map_err(|e| anyhow!("Error verifying endpoint {}: {}", endpoint, e))?;
+            Self::verify_endpoint(&api_client, endpoint).await?
         }
         let sync_range = self.sync_range.get_or_insert_with(|| SyncRange::default());
         if sync_range.from == 0 || sync_range.to == 0 {
@@ -122,8 +677,89 @@ impl BrokerConfig {
         } else if sync_range.from >= sync_range.to {
             bail!("Error verifying sync from/to, greater or equal provided!\nPlease provide lower \"Sync range from\" milestone index");
         }
+        self.backfill.verify()?;
+        self.import_throttle.verify()?;
+        if let Some(wasm_filter) = self.wasm_filter.as_ref() {
+            ensure!(
+                Path::new(&wasm_filter.module_path).is_file(),
+                "Error verifying wasm filter, no module found at {}",
+                wasm_filter.module_path
+            );
+        }
+        for (actor, capacity) in [
+            ("collector", self.inbox_capacity.collector),
+            ("solidifier", self.inbox_capacity.solidifier),
+            ("archiver", self.inbox_capacity.archiver),
+            ("syncer", self.inbox_capacity.syncer),
+            ("importer", self.inbox_capacity.importer),
+        ] {
+            ensure!(
+                capacity > 0,
+                "Error verifying inbox capacity, zero {} capacity provided!",
+                actor
+            );
+        }
+        ensure!(
+            self.endpoint_health.failure_threshold > 0,
+            "Error verifying endpoint health, zero failure_threshold provided!"
+        );
+        ensure!(
+            self.endpoint_health.base_backoff_ms <= self.endpoint_health.max_backoff_ms,
+            "Error verifying endpoint health, base_backoff_ms greater than max_backoff_ms provided!"
+        );
+        if let Some(archive_upload) = self.archive_upload.as_ref() {
+            ensure!(
+                !archive_upload.bucket.is_empty(),
+                "Error verifying archive upload, empty bucket provided!"
+            );
+            if let Some(endpoint) = archive_upload.endpoint.as_ref() {
+                Url::parse(endpoint)
+                    .map_err(|e| anyhow!("Error verifying archive upload endpoint {}: {}", endpoint, e))?;
+            }
+        }
+        if let Some(signing_key_path) = self.log_manifest.signing_key_path.as_ref() {
+            ensure!(
+                Path::new(signing_key_path).is_file(),
+                "Error verifying log manifest, no signing key found at {}",
+                signing_key_path
+            );
+        }
+        for key in self.log_manifest.trusted_keys.iter() {
+            ensure!(
+                key.len() == 64 && key.chars().all(|c| c.is_ascii_hexdigit()),
+                "Error verifying log manifest, invalid trusted key {} provided!\nPlease provide hex encoded Ed25519 public keys",
+                key
+            );
+        }
+        if let Some(feed_recording) = self.feed_recording.as_ref() {
+            ensure!(
+                feed_recording.max_file_size > 0,
+                "Error verifying feed recording, zero max_file_size provided!"
+            );
+            ensure!(
+                feed_recording.max_files != Some(0),
+                "Error verifying feed recording, zero max_files provided!"
+            );
+        }
         Ok(())
     }
+    /// Get the connection settings of the given MQTT feed source address
+    pub fn mqtt_connection(&self, url: &Url) -> MqttConnectionConfig {
+        self.mqtt_connections.get(url).cloned().unwrap_or_default()
+    }
+    /// Get the connection settings of the given api endpoint
+    pub fn api_connection(&self, endpoint: &Url) -> ApiConnectionConfig {
+        self.api_connections.get(endpoint).cloned().unwrap_or_default()
+    }
+    /// Build the client used to request the given api endpoint, sharing the given one if possible
+    pub fn api_client(&self, endpoint: &Url, shared_client: &Client) -> anyhow::Result<ApiClient> {
+        self.api_connection(endpoint)
+            .api_client(shared_client, Duration::from_secs(self.request_timeout_secs))
+    }
+    /// Get the configured MQTT quality of service for the given feed source type
+    pub fn mqtt_qos(&self, mqtt_type: MqttType) -> i32 {
+        self.mqtt_qos.get(&mqtt_type).copied().unwrap_or(0)
+    }
     /// Adjust IOTA api endpoint url and ensure it's correct or return None otherwise
     pub fn adjust_api_endpoint(endpoint: Url) -> Option<Url> {
         let path = endpoint.as_str();
@@ -145,7 +781,7 @@ impl BrokerConfig {
     }
 
     /// Verify if the IOTA api endpoint is active and correct
-    pub async fn verify_endpoint(client: &Client, endpoint: &Url) -> anyhow::Result<()> {
+    pub async fn verify_endpoint(client: &ApiClient, endpoint: &Url) -> anyhow::Result<()> {
         let res = client
             .get(
                 endpoint
@@ -168,3 +804,60 @@ impl BrokerConfig {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    #[test]
+    pub fn mqtt_server_uri() {
+        assert_eq!(
+            MqttConnectionConfig::server_uri(&url("mqtts://example.com:8883")),
+            "ssl://example.com:8883"
+        );
+        assert_eq!(
+            MqttConnectionConfig::server_uri(&url("mqtt://example.com:1883")),
+            "tcp://example.com:1883"
+        );
+        // the paho schemes are kept as is
+        assert_eq!(
+            MqttConnectionConfig::server_uri(&url("tcp://example.com:1883")),
+            "tcp://example.com:1883"
+        );
+        assert_eq!(
+            MqttConnectionConfig::server_uri(&url("wss://example.com/mqtt")),
+            "wss://example.com/mqtt"
+        );
+        assert!(MqttConnectionConfig::is_secure(&url("mqtts://example.com:8883")));
+        assert!(MqttConnectionConfig::is_secure(&url("ssl://example.com:8883")));
+        assert!(MqttConnectionConfig::is_secure(&url("wss://example.com/mqtt")));
+        assert!(!MqttConnectionConfig::is_secure(&url("mqtt://example.com:1883")));
+        assert!(!MqttConnectionConfig::is_secure(&url("ws://example.com/mqtt")));
+    }
+
+    #[test]
+    pub fn mqtt_connect_options() {
+        let credentials = MqttConnectionConfig {
+            username: Some("user".to_owned()),
+            password: Some("password".to_owned()),
+            ..Default::default()
+        };
+        assert!(credentials.connect_options(&url("tcp://example.com:1883")).is_ok());
+        assert!(credentials.connect_options(&url("mqtts://example.com:8883")).is_ok());
+        // the TLS settings require a secure scheme
+        let tls = MqttConnectionConfig {
+            ca_path: Some("ca.pem".to_owned()),
+            ..Default::default()
+        };
+        assert!(tls.connect_options(&url("mqtt://example.com:1883")).is_err());
+        let client_cert = MqttConnectionConfig {
+            client_cert_path: Some("client.pem".to_owned()),
+            ..Default::default()
+        };
+        assert!(client_cert.connect_options(&url("ws://example.com/mqtt")).is_err());
+    }
+}
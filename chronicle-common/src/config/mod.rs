@@ -236,6 +236,20 @@ where
         .ok_or_else(|| serde::de::Error::custom("Invalid socket address"))
 }
 
+fn deserialize_optional_socket_addr<'de, D>(d: D) -> Result<Option<SocketAddr>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(d)?
+        .map(|s| {
+            s.to_socket_addrs()
+                .map_err(serde::de::Error::custom)?
+                .next()
+                .ok_or_else(|| serde::de::Error::custom("Invalid socket address"))
+        })
+        .transpose()
+}
+
 fn deserialize_socket_addr_collected<'de, D, T>(d: D) -> Result<T, D::Error>
 where
     D: Deserializer<'de>,
@@ -325,6 +339,7 @@ mod test {
                             replication_factor: 1,
                         },
                     },
+                    milestone_keys: None,
                 }],
                 listen_address: "localhost:8080".to_socket_addrs().unwrap().next().unwrap(),
                 thread_count: ThreadCount::CoreMultiple(1),
@@ -333,7 +348,7 @@ mod test {
                 nodes: hashset!["localhost:9042".to_socket_addrs().unwrap().next().unwrap()],
                 partition_config: PartitionConfig::default(),
             },
-            api_config: ApiConfig {},
+            api_config: ApiConfig { max_sync_lag: 10 },
             broker_config: BrokerConfig {
                 collector_count: 10,
                 requester_count: 10,
@@ -343,7 +358,9 @@ mod test {
                 retries_per_query: 100,
                 complete_gaps_interval_secs: 3600,
                 websocket_address: "localhost:9000".to_socket_addrs().unwrap().next().unwrap(),
+                metrics_address: "localhost:9100".to_socket_addrs().unwrap().next(),
                 mqtt_stream_capacity: 10000,
+                inbox_capacity: InboxCapacityConfig::default(),
                 mqtt_brokers: hashmap! {
                     MqttType::Messages => hashset![
                         url::Url::parse("tcp://api.hornet-0.testnet.chrysalis2.com:1883").unwrap(),
@@ -352,16 +369,36 @@ mod test {
                     MqttType::MessagesReferenced => hashset![
                         url::Url::parse("tcp://api.hornet-0.testnet.chrysalis2.com:1883").unwrap(),
                         url::Url::parse("tcp://api.hornet-1.testnet.chrysalis2.com:1883").unwrap(),
+                    ],
+                    MqttType::LatestMilestone => hashset![
+                        url::Url::parse("tcp://api.hornet-0.testnet.chrysalis2.com:1883").unwrap(),
+                        url::Url::parse("tcp://api.hornet-1.testnet.chrysalis2.com:1883").unwrap(),
                     ]
                 },
+                mqtt_connections: HashMap::new(),
+                mqtt_qos: hashmap! {
+                    MqttType::Messages => 0,
+                    MqttType::MessagesReferenced => 0,
+                    MqttType::LatestMilestone => 0,
+                },
                 api_endpoints: hashset![
                     url::Url::parse("https://api.hornet-0.testnet.chrysalis2.com/api/v1").unwrap(),
                     url::Url::parse("https://api.hornet-1.testnet.chrysalis2.com/api/v1").unwrap(),
                 ]
                 .into(),
+                api_connections: HashMap::new(),
+                polling_feed: PollingFeedConfig::default(),
+                endpoint_health: EndpointHealthConfig::default(),
+                import_throttle: ImportThrottleConfig::default(),
                 sync_range: Some(SyncRange::default()),
+                backfill: BackfillConfig::default(),
                 logs_dir: Some("chronicle/logs/".to_owned()),
                 max_log_size: Some(4294967296),
+                log_compression: None,
+                archive_upload: None,
+                log_manifest: LogManifestConfig::default(),
+                wasm_filter: None,
+                feed_recording: None,
             },
             historical_config_path: HISTORICAL_CONFIG_PATH.to_owned(),
             alert_config: Default::default(),
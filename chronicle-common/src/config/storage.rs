@@ -82,8 +82,22 @@ impl StorageConfig {
         if self.local_datacenter.eq(&"") {
             bail!("local_datacenter must be non-empty string, ensure your config is correct");
         }
+        for keyspace in self.keyspaces.iter() {
+            if let Some(milestone_keys) = keyspace.milestone_keys.as_ref() {
+                milestone_keys
+                    .verify()
+                    .map_err(|e| anyhow!("Error verifying milestone keys of keyspace {}: {}", keyspace.name, e))?;
+            }
+        }
         Ok(())
     }
+    /// Get the coordinator keys configured for the given keyspace
+    pub fn milestone_keys(&self, keyspace: &str) -> Option<MilestoneKeysConfig> {
+        self.keyspaces
+            .iter()
+            .find(|k| k.name == keyspace)
+            .and_then(|k| k.milestone_keys.clone())
+    }
 }
 
 /// Configuration for a scylla keyspace
@@ -93,6 +107,57 @@ pub struct KeyspaceConfig {
     pub name: KeyspaceName,
     /// Datacenters configured for this keyspace, keyed by name
     pub data_centers: HashMap<DatacenterName, DatacenterConfig>,
+    /// The coordinator public keys used to verify the milestones of the network stored in this keyspace
+    #[serde(default)]
+    pub milestone_keys: Option<MilestoneKeysConfig>,
+}
+
+/// Coordinator public keys config
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct MilestoneKeysConfig {
+    /// The minimum number of valid signatures a milestone must carry
+    pub public_key_count: usize,
+    /// The public keys and the milestone indexes they are applicable to
+    pub key_ranges: Vec<MilestoneKeyRange>,
+}
+
+/// A coordinator public key and the milestone indexes it is applicable to
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct MilestoneKeyRange {
+    /// The hex encoded ed25519 public key
+    pub public_key: String,
+    /// The first milestone index the key is applicable to
+    pub start: u32,
+    /// The last milestone index the key is applicable to, unbounded if not set
+    pub end: Option<u32>,
+}
+
+impl MilestoneKeysConfig {
+    /// Get the public keys applicable to the given milestone index
+    pub fn applicable_public_keys(&self, milestone_index: u32) -> Vec<String> {
+        self.key_ranges
+            .iter()
+            .filter(|range| range.start <= milestone_index && range.end.map_or(true, |end| milestone_index <= end))
+            .map(|range| range.public_key.clone())
+            .collect()
+    }
+    /// Verify that the keys are well formed
+    pub fn verify(&self) -> anyhow::Result<()> {
+        ensure!(self.public_key_count > 0, "public_key_count must be greater than zero");
+        for range in self.key_ranges.iter() {
+            ensure!(
+                range.public_key.len() == 64 && range.public_key.chars().all(|c| c.is_ascii_hexdigit()),
+                "Invalid public key: {}, expected 32 hex encoded bytes",
+                range.public_key
+            );
+            ensure!(
+                range.end.map_or(true, |end| range.start <= end),
+                "Invalid range of public key: {}, start is greater than end",
+                range.public_key
+            );
+        }
+        Ok(())
+    }
 }
 
 impl Default for KeyspaceConfig {
@@ -107,6 +172,7 @@ impl Default for KeyspaceConfig {
                     replication_factor: 1,
                 },
             },
+            milestone_keys: None,
         }
     }
 }
@@ -5,10 +5,14 @@ use super::*;
 pub use prometheus;
 use prometheus::{
     Gauge,
+    GaugeVec,
+    Histogram,
     HistogramOpts,
     HistogramVec,
     IntCounter,
     IntCounterVec,
+    IntGauge,
+    IntGaugeVec,
     Opts,
     Registry,
 };
@@ -37,4 +41,108 @@ lazy_static! {
     pub static ref CONFIRMATION_TIME_COLLECTOR: Gauge =
         Gauge::new("confirmation_time", "Confirmation Times")
             .expect("failed to create metric");
+
+    /// Latest milestone index announced by the network
+    pub static ref LATEST_MILESTONE_INDEX: IntGauge =
+        IntGauge::new("latest_milestone_index", "Latest Milestone Index")
+            .expect("failed to create metric");
+
+    /// Latest milestone timestamp announced by the network
+    pub static ref LATEST_MILESTONE_TIMESTAMP: IntGauge =
+        IntGauge::new("latest_milestone_timestamp", "Latest Milestone Timestamp")
+            .expect("failed to create metric");
+
+    /// Number of milestones between the network tip and the latest synced milestone
+    pub static ref SYNC_LAG: IntGauge =
+        IntGauge::new("sync_lag", "Sync Lag")
+            .expect("failed to create metric");
+
+    /// Latest milestone index synced into the database
+    pub static ref LATEST_SYNCED_MILESTONE: IntGauge =
+        IntGauge::new("latest_synced_milestone", "Latest Synced Milestone")
+            .expect("failed to create metric");
+
+    /// Latest milestone index written to the archive logs
+    pub static ref LATEST_LOGGED_MILESTONE: IntGauge =
+        IntGauge::new("latest_logged_milestone", "Latest Logged Milestone")
+            .expect("failed to create metric");
+
+    /// Payloads received per MQTT source
+    pub static ref MQTT_RECEIVED: IntCounterVec =
+        IntCounterVec::new(Opts::new("mqtt_received", "MQTT Received"), &["source"])
+            .expect("failed to create metric");
+
+    /// Messages received more than once per collector
+    pub static ref DUPLICATE_MESSAGES: IntCounterVec =
+        IntCounterVec::new(Opts::new("duplicate_messages", "Duplicate Messages"), &["collector"])
+            .expect("failed to create metric");
+
+    /// Requests retried after a failed request to an api endpoint
+    pub static ref REQUESTER_RETRIES: IntCounterVec =
+        IntCounterVec::new(Opts::new("requester_retries", "Requester Retries"), &["endpoint"])
+            .expect("failed to create metric");
+
+    /// Time from the first event of a milestone until its cone is solidified
+    pub static ref SOLIDIFICATION_TIME: Histogram = Histogram::with_opts(
+        HistogramOpts::new("solidification_time_seconds", "Solidification Time")
+            .buckets(vec![0.1, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 300.0])
+    )
+    .expect("failed to create metric");
+
+    /// Inserts which failed for good, per table
+    pub static ref CQL_INSERT_ERRORS: IntCounterVec =
+        IntCounterVec::new(Opts::new("cql_insert_errors", "CQL Insert Errors"), &["table"])
+            .expect("failed to create metric");
+
+    /// Bytes written to the archive logs
+    pub static ref ARCHIVER_BYTES_WRITTEN: IntCounter =
+        IntCounter::new("archiver_bytes_written", "Archiver Bytes Written")
+            .expect("failed to create metric");
+
+    /// Milestones imported from the archive logs
+    pub static ref IMPORTED_MILESTONES: IntCounter =
+        IntCounter::new("imported_milestones", "Imported Milestones")
+            .expect("failed to create metric");
+
+    /// Bytes of milestone data imported from the archive logs
+    pub static ref IMPORTED_BYTES: IntCounter =
+        IntCounter::new("imported_bytes", "Imported Bytes")
+            .expect("failed to create metric");
+
+    /// Requests sent to the api endpoints by outcome
+    pub static ref ENDPOINT_REQUESTS: IntCounterVec = IntCounterVec::new(
+        Opts::new("endpoint_requests", "Endpoint Requests"),
+        &["endpoint", "outcome"]
+    )
+    .expect("failed to create metric");
+
+    /// Moving average of the api endpoints latency in milliseconds
+    pub static ref ENDPOINT_LATENCY: GaugeVec =
+        GaugeVec::new(Opts::new("endpoint_latency_ms", "Endpoint Latency"), &["endpoint"])
+            .expect("failed to create metric");
+
+    /// Health score of the api endpoints, from 0 (unusable) to 1 (healthy)
+    pub static ref ENDPOINT_HEALTH_SCORE: GaugeVec =
+        GaugeVec::new(Opts::new("endpoint_health_score", "Endpoint Health Score"), &["endpoint"])
+            .expect("failed to create metric");
+
+    /// Whether the circuit of the api endpoints is open (1) or not (0)
+    pub static ref ENDPOINT_CIRCUIT_OPEN: IntGaugeVec =
+        IntGaugeVec::new(Opts::new("endpoint_circuit_open", "Endpoint Circuit Open"), &["endpoint"])
+            .expect("failed to create metric");
+
+    /// Number of events queued in the actor inboxes
+    pub static ref INBOX_DEPTH: IntGaugeVec =
+        IntGaugeVec::new(Opts::new("inbox_depth", "Inbox Depth"), &["actor"])
+            .expect("failed to create metric");
+
+    /// Number of events dropped because the receiving actor was gone
+    pub static ref INBOX_DROPPED: IntCounterVec =
+        IntCounterVec::new(Opts::new("inbox_dropped", "Inbox Dropped"), &["actor"])
+            .expect("failed to create metric");
+
+    /// Messages rejected by the ingress verification, by source kind, ie mqtt, poller or collector
+    pub static ref REJECTED_MESSAGES: IntCounterVec =
+        IntCounterVec::new(Opts::new("rejected_messages", "Rejected Messages"), &["source"])
+            .expect("failed to create metric");
 }
@@ -1,8 +1,19 @@
 // Copyright 2021 IOTA Stiftung
 // SPDX-License-Identifier: Apache-2.0
+#[cfg(feature = "wasm")]
+use bee_common::packable::Packable;
 use bee_message::Message;
+use serde::{
+    Deserialize,
+    Serialize,
+};
 use std::borrow::Cow;
 
+#[cfg(feature = "wasm")]
+/// WebAssembly filter modules loaded at runtime
+pub mod wasm;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FilterResponse {
     /// The keyspace in which this message should be stored
     pub keyspace: Cow<'static, str>,
@@ -10,7 +21,24 @@ pub struct FilterResponse {
     pub ttl: Option<usize>,
 }
 
-#[allow(unused_variables)]
-pub async fn filter_messages(message: &mut Message) -> FilterResponse {
-    todo!()
+/// Run the currently loaded filter against a message and its json encoded metadata (if any).
+/// Returns `None` if there is no filter or it did not reach a decision, in which case
+/// the caller should fall back to its default keyspace.
+#[cfg(feature = "wasm")]
+pub async fn filter_messages(message: &mut Message, metadata: Option<&[u8]>) -> Option<FilterResponse> {
+    let filter = wasm::current_filter()?;
+    match filter.filter(&message.pack_new(), metadata.unwrap_or_default()) {
+        Ok(res) => res,
+        Err(e) => {
+            log::warn!("Wasm filter {:?} failed: {}", filter.path(), e);
+            None
+        }
+    }
+}
+
+/// Run the currently loaded filter against a message and its json encoded metadata (if any).
+/// Without the `wasm` feature no filter can be loaded, so this always returns `None`.
+#[cfg(not(feature = "wasm"))]
+pub async fn filter_messages(_message: &mut Message, _metadata: Option<&[u8]>) -> Option<FilterResponse> {
+    None
 }
@@ -0,0 +1,261 @@
+// Copyright 2021 IOTA Stiftung
+// SPDX-License-Identifier: Apache-2.0
+
+//! A filter module is a `.wasm` file which exports:
+//! - `memory`: its linear memory
+//! - `chronicle_alloc(len: i32) -> i32`: allocates `len` bytes and returns the pointer
+//! - `chronicle_filter(msg_ptr: i32, msg_len: i32, meta_ptr: i32, meta_len: i32) -> i64`: receives the packed message
+//!   and its json encoded metadata (`meta_len` is zero if there is none yet), and returns `(ptr << 32) | len` of a json
+//!   encoded `FilterResponse`, or zero to leave the decision to the caller.
+//!
+//! Instances are pooled and reused across invocations. Every invocation gets its own fuel budget, and every instance
+//! its own memory budget, so a misbehaving module traps instead of stalling the caller. An instance which trapped, or
+//! whose memory grew past half of its budget, is dropped and replaced by a fresh one.
+
+use super::FilterResponse;
+use anyhow::{
+    anyhow,
+    bail,
+};
+use lazy_static::lazy_static;
+use std::{
+    path::{
+        Path,
+        PathBuf,
+    },
+    sync::{
+        Arc,
+        Mutex,
+        RwLock,
+    },
+};
+use wasmtime::{
+    Config,
+    Engine,
+    Linker,
+    Memory,
+    Module,
+    Store,
+    StoreLimits,
+    StoreLimitsBuilder,
+    TypedFunc,
+};
+
+const ALLOC_EXPORT: &str = "chronicle_alloc";
+const FILTER_EXPORT: &str = "chronicle_filter";
+const MEMORY_EXPORT: &str = "memory";
+
+lazy_static! {
+    static ref WASM_FILTER: RwLock<Option<Arc<WasmFilter>>> = RwLock::new(None);
+}
+
+/// Resource limits applied to every filter invocation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WasmLimits {
+    /// The fuel (roughly, the number of wasm instructions) a single invocation may consume
+    pub fuel_per_call: u64,
+    /// The maximum size in bytes the module's linear memory may grow to
+    pub max_memory_bytes: usize,
+}
+
+impl Default for WasmLimits {
+    fn default() -> Self {
+        Self {
+            fuel_per_call: 10_000_000,
+            max_memory_bytes: 16 * 1024 * 1024,
+        }
+    }
+}
+
+/// A compiled filter module
+pub struct WasmFilter {
+    path: PathBuf,
+    engine: Engine,
+    module: Module,
+    limits: WasmLimits,
+    /// The idle instances of the module
+    instances: Mutex<Vec<FilterInstance>>,
+}
+
+/// An instance of a filter module, along with its store and exports
+struct FilterInstance {
+    store: Store<StoreLimits>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    filter: TypedFunc<(i32, i32, i32, i32), i64>,
+}
+
+impl FilterInstance {
+    /// Run the filter export against a packed message and its json encoded metadata, with a fresh fuel budget
+    fn call(&mut self, message: &[u8], metadata: &[u8], fuel: u64) -> anyhow::Result<Option<FilterResponse>> {
+        let remaining = self.store.consume_fuel(0)?;
+        if remaining < fuel {
+            self.store.add_fuel(fuel - remaining)?;
+        }
+        let msg_ptr = self.alloc.call(&mut self.store, message.len() as i32)?;
+        self.memory.write(&mut self.store, msg_ptr as usize, message)?;
+        let meta_ptr = if metadata.is_empty() {
+            0
+        } else {
+            let meta_ptr = self.alloc.call(&mut self.store, metadata.len() as i32)?;
+            self.memory.write(&mut self.store, meta_ptr as usize, metadata)?;
+            meta_ptr
+        };
+        let res = self.filter.call(
+            &mut self.store,
+            (msg_ptr, message.len() as i32, meta_ptr, metadata.len() as i32),
+        )?;
+        if res == 0 {
+            return Ok(None);
+        }
+        let (res_ptr, res_len) = ((res >> 32) as u32 as usize, res as u32 as usize);
+        let mut buf = vec![0; res_len];
+        self.memory.read(&self.store, res_ptr, &mut buf)?;
+        Ok(Some(serde_json::from_slice(&buf)?))
+    }
+}
+
+impl WasmFilter {
+    /// Compile the module at the given path and check that it provides the expected exports
+    pub fn load<P: AsRef<Path>>(path: P, limits: WasmLimits) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config)?;
+        let module =
+            Module::from_file(&engine, path).map_err(|e| anyhow!("Error compiling wasm filter {:?}: {}", path, e))?;
+        for export in [ALLOC_EXPORT, FILTER_EXPORT, MEMORY_EXPORT] {
+            if module.get_export(export).is_none() {
+                bail!("Wasm filter {:?} does not export `{}`", path, export);
+            }
+        }
+        Ok(Self {
+            path: path.to_owned(),
+            engine,
+            module,
+            limits,
+            instances: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// The path this module was loaded from
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The limits applied to each invocation
+    pub fn limits(&self) -> WasmLimits {
+        self.limits
+    }
+
+    /// Run the module against a packed message and its json encoded metadata
+    pub fn filter(&self, message: &[u8], metadata: &[u8]) -> anyhow::Result<Option<FilterResponse>> {
+        let idle = self.instances.lock().ok().and_then(|mut instances| instances.pop());
+        let mut instance = match idle {
+            Some(instance) => instance,
+            None => self.instantiate()?,
+        };
+        // an instance which trapped is dropped along with the error
+        let res = instance.call(message, metadata, self.limits.fuel_per_call)?;
+        if instance.memory.data_size(&instance.store) <= self.limits.max_memory_bytes / 2 {
+            if let Ok(mut instances) = self.instances.lock() {
+                instances.push(instance);
+            }
+        }
+        Ok(res)
+    }
+
+    fn instantiate(&self) -> anyhow::Result<FilterInstance> {
+        let store_limits = StoreLimitsBuilder::new()
+            .memory_size(self.limits.max_memory_bytes)
+            .instances(1)
+            .build();
+        let mut store = Store::new(&self.engine, store_limits);
+        store.limiter(|limits: &mut StoreLimits| limits);
+        let instance = Linker::new(&self.engine).instantiate(&mut store, &self.module)?;
+        let memory = instance
+            .get_memory(&mut store, MEMORY_EXPORT)
+            .ok_or_else(|| anyhow!("Missing `{}` export", MEMORY_EXPORT))?;
+        let alloc = instance.get_typed_func::<i32, i32, _>(&mut store, ALLOC_EXPORT)?;
+        let filter = instance.get_typed_func::<(i32, i32, i32, i32), i64, _>(&mut store, FILTER_EXPORT)?;
+        Ok(FilterInstance {
+            store,
+            memory,
+            alloc,
+            filter,
+        })
+    }
+}
+
+/// Compile the module at the given path and swap it in as the current filter.
+/// The previous filter, if any, stays in place if the new one fails to load.
+pub fn load_wasm_filter<P: AsRef<Path>>(path: P, limits: WasmLimits) -> anyhow::Result<()> {
+    let filter = WasmFilter::load(path, limits)?;
+    *WASM_FILTER.write().map_err(|_| anyhow!("Wasm filter lock poisoned"))? = Some(Arc::new(filter));
+    Ok(())
+}
+
+/// Get the current filter, if any
+pub fn current_filter() -> Option<Arc<WasmFilter>> {
+    WASM_FILTER.read().ok().and_then(|filter| filter.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Keeps the messages which come with metadata in the `filtered` keyspace, and spins forever on empty messages
+    const FILTER_WAT: &str = r#"
+        (module
+            (memory (export "memory") 1)
+            (global $next (mut i32) (i32.const 1024))
+            (data (i32.const 0) "{\"keyspace\":\"filtered\",\"ttl\":null}")
+            (func (export "chronicle_alloc") (param $len i32) (result i32)
+                (local $ptr i32)
+                (local.set $ptr (global.get $next))
+                (global.set $next (i32.add (global.get $next) (local.get $len)))
+                (local.get $ptr))
+            (func (export "chronicle_filter")
+                (param $msg_ptr i32) (param $msg_len i32) (param $meta_ptr i32) (param $meta_len i32) (result i64)
+                (if (i32.eqz (local.get $msg_len))
+                    (then (loop $spin (br $spin))))
+                (if (result i64) (i32.eqz (local.get $meta_len))
+                    (then (i64.const 0))
+                    (else (i64.const 34)))))
+    "#;
+
+    fn write_module(name: &str, wat: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("chronicle-filter-{}-{}.wat", name, std::process::id()));
+        std::fs::write(&path, wat).unwrap();
+        path
+    }
+
+    #[test]
+    fn filter_decisions() {
+        let path = write_module("decisions", FILTER_WAT);
+        let limits = WasmLimits {
+            fuel_per_call: 100_000,
+            ..Default::default()
+        };
+        let filter = WasmFilter::load(&path, limits).unwrap();
+        // no metadata yet, so no decision
+        assert!(filter.filter(b"message", b"").unwrap().is_none());
+        let response = filter
+            .filter(b"message", b"{}")
+            .unwrap()
+            .expect("Expected a filter decision");
+        assert_eq!(response.keyspace, "filtered");
+        assert_eq!(response.ttl, None);
+        // a module which runs out of fuel traps instead of stalling the caller, and a fresh instance takes over
+        assert!(filter.filter(b"", b"{}").is_err());
+        assert!(filter.filter(b"message", b"{}").unwrap().is_some());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn missing_exports() {
+        let path = write_module("missing-exports", r#"(module (memory (export "memory") 1))"#);
+        assert!(WasmFilter::load(&path, WasmLimits::default()).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
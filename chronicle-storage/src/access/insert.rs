@@ -3,12 +3,19 @@
 
 use super::*;
 
+/// The table a keyspace inserts the key/value pairs of an insert statement into
+pub trait InsertTable<K, V>: Insert<K, V> {
+    /// The name of the table
+    const TABLE: &'static str;
+}
+
 impl Insert<MessageId, Message> for ChronicleKeyspace {
     type QueryOrPrepared = PreparedStatement;
     fn statement(&self) -> std::borrow::Cow<'static, str> {
         format!(
-            "INSERT INTO {}.messages (message_id, message) VALUES (?, ?)",
-            self.name()
+            "INSERT INTO {}.messages (message_id, message) VALUES (?, ?){}",
+            self.name(),
+            self.using_ttl()
         )
         .into()
     }
@@ -20,13 +27,18 @@ impl Insert<MessageId, Message> for ChronicleKeyspace {
         builder.value(&message_id.to_string()).value(&message_bytes.as_slice())
     }
 }
+
+impl InsertTable<MessageId, Message> for ChronicleKeyspace {
+    const TABLE: &'static str = "messages";
+}
 /// Insert Metadata
 impl Insert<MessageId, MessageMetadata> for ChronicleKeyspace {
     type QueryOrPrepared = PreparedStatement;
     fn statement(&self) -> std::borrow::Cow<'static, str> {
         format!(
-            "INSERT INTO {}.messages (message_id, metadata) VALUES (?, ?)",
-            self.name()
+            "INSERT INTO {}.messages (message_id, metadata) VALUES (?, ?){}",
+            self.name(),
+            self.using_ttl()
         )
         .into()
     }
@@ -36,12 +48,17 @@ impl Insert<MessageId, MessageMetadata> for ChronicleKeyspace {
     }
 }
 
+impl InsertTable<MessageId, MessageMetadata> for ChronicleKeyspace {
+    const TABLE: &'static str = "messages";
+}
+
 impl Insert<MessageId, (Message, MessageMetadata)> for ChronicleKeyspace {
     type QueryOrPrepared = PreparedStatement;
     fn statement(&self) -> std::borrow::Cow<'static, str> {
         format!(
-            "INSERT INTO {}.messages (message_id, message, metadata) VALUES (?, ?, ?)",
-            self.name()
+            "INSERT INTO {}.messages (message_id, message, metadata) VALUES (?, ?, ?){}",
+            self.name(),
+            self.using_ttl()
         )
         .into()
     }
@@ -61,14 +78,19 @@ impl Insert<MessageId, (Message, MessageMetadata)> for ChronicleKeyspace {
             .value(meta)
     }
 }
+
+impl InsertTable<MessageId, (Message, MessageMetadata)> for ChronicleKeyspace {
+    const TABLE: &'static str = "messages";
+}
 /// Insert Address into addresses table
 impl Insert<Partitioned<Ed25519Address>, AddressRecord> for ChronicleKeyspace {
     type QueryOrPrepared = PreparedStatement;
     fn statement(&self) -> std::borrow::Cow<'static, str> {
         format!(
             "INSERT INTO {}.addresses (address, partition_id, milestone_index, output_type, transaction_id, idx, amount, address_type, inclusion_state)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
-            self.name()
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?){}",
+            self.name(),
+            self.using_ttl()
         )
         .into()
     }
@@ -96,14 +118,19 @@ impl Insert<Partitioned<Ed25519Address>, AddressRecord> for ChronicleKeyspace {
     }
 }
 
+impl InsertTable<Partitioned<Ed25519Address>, AddressRecord> for ChronicleKeyspace {
+    const TABLE: &'static str = "addresses";
+}
+
 /// Insert Index into Indexes table
 impl Insert<Partitioned<Indexation>, IndexationRecord> for ChronicleKeyspace {
     type QueryOrPrepared = PreparedStatement;
     fn statement(&self) -> std::borrow::Cow<'static, str> {
         format!(
             "INSERT INTO {}.indexes (indexation, partition_id, milestone_index, message_id, inclusion_state)
-            VALUES (?, ?, ?, ?, ?)",
-            self.name()
+            VALUES (?, ?, ?, ?, ?){}",
+            self.name(),
+            self.using_ttl()
         )
         .into()
     }
@@ -124,14 +151,19 @@ impl Insert<Partitioned<Indexation>, IndexationRecord> for ChronicleKeyspace {
     }
 }
 
+impl InsertTable<Partitioned<Indexation>, IndexationRecord> for ChronicleKeyspace {
+    const TABLE: &'static str = "indexes";
+}
+
 /// Insert ParentId into Parents table
 impl Insert<Partitioned<MessageId>, ParentRecord> for ChronicleKeyspace {
     type QueryOrPrepared = PreparedStatement;
     fn statement(&self) -> std::borrow::Cow<'static, str> {
         format!(
             "INSERT INTO {}.parents (parent_id, partition_id, milestone_index, message_id, inclusion_state)
-            VALUES (?, ?, ?, ?, ?)",
-            self.name()
+            VALUES (?, ?, ?, ?, ?){}",
+            self.name(),
+            self.using_ttl()
         )
         .into()
     }
@@ -151,6 +183,10 @@ impl Insert<Partitioned<MessageId>, ParentRecord> for ChronicleKeyspace {
             .value(ledger_inclusion_state)
     }
 }
+
+impl InsertTable<Partitioned<MessageId>, ParentRecord> for ChronicleKeyspace {
+    const TABLE: &'static str = "parents";
+}
 /// Insert Transaction into Transactions table
 /// Note: This can be used to store:
 /// -input variant: (InputTransactionId, InputIndex) -> UTXOInput data column
@@ -161,8 +197,9 @@ impl Insert<(TransactionId, Index), TransactionRecord> for ChronicleKeyspace {
     fn statement(&self) -> std::borrow::Cow<'static, str> {
         format!(
             "INSERT INTO {}.transactions (transaction_id, idx, variant, message_id, data, inclusion_state, milestone_index)
-            VALUES (?, ?, ?, ?, ?, ?, ?)",
-            self.name()
+            VALUES (?, ?, ?, ?, ?, ?, ?){}",
+            self.name(),
+            self.using_ttl()
         )
         .into()
     }
@@ -188,13 +225,18 @@ impl Insert<(TransactionId, Index), TransactionRecord> for ChronicleKeyspace {
     }
 }
 
+impl InsertTable<(TransactionId, Index), TransactionRecord> for ChronicleKeyspace {
+    const TABLE: &'static str = "transactions";
+}
+
 /// Insert Output into Transactions table
 impl Insert<OutputId, TransactionRecord> for ChronicleKeyspace {
     type QueryOrPrepared = PreparedStatement;
     fn statement(&self) -> std::borrow::Cow<'static, str> {
         format!(
-            "INSERT INTO {}.transactions (transaction_id, idx, variant, message_id, data, inclusion_state, milestone_index) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
-            self.name()
+            "INSERT INTO {}.transactions (transaction_id, idx, variant, message_id, data, inclusion_state, milestone_index) VALUES (?, ?, ?, ?, ?, ?, ?, ?){}",
+            self.name(),
+            self.using_ttl()
         )
         .into()
     }
@@ -220,13 +262,18 @@ impl Insert<OutputId, TransactionRecord> for ChronicleKeyspace {
     }
 }
 
+impl InsertTable<OutputId, TransactionRecord> for ChronicleKeyspace {
+    const TABLE: &'static str = "transactions";
+}
+
 /// Insert Hint into Hints table
 impl Insert<Hint, Partition> for ChronicleKeyspace {
     type QueryOrPrepared = PreparedStatement;
     fn statement(&self) -> std::borrow::Cow<'static, str> {
         format!(
-            "INSERT INTO {}.hints (hint, variant, partition_id, milestone_index) VALUES (?, ?, ?, ?)",
-            self.name()
+            "INSERT INTO {}.hints (hint, variant, partition_id, milestone_index) VALUES (?, ?, ?, ?){}",
+            self.name(),
+            self.using_ttl()
         )
         .into()
     }
@@ -239,6 +286,10 @@ impl Insert<Hint, Partition> for ChronicleKeyspace {
     }
 }
 
+impl InsertTable<Hint, Partition> for ChronicleKeyspace {
+    const TABLE: &'static str = "hints";
+}
+
 impl Insert<MilestoneIndex, (MessageId, Box<MilestonePayload>)> for ChronicleKeyspace {
     type QueryOrPrepared = PreparedStatement;
     fn statement(&self) -> std::borrow::Cow<'static, str> {
@@ -265,6 +316,10 @@ impl Insert<MilestoneIndex, (MessageId, Box<MilestonePayload>)> for ChronicleKey
     }
 }
 
+impl InsertTable<MilestoneIndex, (MessageId, Box<MilestonePayload>)> for ChronicleKeyspace {
+    const TABLE: &'static str = "milestones";
+}
+
 impl Insert<Synckey, SyncRecord> for ChronicleKeyspace {
     type QueryOrPrepared = PreparedStatement;
     fn statement(&self) -> std::borrow::Cow<'static, str> {
@@ -291,6 +346,10 @@ impl Insert<Synckey, SyncRecord> for ChronicleKeyspace {
     }
 }
 
+impl InsertTable<Synckey, SyncRecord> for ChronicleKeyspace {
+    const TABLE: &'static str = "sync";
+}
+
 impl Insert<Synckey, AnalyticRecord> for ChronicleKeyspace {
     type QueryOrPrepared = PreparedStatement;
     fn statement(&self) -> std::borrow::Cow<'static, str> {
@@ -318,3 +377,7 @@ impl Insert<Synckey, AnalyticRecord> for ChronicleKeyspace {
             .value(&transferred_tokens.0)
     }
 }
+
+impl InsertTable<Synckey, AnalyticRecord> for ChronicleKeyspace {
+    const TABLE: &'static str = "analytics";
+}
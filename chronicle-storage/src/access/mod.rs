@@ -32,6 +32,7 @@ pub use delete::{
     IndexationPK,
     ParentPK,
 };
+pub use insert::InsertTable;
 use scylla_rs::{
     cql::{
         ColumnDecoder,
@@ -8,12 +8,31 @@ use std::borrow::Cow;
 #[derive(Default, Clone, Debug)]
 pub struct ChronicleKeyspace {
     name: Cow<'static, str>,
+    /// The time-to-live of the rows of the messages, along with their payloads and hints, in seconds
+    ttl: Option<u32>,
 }
 
 impl ChronicleKeyspace {
     /// Create a new instance of the keyspace
     pub fn new(name: String) -> Self {
-        Self { name: name.into() }
+        Self {
+            name: name.into(),
+            ttl: None,
+        }
+    }
+    /// Write the rows of the messages, along with their payloads and hints, with the given time-to-live.
+    /// The `milestones`, `sync` and `analytics` rows never expire, so the synced milestones stay provable
+    pub fn with_ttl(mut self, ttl: Option<u32>) -> Self {
+        self.ttl = ttl;
+        self
+    }
+    /// Get the time-to-live of the rows of the messages
+    pub fn ttl(&self) -> Option<u32> {
+        self.ttl
+    }
+    /// The `USING TTL` clause of the message inserts, if any
+    pub(crate) fn using_ttl(&self) -> String {
+        self.ttl.map(|ttl| format!(" USING TTL {}", ttl)).unwrap_or_default()
     }
 }
 
@@ -135,6 +135,86 @@ fn register_metrics() {
     REGISTRY
         .register(Box::new(CONFIRMATION_TIME_COLLECTOR.clone()))
         .expect("Could not register collector");
+
+    REGISTRY
+        .register(Box::new(LATEST_MILESTONE_INDEX.clone()))
+        .expect("Could not register collector");
+
+    REGISTRY
+        .register(Box::new(LATEST_MILESTONE_TIMESTAMP.clone()))
+        .expect("Could not register collector");
+
+    REGISTRY
+        .register(Box::new(SYNC_LAG.clone()))
+        .expect("Could not register collector");
+
+    REGISTRY
+        .register(Box::new(LATEST_SYNCED_MILESTONE.clone()))
+        .expect("Could not register collector");
+
+    REGISTRY
+        .register(Box::new(LATEST_LOGGED_MILESTONE.clone()))
+        .expect("Could not register collector");
+
+    REGISTRY
+        .register(Box::new(MQTT_RECEIVED.clone()))
+        .expect("Could not register collector");
+
+    REGISTRY
+        .register(Box::new(DUPLICATE_MESSAGES.clone()))
+        .expect("Could not register collector");
+
+    REGISTRY
+        .register(Box::new(REQUESTER_RETRIES.clone()))
+        .expect("Could not register collector");
+
+    REGISTRY
+        .register(Box::new(SOLIDIFICATION_TIME.clone()))
+        .expect("Could not register collector");
+
+    REGISTRY
+        .register(Box::new(CQL_INSERT_ERRORS.clone()))
+        .expect("Could not register collector");
+
+    REGISTRY
+        .register(Box::new(ARCHIVER_BYTES_WRITTEN.clone()))
+        .expect("Could not register collector");
+
+    REGISTRY
+        .register(Box::new(IMPORTED_MILESTONES.clone()))
+        .expect("Could not register collector");
+
+    REGISTRY
+        .register(Box::new(IMPORTED_BYTES.clone()))
+        .expect("Could not register collector");
+
+    REGISTRY
+        .register(Box::new(ENDPOINT_REQUESTS.clone()))
+        .expect("Could not register collector");
+
+    REGISTRY
+        .register(Box::new(ENDPOINT_LATENCY.clone()))
+        .expect("Could not register collector");
+
+    REGISTRY
+        .register(Box::new(ENDPOINT_HEALTH_SCORE.clone()))
+        .expect("Could not register collector");
+
+    REGISTRY
+        .register(Box::new(ENDPOINT_CIRCUIT_OPEN.clone()))
+        .expect("Could not register collector");
+
+    REGISTRY
+        .register(Box::new(INBOX_DEPTH.clone()))
+        .expect("Could not register collector");
+
+    REGISTRY
+        .register(Box::new(INBOX_DROPPED.clone()))
+        .expect("Could not register collector");
+
+    REGISTRY
+        .register(Box::new(REJECTED_MESSAGES.clone()))
+        .expect("Could not register collector");
 }
 
 async fn init_database() -> anyhow::Result<()> {